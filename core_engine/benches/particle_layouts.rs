@@ -0,0 +1,100 @@
+use criterion::{criterion_group, criterion_main, black_box, Criterion, Throughput};
+use core_engine::particles::{ParticleSystem, vec2_floats, vec4_floats};
+use glmath::glmath::{Vec2f, Vec4f};
+
+/// Particles alive at the start of every frame.
+const COUNT: usize = 10_000;
+
+/// Seconds simulated per frame.
+const DELTA_TIME: f32 = 1.0 / 1000.0;
+
+/// A particle stored as an array of structs, the layout ParticleSystem is compared against.
+#[derive(Clone, Copy)]
+struct Particle {
+    position: Vec2f,
+    velocity: Vec2f,
+    lifetime: f32,
+    color: Vec4f
+}
+
+/// Lifetimes vary so some particles die, and are replaced, as frames go by.
+fn lifetime(index: usize) -> f32 {
+    0.5 + (index % 100) as f32 * 0.01
+}
+
+fn velocity(index: usize) -> Vec2f {
+    Vec2f::new(index as f32 * 0.001, 1.0)
+}
+
+fn color() -> Vec4f {
+    Vec4f::new(1.0, 0.5, 0.0, 1.0)
+}
+
+/// Tops the structs up to COUNT, so dead particles are replaced each frame.
+fn refill_structs(structs: &mut Vec<Particle>) {
+    for index in structs.len()..COUNT {
+        structs.push(Particle { position: Vec2f::new(0.0, 0.0), velocity: velocity(index), lifetime: lifetime(index), color: color() });
+    }
+}
+
+/// Tops the system up to COUNT, so dead particles are replaced each frame.
+fn refill_system(system: &mut ParticleSystem) {
+    for index in system.len()..COUNT {
+        system.spawn(Vec2f::new(0.0, 0.0), velocity(index), lifetime(index), color());
+    }
+}
+
+fn update_structs(structs: &mut Vec<Particle>, acceleration: Vec2f) {
+    for particle in structs.iter_mut() {
+        particle.velocity += acceleration * DELTA_TIME;
+        particle.position += particle.velocity * DELTA_TIME;
+        particle.lifetime -= DELTA_TIME;
+    }
+
+    structs.retain(|particle| particle.lifetime > 0.0);
+}
+
+fn bench_particle_layouts(c: &mut Criterion) {
+    let acceleration = Vec2f::new(0.0, -9.8);
+
+    let mut structs = Vec::<Particle>::with_capacity(COUNT);
+    let mut system = ParticleSystem::new(COUNT);
+    system.set_acceleration(acceleration);
+
+    let mut update_group = c.benchmark_group("particle_update");
+    update_group.throughput(Throughput::Elements(COUNT as u64));
+    update_group.bench_function("array_of_structs", |b| b.iter(|| {
+        refill_structs(&mut structs);
+        update_structs(&mut structs, acceleration);
+    }));
+    update_group.bench_function("struct_of_arrays", |b| b.iter(|| {
+        refill_system(&mut system);
+        system.update(DELTA_TIME);
+    }));
+    update_group.finish();
+
+    refill_structs(&mut structs);
+    refill_system(&mut system);
+    let mut staging = Vec::<f32>::with_capacity(COUNT * 6);
+
+    // Structs gather positions and colors into the buffers GL reads. The arrays already are those
+    // buffers, so copying them stands in for glBufferData's copy.
+    let mut upload_group = c.benchmark_group("particle_upload");
+    upload_group.throughput(Throughput::Elements(COUNT as u64));
+    upload_group.bench_function("array_of_structs", |b| b.iter(|| {
+        staging.clear();
+        staging.extend(structs.iter().flat_map(|particle| [particle.position.x, particle.position.y]));
+        staging.extend(structs.iter().flat_map(|particle| [particle.color.x, particle.color.y, particle.color.z, particle.color.w]));
+        black_box(&staging);
+    }));
+    upload_group.bench_function("struct_of_arrays", |b| b.iter(|| {
+        staging.clear();
+        staging.extend_from_slice(vec2_floats(system.positions()));
+        staging.extend_from_slice(vec4_floats(system.colors()));
+        black_box(&staging);
+    }));
+    upload_group.finish();
+}
+
+criterion_group!(benches, bench_particle_layouts);
+criterion_main!(benches);
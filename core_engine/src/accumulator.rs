@@ -0,0 +1,64 @@
+/// Accumulates frame delta times and fires at a fixed interval, so gameplay logic can run at a
+/// steady rate regardless of the frame rate. Unlike a Stopwatch it doesn't read the clock itself,
+/// it's fed whatever delta time the caller measures.
+///
+/// Each call to `should_tick` consumes one interval, so call it in a loop to catch up after a long frame:
+/// while accumulator.should_tick() { step(); }
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Accumulator {
+    interval: f32,
+    accumulated: f32
+}
+
+impl Accumulator {
+    /// Creates an accumulator firing every `interval` seconds.
+    pub fn new(interval: f32) -> Self {
+        #[cfg(debug_assertions)]
+        assert!(interval > 0.0, "Accumulator interval must be positive");
+
+        Accumulator {
+            interval,
+            accumulated: 0.0
+        }
+    }
+
+    /// Creates an accumulator firing `rate` times per second.
+    pub fn from_rate(rate: f32) -> Self {
+        Accumulator::new(1.0 / rate)
+    }
+
+    pub fn get_interval(&self) -> f32 {
+        self.interval
+    }
+
+    /// Changes the interval, keeping any time already accumulated.
+    pub fn set_interval(&mut self, interval: f32) {
+        self.interval = interval;
+    }
+
+    /// Adds the time elapsed since the last frame.
+    pub fn add(&mut self, delta_time: f32) {
+        self.accumulated += delta_time;
+    }
+
+    /// Returns true and consumes one interval if enough time has accumulated.
+    pub fn should_tick(&mut self) -> bool {
+        if self.accumulated >= self.interval {
+            self.accumulated -= self.interval;
+            return true;
+        }
+
+        false
+    }
+
+    /// Returns how far into the next interval the accumulated time is, from 0 to 1.
+    /// Useful to interpolate rendering between two ticks.
+    pub fn alpha(&self) -> f32 {
+        f32::min(self.accumulated / self.interval, 1.0)
+    }
+
+    /// Drops any accumulated time, for example after the game is paused.
+    pub fn reset(&mut self) {
+        self.accumulated = 0.0;
+    }
+}
@@ -0,0 +1,295 @@
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::parser::JsonNode;
+
+use crate::atlas::{AtlasError, TextureAtlas, TextureRegion};
+use crate::texture::packing::Rect;
+
+/// How an animation continues once its last frame has been shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Stops on the last frame.
+    Once,
+    /// Starts over from the first frame.
+    #[default]
+    Loop,
+    /// Plays forwards, then backwards to the first frame, and repeats. The first and last frames
+    /// aren't repeated when turning around.
+    PingPong
+}
+
+impl PlayMode {
+    /// Reads the names used by animation declarations: "once", "loop", and "ping_pong".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "once" => Some(PlayMode::Once),
+            "loop" => Some(PlayMode::Loop),
+            "ping_pong" => Some(PlayMode::PingPong),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AnimationFrame {
+    pub region: TextureRegion,
+    /// Seconds the frame is shown for.
+    pub duration: f32
+}
+
+/// An ordered list of texture regions shown one after another.
+///
+/// Animations are declared in the manifest next to their atlas, under "animations":
+/// { "frames": ["walk_0", "walk_1"], "duration": 0.1, "mode": "loop" }
+/// or as a strip of equally sized frames, optionally within a named region of the atlas:
+/// { "grid": { "region": "walk", "width": 16, "height": 16, "count": 4, "row": 0 }, "durations": [0.1, 0.2, 0.1, 0.2], "mode": "ping_pong" }
+/// "duration" gives every frame the same length, while "durations" sets one per frame.
+#[derive(Clone)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    mode: PlayMode
+}
+
+impl Animation {
+    /// Creates an animation from its frames. An animation without frames has no region to show.
+    pub fn new(frames: Vec<AnimationFrame>, mode: PlayMode) -> Self {
+        Animation { frames, mode }
+    }
+
+    /// Creates an animation showing every region for the same length of time.
+    pub fn uniform(regions: Vec<TextureRegion>, frame_duration: f32, mode: PlayMode) -> Self {
+        let frames = regions.into_iter()
+            .map(|region| AnimationFrame { region, duration: frame_duration })
+            .collect();
+
+        Animation::new(frames, mode)
+    }
+
+    /// Reads an animation declaration, see `Animation`, taking its frames from the atlas.
+    pub fn from_json(node: &JsonNode, atlas: &TextureAtlas) -> Result<Self, AtlasError> {
+        let rects = match (node.pointer("frames"), node.pointer("grid")) {
+            (Some(frames), _) => {
+                let names = Vec::<String>::from_json(frames)
+                    .ok_or_else(|| AtlasError::InvalidData("expected \"frames\" to be an array of region names".to_string()))?;
+
+                names.iter()
+                    .map(|name| atlas.pixel_rect(name).ok_or_else(|| AtlasError::InvalidData(format!("unknown region {}", name))))
+                    .collect::<Result<Vec<Rect>, AtlasError>>()?
+            },
+            (None, Some(grid)) => grid_frames(grid, atlas)?,
+            (None, None) => return Err(AtlasError::InvalidData("expected an animation to have \"frames\" or a \"grid\"".to_string()))
+        };
+
+        if rects.is_empty() {
+            return Err(AtlasError::InvalidData("expected an animation to have at least one frame".to_string()));
+        }
+
+        let durations = match (node.pointer("duration").and_then(f32::from_json), node.pointer("durations")) {
+            (Some(duration), _) => vec![duration; rects.len()],
+            (None, Some(durations)) => Vec::<f32>::from_json(durations)
+                .filter(|durations| durations.len() == rects.len())
+                .ok_or_else(|| AtlasError::InvalidData(format!("expected \"durations\" to hold {} numbers", rects.len())))?,
+            (None, None) => return Err(AtlasError::InvalidData("expected a \"duration\" or \"durations\"".to_string()))
+        };
+
+        if durations.iter().any(|duration| duration.is_nan() || *duration <= 0.0) {
+            return Err(AtlasError::InvalidData("frame durations must be greater than zero".to_string()));
+        }
+
+        let mode = match node.pointer("mode") {
+            Some(mode) => String::from_json(mode).as_deref().and_then(PlayMode::from_name)
+                .ok_or_else(|| AtlasError::InvalidData("expected \"mode\" to be \"once\", \"loop\", or \"ping_pong\"".to_string()))?,
+            None => PlayMode::default()
+        };
+
+        let frames = rects.into_iter().zip(durations)
+            .map(|(rect, duration)| AnimationFrame { region: atlas.region_at(rect), duration })
+            .collect();
+
+        Ok(Animation::new(frames, mode))
+    }
+
+    pub fn frames(&self) -> &Vec<AnimationFrame> {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> PlayMode {
+        self.mode
+    }
+
+    /// Returns the frame indices of one full cycle. Ping pong cycles return back to the first frame.
+    fn cycle_frames(&self) -> impl Iterator<Item = usize> {
+        let count = self.frames.len();
+        let returning = if self.mode == PlayMode::PingPong && count > 2 { 1..count - 1 } else { 0..0 };
+
+        (0..count).chain(returning.rev())
+    }
+
+    /// Seconds before the animation finishes, or starts its next cycle.
+    pub fn cycle_duration(&self) -> f32 {
+        self.cycle_frames().map(|index| self.frames[index].duration).sum()
+    }
+
+    /// Returns the index of the frame shown the given number of seconds after the animation started.
+    /// A frame starts exactly on its start time, so the first frame lasts until just before its duration.
+    /// Animations playing once hold their last frame after they finish.
+    pub fn frame_at(&self, time: f32) -> usize {
+        let cycle_duration = self.cycle_duration();
+
+        if self.frames.is_empty() {
+            return 0;
+        }
+
+        if self.mode == PlayMode::Once && time >= cycle_duration {
+            return self.frames.len() - 1;
+        }
+
+        let mut remaining = match self.mode {
+            PlayMode::Once => time.max(0.0),
+            _ if cycle_duration > 0.0 => time.rem_euclid(cycle_duration),
+            _ => 0.0
+        };
+
+        for index in self.cycle_frames() {
+            if remaining < self.frames[index].duration {
+                return index;
+            }
+
+            remaining -= self.frames[index].duration;
+        }
+
+        // Rounding can leave a sliver of time past the last frame of the cycle.
+        self.cycle_frames().last().unwrap_or(0)
+    }
+}
+
+/// Reads the frames of a grid declaration: "count" frames of "width" x "height" pixels, side by side along
+/// "row" of the named region, or of the whole atlas when no region is given.
+fn grid_frames(grid: &JsonNode, atlas: &TextureAtlas) -> Result<Vec<Rect>, AtlasError> {
+    let field = |field: &str| grid.pointer(field).and_then(u32::from_json)
+        .ok_or_else(|| AtlasError::InvalidData(format!("expected the animation grid to have a whole number \"{}\"", field)));
+
+    let (width, height, count) = (field("width")?, field("height")?, field("count")?);
+    let row = grid.pointer("row").and_then(u32::from_json).unwrap_or(0);
+
+    let (area_name, area) = match grid.pointer("region").and_then(String::from_json) {
+        Some(name) => {
+            let area = atlas.pixel_rect(&name).ok_or_else(|| AtlasError::InvalidData(format!("unknown region {}", name)))?;
+            (name, area)
+        },
+        None => ("atlas".to_string(), Rect::new(0, 0, atlas.size().0, atlas.size().1))
+    };
+
+    let frames: Vec<Rect> = (0..count)
+        .map(|column| Rect::new(area.x + column * width, area.y + row * height, width, height))
+        .collect();
+
+    // Every frame must lie within the area the grid covers.
+    if let Some(outside) = frames.iter().find(|frame| width == 0 || height == 0
+        || frame.x + frame.width > area.x + area.width || frame.y + frame.height > area.y + area.height) {
+        return Err(AtlasError::RegionOutOfBounds { name: area_name, rect: *outside, width: area.width, height: area.height });
+    }
+
+    Ok(frames)
+}
+
+/// Plays an animation, advanced by the time passed each update.
+///
+/// The started and finished events hold for the single update they happened in. Looping animations
+/// report finished each time they complete a cycle.
+#[derive(Clone)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    time: f32,
+    playing: bool,
+    /// Set by play, and turned into the started event by the next update.
+    pending_start: bool,
+    started: bool,
+    finished: bool
+}
+
+impl AnimationPlayer {
+    /// Creates a player which starts playing from the first frame on the next update.
+    pub fn new(animation: Animation) -> Self {
+        let mut player = AnimationPlayer {
+            animation,
+            time: 0.0,
+            playing: false,
+            pending_start: false,
+            started: false,
+            finished: false
+        };
+
+        player.play();
+        player
+    }
+
+    /// Restarts the animation from the first frame.
+    pub fn play(&mut self) {
+        self.time = 0.0;
+        self.playing = true;
+        self.pending_start = true;
+    }
+
+    /// Pauses on the current frame.
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.started = std::mem::take(&mut self.pending_start);
+        self.finished = false;
+
+        if !self.playing {
+            return;
+        }
+
+        let cycle_duration = self.animation.cycle_duration();
+        self.time += delta_time;
+
+        if self.time >= cycle_duration {
+            self.finished = true;
+
+            match self.animation.mode {
+                PlayMode::Once => {
+                    self.time = cycle_duration;
+                    self.playing = false;
+                },
+                // Keep the time within a cycle so it doesn't lose precision as it grows.
+                _ if cycle_duration > 0.0 => self.time = self.time.rem_euclid(cycle_duration),
+                _ => self.time = 0.0
+            }
+        }
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.animation.frame_at(self.time)
+    }
+
+    /// Returns the region of the frame currently shown, or None when the animation has no frames.
+    pub fn current_region(&self) -> Option<&TextureRegion> {
+        self.animation.frames.get(self.current_frame()).map(|frame| &frame.region)
+    }
+
+    /// Seconds since the start of the current cycle.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// True for the update after play was called.
+    pub fn just_started(&self) -> bool {
+        self.started
+    }
+
+    /// True for the update the animation reached its end, or completed a cycle when looping.
+    pub fn just_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn get_animation(&self) -> &Animation {
+        &self.animation
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use glmath::glmath::Vec2f;
+use image::GenericImageView;
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::lexer::JsonLexer;
+use serializers::json::parser::{parse_json, JsonNode};
+
+use crate::animation::Animation;
+use crate::resource_manager::ResourceDestroy;
+use crate::texture::{Texture, TextureError, TextureSampling, max_texture_size};
+use crate::texture::packing::{PackingAlgorithm, Rect};
+use crate::ui::NineSlice;
+
+/// Part of a texture, drawn by the sprite batch in place of the whole texture.
+/// UVs are normalized, with (0, 0) at the top left of the texture.
+#[derive(Clone)]
+pub struct TextureRegion {
+    pub texture: Texture,
+    pub uv_min: Vec2f,
+    pub uv_max: Vec2f
+}
+
+impl TextureRegion {
+    /// A region covering the whole texture.
+    pub fn full(texture: &Texture) -> Self {
+        TextureRegion {
+            texture: texture.clone(),
+            uv_min: Vec2f::new(0.0, 0.0),
+            uv_max: Vec2f::new(1.0, 1.0)
+        }
+    }
+
+    /// Returns the UVs of each corner of the -1..1 quad in res/meshes/quad.json, in its vertex order
+    /// (bottom left, top left, top right, bottom right), for meshes which carry their own texture coordinates.
+    pub fn quad_uvs(&self) -> Vec<f32> {
+        vec![
+            self.uv_min.x, self.uv_max.y,
+            self.uv_min.x, self.uv_min.y,
+            self.uv_max.x, self.uv_min.y,
+            self.uv_max.x, self.uv_max.y
+        ]
+    }
+}
+
+/// Errors from loading or building an atlas.
+#[derive(Debug)]
+pub enum AtlasError {
+    /// The description couldn't be read.
+    Io(std::io::Error),
+    /// The description isn't valid json.
+    Parse(String),
+    /// The json is valid but doesn't describe an atlas.
+    InvalidData(String),
+    Texture(TextureError),
+    DuplicateRegion(String),
+    RegionOutOfBounds { name: String, rect: Rect, width: u32, height: u32 },
+    /// The images can't be packed within the max texture size.
+    DoesNotFit { max_size: u32 }
+}
+
+impl Display for AtlasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AtlasError::Io(err) => write!(f, "Unable to read atlas: {}", err),
+            AtlasError::Parse(path) => write!(f, "Atlas file {} is not valid json", path),
+            AtlasError::InvalidData(reason) => write!(f, "Invalid atlas: {}", reason),
+            AtlasError::Texture(err) => write!(f, "Unable to load atlas image: {}", err),
+            AtlasError::DuplicateRegion(name) => write!(f, "Atlas region {} is defined more than once", name),
+            AtlasError::RegionOutOfBounds { name, rect, width, height } =>
+                write!(f, "Atlas region {} at {},{} sized {}x{} lies outside the {}x{} image", name, rect.x, rect.y, rect.width, rect.height, width, height),
+            AtlasError::DoesNotFit { max_size } => write!(f, "Atlas images don't fit in a {}x{} texture", max_size, max_size)
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<std::io::Error> for AtlasError {
+    fn from(err: std::io::Error) -> Self {
+        AtlasError::Io(err)
+    }
+}
+
+impl From<TextureError> for AtlasError {
+    fn from(err: TextureError) -> Self {
+        AtlasError::Texture(err)
+    }
+}
+
+impl From<image::ImageError> for AtlasError {
+    fn from(err: image::ImageError) -> Self {
+        AtlasError::Texture(TextureError::Image(err))
+    }
+}
+
+/// One texture holding many named images, so sprites drawn from it share a single texture bind.
+pub struct TextureAtlas {
+    texture: Texture,
+    width: u32,
+    height: u32,
+    regions: HashMap<String, Rect>,
+    /// Animations declared next to the atlas, playing its regions.
+    animations: HashMap<String, Animation>,
+    /// Nine slices declared next to the atlas, cut from its regions.
+    nine_slices: HashMap<String, NineSlice>
+}
+
+impl TextureAtlas {
+    /// Wraps a texture of the given size holding the regions.
+    pub(crate) fn new(texture: Texture, width: u32, height: u32, regions: HashMap<String, Rect>) -> Self {
+        TextureAtlas {
+            texture,
+            width,
+            height,
+            regions,
+            animations: HashMap::<String, Animation>::new(),
+            nine_slices: HashMap::<String, NineSlice>::new()
+        }
+    }
+
+    /// Loads an atlas from a json description of the form
+    /// { "image": "sprites.png", "regions": [{ "name": "head", "x": 0, "y": 0, "width": 16, "height": 16 }, ...] }
+    /// The image path is relative to the description. Regions are in pixels from the top left of the image.
+    pub fn open(description_path: &str, srgb: bool, sampling: TextureSampling) -> Result<Self, AtlasError> {
+        let mut lexer = JsonLexer::new(description_path)?;
+        let node = parse_json(&mut lexer).ok_or_else(|| AtlasError::Parse(description_path.to_string()))?;
+
+        let image_file = node.pointer("image").and_then(String::from_json)
+            .ok_or_else(|| AtlasError::InvalidData("expected an \"image\" path".to_string()))?;
+        let image_path = std::path::Path::new(description_path).with_file_name(image_file);
+
+        let img = image::open(image_path)?;
+        let (width, height) = img.dimensions();
+        let regions = parse_atlas_regions(&node, width, height)?;
+
+        Ok(TextureAtlas::new(Texture::from_image(img, srgb, sampling)?, width, height, regions))
+    }
+
+    /// Builds an atlas at runtime by packing individual images, so sprites can be iterated on
+    /// without regenerating an atlas image. Regions are named after their image.
+    /// Padding is the number of empty pixels left between images, to keep filtering from bleeding neighbours in.
+    pub fn from_images(images: Vec<(String, image::DynamicImage)>, padding: u32, srgb: bool, sampling: TextureSampling) -> Result<Self, AtlasError> {
+        images.into_iter()
+            .fold(TextureAtlasBuilder::new().with_padding(padding), |builder, (name, img)| builder.with_image(&name, img))
+            .build(srgb, sampling)
+    }
+
+    /// Loads and packs image files, see `from_images`.
+    pub fn from_files(files: &[(&str, &str)], padding: u32, srgb: bool, sampling: TextureSampling) -> Result<Self, AtlasError> {
+        let mut images = Vec::<(String, image::DynamicImage)>::new();
+
+        for (name, path) in files {
+            images.push((name.to_string(), image::open(path)?));
+        }
+
+        Self::from_images(images, padding, srgb, sampling)
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Size of the atlas image in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixel_rect(&self, name: &str) -> Option<Rect> {
+        self.regions.get(name).copied()
+    }
+
+    /// Returns a handle to the named region, which can be kept and drawn without the atlas.
+    pub fn region(&self, name: &str) -> Option<TextureRegion> {
+        self.pixel_rect(name).map(|rect| self.region_at(rect))
+    }
+
+    /// Returns a handle to any rectangle of the atlas, ex: a frame of a sprite sheet.
+    pub fn region_at(&self, rect: Rect) -> TextureRegion {
+        TextureRegion {
+            texture: self.texture.clone(),
+            uv_min: Vec2f::new(rect.x as f32 / self.width as f32, rect.y as f32 / self.height as f32),
+            uv_max: Vec2f::new((rect.x + rect.width) as f32 / self.width as f32, (rect.y + rect.height) as f32 / self.height as f32)
+        }
+    }
+
+    pub fn region_names(&self) -> impl Iterator<Item = &String> {
+        self.regions.keys()
+    }
+
+    pub fn add_animation(&mut self, name: &str, animation: Animation) {
+        self.animations.insert(name.to_string(), animation);
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+
+    pub fn add_nine_slice(&mut self, name: &str, nine_slice: NineSlice) {
+        self.nine_slices.insert(name.to_string(), nine_slice);
+    }
+
+    pub fn nine_slice(&self, name: &str) -> Option<&NineSlice> {
+        self.nine_slices.get(name)
+    }
+}
+
+/// The texture is freed when the last region handle is dropped.
+impl ResourceDestroy for TextureAtlas {}
+
+/// Collects individual images and packs them into an atlas.
+/// ex: TextureAtlasBuilder::new().with_padding(2).with_image("head", head).with_image("body", body).build(false, sampling)
+pub struct TextureAtlasBuilder {
+    images: Vec<(String, image::DynamicImage)>,
+    padding: u32,
+    algorithm: PackingAlgorithm
+}
+
+impl Default for TextureAtlasBuilder {
+    fn default() -> Self {
+        TextureAtlasBuilder {
+            images: Vec::new(),
+            padding: 1,
+            algorithm: PackingAlgorithm::default()
+        }
+    }
+}
+
+impl TextureAtlasBuilder {
+    /// Creates a builder leaving one pixel between images, packed with the guillotine packer.
+    pub fn new() -> Self {
+        TextureAtlasBuilder::default()
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_algorithm(mut self, algorithm: PackingAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_image(mut self, name: &str, img: image::DynamicImage) -> Self {
+        self.add_image(name, img);
+        self
+    }
+
+    pub fn add_image(&mut self, name: &str, img: image::DynamicImage) {
+        self.images.push((name.to_string(), img));
+    }
+
+    /// Packs the images into one texture, stored as sRGB when srgb is set.
+    pub fn build(self, srgb: bool, sampling: TextureSampling) -> Result<TextureAtlas, AtlasError> {
+        let mut names = HashSet::<&str>::new();
+
+        if let Some((duplicate, _)) = self.images.iter().find(|(name, _)| !names.insert(name.as_str())) {
+            return Err(AtlasError::DuplicateRegion(duplicate.clone()));
+        }
+
+        let sizes: Vec<(u32, u32)> = self.images.iter().map(|(_, img)| img.dimensions()).collect();
+        let max_size = max_texture_size();
+        let (positions, width, height) = pack_rects(&sizes, self.padding, max_size, self.algorithm)
+            .ok_or(AtlasError::DoesNotFit { max_size })?;
+
+        let mut atlas_image = image::RgbaImage::new(width, height);
+        let mut regions = HashMap::<String, Rect>::new();
+
+        for ((name, img), (x, y)) in self.images.into_iter().zip(positions) {
+            image::imageops::replace(&mut atlas_image, &img.to_rgba8(), x as i64, y as i64);
+            regions.insert(name, Rect::new(x, y, img.width(), img.height()));
+        }
+
+        let texture = Texture::from_image(image::DynamicImage::ImageRgba8(atlas_image), srgb, sampling)?;
+
+        Ok(TextureAtlas::new(texture, width, height, regions))
+    }
+}
+
+/// Reads the "regions" array of an atlas description, checking that names are unique and
+/// every region lies within an image of the given size.
+pub(crate) fn parse_atlas_regions(node: &JsonNode, width: u32, height: u32) -> Result<HashMap<String, Rect>, AtlasError> {
+    let regions_array = match node.pointer("regions") {
+        Some(JsonNode::Array(regions_array)) => regions_array,
+        _ => return Err(AtlasError::InvalidData("expected a \"regions\" array".to_string()))
+    };
+
+    let mut regions = HashMap::<String, Rect>::new();
+
+    for region in regions_array.iter() {
+        let name = region.pointer("name").and_then(String::from_json)
+            .ok_or_else(|| AtlasError::InvalidData("expected every region to have a \"name\"".to_string()))?;
+
+        let field = |field: &str| region.pointer(field).and_then(u32::from_json)
+            .ok_or_else(|| AtlasError::InvalidData(format!("expected region {} to have a whole number \"{}\"", name, field)));
+
+        let rect = Rect::new(field("x")?, field("y")?, field("width")?, field("height")?);
+
+        if rect.width == 0 || rect.height == 0 || rect.x as u64 + rect.width as u64 > width as u64 || rect.y as u64 + rect.height as u64 > height as u64 {
+            return Err(AtlasError::RegionOutOfBounds { name, rect, width, height });
+        }
+
+        if regions.insert(name.clone(), rect).is_some() {
+            return Err(AtlasError::DuplicateRegion(name));
+        }
+    }
+
+    Ok(regions)
+}
+
+/// The position of each packed rectangle, followed by the width and height of the atlas.
+pub(crate) type Packing = (Vec<(u32, u32)>, u32, u32);
+
+/// Packs rectangles, largest first, into the smallest square power of two bin, no larger than max_size,
+/// which holds them all. Returns the top left corner of each rectangle, in the order given, and the
+/// atlas size, trimmed to the rows used.
+pub(crate) fn pack_rects(sizes: &[(u32, u32)], padding: u32, max_size: u32, algorithm: PackingAlgorithm) -> Option<Packing> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse((sizes[*index].1, sizes[*index].0)));
+
+    let padded = |size: u32| size + padding;
+    let area: u64 = sizes.iter().map(|(width, height)| padded(*width) as u64 * padded(*height) as u64).sum();
+    let largest = sizes.iter().map(|(width, height)| padded(*width).max(padded(*height))).max().unwrap_or(1);
+
+    let mut atlas_size = ((area as f64).sqrt().ceil() as u32).max(largest).max(1).next_power_of_two();
+
+    while atlas_size <= max_size {
+        let mut packer = algorithm.packer(atlas_size, atlas_size);
+        let mut positions = vec![(0, 0); sizes.len()];
+        let mut used_height = 1;
+
+        let packed = order.iter().all(|index| {
+            let (width, height) = (padded(sizes[*index].0), padded(sizes[*index].1));
+
+            match packer.pack(width, height) {
+                Some(rect) => {
+                    positions[*index] = (rect.x, rect.y);
+                    used_height = used_height.max(rect.y + height);
+                    true
+                },
+                None => false
+            }
+        });
+
+        if packed {
+            return Some((positions, atlas_size, used_height));
+        }
+
+        atlas_size *= 2;
+    }
+
+    None
+}
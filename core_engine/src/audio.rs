@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use crate::resource_manager::ResourceDestroy;
+
+/// A loaded audio clip. The engine doesn't play audio itself, the handle refers to a buffer
+/// owned by whichever audio backend the game wires in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    handle: u64,
+    path: String
+}
+
+impl Sound {
+    /// Wraps a buffer the audio backend loaded from the given path.
+    pub fn new(handle: u64, path: &str) -> Self {
+        Sound {
+            handle,
+            path: path.to_string()
+        }
+    }
+
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// The backend owns the buffer, so there's nothing to free here.
+impl ResourceDestroy for Sound {}
+
+/// Sounds are shared with the events playing them.
+impl ResourceDestroy for Arc<Sound> {}
+
+/// Requests pipelines send to the audio backend each frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioEvent {
+    /// Plays the sound once, volume is 0..1.
+    Play { sound: Arc<Sound>, volume: f32 },
+    StopAll
+}
+
+/// Receives the audio events of every frame. Implemented by the game on top of an audio library.
+pub trait AudioBackend {
+    fn handle_events(&mut self, events: Vec<AudioEvent>);
+}
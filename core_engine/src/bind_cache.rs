@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ogl33::*;
+
+use crate::gl_debug;
+
+/// How many binds of one kind of object were asked for, and how many reached GL.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BindCounter {
+    pub requested: u32,
+    pub issued: u32
+}
+
+impl BindCounter {
+    /// Returns the binds skipped because the object was already bound.
+    pub fn skipped(&self) -> u32 {
+        self.requested - self.issued
+    }
+
+    fn count(&mut self, issued: bool) -> bool {
+        self.requested += 1;
+        self.issued += issued as u32;
+        issued
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BindStats {
+    pub programs: BindCounter,
+    pub textures: BindCounter,
+    pub vertex_arrays: BindCounter,
+    /// Meshes drawn, counting an instanced draw once.
+    pub draw_calls: u32,
+    /// Uniforms loaded through ShaderProgram.
+    pub uniform_calls: u32
+}
+
+/// The objects bound to the GL context as far as the engine knows. None means unknown, so the next
+/// bind is always issued.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct BindState {
+    program: Option<GLuint>,
+    vertex_array: Option<GLuint>,
+    active_unit: Option<u32>,
+    /// The 2D texture bound to each unit.
+    textures: HashMap<u32, GLuint>,
+    stats: BindStats
+}
+
+impl BindState {
+    /// Records a glUseProgram, returning whether it needs to be issued.
+    pub(crate) fn use_program(&mut self, program: GLuint) -> bool {
+        let issue = self.program != Some(program);
+        self.program = Some(program);
+
+        self.stats.programs.count(issue)
+    }
+
+    /// Records a glBindVertexArray, returning whether it needs to be issued.
+    pub(crate) fn bind_vertex_array(&mut self, vertex_array: GLuint) -> bool {
+        let issue = self.vertex_array != Some(vertex_array);
+        self.vertex_array = Some(vertex_array);
+
+        self.stats.vertex_arrays.count(issue)
+    }
+
+    /// Records a glActiveTexture, returning whether it needs to be issued. Not counted as a bind.
+    pub(crate) fn active_texture(&mut self, unit: u32) -> bool {
+        let issue = self.active_unit != Some(unit);
+        self.active_unit = Some(unit);
+
+        issue
+    }
+
+    /// Records binding a 2D texture to the active unit, returning whether it needs to be issued.
+    pub(crate) fn bind_texture(&mut self, texture: GLuint) -> bool {
+        let issue = match self.active_unit {
+            Some(unit) => self.textures.insert(unit, texture) != Some(texture),
+            None => true
+        };
+
+        self.stats.textures.count(issue)
+    }
+
+    pub(crate) fn count_draw_call(&mut self) {
+        self.stats.draw_calls += 1;
+    }
+
+    pub(crate) fn count_uniform_call(&mut self) {
+        self.stats.uniform_calls += 1;
+    }
+
+    /// Forgets a deleted program. A program in use isn't deleted until it's replaced, so its
+    /// binding becomes unknown rather than zero.
+    pub(crate) fn forget_program(&mut self, program: GLuint) {
+        if self.program == Some(program) {
+            self.program = None;
+        }
+    }
+
+    /// Deleting a bound vertex array binds zero in its place.
+    pub(crate) fn forget_vertex_array(&mut self, vertex_array: GLuint) {
+        if self.vertex_array == Some(vertex_array) {
+            self.vertex_array = Some(0);
+        }
+    }
+
+    /// Deleting a bound texture binds zero in its place, on every unit it was bound to.
+    pub(crate) fn forget_texture(&mut self, texture: GLuint) {
+        for bound in self.textures.values_mut().filter(|bound| **bound == texture) {
+            *bound = 0;
+        }
+    }
+
+    /// Forgets every binding, keeping the counters.
+    pub(crate) fn invalidate(&mut self) {
+        *self = BindState { stats: self.stats, ..BindState::default() };
+    }
+
+    pub(crate) fn stats(&self) -> BindStats {
+        self.stats
+    }
+
+    pub(crate) fn reset_stats(&mut self) {
+        self.stats = BindStats::default();
+    }
+}
+
+thread_local! {
+    /// Objects are bound on the thread owning the GL context, so the state is kept per thread.
+    static BIND_STATE: RefCell<BindState> = RefCell::new(BindState::default());
+}
+
+fn with_state<T>(f: impl FnOnce(&mut BindState) -> T) -> T {
+    BIND_STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Forgets what's bound, so the next binds are issued. Call this after binding programs, textures,
+/// or vertex arrays with GL directly, since the engine skips binding objects it thinks are already bound.
+pub fn invalidate() {
+    with_state(|state| state.invalidate());
+}
+
+/// Returns the binds requested and issued since the counters were last reset.
+/// The engine resets them at the start of every frame, see `GameManager::bind_stats`.
+pub fn stats() -> BindStats {
+    with_state(|state| state.stats())
+}
+
+pub fn reset_stats() {
+    with_state(|state| state.reset_stats());
+}
+
+#[track_caller]
+pub(crate) fn use_program(program: GLuint) {
+    if with_state(|state| state.use_program(program)) {
+        unsafe {
+            glUseProgram(program);
+        }
+
+        gl_debug::check_errors("glUseProgram");
+    }
+}
+
+#[track_caller]
+pub(crate) fn bind_vertex_array(vertex_array: GLuint) {
+    if with_state(|state| state.bind_vertex_array(vertex_array)) {
+        unsafe {
+            glBindVertexArray(vertex_array);
+        }
+
+        gl_debug::check_errors("glBindVertexArray");
+    }
+}
+
+/// Selects the texture unit later binds apply to, for any texture target.
+#[track_caller]
+pub(crate) fn active_texture(unit: u32) {
+    if with_state(|state| state.active_texture(unit)) {
+        unsafe {
+            glActiveTexture(GL_TEXTURE0 + unit);
+        }
+
+        gl_debug::check_errors("glActiveTexture");
+    }
+}
+
+/// Binds a 2D texture to the active unit.
+#[track_caller]
+pub(crate) fn bind_texture_2d(texture: GLuint) {
+    if with_state(|state| state.bind_texture(texture)) {
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, texture);
+        }
+
+        gl_debug::check_errors("glBindTexture");
+    }
+}
+
+pub(crate) fn count_draw_call() {
+    with_state(|state| state.count_draw_call());
+}
+
+pub(crate) fn count_uniform_call() {
+    with_state(|state| state.count_uniform_call());
+}
+
+pub(crate) fn forget_program(program: GLuint) {
+    with_state(|state| state.forget_program(program));
+}
+
+pub(crate) fn forget_vertex_array(vertex_array: GLuint) {
+    with_state(|state| state.forget_vertex_array(vertex_array));
+}
+
+pub(crate) fn forget_texture(texture: GLuint) {
+    with_state(|state| state.forget_texture(texture));
+}
@@ -0,0 +1,171 @@
+use glmath::glmath::{Vec2f, Vec3f, Vec4f, Mat44f, StandardMat, StandardMat44};
+
+/// Where the origin of world space is and which way its axes point, see `Camera2D::set_coordinate_system`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CoordSystem {
+    /// The origin is the center of the viewport with y up. The viewport is -1..1 vertically and
+    /// as wide as its aspect ratio needs, see `Camera2D::aspect_projection`.
+    #[default]
+    CenterNdc,
+    /// The origin is the top left corner of the viewport with y down, and one unit is one pixel,
+    /// so the bottom right corner is (width, height). Rotation turns clockwise on screen.
+    TopLeftPixels
+}
+
+/// A 2D camera which moves, zooms, and rotates the view of the world.
+/// The view projection matrix maps world coordinates into normalized device coordinates,
+/// so with the default camera world space matches NDC.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera2D {
+    /// The point in the world at the center of the screen.
+    pub position: Vec2f,
+    /// Values above 1 zoom in, values below 1 zoom out.
+    pub zoom: f32,
+    /// Counter clockwise rotation of the camera in radians.
+    pub rotation: f32,
+    /// How quickly `follow` catches up with its target. Higher values are stiffer.
+    pub follow_stiffness: f32,
+
+    projection: Mat44f,
+    coord_system: CoordSystem,
+    viewport_size: Vec2f,
+    /// Top left corner of the viewport in window pixels.
+    viewport_offset: Vec2f
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Camera2D::new()
+    }
+}
+
+impl Camera2D {
+    pub fn new() -> Self {
+        Camera2D {
+            position: Vec2f::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+            follow_stiffness: 5.0,
+            projection: Mat44f::IDENTITY,
+            coord_system: CoordSystem::CenterNdc,
+            viewport_size: Vec2f::new(1.0, 1.0),
+            viewport_offset: Vec2f::ZERO
+        }
+    }
+
+    /// Returns an orthographic projection showing -1..1 vertically, with the horizontal extent
+    /// widened or narrowed to match the aspect ratio so world units stay square on any window shape.
+    pub fn aspect_projection(width: f32, height: f32) -> Mat44f {
+        let aspect = width / f32::max(height, 1.0);
+        Mat44f::ortho(-aspect, aspect, -1.0, 1.0, -1.0, 1.0)
+    }
+
+    /// Returns an orthographic projection mapping (0, 0) to the top left corner and (width, height)
+    /// to the bottom right corner.
+    pub fn top_left_pixel_projection(width: f32, height: f32) -> Mat44f {
+        Mat44f::ortho(0.0, width, height, 0.0, -1.0, 1.0)
+    }
+
+    /// Returns the projection of the coordinate system for a viewport of the given size.
+    pub fn coordinate_projection(coord_system: CoordSystem, width: f32, height: f32) -> Mat44f {
+        match coord_system {
+            CoordSystem::CenterNdc => Camera2D::aspect_projection(width, height),
+            CoordSystem::TopLeftPixels => Camera2D::top_left_pixel_projection(width, height)
+        }
+    }
+
+    /// Sets where the origin is and which way y points, replacing the projection with the coordinate
+    /// system's for the current viewport. Kept through resizes.
+    pub fn set_coordinate_system(&mut self, coord_system: CoordSystem) {
+        self.coord_system = coord_system;
+        self.projection = Camera2D::coordinate_projection(coord_system, self.viewport_size.x, self.viewport_size.y);
+    }
+
+    pub fn get_coordinate_system(&self) -> CoordSystem {
+        self.coord_system
+    }
+
+    /// Sets the viewport size and replaces the projection with the coordinate system's projection for that size.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.fit_viewport(0.0, 0.0, width, height);
+    }
+
+    /// Like `resize`, for a camera drawing into part of the window.
+    /// The position is the top left corner of the viewport in window pixels (y down).
+    pub fn fit_viewport(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.viewport_offset = Vec2f::new(x, y);
+        self.set_viewport_size(width, height);
+        self.projection = Camera2D::coordinate_projection(self.coord_system, width, height);
+    }
+
+    /// Sets the projection applied after the view transform.
+    pub fn set_projection(&mut self, projection: Mat44f) {
+        self.projection = projection;
+    }
+
+    pub fn get_projection(&self) -> Mat44f {
+        self.projection
+    }
+
+    /// Sets the size of the window in pixels, used when converting screen coordinates.
+    pub fn set_viewport_size(&mut self, width: f32, height: f32) {
+        self.viewport_size = Vec2f::new(width, height);
+    }
+
+    pub fn get_viewport_size(&self) -> Vec2f {
+        self.viewport_size
+    }
+
+    pub fn get_viewport_offset(&self) -> Vec2f {
+        self.viewport_offset
+    }
+
+    /// Returns the matrix transforming world coordinates into camera space.
+    pub fn view_matrix(&self) -> Mat44f {
+        let mut view = Mat44f::IDENTITY;
+        view.scale(Vec3f::new(self.zoom, self.zoom, 1.0));
+        view.rotate(Vec3f::Z, -self.rotation);
+        view.translate(Vec3f::new(-self.position.x, -self.position.y, 0.0));
+        view
+    }
+
+    /// Returns the projection multiplied by the view matrix.
+    pub fn view_projection(&self) -> Mat44f {
+        self.projection * self.view_matrix()
+    }
+
+    /// Converts a point in window pixels (top left origin, y down) into world coordinates.
+    pub fn screen_to_world(&self, screen: Vec2f) -> Vec2f {
+        let ndc = self.screen_to_ndc(screen);
+        let ndc = Vec4f::new(ndc.x, ndc.y, 0.0, 1.0);
+
+        let world = self.view_projection().get_inverted() * ndc;
+        Vec2f::new(world.x / world.w, world.y / world.w)
+    }
+
+    /// Converts a point in window pixels (top left origin, y down) into normalized device coordinates,
+    /// where the viewport spans -1 to 1 with y up.
+    pub fn screen_to_ndc(&self, screen: Vec2f) -> Vec2f {
+        let screen = screen - self.viewport_offset;
+        Vec2f::new(
+            screen.x / self.viewport_size.x * 2.0 - 1.0,
+            1.0 - screen.y / self.viewport_size.y * 2.0)
+    }
+
+    /// Converts a point in world coordinates into window pixels (top left origin, y down).
+    pub fn world_to_screen(&self, world: Vec2f) -> Vec2f {
+        let clip = self.view_projection() * Vec4f::new(world.x, world.y, 0.0, 1.0);
+        let ndc = Vec2f::new(clip.x / clip.w, clip.y / clip.w);
+
+        Vec2f::new(
+            (ndc.x + 1.0) * 0.5 * self.viewport_size.x,
+            (1.0 - ndc.y) * 0.5 * self.viewport_size.y) + self.viewport_offset
+    }
+
+    /// Moves the camera toward the target. The blend is frame rate independent, so the camera
+    /// covers the same fraction of the distance per second regardless of how often this is called.
+    pub fn follow(&mut self, target: Vec2f, delta_time: f32) {
+        let blend = 1.0 - f32::exp(-self.follow_stiffness * delta_time);
+        self.position += (target - self.position) * blend;
+    }
+}
@@ -0,0 +1,113 @@
+use glmath::glmath::Vec4f;
+use window::window::WindowClearColor;
+
+/// Converts an sRGB encoded channel, as picked in an art program, to linear light.
+pub fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    }
+    else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear channel back to its sRGB encoding.
+pub fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    }
+    else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// An RGBA color with channels in the 0..1 range.
+///
+/// Whether the channels are sRGB encoded or linear depends on where the color came from. Colors picked in
+/// an art program or written in the config are sRGB. When the window is sRGB, shader math and blending
+/// happen in linear space, so colors passed to shaders and the clear color should be converted with `to_linear`.
+/// Alpha is never converted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::BLACK
+    }
+}
+
+impl Color {
+    pub const BLACK: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+    pub const WHITE: Color = Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+    pub const TRANSPARENT: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 };
+
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Creates an opaque color.
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    /// Parses a "#RRGGBB" or "#RRGGBBAA" hex color, the '#' is optional. The result is still sRGB encoded.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+
+        if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |index: usize| {
+            u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok().map(|value| value as f32 / 255.0)
+        };
+
+        Some(Color {
+            r: channel(0)?,
+            g: channel(1)?,
+            b: channel(2)?,
+            a: if hex.len() == 8 { channel(3)? } else { 1.0 }
+        })
+    }
+
+    /// Converts the color to 8 bit channels, clamping each to 0..1 first.
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        [channel(self.r), channel(self.g), channel(self.b), channel(self.a)]
+    }
+
+    /// Converts an sRGB encoded color to linear.
+    pub fn to_linear(&self) -> Self {
+        Color::new(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a)
+    }
+
+    /// Converts a linear color to sRGB.
+    pub fn to_srgb(&self) -> Self {
+        Color::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a)
+    }
+}
+
+/// For loading colors into vec4 uniforms.
+impl From<Color> for Vec4f {
+    fn from(color: Color) -> Self {
+        Vec4f::new(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl From<WindowClearColor> for Color {
+    fn from(color: WindowClearColor) -> Self {
+        Color::new(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl From<Color> for WindowClearColor {
+    fn from(color: Color) -> Self {
+        WindowClearColor { r: color.r, g: color.g, b: color.b, a: color.a }
+    }
+}
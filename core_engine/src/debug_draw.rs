@@ -0,0 +1,301 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glmath::glmath::{Vec2f, Mat44f};
+use window::window::MouseKeyboardInputControl;
+
+use crate::camera::Camera2D;
+use crate::color::Color;
+use crate::mesh::{Mesh2D, DrawableMesh, PrimitiveMode};
+use crate::render_pipeline::RenderPipelineHandler;
+use crate::render_state::{BlendMode, RenderState};
+use crate::shader_program::{ShaderProgram, UniformSet};
+
+/// Number of lines a debug circle is drawn with.
+pub const CIRCLE_SEGMENTS: u32 = 32;
+/// Grids finer than this many lines across the view are skipped rather than drawn as a solid block.
+pub const MAX_GRID_LINES: usize = 512;
+
+const DEBUG_DRAW_VERTEX_SHADER: &str = "#version 130
+in vec2 position;
+in vec4 vertexColor;
+
+uniform mat4 viewProjection;
+
+out vec4 lineColor;
+
+void main() {
+    gl_Position = viewProjection * vec4(position, 0, 1);
+    lineColor = vertexColor;
+}";
+
+const DEBUG_DRAW_FRAGMENT_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec4 lineColor;
+
+void main() {
+    color = lineColor;
+}";
+
+/// A grid queued for the frame. Its lines are laid out over whatever the camera sees when it's drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DebugGrid {
+    origin: Vec2f,
+    spacing: f32,
+    color: Color
+}
+
+/// The lines queued for the current frame, in world coordinates.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct DebugShapes {
+    /// Two points of x and y per line.
+    pub(crate) positions: Vec<f32>,
+    /// An rgba color per point.
+    pub(crate) colors: Vec<f32>,
+    grids: Vec<DebugGrid>
+}
+
+impl DebugShapes {
+    pub(crate) fn push_line(&mut self, p0: Vec2f, p1: Vec2f, color: Color) {
+        self.positions.extend_from_slice(&[p0.x, p0.y, p1.x, p1.y]);
+
+        for _ in 0..2 {
+            self.colors.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    pub(crate) fn line_count(&self) -> usize {
+        self.positions.len() / 4
+    }
+
+    /// Adds the lines of every queued grid which cross the area from min to max.
+    pub(crate) fn expand_grids(&mut self, min: Vec2f, max: Vec2f) {
+        for grid in std::mem::take(&mut self.grids) {
+            for (p0, p1) in grid_lines(grid.origin, grid.spacing, min, max) {
+                self.push_line(p0, p1, grid.color);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.positions.clear();
+        self.colors.clear();
+        self.grids.clear();
+    }
+}
+
+/// Returns the lines of a grid through the origin which cross the area from min to max, vertical lines first.
+/// Returns nothing when the spacing isn't positive or the grid would need more than MAX_GRID_LINES lines.
+pub fn grid_lines(origin: Vec2f, spacing: f32, min: Vec2f, max: Vec2f) -> Vec<(Vec2f, Vec2f)> {
+    if spacing <= 0.0 || !spacing.is_finite() {
+        return vec![];
+    }
+
+    // The index of the first and last line along an axis inside the area.
+    let line_range = |min: f32, max: f32, origin: f32| {
+        ((min - origin) / spacing).ceil() as i64..=((max - origin) / spacing).floor() as i64
+    };
+
+    let columns = line_range(min.x, max.x, origin.x);
+    let rows = line_range(min.y, max.y, origin.y);
+
+    if columns.clone().count() + rows.clone().count() > MAX_GRID_LINES {
+        return vec![];
+    }
+
+    let vertical = columns.map(|column| {
+        let x = origin.x + column as f32 * spacing;
+        (Vec2f::new(x, min.y), Vec2f::new(x, max.y))
+    });
+
+    let horizontal = rows.map(|row| {
+        let y = origin.y + row as f32 * spacing;
+        (Vec2f::new(min.x, y), Vec2f::new(max.x, y))
+    });
+
+    vertical.chain(horizontal).collect()
+}
+
+/// Queues lines, rectangles, circles, and grids to be drawn over the frame in world coordinates, for
+/// debugging collision, paths, and layouts. Shapes are drawn once, after every pipeline, then cleared.
+///
+/// Handles are cheap to clone and all refer to the engine's queue, so pipelines keep one from
+/// `GameManager::debug_draw` and draw from update or execute. When debug drawing is turned off the
+/// handle is empty, and every call returns before doing any work.
+#[derive(Debug, Default, Clone)]
+pub struct DebugDraw {
+    pub(crate) shapes: Option<Rc<RefCell<DebugShapes>>>
+}
+
+impl DebugDraw {
+    /// A handle whose calls do nothing.
+    pub fn disabled() -> Self {
+        DebugDraw { shapes: None }
+    }
+
+    pub(crate) fn enabled() -> Self {
+        DebugDraw { shapes: Some(Rc::new(RefCell::new(DebugShapes::default()))) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.shapes.is_some()
+    }
+
+    pub fn line(&self, p0: Vec2f, p1: Vec2f, color: Color) {
+        self.with_shapes(|shapes| shapes.push_line(p0, p1, color));
+    }
+
+    /// Outlines the axis aligned rectangle between the corners.
+    pub fn rect(&self, min: Vec2f, max: Vec2f, color: Color) {
+        self.with_shapes(|shapes| {
+            let corners = [min, Vec2f::new(max.x, min.y), max, Vec2f::new(min.x, max.y)];
+
+            for (index, corner) in corners.iter().enumerate() {
+                shapes.push_line(*corner, corners[(index + 1) % corners.len()], color);
+            }
+        });
+    }
+
+    /// Outlines the circle with CIRCLE_SEGMENTS lines.
+    pub fn circle(&self, center: Vec2f, radius: f32, color: Color) {
+        self.with_shapes(|shapes| {
+            let point = |segment: u32| {
+                let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                center + Vec2f::new(angle.cos(), angle.sin()) * radius
+            };
+
+            for segment in 0..CIRCLE_SEGMENTS {
+                shapes.push_line(point(segment), point(segment + 1), color);
+            }
+        });
+    }
+
+    /// Draws lines every `spacing` units across the whole view, through the world origin.
+    pub fn grid(&self, spacing: f32, color: Color) {
+        self.grid_at(Vec2f::ZERO, spacing, color);
+    }
+
+    /// Draws lines every `spacing` units across the whole view, through the origin, ex: offset by half
+    /// a tile to outline tiles centered on the grid points.
+    pub fn grid_at(&self, origin: Vec2f, spacing: f32, color: Color) {
+        self.with_shapes(|shapes| shapes.grids.push(DebugGrid { origin, spacing, color }));
+    }
+
+    #[inline]
+    fn with_shapes(&self, queue: impl FnOnce(&mut DebugShapes)) {
+        if let Some(shapes) = &self.shapes {
+            queue(&mut shapes.borrow_mut());
+        }
+    }
+}
+
+/// Draws the shapes queued through a DebugDraw handle. Owned and drawn by the GameManager, after every
+/// other pipeline and before the debug overlay, see `GameManager::debug_draw`.
+pub struct DebugDrawPipeline {
+    queue: DebugDraw,
+    shader: ShaderProgram,
+    mesh: Mesh2D,
+    position_buffer: usize,
+    color_buffer: usize,
+    view_projection: Mat44f,
+    line_count: usize
+}
+
+impl DebugDrawPipeline {
+    /// Creates the pipeline drawing the shapes queued through the handle.
+    pub fn new(queue: DebugDraw) -> Self {
+        let shader = ShaderProgram::from_source(DEBUG_DRAW_VERTEX_SHADER, DEBUG_DRAW_FRAGMENT_SHADER);
+
+        // The shader leaves its attribute locations to the linker.
+        let mut mesh = Mesh2D::new();
+        mesh.set_primitive_mode(PrimitiveMode::Lines);
+        let position_buffer = mesh.add_dynamic_float_buffer(shader.query_attribute_location("position").unwrap_or(0), 2);
+        let color_buffer = mesh.add_dynamic_float_buffer(shader.query_attribute_location("vertexColor").unwrap_or(1), 4);
+
+        DebugDrawPipeline {
+            queue,
+            shader,
+            mesh,
+            position_buffer,
+            color_buffer,
+            view_projection: Mat44f::IDENTITY,
+            line_count: 0
+        }
+    }
+
+    /// Uploads the shapes queued since the last flush, laying grids out over what the camera sees,
+    /// and empties the queue for the next frame.
+    pub fn flush(&mut self, camera: &Camera2D) {
+        let shapes = match &self.queue.shapes {
+            Some(shapes) => shapes.clone(),
+            None => return
+        };
+        let mut shapes = shapes.borrow_mut();
+
+        let (min, max) = visible_bounds(camera);
+        shapes.expand_grids(min, max);
+
+        // Nothing queued in this frame or the last, so there's nothing to upload.
+        if shapes.line_count() > 0 || self.line_count > 0 {
+            self.mesh.update_float_buffer(self.position_buffer, &shapes.positions);
+            self.mesh.update_float_buffer(self.color_buffer, &shapes.colors);
+        }
+
+        self.line_count = shapes.line_count();
+        shapes.clear();
+    }
+
+    /// Returns a handle queueing shapes for this pipeline.
+    pub fn handle(&self) -> DebugDraw {
+        self.queue.clone()
+    }
+
+    /// Returns the number of lines uploaded by the last flush.
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+}
+
+/// Returns the corners of the axis aligned box around everything the camera sees, in world coordinates.
+fn visible_bounds(camera: &Camera2D) -> (Vec2f, Vec2f) {
+    let offset = camera.get_viewport_offset();
+    let size = camera.get_viewport_size();
+    let corners = [offset, offset + Vec2f::new(size.x, 0.0), offset + size, offset + Vec2f::new(0.0, size.y)]
+        .map(|corner| camera.screen_to_world(corner));
+
+    corners.iter().fold((corners[0], corners[0]), |(min, max), corner| (
+        Vec2f::new(min.x.min(corner.x), min.y.min(corner.y)),
+        Vec2f::new(max.x.max(corner.x), max.y.max(corner.y))
+    ))
+}
+
+impl RenderPipelineHandler for DebugDrawPipeline {
+    fn init(&mut self) {}
+
+    fn prepare(&self) {
+        self.shader.bind();
+        self.shader.load_uniforms(UniformSet::new().set_mat4("viewProjection", self.view_projection));
+    }
+
+    fn update(&mut self, _input: &Box<dyn MouseKeyboardInputControl>) {}
+
+    fn execute(&self) {
+        if self.line_count > 0 {
+            self.mesh.render();
+        }
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.view_projection = camera.view_projection();
+    }
+
+    /// Drawn after every pipeline but the debug overlay.
+    fn priority(&self) -> i32 {
+        i32::MAX - 1
+    }
+
+    fn render_state(&self) -> RenderState {
+        RenderState::with_blend(BlendMode::Alpha)
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt::Write;
+
+use glmath::glmath::{Vec2f, Vec4f, Mat44f};
+use window::Key;
+use window::window::MouseKeyboardInputControl;
+
+use crate::bind_cache::BindStats;
+use crate::camera::Camera2D;
+use crate::color::Color;
+use crate::font::{Font, GlyphQuad};
+use crate::frame_time::FrameStats;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::render_pipeline::RenderPipelineHandler;
+use crate::shader_program::{ShaderProgram, UniformSet};
+use crate::texture::Texture;
+
+/// Number of frames shown in the frame time graph.
+pub const FRAME_HISTORY_LEN: usize = 120;
+
+/// Pixel size of the overlay's text.
+const TEXT_SIZE: u32 = 14;
+/// Distance of the panel from the corner of the window, in pixels.
+const MARGIN: f32 = 8.0;
+/// Space between the edge of the panel and its contents, in pixels.
+const PADDING: f32 = 6.0;
+const GRAPH_HEIGHT: f32 = 48.0;
+const BAR_WIDTH: f32 = 2.0;
+/// Frame time of a full height bar. Longer frames are clipped to the top of the graph.
+const GRAPH_MAX_MS: f32 = 50.0;
+/// Bars up to a 60 fps frame are green, up to a 30 fps frame yellow, and red beyond that.
+const GREEN_MAX_MS: f32 = 1000.0 / 60.0;
+const YELLOW_MAX_MS: f32 = 1000.0 / 30.0;
+
+const FULL_UV: Vec2f = Vec2f { x: 1.0, y: 1.0 };
+
+const PANEL_COLOR: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.6 };
+
+/// Positions quads in pixels from the bottom left of the window. The texture coordinates aren't
+/// flipped, unlike the GUI shader, since glyph atlases store their rows bottom up.
+const OVERLAY_VERTEX_SHADER: &str = "#version 130
+in vec2 position;
+
+uniform vec2 pos;
+uniform vec2 scale;
+uniform mat4 projection;
+uniform vec2 uvMin = vec2(0, 0);
+uniform vec2 uvMax = vec2(1, 1);
+
+out vec2 texCoord;
+
+void main() {
+    gl_Position = projection * vec4((position.x * scale.x) + pos.x, (position.y * scale.y) + pos.y, 0, 1);
+    texCoord = mix(uvMin, uvMax, position * .5 + .5);
+}";
+
+const OVERLAY_FRAGMENT_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D overlayTexture;
+uniform vec4 tint;
+
+void main() {
+    color = texture(overlayTexture, texCoord) * tint;
+}";
+
+/// The numbers shown by the overlay, gathered by the GameManager once per frame while it's visible.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DebugOverlayStats {
+    pub fps: f32,
+    pub frame: FrameStats,
+    pub binds: BindStats,
+    /// Registries loaded by each resource manager, by the manager's name.
+    pub resource_counts: Vec<(String, usize)>
+}
+
+/// The times of the last FRAME_HISTORY_LEN frames, in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FrameHistory {
+    times: [f32; FRAME_HISTORY_LEN],
+    /// Slot the next frame is written to, the oldest frame once the history is full.
+    next: usize,
+    len: usize
+}
+
+impl Default for FrameHistory {
+    fn default() -> Self {
+        FrameHistory {
+            times: [0.0; FRAME_HISTORY_LEN],
+            next: 0,
+            len: 0
+        }
+    }
+}
+
+impl FrameHistory {
+    pub(crate) fn push(&mut self, frame_ms: f32) {
+        self.times[self.next] = frame_ms;
+        self.next = (self.next + 1) % FRAME_HISTORY_LEN;
+        self.len = (self.len + 1).min(FRAME_HISTORY_LEN);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the frame times, oldest first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        let start = (self.next + FRAME_HISTORY_LEN - self.len) % FRAME_HISTORY_LEN;
+
+        (0..self.len).map(move |offset| self.times[(start + offset) % FRAME_HISTORY_LEN])
+    }
+}
+
+/// Returns the color of a frame time's bar in the graph.
+pub(crate) fn frame_time_color(frame_ms: f32) -> Color {
+    if frame_ms <= GREEN_MAX_MS {
+        Color { r: 0.2, g: 0.9, b: 0.3, a: 1.0 }
+    }
+    else if frame_ms <= YELLOW_MAX_MS {
+        Color { r: 0.95, g: 0.8, b: 0.2, a: 1.0 }
+    }
+    else {
+        Color { r: 0.95, g: 0.25, b: 0.2, a: 1.0 }
+    }
+}
+
+/// Returns the height in pixels of a frame time's bar in the graph.
+pub(crate) fn frame_time_bar_height(frame_ms: f32) -> f32 {
+    (frame_ms / GRAPH_MAX_MS).clamp(0.0, 1.0) * GRAPH_HEIGHT
+}
+
+/// Formats the overlay's text, one stat per line.
+pub(crate) fn overlay_text(stats: &DebugOverlayStats) -> String {
+    let mut text = String::new();
+
+    let _ = writeln!(text, "{:.0} fps  {:.2} ms", stats.fps, stats.frame.cpu_ms);
+
+    match stats.frame.gpu_ms {
+        Some(gpu_ms) => { let _ = writeln!(text, "gpu {:.2} ms", gpu_ms); },
+        None => { let _ = writeln!(text, "gpu n/a"); }
+    }
+
+    for (index, pipeline_ms) in stats.frame.pipeline_gpu_ms.iter().enumerate() {
+        if let Some(pipeline_ms) = pipeline_ms {
+            let _ = writeln!(text, "  pipeline {} {:.2} ms", index, pipeline_ms);
+        }
+    }
+
+    let _ = writeln!(text, "draws {}  uniforms {}", stats.binds.draw_calls, stats.binds.uniform_calls);
+    let _ = writeln!(text, "binds {}/{} programs {}/{} textures",
+        stats.binds.programs.issued, stats.binds.programs.requested,
+        stats.binds.textures.issued, stats.binds.textures.requested);
+
+    let counts: Vec<String> = stats.resource_counts.iter()
+        .map(|(name, count)| format!("{} {}", name.to_lowercase(), count))
+        .collect();
+
+    for line in counts.chunks(3) {
+        let _ = writeln!(text, "{}", line.join("  "));
+    }
+
+    text.trim_end().to_string()
+}
+
+/// Draws frame times, GPU times, draw stats, and resource counts over the top left corner of the window.
+///
+/// The GameManager creates one when "game/dev_mode" is set and draws it after everything else, including
+/// post processing. While hidden, frames only add their time to the graph's history, so the graph is
+/// already full when the overlay is shown. Text needs a font, without one only the graph is drawn.
+pub struct DebugOverlayPipeline {
+    shader: ShaderProgram,
+    mesh: Mesh2D,
+    white_texture: Texture,
+    font: Option<Font>,
+    toggle_key: Key,
+    toggle_key_down: bool,
+    visible: bool,
+    frame_times: FrameHistory,
+    /// The laid out stats text, relative to the first baseline.
+    text: Vec<GlyphQuad>,
+    text_width: f32,
+    text_height: f32,
+    ascent: f32,
+    screen_size: Vec2f
+}
+
+impl DebugOverlayPipeline {
+    pub fn new(font: Option<Font>, toggle_key: Key) -> Self {
+        DebugOverlayPipeline {
+            shader: ShaderProgram::from_source(OVERLAY_VERTEX_SHADER, OVERLAY_FRAGMENT_SHADER),
+            mesh: Mesh2D::generate_quad(),
+            white_texture: Texture::solid_color(Color::WHITE),
+            font,
+            toggle_key,
+            toggle_key_down: false,
+            visible: false,
+            frame_times: FrameHistory::default(),
+            text: Vec::<GlyphQuad>::default(),
+            text_width: 0.0,
+            text_height: 0.0,
+            ascent: 0.0,
+            screen_size: Vec2f::ZERO
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn set_toggle_key(&mut self, key: Key) {
+        self.toggle_key = key;
+    }
+
+    pub fn get_toggle_key(&self) -> Key {
+        self.toggle_key
+    }
+
+    /// Adds the time the last frame took, in seconds, to the graph.
+    pub fn add_frame_time(&mut self, frame_time: f32) {
+        self.frame_times.push(frame_time * 1000.0);
+    }
+
+    /// Lays out the stats text for the next draw. Only needed while visible.
+    pub fn set_stats(&mut self, stats: &DebugOverlayStats) {
+        let font = match &mut self.font {
+            Some(font) => font,
+            None => return
+        };
+
+        let metrics = font.line_metrics(TEXT_SIZE);
+        let text = overlay_text(stats);
+
+        self.text = font.layout_text(&text, TEXT_SIZE);
+        self.text_width = self.text.iter().fold(0.0, |width, quad| width.max(quad.max.x));
+        self.text_height = text.lines().count() as f32 * metrics.line_height();
+        self.ascent = metrics.ascent;
+    }
+
+    /// Draws a single quad from min to max in pixels, with the bound texture.
+    fn draw_quad(&self, min: Vec2f, max: Vec2f, uv_min: Vec2f, uv_max: Vec2f, tint: Color) {
+        let mut uniforms = UniformSet::new();
+        uniforms.set_vec2("pos", (min + max) * 0.5)
+            .set_vec2("scale", (max - min) * 0.5)
+            .set_vec2("uvMin", uv_min)
+            .set_vec2("uvMax", uv_max)
+            .set_vec4("tint", Vec4f::new(tint.r, tint.g, tint.b, tint.a));
+
+        self.shader.load_uniforms(&uniforms);
+        self.mesh.render();
+    }
+}
+
+impl RenderPipelineHandler for DebugOverlayPipeline {
+    fn init(&mut self) {
+        self.shader.bind();
+        self.shader.load_uniforms(UniformSet::new().set_int("overlayTexture", 0));
+    }
+
+    fn prepare(&self) {
+        let projection = Mat44f::ortho(0.0, self.screen_size.x, 0.0, self.screen_size.y, -1.0, 1.0);
+
+        self.shader.bind();
+        self.shader.load_uniforms(UniformSet::new().set_mat4("projection", projection));
+    }
+
+    /// Shows or hides the overlay when the toggle key is pressed.
+    fn update(&mut self, input: &Box<dyn MouseKeyboardInputControl>) {
+        let key_down = input.is_key_down(self.toggle_key);
+
+        if key_down && !self.toggle_key_down {
+            self.toggle();
+        }
+
+        self.toggle_key_down = key_down;
+    }
+
+    fn execute(&self) {
+        if !self.visible {
+            return;
+        }
+
+        let graph_width = FRAME_HISTORY_LEN as f32 * BAR_WIDTH;
+        let top = self.screen_size.y - MARGIN;
+        let left = MARGIN;
+        let text_gap = if self.text.is_empty() { 0.0 } else { self.text_height + PADDING };
+        let graph_bottom = top - PADDING - text_gap - GRAPH_HEIGHT;
+        let panel_width = graph_width.max(self.text_width) + PADDING * 2.0;
+
+        self.white_texture.bind(0);
+        self.draw_quad(Vec2f::new(left, graph_bottom - PADDING), Vec2f::new(left + panel_width, top),
+            Vec2f::ZERO, FULL_UV, PANEL_COLOR);
+
+        // The newest frame is on the right.
+        let graph_left = left + PADDING + (FRAME_HISTORY_LEN - self.frame_times.len()) as f32 * BAR_WIDTH;
+
+        for (index, frame_ms) in self.frame_times.iter().enumerate() {
+            let x = graph_left + index as f32 * BAR_WIDTH;
+            let height = frame_time_bar_height(frame_ms);
+
+            self.draw_quad(Vec2f::new(x, graph_bottom), Vec2f::new(x + BAR_WIDTH, graph_bottom + height),
+                Vec2f::ZERO, FULL_UV, frame_time_color(frame_ms));
+        }
+
+        let font = match &self.font {
+            Some(font) => font,
+            None => return
+        };
+
+        let origin = Vec2f::new(left + PADDING, top - PADDING - self.ascent);
+
+        for quad in self.text.iter() {
+            if let Some(texture) = font.page_texture(TEXT_SIZE, quad.page) {
+                texture.bind(0);
+                self.draw_quad(origin + quad.min, origin + quad.max, quad.uv_min, quad.uv_max, Color::WHITE);
+            }
+        }
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.screen_size = camera.get_viewport_size();
+    }
+
+    /// Drawn after every other pipeline.
+    fn priority(&self) -> i32 {
+        i32::MAX
+    }
+}
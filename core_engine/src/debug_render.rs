@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ogl33::*;
+
+use crate::render_state::{BlendMode, RenderState};
+
+/// Set while the pipelines render in overdraw mode, so shader programs bind their flat variant.
+static OVERDRAW: AtomicBool = AtomicBool::new(false);
+
+/// Fragment shader of the overdraw variants. Each fragment adds a little red, so the more often a pixel
+/// is drawn the brighter it gets.
+pub(crate) const OVERDRAW_FRAGMENT_SHADER: &str = "#version 130
+out vec4 color;
+
+void main() {
+    color = vec4(0.15, 0.06, 0.02, 1.0);
+}";
+
+/// How the pipelines are drawn, for debugging, see `GameManager::set_debug_render_mode`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DebugRenderMode {
+    #[default]
+    Normal,
+    /// Triangles are drawn as outlines, to inspect generated meshes.
+    Wireframe,
+    /// Everything is drawn in one flat color added on top of what's below, on black, so the brightest
+    /// areas are the ones drawn the most times.
+    Overdraw
+}
+
+impl DebugRenderMode {
+    /// Returns the render state a pipeline's state is drawn with in this mode.
+    /// Overdraw adds every fragment and never discards one for depth, the rest of the state is kept.
+    pub fn adjust_render_state(&self, state: RenderState) -> RenderState {
+        match self {
+            DebugRenderMode::Overdraw => RenderState {
+                blend: BlendMode::Additive,
+                depth_test: false,
+                depth_write: false,
+                ..state
+            },
+            _ => state
+        }
+    }
+
+    /// Sets up the mode's GL state before the pipelines render.
+    pub(crate) fn begin(&self) {
+        match self {
+            DebugRenderMode::Normal => {},
+            DebugRenderMode::Wireframe => unsafe {
+                glPolygonMode(GL_FRONT_AND_BACK, GL_LINE);
+            },
+            DebugRenderMode::Overdraw => OVERDRAW.store(true, Ordering::Relaxed)
+        }
+    }
+
+    /// Restores normal rendering after the pipelines, before post processing and screenshots.
+    pub(crate) fn end(&self) {
+        match self {
+            DebugRenderMode::Normal => {},
+            DebugRenderMode::Wireframe => unsafe {
+                glPolygonMode(GL_FRONT_AND_BACK, GL_FILL);
+            },
+            DebugRenderMode::Overdraw => OVERDRAW.store(false, Ordering::Relaxed)
+        }
+    }
+}
+
+/// Returns true while shader programs should bind their overdraw variant.
+#[inline]
+pub(crate) fn overdraw_active() -> bool {
+    OVERDRAW.load(Ordering::Relaxed)
+}
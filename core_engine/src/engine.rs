@@ -1,15 +1,44 @@
-use crate::render_pipeline::RenderPipelineHandler;
-use crate::resource_manager::{ResourceManager, ResourceDestroy};
-use crate::texture::{Texture};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::render_pipeline::{RenderPipelineHandler, RenderGraph, PipelineId};
+use crate::resource_manager::{ResourceManager};
+use crate::texture::{Texture, TextureSampling, TextureStorage};
+use crate::texture_array::{TextureArray};
+use crate::atlas::{TextureAtlas};
+use crate::animation::{Animation};
+use crate::ui::NineSlice;
 use crate::mesh::{Mesh2D};
 use crate::shader_program::{ShaderProgram};
-
-use ogl33::{glClearColor};
+use crate::shader_warmup::{self, ShaderWarmupError};
+use crate::font::{Font};
+use crate::camera::{Camera2D, CoordSystem};
+use glmath::glmath::Vec2f;
+pub use crate::framebuffer::{Framebuffer};
+use crate::color::{Color};
+use crate::render_state::{Rect2i, RenderState, RenderStateCache, BlendMode, StencilState};
+use crate::post_process::{PostProcessChain, PostEffect, parse_post_effects};
+use crate::audio::{Sound, AudioBackend};
+use crate::frame_time::{FrameStats, FrameTimeAverage};
+use crate::gpu_timer::GpuFrameTimers;
+use crate::bind_cache::{self, BindStats};
+use crate::screenshot::{self, PendingScreenshot, ScreenshotRequest};
+use crate::scheduler::Scheduler;
+use crate::resource_paths::ResourcePaths;
+use crate::debug_render::DebugRenderMode;
+use crate::debug_overlay::{DebugOverlayPipeline, DebugOverlayStats};
+use crate::debug_draw::{DebugDraw, DebugDrawPipeline};
+use crate::save::{SaveError, SaveWriter};
+use crate::gl_debug;
+
+use ogl33::{glClearColor, glClear, GL_STENCIL_BUFFER_BIT};
 // External dependencies.
 use timer::Stopwatch;
 use window::window::*;
+use window::{CursorMode, Key};
 use serializers::json::lexer::*;
 use serializers::json::parser::*;
+use serializers::json::deserialize::JsonDeserialize;
 
 /// Each implementation of the framework needs to 
 /// create a module to load resources. While the calling of this
@@ -20,15 +49,10 @@ pub trait ResourceLoader {
     fn load_shaders(&self, shader_resources: &mut ResourceManager<ShaderProgram>);
     fn load_meshes(&self, mesh_resources: &mut ResourceManager<Mesh2D>);
     fn load_frame_buffers(&self, framebuffer_resources: &mut ResourceManager<Framebuffer>);
-}
-
-#[derive(Default)]
-pub struct Framebuffer {
-}
-
-impl ResourceDestroy for Framebuffer {
-    fn destroy(&mut self) {
-    }
+    fn load_fonts(&self, _font_resources: &mut ResourceManager<Font>) {}
+    fn load_sounds(&self, _sound_resources: &mut ResourceManager<Arc<Sound>>) {}
+    fn load_atlases(&self, _atlas_resources: &mut ResourceManager<TextureAtlas>) {}
+    fn load_texture_arrays(&self, _texture_array_resources: &mut ResourceManager<TextureArray>) {}
 }
 
 /// Stores information loaded by the engine.
@@ -40,9 +64,35 @@ pub struct GameResources {
     pub shader_resouces: ResourceManager<ShaderProgram>,
     pub mesh_resources: ResourceManager<Mesh2D>,
     pub framebuffer_resources: ResourceManager<Framebuffer>,
+    pub font_resources: ResourceManager<Font>,
+    pub sound_resources: ResourceManager<Arc<Sound>>,
+    pub atlas_resources: ResourceManager<TextureAtlas>,
+    pub texture_array_resources: ResourceManager<TextureArray>,
 
-    /// Holds the path from which the resource files should be loaded.
-    res_path: String
+    /// The directories resource files are loaded from, see `GameManager::add_resource_override_path`.
+    res_paths: ResourcePaths,
+
+    /// When set, color textures are stored as sRGB. Follows the window's srgb setting.
+    srgb_textures: bool,
+
+    /// Filters and wrap modes of textures which don't set their own.
+    pub texture_defaults: TextureSampling
+}
+
+impl GameResources {
+    /// Returns the name of every resource manager with the number of registries it holds.
+    pub fn resource_counts(&self) -> Vec<(String, usize)> {
+        vec![
+            (self.texture_resources.get_name().to_string(), self.texture_resources.len()),
+            (self.shader_resouces.get_name().to_string(), self.shader_resouces.len()),
+            (self.mesh_resources.get_name().to_string(), self.mesh_resources.len()),
+            (self.framebuffer_resources.get_name().to_string(), self.framebuffer_resources.len()),
+            (self.font_resources.get_name().to_string(), self.font_resources.len()),
+            (self.sound_resources.get_name().to_string(), self.sound_resources.len()),
+            (self.atlas_resources.get_name().to_string(), self.atlas_resources.len()),
+            (self.texture_array_resources.get_name().to_string(), self.texture_array_resources.len())
+        ]
+    }
 }
 
 impl Default for GameResources {
@@ -52,11 +102,23 @@ impl Default for GameResources {
             shader_resouces: ResourceManager::new("Shaders"), 
             mesh_resources: ResourceManager::new("Meshes"),
             framebuffer_resources: ResourceManager::new("Framebuffers"), 
-            res_path: String::from("")
+            font_resources: ResourceManager::new("Fonts"),
+            sound_resources: ResourceManager::new("Sounds"),
+            atlas_resources: ResourceManager::new("Atlases"),
+            texture_array_resources: ResourceManager::new("TextureArrays"),
+            res_paths: ResourcePaths::default(),
+            srgb_textures: false,
+            texture_defaults: TextureSampling::default()
         }
     }
 }
 
+/// Name of the built in 1x1 white texture.
+pub const WHITE_TEXTURE: &str = "white";
+
+/// Name of the built in magenta and black checkerboard used for textures which failed to load.
+pub const MISSING_TEXTURE: &str = "missing";
+
 pub struct GameTick {
     /// The number of times per second the game state updates.
     /// 0 indicates no limits.
@@ -107,8 +169,8 @@ impl GameTick {
 }
 
 pub struct GameManager {
-    /// Holds a render pipeline object.
-    render_pipelines: Vec<Box<dyn RenderPipelineHandler>>,
+    /// Holds the render pipelines and the framebuffer slots connecting them.
+    render_graph: RenderGraph,
 
     /// Holds the global game resources loaded by the implementation.
     pub resources: GameResources,
@@ -116,6 +178,18 @@ pub struct GameManager {
     /// The currently active render pipeline.
     active_pipeline: Option<usize>,
 
+    /// Full screen effects run over the rendered pipelines.
+    post_process: PostProcessChain,
+
+    /// GPU timer queries around the frame and each pipeline.
+    gpu_timers: GpuFrameTimers,
+
+    /// Frame stats drawn over everything else, created when "game/dev_mode" is set.
+    debug_overlay: Option<DebugOverlayPipeline>,
+
+    /// Draws the lines queued through DebugDraw handles, created when "game/debug_draw" is set.
+    debug_draw: Option<DebugDrawPipeline>,
+
     /// Holds a control to the window.
     /// Note: the window is at the bottom of the list of members because Drop should be called last.
     /// Not doing so will result in invalid opengl calls.
@@ -125,7 +199,49 @@ pub struct GameManager {
     input: Box<dyn MouseKeyboardInputControl>,
 
     /// The time at which the game should tick.
-    game_tick: GameTick
+    game_tick: GameTick,
+
+    /// Measures the time between calls to update.
+    frame_timer: Stopwatch,
+
+    /// The smoothed time between frames.
+    frame_time: FrameTimeAverage,
+
+    /// Binds requested and issued during the last frame.
+    bind_stats: BindStats,
+
+    /// Delayed and repeating callbacks, advanced by the frame time at the start of each update.
+    scheduler: Scheduler,
+
+    /// The camera given to every pipeline before it renders.
+    camera: Camera2D,
+
+    /// The color the window is cleared to before each frame.
+    clear_color: Color,
+
+    /// The render state last applied, so switching pipelines only changes what differs.
+    render_state_cache: RenderStateCache,
+
+    /// Draws the pipelines as wireframes or as overdraw instead of normally.
+    debug_render_mode: DebugRenderMode,
+
+    /// The stencil test of pipelines whose render state doesn't set one.
+    stencil_test: Option<StencilState>,
+
+    /// Set by clear_stencil, the stencil buffer is cleared before the next frame's pipelines render.
+    stencil_clear_pending: bool,
+
+    /// Plays the audio events pipelines send each frame. Without one the events are dropped.
+    audio_backend: Option<Box<dyn AudioBackend>>,
+
+    /// Screenshots to read back at the end of the next frame.
+    screenshot_requests: Vec<ScreenshotRequest>,
+
+    /// Writes save files in the background, started by the first save.
+    save_writer: Option<SaveWriter>,
+
+    /// The parsed app config, or Null when the manager wasn't created from a config file.
+    config: JsonNode
 }
 
 impl GameManager {
@@ -133,14 +249,50 @@ impl GameManager {
     pub fn new(window_conf: WindowConfig) -> Option<Self> {
         let window = window::window::GraphicsWindow::new(&window_conf);
 
-        Some(GameManager {
-            window: Box::new(window),
+        if window_conf.gl_debug {
+            gl_debug::enable();
+        }
+
+        let mut game_manager = Self::with_window(Box::new(window), &window_conf);
+
+        // Built in textures, for untextured quads and standing in for textures which fail to load.
+        game_manager.resources.texture_resources.add_registry(WHITE_TEXTURE, Texture::solid_color(Color::WHITE));
+        game_manager.resources.texture_resources.add_registry(MISSING_TEXTURE, Texture::missing());
+
+        Some(game_manager)
+    }
+
+    /// Creates a game manager around an existing window.
+    pub(crate) fn with_window(window: Box<dyn WindowControl>, window_conf: &WindowConfig) -> Self {
+        let mut camera = Camera2D::new();
+        camera.resize(window_conf.xres as f32, window_conf.yres as f32);
+
+        GameManager {
+            window,
             resources: GameResources::default(),
-            render_pipelines: Vec::<Box<dyn RenderPipelineHandler>>::default(),
+            render_graph: RenderGraph::new(),
             active_pipeline: None,
             input: Box::new(MouseKeyboardInput::new()),
-            game_tick: GameTick::new(0)
-        })
+            game_tick: GameTick::new(0),
+            frame_timer: Stopwatch::new(),
+            frame_time: FrameTimeAverage::default(),
+            bind_stats: BindStats::default(),
+            gpu_timers: GpuFrameTimers::default(),
+            scheduler: Scheduler::new(),
+            camera,
+            clear_color: Color::from(window_conf.clear_color),
+            render_state_cache: RenderStateCache::new(),
+            debug_render_mode: DebugRenderMode::Normal,
+            stencil_test: None,
+            stencil_clear_pending: false,
+            post_process: PostProcessChain::new(),
+            debug_overlay: None,
+            debug_draw: None,
+            audio_backend: None,
+            screenshot_requests: Vec::<ScreenshotRequest>::default(),
+            save_writer: None,
+            config: JsonNode::Null
+        }
     }
 
     /// Sets the tick rate of the update loop.
@@ -149,17 +301,67 @@ impl GameManager {
         self.game_tick = GameTick::new(tick_rate);
     }
 
+    /// Waits for every registered shader to finish compiling and linking, so the first frames don't stall
+    /// on them. Call it during loading, progress is called with (finished, total) as each shader is done.
+    /// With "shader_compiler_threads" set in the resources config, the driver builds them in the background
+    /// while the game loads, where supported. Every failure is reported together.
+    pub fn warmup_shaders(&self, progress: impl FnMut(usize, usize)) -> Result<usize, ShaderWarmupError> {
+        shader_warmup::warmup_programs(self.resources.shader_resouces.iter(),
+            |program: &ShaderProgram| program.is_link_complete().then(|| program.link_status()), progress)
+    }
+
     /// Loads global game resources from the implementation.
     pub fn load_game_resources(&mut self, resource_loader: &dyn ResourceLoader) {
         resource_loader.load_frame_buffers(&mut self.resources.framebuffer_resources);
         resource_loader.load_shaders(&mut self.resources.shader_resouces);
         resource_loader.load_textures(&mut self.resources.texture_resources);
         resource_loader.load_meshes(&mut self.resources.mesh_resources);
+        resource_loader.load_fonts(&mut self.resources.font_resources);
+        resource_loader.load_sounds(&mut self.resources.sound_resources);
+        resource_loader.load_atlases(&mut self.resources.atlas_resources);
+        resource_loader.load_texture_arrays(&mut self.resources.texture_array_resources);
+    }
+
+    /// When enabled, missing textures and shaders are replaced by a magenta "missing" texture and a
+    /// passthrough shader instead of None, so a bad resource name shows up on screen rather than crashing.
+    /// The fallbacks are created the first time this is enabled.
+    pub fn set_use_fallback_resources(&mut self, use_fallback: bool) {
+        if use_fallback {
+            if !self.resources.texture_resources.has_fallback() {
+                let missing = match self.resources.texture_resources.contains_registry(MISSING_TEXTURE) {
+                    true => self.resources.texture_resources.get_registry(MISSING_TEXTURE).cloned(),
+                    false => None
+                };
+
+                self.resources.texture_resources.set_fallback(missing.unwrap_or_else(Texture::missing));
+            }
+
+            if !self.resources.shader_resouces.has_fallback() {
+                self.resources.shader_resouces.set_fallback(ShaderProgram::passthrough());
+            }
+        }
+
+        self.resources.texture_resources.set_use_fallback(use_fallback);
+        self.resources.shader_resouces.set_use_fallback(use_fallback);
+    }
+
+    /// Makes resource files in the directory replace the ones in the resource directory, checked after the
+    /// overrides added before it. Affects resources loaded from then on, ex: through `resource_path`.
+    pub fn add_resource_override_path(&mut self, path: &str) {
+        self.resources.res_paths.add_override(path);
+    }
+
+    /// Returns where a file in the resource directory is loaded from, after applying the override directories.
+    pub fn resource_path(&self, relative_path: &str) -> String {
+        self.resources.res_paths.resolve(relative_path)
     }
 
     /// Loads the game manager from an app config file.
+    /// Override directories from GAME_RESOURCE_OVERRIDES and the config's "resources/override_paths" are
+    /// checked before res_path for every file, including the config itself, see `ResourcePaths`.
     pub fn from_conf(res_path: &str, config_file_name: &str) -> Option<Self> {
-        let full_conf_name = res_path.to_string() + "/" + config_file_name;
+        let mut res_paths = ResourcePaths::from_env(res_path);
+        let full_conf_name = res_paths.resolve(config_file_name);
         
         let json_lexer = JsonLexer::new(&full_conf_name);
 
@@ -168,24 +370,73 @@ impl GameManager {
                 let user_config = parse_json(&mut json_lexer);
 
                 if let Some(user_config) = user_config {
-                    let config = load_window_config(&user_config);
-                    let engine = Self::new(config);
+                    if let Some(JsonNode::Array(override_paths)) = user_config.pointer("resources/override_paths") {
+                        for path in override_paths.iter().filter_map(String::from_json) {
+                            res_paths.add_override(&path);
+                        }
+                    }
+
+                    let config = load_window_config(&user_config, &res_paths);
+
+                    // Set before the window is created, so errors while loading resources panic too.
+                    if let Some(panic_on_error) = user_config.pointer("graphics/gl_debug_panic").and_then(bool::from_json) {
+                        gl_debug::set_panic_on_error(panic_on_error);
+                    }
+
+                    let engine = Self::new(config.clone());
 
                     // Load game resources.
                     match engine {
                         Some(mut game_manager) => {
-                            game_manager.resources.res_path = res_path.to_string();
+                            game_manager.resources.res_paths = res_paths;
+                            game_manager.resources.srgb_textures = config.srgb;
+                            game_manager.post_process.set_samples(valid_msaa_samples(config.msaa_samples));
 
                             // Load data from the "resources" object into the resource manager.
-                            load_config_resources(&user_config, &mut game_manager.resources);
+                            if !load_config_resources(&user_config, &mut game_manager.resources) {
+                                println!("Resources failed to load with \"strict\" set: {}", &full_conf_name);
+                                return None;
+                            }
+
                             let game_config = load_game_config(&user_config);
                             game_manager.game_tick = GameTick::new(game_config.update_tick_rate);
+
+                            if let Some(effects) = user_config.pointer("graphics/post_processing") {
+                                for desc in parse_post_effects(effects) {
+                                    if let Some(effect) = PostEffect::from_desc(&desc, &game_manager.resources.shader_resouces) {
+                                        game_manager.post_process.add_effect(effect);
+                                    }
+                                }
+                            }
+
+                            let dev_mode = user_config.pointer("game/dev_mode").and_then(bool::from_json).unwrap_or(false);
+
+                            if dev_mode {
+                                let mut debug_overlay = load_debug_overlay(&user_config, &game_manager.resources.res_paths);
+                                debug_overlay.init();
+                                game_manager.debug_overlay = Some(debug_overlay);
+                            }
+
+                            // Debug drawing follows dev mode unless it's set on its own.
+                            if user_config.pointer("game/debug_draw").and_then(bool::from_json).unwrap_or(dev_mode) {
+                                game_manager.debug_draw = Some(DebugDrawPipeline::new(DebugDraw::enabled()));
+                            }
+
+                            game_manager.config = user_config;
                             return Some(game_manager);
                         }
                         None => return None
                     }
                 }
 
+                println!("Unable to parse config file: {}", &full_conf_name);
+
+                // Show how the file tokenized to help find the mistake.
+                #[cfg(debug_assertions)]
+                for token in json_lexer.dump_tokens() {
+                    println!("    {}", token);
+                }
+
                 None 
             }
             _ => {
@@ -195,17 +446,92 @@ impl GameManager {
         }
     }
 
+    /// Reads a value from the app config by a '/' separated path, ex: "snake/tile_size".
+    /// Returns None when the path doesn't exist or holds a different type of value.
+    pub fn config_get<T: JsonDeserialize>(&self, path: &str) -> Option<T> {
+        self.config.pointer(path).and_then(T::from_json)
+    }
+
     pub fn terminate_program(&mut self) {
-        self.window.close_window()
+        self.request_close();
     }
 
-    /// Sets the clear color of the active window.
-    pub fn set_clear_color(r: f32, g: f32, b: f32) {
-        unsafe {
-            glClearColor(r, g, b, 1.0);
+    /// Asks the game to shut down, ex: from a quit button. The next update reports the close without rendering.
+    pub fn request_close(&mut self) {
+        self.window.close_window();
+    }
+
+    /// True once the window has been closed by the user or request_close, so shutdown logic can run before the loop exits.
+    pub fn should_close(&self) -> bool {
+        self.window.should_close()
+    }
+
+    /// Sets the color the window is cleared to at the start of each frame.
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    pub fn get_clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Draws the pipelines as wireframes or as overdraw, for debugging. Post processing is unaffected,
+    /// and setting DebugRenderMode::Normal returns to normal rendering from the next frame.
+    pub fn set_debug_render_mode(&mut self, mode: DebugRenderMode) {
+        self.debug_render_mode = mode;
+    }
+
+    pub fn get_debug_render_mode(&self) -> DebugRenderMode {
+        self.debug_render_mode
+    }
+
+    /// Sets the stencil test used by pipelines whose render state doesn't set its own, or turns it off with None.
+    /// Needs a window with stencil bits, see "stencil_bits" in the graphics config.
+    pub fn set_stencil_test(&mut self, stencil: Option<StencilState>) {
+        self.stencil_test = stencil;
+    }
+
+    pub fn get_stencil_test(&self) -> Option<StencilState> {
+        self.stencil_test
+    }
+
+    /// Clears the stencil buffer to 0 before the pipelines of the next frame render. Only needed when
+    /// "clear_stencil" is off, so masks drawn in one frame would otherwise carry over to the next.
+    pub fn clear_stencil(&mut self) {
+        self.stencil_clear_pending = true;
+    }
+
+    /// Sets the overlay of frame times and draw stats drawn over every frame, or removes it.
+    /// The overlay is drawn after post processing and isn't included in screenshots.
+    pub fn set_debug_overlay(&mut self, debug_overlay: Option<DebugOverlayPipeline>) {
+        self.debug_overlay = debug_overlay;
+    }
+
+    pub fn get_debug_overlay_mut(&mut self) -> Option<&mut DebugOverlayPipeline> {
+        self.debug_overlay.as_mut()
+    }
+
+    /// Returns a handle for queueing debug lines, rectangles, circles, and grids, drawn in world coordinates
+    /// after every pipeline and cleared each frame. When "game/debug_draw" isn't set, or "game/dev_mode"
+    /// without it, the handle does nothing.
+    pub fn debug_draw(&self) -> DebugDraw {
+        match &self.debug_draw {
+            Some(debug_draw) => debug_draw.handle(),
+            None => DebugDraw::disabled()
         }
     }
 
+    /// Shows, hides, or grabs the cursor. While the cursor is disabled the input's mouse_delta keeps
+    /// reporting movement past the window's edges, and switching modes never reports the cursor's jump.
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        self.window.set_cursor_mode(mode);
+        self.input.set_cursor_mode(mode);
+    }
+
+    pub fn get_cursor_mode(&self) -> CursorMode {
+        self.window.get_cursor_mode()
+    }
+
     /// Returns an immutable ref to the window.
     pub fn get_window(&self) -> &Box<dyn WindowControl> {
         &self.window
@@ -215,34 +541,227 @@ impl GameManager {
         &mut self.window
     }
 
-    pub fn add_render_pipeline(&mut self, pipeline: Box<dyn RenderPipelineHandler>) {
-        self.render_pipelines.push(pipeline);
+    /// Returns the time between frames in seconds, smoothed over the last several frames.
+    pub fn average_frame_time(&self) -> f32 {
+        self.frame_time.average()
+    }
+
+    /// Returns the frames per second of the smoothed frame time.
+    pub fn fps(&self) -> f32 {
+        self.frame_time.fps()
+    }
+
+    /// Returns the program, texture, and vertex array binds requested during the last frame, and how many
+    /// reached GL. Binds of objects which are already bound are skipped.
+    pub fn bind_stats(&self) -> BindStats {
+        self.bind_stats
+    }
+
+    /// Returns the smoothed CPU frame time and GPU times of the frame and of each render graph node.
+    /// GPU times lag a couple of frames behind, since they're read back without waiting on the GPU.
+    pub fn frame_stats(&self) -> FrameStats {
+        let mut stats = FrameStats {
+            cpu_ms: self.frame_time.average() * 1000.0,
+            ..FrameStats::default()
+        };
+
+        self.gpu_timers.write_stats(&mut stats);
+        stats
+    }
+
+    /// Returns the input as of the last update tick.
+    pub fn get_input(&self) -> &Box<dyn MouseKeyboardInputControl> {
+        &self.input
+    }
+
+    /// Returns the cursor position in normalized device coordinates of the window's camera, -1 to 1 with y up.
+    pub fn mouse_position_ndc(&self) -> Vec2f {
+        self.camera.screen_to_ndc(self.input.mouse_position())
+    }
+
+    pub fn get_post_process(&self) -> &PostProcessChain {
+        &self.post_process
+    }
+
+    pub fn get_post_process_mut(&mut self) -> &mut PostProcessChain {
+        &mut self.post_process
+    }
+
+    /// Schedules callbacks to run after a delay or at an interval, ex: `get_scheduler_mut().after(2.0, || ...)`.
+    /// Due callbacks fire at the start of update, before the pipelines update.
+    pub fn get_scheduler_mut(&mut self) -> &mut Scheduler {
+        &mut self.scheduler
+    }
+
+    /// Sets the coordinate system the camera's projection is built for, ex: CoordSystem::TopLeftPixels
+    /// to place sprites in window pixels.
+    pub fn set_coordinate_system(&mut self, coord_system: CoordSystem) {
+        self.camera.set_coordinate_system(coord_system);
+    }
+
+    pub fn get_coordinate_system(&self) -> CoordSystem {
+        self.camera.get_coordinate_system()
+    }
+
+    /// Returns the camera used to render the world.
+    /// Its projection follows the coordinate system and is recomputed when the window is resized.
+    pub fn get_camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    /// Adds a pipeline drawing to the window, independent of the other pipelines, returning its id.
+    /// The pipeline becomes the active one when no other is.
+    pub fn add_render_pipeline(&mut self, pipeline: Box<dyn RenderPipelineHandler>) -> PipelineId {
+        let index = self.render_graph.add_pipeline(pipeline);
 
         match self.active_pipeline {
             Some(_) => {},
-            None => self.set_active_render_pipeline(0)
+            None => self.set_active_render_pipeline(index)
         }
+
+        self.render_graph.get_id(index).expect("the pipeline was just added")
+    }
+
+    /// Removes a pipeline from the render graph, returning it so its resources can be freed or it can
+    /// be added again later, ex: when switching from gameplay to a menu. Returns None when the pipeline
+    /// was already removed. Removing the active pipeline leaves none active until another is set or added.
+    pub fn remove_render_pipeline(&mut self, id: PipelineId) -> Option<Box<dyn RenderPipelineHandler>> {
+        let index = self.render_graph.index_of(id)?;
+        let node = self.render_graph.remove_node(id)?;
+
+        self.active_pipeline = match self.active_pipeline {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active
+        };
+
+        // The nodes after the removed one changed index.
+        self.gpu_timers.clear_pipelines();
+
+        Some(node.into_pipeline())
+    }
+
+    /// Limits a pipeline's drawing to a rect of the window or its output slot, ex: one player's half of a
+    /// split screen. None disables the scissor test for the pipeline, see `RenderGraphNode::set_scissor`.
+    pub fn set_render_pipeline_scissor(&mut self, id: PipelineId, rect: Option<Rect2i>) {
+        match self.render_graph.index_of(id).and_then(|index| self.render_graph.get_node_mut(index)) {
+            Some(node) => node.set_scissor(rect),
+            None => println!("Unable to set the scissor of render pipeline {:?}, it was removed", id)
+        }
+    }
+
+    /// Saves the window as a PNG once the next frame is drawn, after post processing.
+    /// The whole window is captured, including letterbox bars. Only the readback happens during the frame,
+    /// the PNG is encoded on a worker thread, so check the returned screenshot for the result.
+    pub fn capture_screenshot(&mut self, path: &str) -> PendingScreenshot {
+        let (request, pending) = ScreenshotRequest::new(Path::new(path));
+        self.screenshot_requests.push(request);
+
+        pending
+    }
+
+    /// Queues serialized state to be written to the path on a background thread and returns without waiting.
+    /// The file is replaced atomically. Call flush_saves before exiting so queued writes aren't lost.
+    pub fn save_state_async(&mut self, path: &str, bytes: Vec<u8>) -> Result<(), SaveError> {
+        self.save_writer.get_or_insert_with(SaveWriter::default).save_state_async(path, bytes)
+    }
+
+    /// Blocks until every queued save has been written, returning the first which failed.
+    pub fn flush_saves(&self) -> Result<(), SaveError> {
+        match &self.save_writer {
+            Some(save_writer) => save_writer.flush(),
+            None => Ok(())
+        }
+    }
+
+    /// Sets the backend the pipelines' audio events are forwarded to each frame.
+    pub fn set_audio_backend(&mut self, audio_backend: Box<dyn AudioBackend>) {
+        self.audio_backend = Some(audio_backend);
+    }
+
+    /// Replaces every pipeline with the nodes of the graph. The first node becomes the active pipeline.
+    /// A graph whose nodes depend on each other is still used, but runs in priority order.
+    pub fn set_render_graph(&mut self, graph: RenderGraph) {
+        if let Err(err) = graph.execution_order() {
+            println!("Invalid render graph, running its pipelines in priority order: {}", err);
+        }
+
+        self.active_pipeline = if graph.is_empty() { None } else { Some(0) };
+        self.render_graph = graph;
+        self.gpu_timers.clear_pipelines();
+    }
+
+    pub fn get_render_graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.render_graph
     }
 
     pub fn init(&mut self) {
-        for pipeline in self.render_pipelines.iter_mut() {
-            pipeline.init();
+        for node in self.render_graph.nodes_mut() {
+            node.get_pipeline_mut().init();
         }
+
+        // Loading isn't a frame, start timing from the first update.
+        self.frame_timer.start();
+        self.frame_time.reset();
     }
 
     pub fn set_active_render_pipeline(&mut self, index: usize) {
         self.active_pipeline = Some(index);
     }
+
+    /// Returns the id of the pipeline receiving input and updates, if any.
+    pub fn get_active_render_pipeline(&self) -> Option<PipelineId> {
+        self.active_pipeline.and_then(|index| self.render_graph.get_id(index))
+    }
     
     pub fn update(&mut self) -> bool {
+        // A close was requested since the last frame, don't draw another.
+        if self.should_close() {
+            return true;
+        }
+
+        // The window clears after presenting, so this color applies to the frame rendered below.
+        // Overdraw is counted up from black.
+        let clear_color = match self.debug_render_mode {
+            DebugRenderMode::Overdraw => Color::BLACK,
+            _ => self.clear_color
+        };
+
+        unsafe {
+            glClearColor(clear_color.r, clear_color.g, clear_color.b, clear_color.a);
+        }
+
         let should_close = self.window.update_window();
 
+        let delta_time = self.frame_timer.elapsed_seconds() as f32;
+        self.frame_time.add(delta_time);
+        self.frame_timer.start();
+
+        self.scheduler.update(delta_time);
+
+        self.bind_stats = bind_cache::stats();
+        bind_cache::reset_stats();
+
+        // Keep the projection matching the window shape. A minimized window reports a zero size.
+        let (width, height) = self.window.get_framebuffer_size();
+        let viewport_size = self.camera.get_viewport_size();
+
+        if width > 0 && height > 0 && (width as f32 != viewport_size.x || height as f32 != viewport_size.y) {
+            self.camera.resize(width as f32, height as f32);
+        }
+
+        // Only the active pipeline receives input and updates.
         match self.active_pipeline {
             Some(active) => {
-                let render_pipeline = self.render_pipelines.get_mut(active);
+                let node = self.render_graph.get_node_mut(active);
 
-                match render_pipeline {
-                    Some(render_pipeline) => {
+                match node {
+                    Some(node) => {
+                        let render_pipeline = node.get_pipeline_mut();
                         let mut update_count = self.game_tick.tick();
 
                         while update_count > 0 {
@@ -250,9 +769,6 @@ impl GameManager {
                             render_pipeline.update(&self.input);
                             update_count -= 1;
                         }
-
-                        render_pipeline.prepare();
-                        render_pipeline.render();
                     },
                     _ => {}
                 }
@@ -260,6 +776,168 @@ impl GameManager {
             None => {}
         }
 
+        if let Some(debug_overlay) = &mut self.debug_overlay {
+            debug_overlay.update(&self.input);
+            debug_overlay.add_frame_time(delta_time);
+        }
+
+        // Hidden, the overlay only keeps the graph's history.
+        if self.debug_overlay.as_ref().is_some_and(DebugOverlayPipeline::is_visible) {
+            let stats = DebugOverlayStats {
+                fps: self.fps(),
+                frame: self.frame_stats(),
+                binds: self.bind_stats,
+                resource_counts: self.resources.resource_counts()
+            };
+
+            if let Some(debug_overlay) = &mut self.debug_overlay {
+                debug_overlay.set_stats(&stats);
+            }
+        }
+
+        // Every pipeline renders after the pipelines it reads from, then opaque pipelines front to back,
+        // then transparent ones back to front. See RenderGraph::execution_order.
+        let render_order = self.render_graph.render_order();
+
+        let framebuffer_size = self.window.get_framebuffer_size();
+
+        self.gpu_timers.begin_frame(self.render_graph.len());
+
+        // Redirect the pipelines into the post processing chain when it has effects to run.
+        let post_processing = self.post_process.begin(framebuffer_size);
+
+        // The stencil write mask is restored at the end of every frame, so the whole buffer clears.
+        if self.stencil_clear_pending {
+            unsafe {
+                glClear(GL_STENCIL_BUFFER_BIT);
+            }
+
+            self.stencil_clear_pending = false;
+        }
+
+        // Slots drawn to so far this frame, and whether the scene target needs binding again.
+        let mut cleared_slots = Vec::<String>::new();
+        let mut scene_target_unbound = false;
+        let debug_render_mode = self.debug_render_mode;
+        debug_render_mode.begin();
+
+        for index in render_order {
+            // Resolving multisampled inputs binds the window, so the target is bound after the inputs.
+            let has_inputs = !self.render_graph.get_node(index).map(|node| node.inputs().is_empty()).unwrap_or(true);
+            self.render_graph.bind_inputs(index);
+
+            let target_size = match self.render_graph.bind_output(index, &mut cleared_slots) {
+                Some(slot_size) => {
+                    scene_target_unbound = true;
+                    self.render_state_cache.invalidate_viewport();
+                    slot_size
+                },
+                None => {
+                    if scene_target_unbound || has_inputs {
+                        self.post_process.bind_scene_target(post_processing);
+                        self.render_state_cache.invalidate_viewport();
+                        scene_target_unbound = false;
+                    }
+
+                    framebuffer_size
+                }
+            };
+
+            let (render_pipeline, scissor) = match self.render_graph.get_node_mut(index) {
+                Some(node) => {
+                    let scissor = node.scissor();
+                    (node.get_pipeline_mut(), scissor)
+                },
+                None => continue
+            };
+            let render_state = debug_render_mode.adjust_render_state(render_pipeline.render_state()
+                .with_scissor_rect(scissor).with_default_stencil(self.stencil_test));
+
+            // Pipelines drawing into part of the window get a camera fitted to their viewport,
+            // so the projection keeps its aspect and mouse conversions land in the sub-view.
+            let mut camera = self.camera;
+            if let Some(viewport) = render_state.viewport {
+                let (x, y, width, height) = viewport.to_pixels(target_size.0, target_size.1);
+                let top = target_size.1 - (y + height);
+                camera.fit_viewport(x as f32, top as f32, width as f32, height as f32);
+            }
+
+            render_pipeline.set_camera(&camera);
+            self.gpu_timers.begin_pipeline(index);
+
+            if render_pipeline.pass_count() == 1 {
+                self.render_state_cache.apply(&render_state, target_size);
+                render_pipeline.prepare();
+                render_pipeline.render();
+            }
+            else {
+                // Only the states differing between passes are sent to GL.
+                render_pipeline.prepare();
+
+                for pass in 0..render_pipeline.pass_count() {
+                    let pass_state = render_pipeline.pass_render_state(pass).with_scissor_rect(scissor).with_default_stencil(self.stencil_test);
+                    self.render_state_cache.apply(&debug_render_mode.adjust_render_state(pass_state), target_size);
+                    render_pipeline.execute_pass(pass);
+                }
+            }
+
+            self.gpu_timers.end_pipeline(index);
+        }
+
+        debug_render_mode.end();
+
+        if scene_target_unbound {
+            self.post_process.bind_scene_target(post_processing);
+            self.render_state_cache.invalidate_viewport();
+        }
+
+        if post_processing {
+            self.render_state_cache.apply(&RenderState::with_blend(BlendMode::Opaque), framebuffer_size);
+            self.post_process.end();
+        }
+
+        self.gpu_timers.end_frame();
+
+        // The frame is complete and not swapped yet, so the back buffer holds exactly what will be shown.
+        if !self.screenshot_requests.is_empty() {
+            let (width, height) = framebuffer_size;
+            let image = screenshot::capture_window(width.max(0) as u32, height.max(0) as u32);
+
+            for request in self.screenshot_requests.drain(..) {
+                request.save_in_background(image.clone());
+            }
+        }
+
+        // Debug shapes and the overlay are drawn last, over post processing, and left out of screenshots.
+        if let Some(debug_draw) = &mut self.debug_draw {
+            debug_draw.flush(&self.camera);
+
+            if debug_draw.line_count() > 0 {
+                self.render_state_cache.apply(&debug_draw.render_state(), framebuffer_size);
+                debug_draw.set_camera(&self.camera);
+                debug_draw.render();
+            }
+        }
+
+        if let Some(debug_overlay) = &mut self.debug_overlay {
+            if debug_overlay.is_visible() {
+                self.render_state_cache.apply(&debug_overlay.render_state(), framebuffer_size);
+                debug_overlay.set_camera(&self.camera);
+                debug_overlay.render();
+            }
+        }
+
+        // Return to the default state so depth writes, the stencil mask, and the scissor don't block the next clear.
+        self.render_state_cache.apply(&RenderState::default(), framebuffer_size);
+
+        let audio_events = self.render_graph.audio_events();
+
+        if let Some(audio_backend) = &mut self.audio_backend {
+            if !audio_events.is_empty() {
+                audio_backend.handle_events(audio_events);
+            }
+        }
+
         return should_close;
     }
 }
@@ -294,28 +972,88 @@ fn load_game_config(game_config: &JsonNode) -> GameConfig {
 }
 
 /// Parses resources from the config file into named game resources.
-fn load_config_resources(user_config: &JsonNode, game_resources: &mut GameResources) {
+/// Returns false when a texture failed to load and "strict" is set. Otherwise textures which fail
+/// to load are replaced by the missing texture, so the game still runs and the problem is visible.
+fn load_config_resources(user_config: &JsonNode, game_resources: &mut GameResources) -> bool {
+    let mut loaded = true;
+
     match user_config {
         JsonNode::Object(entire_object) => {
             match entire_object.get("resources") {
                 Some(JsonNode::Object(resources_object)) => {
+                    let strict = resources_object.get("strict").and_then(bool::from_json).unwrap_or(false);
+
+                    // When set, compressed textures the GPU can't use are decompressed rather than failing to load.
+                    if let Some(decompress) = resources_object.get("decompress_unsupported_textures").and_then(bool::from_json) {
+                        Texture::set_decompress_unsupported(decompress);
+                    }
+
+                    // Load the sampling options used by textures which don't set their own.
+                    if let Some(texture_defaults) = resources_object.get("texture_defaults") {
+                        game_resources.texture_defaults = TextureSampling::from_json(texture_defaults, game_resources.texture_defaults);
+                    }
+
                     // Load textures.
                     match resources_object.get("textures") {
                         Some(JsonNode::Object(textures_object)) => {
-                            load_textures(textures_object, &mut game_resources.texture_resources, &game_resources.res_path);
+                            let textures_loaded = load_textures(textures_object, &mut game_resources.texture_resources, &game_resources.res_paths,
+                                game_resources.srgb_textures, game_resources.texture_defaults, strict);
+                            loaded &= textures_loaded || !strict;
                         }
                         _ => {}
                     }
 
+                    // Load atlases.
+                    match resources_object.get("atlases") {
+                        Some(JsonNode::Object(atlases_object)) => {
+                            let atlases_loaded = load_atlases(atlases_object, &mut game_resources.atlas_resources, &game_resources.res_paths,
+                                game_resources.srgb_textures, game_resources.texture_defaults);
+                            loaded &= atlases_loaded || !strict;
+                        }
+                        _ => {}
+                    }
+
+                    // Load texture arrays.
+                    match resources_object.get("texture_arrays") {
+                        Some(JsonNode::Object(texture_arrays_object)) => {
+                            let arrays_loaded = load_texture_arrays(texture_arrays_object, &mut game_resources.texture_array_resources,
+                                &game_resources.res_paths, game_resources.srgb_textures, game_resources.texture_defaults);
+                            loaded &= arrays_loaded || !strict;
+                        }
+                        _ => {}
+                    }
+
+                    // Shaders are compiled by the driver in the background when it supports it, see warmup_shaders.
+                    if let Some(threads) = resources_object.get("shader_compiler_threads").and_then(u32::from_json) {
+                        if !ShaderProgram::set_compiler_threads(threads) {
+                            println!("Background shader compilation isn't supported, shaders compile as they load");
+                        }
+                    }
+
                     // Load shaders.
                     match resources_object.get("shaders") {
                         Some(JsonNode::Object(shaders_object)) => {
-                            load_shaders(shaders_object, &mut game_resources.shader_resouces, &game_resources.res_path);
+                            load_shaders(shaders_object, &mut game_resources.shader_resouces, &game_resources.res_paths);
+                        }
+                        _ => {}
+                    }
+
+                    // Load fonts.
+                    match resources_object.get("fonts") {
+                        Some(JsonNode::Object(fonts_object)) => {
+                            load_fonts(fonts_object, &mut game_resources.font_resources, &game_resources.res_paths);
                         }
                         _ => {}
                     }
+                    // Load meshes.
+                    match resources_object.get("meshes") {
+                        Some(JsonNode::Object(meshes_object)) => {
+                            load_meshes(meshes_object, &mut game_resources.mesh_resources, &game_resources.res_paths);
+                        }
+                        _ => {}
+                    }
+
                     // Load framebuffers.
-                    // Load models.
                 }
                 _ => {}
             }
@@ -323,38 +1061,233 @@ fn load_config_resources(user_config: &JsonNode, game_resources: &mut GameResour
         // No object specified: nothing to load.
         _ => {}
     }
+
+    loaded
+}
+
+/// Reads a texture entry, either a path or { "path": path, "linear": true, "filter": "nearest", ... },
+/// as (path, linear, sampling). Linear textures are never stored as sRGB, for data like noise or normal maps.
+/// Filters and wrap modes the entry doesn't set come from the defaults.
+fn parse_texture_entry(entry: &JsonNode, defaults: TextureSampling) -> Option<(String, bool, TextureSampling)> {
+    match entry {
+        JsonNode::String(path) => Some((path.get().clone(), false, defaults)),
+        JsonNode::Object(texture_object) => {
+            let path = texture_object.get("path").and_then(String::from_json)?;
+            let linear = texture_object.get("linear").and_then(bool::from_json).unwrap_or(false);
+            Some((path, linear, TextureSampling::from_json(entry, defaults)))
+        },
+        _ => None
+    }
 }
 
 /// load textures in textures_object into memory with the given name.
-fn load_textures(textures_object: &JsonObject, texture_resources: &mut ResourceManager<Texture>, res_path: &str) {
+/// When srgb is set, color textures are stored as sRGB unless the entry is marked linear.
+/// Textures which fail to load are registered as the missing texture unless strict is set.
+/// Returns false when any texture failed to load.
+fn load_textures(textures_object: &JsonObject, texture_resources: &mut ResourceManager<Texture>, res_paths: &ResourcePaths,
+    srgb: bool, texture_defaults: TextureSampling, strict: bool) -> bool {
     let stopwatch = Stopwatch::new();
+    let mut loaded = true;
+    let (mut compressed_bytes, mut uncompressed_bytes) = (0, 0);
 
-    for (texture, path) in textures_object.iter() {
-        match path {
-            JsonNode::String(texture_file_path) => {
+    for (texture, entry) in textures_object.iter() {
+        match parse_texture_entry(entry, texture_defaults) {
+            Some((texture_file_path, linear, sampling)) => {
                 // Load the texture, otherwise warning.
-                let texture_path = res_path.to_string() + "/" + texture_file_path.get();
-                let loaded_texture = Texture::open(&texture_path);
+                let texture_path = res_paths.resolve(&texture_file_path);
+                let loaded_texture = Texture::open_with(&texture_path, srgb && !linear, sampling);
 
                 match loaded_texture {
                     Ok(loaded_texture) => {
+                        // Report compressed textures, so the memory they save can be confirmed.
+                        match loaded_texture.storage() {
+                            TextureStorage::Uncompressed => {},
+                            storage => println!("Loaded texture {} as {}", texture, storage)
+                        }
+
+                        if let TextureStorage::Compressed { bytes, uncompressed_bytes: uncompressed, .. } = loaded_texture.storage() {
+                            compressed_bytes += bytes;
+                            uncompressed_bytes += uncompressed;
+                        }
+
                         // Transfer ownership of the loaded texture to the registry.
                         texture_resources.add_registry(texture, loaded_texture);
                         //println!("Loaded texture: {} {}", texture, texture_path);
                     }
-                    _ => {
-                        println!("Failed to load texture: {} {}", texture, texture_file_path.get());
+                    Err(err) => {
+                        println!("Failed to load texture: {} {} ({})", texture, texture_file_path, err);
+                        loaded = false;
+
+                        // Stand in with the missing texture so the problem shows up in game.
+                        if !strict && texture_resources.contains_registry(MISSING_TEXTURE) {
+                            let missing = texture_resources.get_registry(MISSING_TEXTURE).cloned();
+
+                            if let Some(missing) = missing {
+                                texture_resources.add_registry(texture, missing);
+                            }
+                        }
+                    }
+                }
+            },
+            None => println!("Expected a path or {{ \"path\": path, \"linear\": bool }} for texture {}", texture)
+        }
+    }
+
+    if compressed_bytes > 0 {
+        println!("Compressed textures take {:.2} MiB instead of {:.2} MiB",
+            compressed_bytes as f64 / (1024.0 * 1024.0), uncompressed_bytes as f64 / (1024.0 * 1024.0));
+    }
+
+    println!("Loaded game textures in {} seconds", stopwatch.elapsed_seconds());
+    loaded
+}
+
+/// Load the atlases in atlases_object. An entry is either the path of an atlas description, see `TextureAtlas::open`,
+/// or { "images": { region: path, ... }, "padding": 1 } to pack individual images at load time.
+/// Objects may also set the sampling options of textures, and declare { "animations": { name: animation, ... } }
+/// playing the atlas regions, see `Animation`, and { "nine_slices": { name: nine slice, ... } } cut from
+/// them, see `NineSlice::from_json`. Returns false when any atlas, animation, or nine slice failed to load.
+fn load_atlases(atlases_object: &JsonObject, atlas_resources: &mut ResourceManager<TextureAtlas>, res_paths: &ResourcePaths,
+    srgb: bool, texture_defaults: TextureSampling) -> bool {
+    let mut loaded = true;
+
+    for (atlas_name, entry) in atlases_object.iter() {
+        let sampling = TextureSampling::from_json(entry, texture_defaults);
+
+        let atlas = match (entry, entry.pointer("path"), entry.pointer("images")) {
+            (JsonNode::String(path), _, _) | (_, Some(JsonNode::String(path)), _) =>
+                TextureAtlas::open(&res_paths.resolve(path.get()), srgb, sampling),
+            (_, _, Some(JsonNode::Object(images_object))) => {
+                // Sorted so the images pack the same way every run.
+                let mut files: Vec<(String, String)> = images_object.iter()
+                    .filter_map(|(name, path)| String::from_json(path).map(|path| (name.clone(), res_paths.resolve(&path))))
+                    .collect();
+                files.sort();
+
+                let files: Vec<(&str, &str)> = files.iter().map(|(name, path)| (name.as_str(), path.as_str())).collect();
+                let padding = entry.pointer("padding").and_then(u32::from_json).unwrap_or(1);
+
+                TextureAtlas::from_files(&files, padding, srgb, sampling)
+            },
+            _ => {
+                println!("Expected a path, {{ \"path\": path }}, or {{ \"images\": {{ name: path }} }} for atlas {}", atlas_name);
+                loaded = false;
+                continue;
+            }
+        };
+
+        match atlas {
+            Ok(mut atlas) => {
+                if let Some(JsonNode::Object(animations_object)) = entry.pointer("animations") {
+                    for (animation_name, animation) in animations_object.iter() {
+                        match Animation::from_json(animation, &atlas) {
+                            Ok(animation) => atlas.add_animation(animation_name, animation),
+                            Err(err) => {
+                                println!("Failed to load animation: {} {} ({})", atlas_name, animation_name, err);
+                                loaded = false;
+                            }
+                        }
                     }
                 }
+
+                if let Some(JsonNode::Object(nine_slices_object)) = entry.pointer("nine_slices") {
+                    for (slice_name, nine_slice) in nine_slices_object.iter() {
+                        match NineSlice::from_json(nine_slice, &atlas) {
+                            Ok(nine_slice) => atlas.add_nine_slice(slice_name, nine_slice),
+                            Err(err) => {
+                                println!("Failed to load nine slice: {} {} ({})", atlas_name, slice_name, err);
+                                loaded = false;
+                            }
+                        }
+                    }
+                }
+
+                atlas_resources.add_registry(atlas_name, atlas);
+            },
+            Err(err) => {
+                println!("Failed to load atlas: {} ({})", atlas_name, err);
+                loaded = false;
+            }
+        }
+    }
+
+    loaded
+}
+
+/// Load the texture arrays in texture_arrays_object. An entry is either an array of layer paths, or
+/// { "layers": [path, ...], "linear": true, "filter": "nearest", ... } with the same options as textures.
+/// Returns false when any texture array failed to load.
+fn load_texture_arrays(texture_arrays_object: &JsonObject, texture_array_resources: &mut ResourceManager<TextureArray>, res_paths: &ResourcePaths,
+    srgb: bool, texture_defaults: TextureSampling) -> bool {
+    let mut loaded = true;
+
+    for (array_name, entry) in texture_arrays_object.iter() {
+        let layers = match entry {
+            JsonNode::Array(_) => Vec::<String>::from_json(entry),
+            _ => entry.pointer("layers").and_then(Vec::<String>::from_json)
+        };
+
+        let layers = match layers {
+            Some(layers) => layers,
+            None => {
+                println!("Expected an array of paths or {{ \"layers\": [path, ...] }} for texture array {}", array_name);
+                loaded = false;
+                continue;
+            }
+        };
+
+        let paths: Vec<String> = layers.iter().map(|layer| res_paths.resolve(layer)).collect();
+        let paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+        let linear = entry.pointer("linear").and_then(bool::from_json).unwrap_or(false);
+        let sampling = TextureSampling::from_json(entry, texture_defaults);
+
+        match TextureArray::from_files(&paths, srgb && !linear, sampling) {
+            Ok(texture_array) => texture_array_resources.add_registry(array_name, texture_array),
+            Err(err) => {
+                println!("Failed to load texture array: {} ({})", array_name, err);
+                loaded = false;
+            }
+        }
+    }
+
+    loaded
+}
+
+/// Load the json mesh descriptions in meshes_object into memory with the given name.
+fn load_meshes(meshes_object: &JsonObject, mesh_resources: &mut ResourceManager<Mesh2D>, res_paths: &ResourcePaths) {
+    for (mesh_name, path) in meshes_object.iter() {
+        match path {
+            JsonNode::String(mesh_file_path) => {
+                let mesh_path = res_paths.resolve(mesh_file_path.get());
+
+                match Mesh2D::from_json(&mesh_path) {
+                    Ok(mesh) => mesh_resources.add_registry(mesh_name, mesh),
+                    Err(err) => println!("Failed to load mesh: {} {} ({})", mesh_name, mesh_file_path.get(), err)
+                }
             },
             _ => {}
         }
     }
+}
 
-    println!("Loaded game textures in {} seconds", stopwatch.elapsed_seconds());
+/// Load the fonts in fonts_object. Glyphs are rasterized later, the first time they're drawn.
+fn load_fonts(fonts_object: &JsonObject, font_resources: &mut ResourceManager<Font>, res_paths: &ResourcePaths) {
+    for (font_name, path) in fonts_object.iter() {
+        match path {
+            JsonNode::String(font_file_path) => {
+                let font_path = res_paths.resolve(font_file_path.get());
+
+                match Font::open(&font_path) {
+                    Ok(font) => font_resources.add_registry(font_name, font),
+                    Err(err) => println!("Failed to load font: {} {} ({})", font_name, font_file_path.get(), err)
+                }
+            },
+            _ => {}
+        }
+    }
 }
 
-fn load_shaders(shaders_object: &JsonObject, shader_resources: &mut ResourceManager<ShaderProgram>, res_path: &str) {
+fn load_shaders(shaders_object: &JsonObject, shader_resources: &mut ResourceManager<ShaderProgram>, res_paths: &ResourcePaths) {
     let stopwatch = Stopwatch::new();
 
     for (shader_name, shader_data) in shaders_object.iter() {
@@ -363,8 +1296,8 @@ fn load_shaders(shaders_object: &JsonObject, shader_resources: &mut ResourceMana
                 // Load the shader program if we can.
                 if let Some(JsonNode::String(vertex_shader_path)) = shader_data_object.get("vertex") {
                     if let Some(JsonNode::String(fragment_shader_path)) = shader_data_object.get("fragment") {
-                        let vs_shader = res_path.to_string() + "/" + vertex_shader_path.get();
-                        let fs_shader = res_path.to_string() + "/" + fragment_shader_path.get();
+                        let vs_shader = res_paths.resolve(vertex_shader_path.get());
+                        let fs_shader = res_paths.resolve(fragment_shader_path.get());
                         let shader_program = ShaderProgram::open_shaders(&vs_shader, &fs_shader);
 
                         // If we successfully load a shader, attempt to load the next one.
@@ -387,6 +1320,15 @@ fn load_shaders(shaders_object: &JsonObject, shader_resources: &mut ResourceMana
     println!("Loaded game shaders in {} seconds", stopwatch.elapsed_seconds());
 }
 
+/// Reads a number node as a float. Whole numbers are parsed as integers, so both are accepted.
+fn json_to_f32(node: &JsonNode) -> Option<f32> {
+    match node {
+        JsonNode::Float(value) => Some(*value.get() as f32),
+        JsonNode::Number(value) => Some(*value.get() as f32),
+        _ => None
+    }
+}
+
 /// Loads an app config. When I learn how macros work, determine a way
 /// to set the following attributes 
 /// path: ex("/window/xres")
@@ -398,7 +1340,7 @@ fn load_shaders(shaders_object: &JsonObject, shader_resources: &mut ResourceMana
 /// required: ex(true) -> simply tells the system whether the setting must be included in the file.
 /// default_value: ex(1920)
 /// ^^ TODO
-fn load_window_config(user_config: &JsonNode) -> WindowConfig {
+fn load_window_config(user_config: &JsonNode, res_paths: &ResourcePaths) -> WindowConfig {
     let mut config = WindowConfig::default();
 
     match user_config {
@@ -423,6 +1365,22 @@ fn load_window_config(user_config: &JsonNode) -> WindowConfig {
                     if let Some(JsonNode::Bool(resizable)) = window_object.get("resizable") {
                         config.resizable = *resizable.get();
                     }
+
+                    // The icon is either a single path or an array of paths to different sizes.
+                    match window_object.get("icon") {
+                        Some(JsonNode::String(icon_path)) => {
+                            config.icon_paths.push(res_paths.resolve(icon_path.get()));
+                        },
+                        Some(JsonNode::Array(icon_paths)) => {
+                            for icon_path in icon_paths.iter() {
+                                match icon_path {
+                                    JsonNode::String(icon_path) => config.icon_paths.push(res_paths.resolve(icon_path.get())),
+                                    _ => println!("Expected a path string in the window icon array")
+                                }
+                            }
+                        },
+                        _ => {}
+                    }
                 }
                 // The window config is not required.
                 _ => {}
@@ -431,19 +1389,41 @@ fn load_window_config(user_config: &JsonNode) -> WindowConfig {
             // Load the graphics configuration.
             match file_object.get("graphics") {
                 Some(JsonNode::Object(graphics_object)) => {
-                    // Load the clear color attribute.
+                    // Load the clear color attribute, either as [r, g, b, a], "#RRGGBB(AA)", or { "r": r, "g": g, "b": b }.
+                    // The color is sRGB, like colors picked in an art program.
                     match graphics_object.get("clear_color") {
+                        Some(JsonNode::String(clear_color_hex)) => {
+                            match Color::from_hex(clear_color_hex.get()) {
+                                Some(color) => config.clear_color = WindowClearColor::from(color),
+                                None => println!("Expected clear_color to be a hex color like \"#1A1A1A\", found {}", clear_color_hex.get())
+                            }
+                        }
+                        Some(JsonNode::Array(clear_color_array)) => {
+                            let channels: Vec<f32> = clear_color_array.iter()
+                                .filter_map(|channel| json_to_f32(channel))
+                                .collect();
+
+                            if channels.len() == 3 || channels.len() == 4 {
+                                config.clear_color.r = channels[0];
+                                config.clear_color.g = channels[1];
+                                config.clear_color.b = channels[2];
+                                config.clear_color.a = channels.get(3).copied().unwrap_or(1.0);
+                            }
+                            else {
+                                println!("Expected 3 or 4 numbers in clear_color, found {}", clear_color_array.size());
+                            }
+                        }
                         Some(JsonNode::Object(clear_color_object)) => {
-                            if let Some(JsonNode::Float(r)) = clear_color_object.get("r") {
-                                config.clear_color.r = *r.get() as f32;
+                            if let Some(r) = clear_color_object.get("r").and_then(json_to_f32) {
+                                config.clear_color.r = r;
                             }
 
-                            if let Some(JsonNode::Float(g)) = clear_color_object.get("g") {
-                                config.clear_color.g = *g.get() as f32;
+                            if let Some(g) = clear_color_object.get("g").and_then(json_to_f32) {
+                                config.clear_color.g = g;
                             }
 
-                            if let Some(JsonNode::Float(b)) = clear_color_object.get("b") {
-                                config.clear_color.b = *b.get() as f32;
+                            if let Some(b) = clear_color_object.get("b").and_then(json_to_f32) {
+                                config.clear_color.b = b;
                             }
                         }
                         _ => {}
@@ -455,6 +1435,41 @@ fn load_window_config(user_config: &JsonNode) -> WindowConfig {
                         },
                         _ => {}
                     }
+
+                    if let Some(JsonNode::Bool(srgb)) = graphics_object.get("srgb") {
+                        config.srgb = *srgb.get();
+                    }
+
+                    if let Some(JsonNode::Bool(clear_depth)) = graphics_object.get("clear_depth") {
+                        config.clear_depth = *clear_depth.get();
+                    }
+
+                    if let Some(JsonNode::Bool(clear_stencil)) = graphics_object.get("clear_stencil") {
+                        config.clear_stencil = *clear_stencil.get();
+                    }
+
+                    if let Some(stencil_bits) = graphics_object.get("stencil_bits").and_then(u32::from_json) {
+                        config.stencil_bits = stencil_bits;
+                    }
+
+                    if let Some(JsonNode::Number(msaa_samples)) = graphics_object.get("msaa_samples") {
+                        let requested = u32::try_from(*msaa_samples.get()).unwrap_or(0);
+                        config.msaa_samples = valid_msaa_samples(requested);
+
+                        if config.msaa_samples != requested {
+                            println!("Unsupported msaa_samples {}, using {} instead. Supported values are {:?}",
+                                requested, config.msaa_samples, MSAA_SAMPLE_COUNTS);
+                        }
+                    }
+
+                    if let Some(JsonNode::Bool(gl_debug)) = graphics_object.get("gl_debug") {
+                        config.gl_debug = *gl_debug.get();
+                    }
+
+                    // An sRGB window blends in linear space, and converts the clear color to sRGB when it's written.
+                    if config.srgb {
+                        config.clear_color = WindowClearColor::from(Color::from(config.clear_color).to_linear());
+                    }
                 },
                 // If we don't have a graphics branch, don't try to load it.
                 _ => {}
@@ -465,3 +1480,30 @@ fn load_window_config(user_config: &JsonNode) -> WindowConfig {
 
     config
 }
+
+/// Creates the debug overlay from the "game/debug_overlay" object: the "key" toggling it, F3 by default,
+/// and the "font" its text is drawn with. Without a font only the frame time graph is drawn.
+fn load_debug_overlay(user_config: &JsonNode, res_paths: &ResourcePaths) -> DebugOverlayPipeline {
+    let key = match user_config.pointer("game/debug_overlay/key").and_then(String::from_json) {
+        Some(name) => Key::from_name(&name).unwrap_or_else(|| {
+            println!("Unknown debug overlay key {}, using F3", name);
+            Key::F3
+        }),
+        None => Key::F3
+    };
+
+    let font = user_config.pointer("game/debug_overlay/font").and_then(String::from_json)
+        .and_then(|font_path| {
+            let font_path = res_paths.resolve(&font_path);
+
+            match Font::open(&font_path) {
+                Ok(font) => Some(font),
+                Err(err) => {
+                    println!("Failed to load the debug overlay font {}: {}", font_path, err);
+                    None
+                }
+            }
+        });
+
+    DebugOverlayPipeline::new(font, key)
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font as AbFont, FontVec, GlyphId, PxScale, ScaleFont};
+use glmath::glmath::Vec2f;
+
+use crate::resource_manager::ResourceDestroy;
+use crate::texture::Texture;
+use crate::texture::packing::{RectPacker, ShelfPacker};
+
+/// Width and height of every atlas page in pixels.
+pub(crate) const ATLAS_PAGE_SIZE: u32 = 512;
+
+/// Empty pixels left around each glyph so linear filtering doesn't bleed neighbours in.
+pub(crate) const GLYPH_PADDING: u32 = 1;
+
+/// Vertical metrics of a font at a given pixel size.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    /// Distance from the baseline to the top of the tallest glyph.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest glyph. Usually negative.
+    pub descent: f32,
+    /// Extra space the font asks for between lines.
+    pub line_gap: f32
+}
+
+impl LineMetrics {
+    /// Distance between two consecutive baselines.
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Location and placement of a single rasterized glyph.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct GlyphInfo {
+    /// Index of the atlas page holding the glyph. None for glyphs without an outline (spaces).
+    pub(crate) page: Option<usize>,
+    pub(crate) uv_min: Vec2f,
+    pub(crate) uv_max: Vec2f,
+    /// Offset from the pen position on the baseline to the bottom left corner of the glyph.
+    pub(crate) offset: Vec2f,
+    pub(crate) size: Vec2f
+}
+
+/// A single quad produced by laying out text.
+/// Positions are in pixels relative to the start of the first baseline, with y pointing up.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    /// Atlas page which should be bound when drawing this quad.
+    pub page: usize,
+    pub min: Vec2f,
+    pub max: Vec2f,
+    pub uv_min: Vec2f,
+    pub uv_max: Vec2f
+}
+
+/// Decides where glyphs go in the atlas pages, without touching their textures.
+/// Glyphs are of similar heights, so each page is packed into shelves, and a page is added once
+/// a glyph doesn't fit in the newest one.
+pub(crate) struct GlyphPlacer {
+    pages: Vec<ShelfPacker>
+}
+
+impl GlyphPlacer {
+    pub(crate) fn new() -> Self {
+        GlyphPlacer {
+            pages: Vec::<ShelfPacker>::default()
+        }
+    }
+
+    pub(crate) fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Reserves a padded region for a glyph, returning its page and the corner of the glyph within it.
+    /// Returns None when the glyph is larger than a page.
+    pub(crate) fn place(&mut self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let padded_width = width + GLYPH_PADDING * 2;
+        let padded_height = height + GLYPH_PADDING * 2;
+
+        // Try the newest page first, then fall back to adding another page.
+        let rect = match self.pages.last_mut().and_then(|page| page.pack(padded_width, padded_height)) {
+            Some(rect) => rect,
+            None => {
+                let mut page = ShelfPacker::new(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE);
+                let rect = page.pack(padded_width, padded_height)?;
+                self.pages.push(page);
+                rect
+            }
+        };
+
+        Some((self.pages.len() - 1, rect.x + GLYPH_PADDING, rect.y + GLYPH_PADDING))
+    }
+}
+
+/// All the glyphs of a font rasterized at a single pixel size.
+/// Glyphs are rasterized lazily the first time they are laid out.
+pub(crate) struct GlyphAtlas {
+    scale: PxScale,
+    placer: GlyphPlacer,
+    /// One texture per page of the placer.
+    pages: Vec<Texture>,
+    pub(crate) glyphs: HashMap<char, GlyphInfo>
+}
+
+impl GlyphAtlas {
+    pub(crate) fn new(pixel_size: u32) -> Self {
+        GlyphAtlas {
+            scale: PxScale::from(pixel_size as f32),
+            placer: GlyphPlacer::new(),
+            pages: Vec::<Texture>::default(),
+            glyphs: HashMap::<char, GlyphInfo>::default()
+        }
+    }
+
+    /// Returns the glyph info of a character, rasterizing it into the atlas if needed.
+    fn glyph(&mut self, font: &FontVec, ch: char) -> GlyphInfo {
+        if let Some(info) = self.glyphs.get(&ch) {
+            return *info;
+        }
+
+        let info = self.rasterize(font, ch);
+        self.glyphs.insert(ch, info);
+        info
+    }
+
+    fn rasterize(&mut self, font: &FontVec, ch: char) -> GlyphInfo {
+        let glyph = font.as_scaled(self.scale).scaled_glyph(ch);
+
+        let outline = match font.outline_glyph(glyph) {
+            Some(outline) => outline,
+            // Whitespace and missing glyphs have nothing to draw.
+            None => return GlyphInfo::default()
+        };
+
+        let bounds = outline.px_bounds();
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+
+        // Rasterize the coverage into white pixels so the texture can be tinted in the shader.
+        // Rows are flipped so the bottom of the glyph is at the bottom of the texture region.
+        let mut pixels = vec![255u8; (width * height * 4) as usize];
+        outline.draw(|x, y, coverage| {
+            let index = ((height - 1 - y) * width + x) as usize * 4;
+            pixels[index + 3] = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+        });
+
+        let (page, x, y) = match self.placer.place(width, height) {
+            Some(placement) => placement,
+            None => {
+                println!("Glyph '{}' is too large to fit in a {}px atlas page", ch, ATLAS_PAGE_SIZE);
+                return GlyphInfo::default();
+            }
+        };
+
+        while self.pages.len() < self.placer.page_count() {
+            self.pages.push(Texture::blank(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE));
+        }
+
+        if let Err(err) = self.pages[page].update_region(x, y, width, height, &pixels) {
+            println!("Failed to upload glyph '{}': {}", ch, err);
+            return GlyphInfo::default();
+        }
+
+        let page_size = ATLAS_PAGE_SIZE as f32;
+
+        GlyphInfo {
+            page: Some(page),
+            uv_min: Vec2f::new(x as f32 / page_size, y as f32 / page_size),
+            uv_max: Vec2f::new((x + width) as f32 / page_size, (y + height) as f32 / page_size),
+            // px_bounds is y down from the baseline, so the bottom of the glyph is at -max.y.
+            offset: Vec2f::new(bounds.min.x, -bounds.max.y),
+            size: Vec2f::new(width as f32, height as f32)
+        }
+    }
+}
+
+/// A TTF or OTF font which rasterizes glyphs into texture atlases on demand.
+///
+/// An atlas is cached for every pixel size the font is requested at. Atlases grow by adding
+/// pages when they fill up, and glyphs are never evicted: every page lives until the font
+/// is dropped.
+pub struct Font {
+    font: FontVec,
+    pub(crate) atlases: HashMap<u32, GlyphAtlas>
+}
+
+impl Font {
+    /// Loads a font from a .ttf or .otf file.
+    pub fn open(font_path: &str) -> Result<Font, Box<dyn std::error::Error>> {
+        let font_data = std::fs::read(font_path)?;
+        Font::from_bytes(font_data)
+    }
+
+    /// Creates a font from the contents of a font file.
+    pub fn from_bytes(font_data: Vec<u8>) -> Result<Font, Box<dyn std::error::Error>> {
+        let font = FontVec::try_from_vec(font_data)?;
+
+        Ok(Font {
+            font,
+            atlases: HashMap::<u32, GlyphAtlas>::default()
+        })
+    }
+
+    /// Returns the vertical metrics of the font at the given pixel size.
+    pub fn line_metrics(&self, pixel_size: u32) -> LineMetrics {
+        let scaled = self.font.as_scaled(PxScale::from(pixel_size as f32));
+
+        LineMetrics {
+            ascent: scaled.ascent(),
+            descent: scaled.descent(),
+            line_gap: scaled.line_gap()
+        }
+    }
+
+    /// Returns the texture of an atlas page, to be bound when drawing quads from that page.
+    pub fn page_texture(&self, pixel_size: u32, page: usize) -> Option<&Texture> {
+        self.atlases.get(&pixel_size)
+            .and_then(|atlas| atlas.pages.get(page))
+    }
+
+    /// Lays out a string into textured quads, rasterizing any glyphs which haven't been used yet.
+    /// Kerning and line spacing come from the font, and '\n' moves the pen to the next line.
+    pub fn layout_text(&mut self, text: &str, pixel_size: u32) -> Vec<GlyphQuad> {
+        let line_height = self.line_metrics(pixel_size).line_height();
+
+        let font = &self.font;
+        let atlas = self.atlases.entry(pixel_size)
+            .or_insert_with(|| GlyphAtlas::new(pixel_size));
+        let scaled = font.as_scaled(atlas.scale);
+
+        let mut quads = Vec::<GlyphQuad>::with_capacity(text.len());
+        let mut pen = Vec2f::ZERO;
+        let mut previous: Option<GlyphId> = None;
+
+        for ch in text.chars() {
+            if ch == '\n' {
+                pen = Vec2f::new(0.0, pen.y - line_height);
+                previous = None;
+                continue;
+            }
+
+            let glyph_id = font.glyph_id(ch);
+
+            if let Some(previous) = previous {
+                pen.x += scaled.kern(previous, glyph_id);
+            }
+
+            let info = atlas.glyph(font, ch);
+
+            if let Some(page) = info.page {
+                let min = pen + info.offset;
+
+                quads.push(GlyphQuad {
+                    page,
+                    min,
+                    max: min + info.size,
+                    uv_min: info.uv_min,
+                    uv_max: info.uv_max
+                });
+            }
+
+            pen.x += scaled.h_advance(glyph_id);
+            previous = Some(glyph_id);
+        }
+
+        quads
+    }
+}
+
+/// The atlas page textures are freed when the font is dropped.
+impl ResourceDestroy for Font {}
@@ -0,0 +1,84 @@
+/// Smooths frame times with an exponential moving average, so reported frame rates don't jitter
+/// from frame to frame the way a count of whole frames per second does.
+///
+/// Each frame moves the average `smoothing` of the way towards the new frame time. The first frame
+/// time is taken as is, so the average doesn't have to climb up from zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTimeAverage {
+    smoothing: f32,
+    average: Option<f32>
+}
+
+impl Default for FrameTimeAverage {
+    fn default() -> Self {
+        FrameTimeAverage::new(0.1)
+    }
+}
+
+impl FrameTimeAverage {
+    /// Creates an average weighing each new frame by smoothing, between 0 and 1.
+    /// Smaller values are steadier but slower to follow changes in the frame rate.
+    pub fn new(smoothing: f32) -> Self {
+        #[cfg(debug_assertions)]
+        assert!(smoothing > 0.0 && smoothing <= 1.0, "FrameTimeAverage smoothing must be within (0, 1]");
+
+        FrameTimeAverage {
+            smoothing,
+            average: None
+        }
+    }
+
+    /// Adds the time in seconds the last frame took.
+    pub fn add(&mut self, frame_time: f32) {
+        self.average = Some(match self.average {
+            Some(average) => average + (frame_time - average) * self.smoothing,
+            None => frame_time
+        });
+    }
+
+    /// Returns the average frame time in seconds, or zero before the first frame.
+    pub fn average(&self) -> f32 {
+        self.average.unwrap_or(0.0)
+    }
+
+    /// Returns the frames per second of the average frame time, or zero before the first frame.
+    pub fn fps(&self) -> f32 {
+        match self.average {
+            Some(average) if average > 0.0 => 1.0 / average,
+            _ => 0.0
+        }
+    }
+
+    /// Forgets the frames seen so far, for example after a load screen stalled the game.
+    pub fn reset(&mut self) {
+        self.average = None;
+    }
+}
+
+/// Timings of the last frames, from `GameManager::frame_stats`. Times are in milliseconds and smoothed.
+///
+/// GPU times are None when the driver doesn't support timer queries, and for the first frames while
+/// their results are still in flight, so an unmeasured time is never mistaken for a free one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// The time between frames on the CPU.
+    pub cpu_ms: f32,
+    /// The GPU time of the whole frame, from the first pipeline through post processing.
+    pub gpu_ms: Option<f32>,
+    /// The GPU time of each render graph node, by node index.
+    pub pipeline_gpu_ms: Vec<Option<f32>>
+}
+
+impl FrameStats {
+    /// Returns the GPU time of the node at the index, or None when it wasn't measured.
+    pub fn pipeline_gpu_ms(&self, index: usize) -> Option<f32> {
+        self.pipeline_gpu_ms.get(index).copied().flatten()
+    }
+
+    /// Returns the GPU time of the frame spent outside of the pipelines, ex: post processing.
+    pub fn other_gpu_ms(&self) -> Option<f32> {
+        let pipelines_ms = self.pipeline_gpu_ms.iter().try_fold(0.0, |total, ms| ms.map(|ms| total + ms))?;
+
+        self.gpu_ms.map(|gpu_ms| (gpu_ms - pipelines_ms).max(0.0))
+    }
+}
@@ -0,0 +1,371 @@
+use ogl33::*;
+
+use crate::bind_cache;
+use crate::gl_handle::GlHandle;
+use crate::resource_manager::ResourceDestroy;
+use crate::texture::Texture;
+
+/// How a blit samples the source when the destination is a different size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Copies the closest pixel, keeping hard pixel edges.
+    #[default]
+    Nearest,
+    /// Blends the closest pixels, smoothing scaled copies.
+    Linear
+}
+
+impl FilterMode {
+    pub fn to_gl(self) -> GLenum {
+        match self {
+            FilterMode::Nearest => GL_NEAREST,
+            FilterMode::Linear => GL_LINEAR
+        }
+    }
+}
+
+/// The arguments of the glBlitFramebuffer call copying a framebuffer's color into a rect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlitParams {
+    /// Corners of the copied area of the source, x0, y0, x1, y1.
+    pub source: [GLint; 4],
+    /// Corners of the area written in the destination.
+    pub dest: [GLint; 4],
+    pub mask: GLbitfield,
+    pub filter: GLenum,
+    /// Set when the copy has to read the resolved color instead of the multisampled color.
+    pub read_resolved: bool
+}
+
+/// Returns how to copy the color of a source of the given size into the rect of x, y, width, and height.
+/// Only color is copied, since depth and stencil can't be filtered. Multisampled color can only be blit
+/// at its own size, so scaled copies read the resolved color.
+pub(crate) fn blit_params((width, height): (u32, u32), multisampled: bool, [x, y, dest_width, dest_height]: [GLint; 4], filter: FilterMode) -> BlitParams {
+    let same_size = dest_width == width as i32 && dest_height == height as i32;
+
+    BlitParams {
+        source: [0, 0, width as i32, height as i32],
+        dest: [x, y, x + dest_width, y + dest_height],
+        mask: GL_COLOR_BUFFER_BIT,
+        filter: filter.to_gl(),
+        read_resolved: multisampled && !same_size
+    }
+}
+
+/// An offscreen render target with an RGBA color texture and a depth attachment.
+/// Framebuffers created with `new` also have an 8 bit stencil buffer.
+/// Clones share the same GL objects, which are deleted when the last clone is dropped.
+#[derive(Default, Clone)]
+pub struct Framebuffer {
+    handle: GlHandle,
+    color_texture: Option<Texture>,
+    /// Set when the depth attachment is a texture which can be sampled.
+    depth_texture: Option<Texture>,
+    /// Set when the depth attachment is a renderbuffer, which is faster but can't be sampled.
+    depth_renderbuffer: Option<GlHandle>,
+    /// Set for multisampled framebuffers, whose color can't be sampled until it's resolved.
+    color_renderbuffer: Option<GlHandle>,
+    /// The single sampled framebuffer a multisampled one resolves into.
+    resolve_target: Option<Box<Framebuffer>>,
+    samples: u32,
+    width: u32,
+    height: u32
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with a color texture and a combined depth and stencil renderbuffer.
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        let mut framebuffer = Framebuffer::with_color(width, height);
+
+        unsafe {
+            let mut renderbuffer: GLuint = 0;
+            glGenRenderbuffers(1, &mut renderbuffer);
+            glBindRenderbuffer(GL_RENDERBUFFER, renderbuffer);
+            glRenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8, width as i32, height as i32);
+            glBindRenderbuffer(GL_RENDERBUFFER, 0);
+
+            glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_DEPTH_STENCIL_ATTACHMENT, GL_RENDERBUFFER, renderbuffer);
+            framebuffer.depth_renderbuffer = Some(GlHandle::renderbuffer(renderbuffer));
+        }
+
+        framebuffer.finish();
+        framebuffer
+    }
+
+    /// Creates a framebuffer whose depth is written to a texture instead of a renderbuffer,
+    /// so it can be sampled later, e.g. as a shadow map.
+    /// The texture compares against the reference value when sampled, so it should be bound to a
+    /// sampler2DShadow, and linear filtering gives hardware 2x2 PCF.
+    pub fn with_depth_texture(width: u32, height: u32) -> Framebuffer {
+        let mut framebuffer = Framebuffer::with_color(width, height);
+
+        unsafe {
+            let mut texture: GLuint = 0;
+            glGenTextures(1, &mut texture);
+            bind_cache::bind_texture_2d(texture);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_COMPARE_MODE, GL_COMPARE_REF_TO_TEXTURE as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_COMPARE_FUNC, GL_LEQUAL as GLint);
+
+            glTexImage2D(GL_TEXTURE_2D, 0, GL_DEPTH_COMPONENT24 as GLint,
+                width as i32, height as i32, 0,
+                GL_DEPTH_COMPONENT, GL_FLOAT, std::ptr::null());
+
+            bind_cache::bind_texture_2d(0);
+
+            glFramebufferTexture2D(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, GL_TEXTURE_2D, texture, 0);
+            framebuffer.depth_texture = Some(Texture::from_handle(GlHandle::texture(texture), width, height));
+        }
+
+        framebuffer.finish();
+        framebuffer
+    }
+
+    /// Creates a multisampled framebuffer with depth and stencil, for smooth edges when rendering offscreen.
+    /// Multisampled attachments can't be sampled, so call `resolve` after drawing and read the color
+    /// texture, which belongs to a single sampled framebuffer of the same size.
+    /// Sample counts above what the driver supports are clamped, and 0 or 1 samples creates a regular framebuffer.
+    pub fn multisampled(width: u32, height: u32, samples: u32) -> Framebuffer {
+        let mut max_samples: GLint = 0;
+
+        unsafe {
+            glGetIntegerv(GL_MAX_SAMPLES, &mut max_samples);
+        }
+
+        let samples = if samples > max_samples.max(0) as u32 {
+            println!("{} framebuffer samples requested, but only {} are supported", samples, max_samples);
+            max_samples.max(0) as u32
+        }
+        else {
+            samples
+        };
+
+        if samples <= 1 {
+            return Framebuffer::new(width, height);
+        }
+
+        unsafe {
+            let mut fbo: GLuint = 0;
+            glGenFramebuffers(1, &mut fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, fbo);
+
+            let mut renderbuffers: [GLuint; 2] = [0; 2];
+            glGenRenderbuffers(2, renderbuffers.as_mut_ptr());
+            let [color, depth] = renderbuffers;
+
+            glBindRenderbuffer(GL_RENDERBUFFER, color);
+            glRenderbufferStorageMultisample(GL_RENDERBUFFER, samples as i32, GL_RGBA8, width as i32, height as i32);
+            glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_RENDERBUFFER, color);
+
+            glBindRenderbuffer(GL_RENDERBUFFER, depth);
+            glRenderbufferStorageMultisample(GL_RENDERBUFFER, samples as i32, GL_DEPTH24_STENCIL8, width as i32, height as i32);
+            glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_DEPTH_STENCIL_ATTACHMENT, GL_RENDERBUFFER, depth);
+
+            glBindRenderbuffer(GL_RENDERBUFFER, 0);
+
+            let framebuffer = Framebuffer {
+                handle: GlHandle::framebuffer(fbo),
+                color_texture: None,
+                depth_texture: None,
+                depth_renderbuffer: Some(GlHandle::renderbuffer(depth)),
+                color_renderbuffer: Some(GlHandle::renderbuffer(color)),
+                resolve_target: None,
+                samples,
+                width,
+                height
+            };
+
+            framebuffer.finish();
+
+            Framebuffer {
+                resolve_target: Some(Box::new(Framebuffer::new(width, height))),
+                ..framebuffer
+            }
+        }
+    }
+
+    /// Copies the multisampled color into the resolve target so it can be sampled.
+    /// Does nothing for framebuffers which aren't multisampled. Leaves the window bound as the draw target.
+    pub fn resolve(&self) {
+        if let Some(resolve_target) = &self.resolve_target {
+            unsafe {
+                glBindFramebuffer(GL_READ_FRAMEBUFFER, self.handle.id());
+                glBindFramebuffer(GL_DRAW_FRAMEBUFFER, resolve_target.framebuffer_id());
+
+                glBlitFramebuffer(0, 0, self.width as i32, self.height as i32,
+                    0, 0, self.width as i32, self.height as i32,
+                    GL_COLOR_BUFFER_BIT, GL_NEAREST);
+
+                glBindFramebuffer(GL_FRAMEBUFFER, 0);
+            }
+        }
+    }
+
+    /// Copies the color into the destination, scaling it to the destination's size with the filter, ex: to
+    /// downsample the scene before blurring it. Multisampled destinations receive the copy in the texture
+    /// returned by their `color_texture`. Leaves the window bound as the draw target.
+    pub fn blit_to(&self, dest: &Framebuffer, filter: FilterMode) {
+        let dest_id = dest.resolve_target_id().unwrap_or(dest.framebuffer_id());
+        self.blit(dest_id, [0, 0, dest.width as i32, dest.height as i32], filter);
+    }
+
+    /// Copies the color into the window, scaled to fill the current viewport with the filter.
+    /// Leaves the window bound as the draw target.
+    pub fn blit_to_screen(&self, filter: FilterMode) {
+        let mut viewport: [GLint; 4] = [0; 4];
+
+        unsafe {
+            glGetIntegerv(GL_VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        self.blit(0, viewport, filter);
+    }
+
+    /// Blits the color into the rect of x, y, width, and height of the draw framebuffer.
+    fn blit(&self, draw_framebuffer: GLuint, rect: [GLint; 4], filter: FilterMode) {
+        let params = blit_params((self.width, self.height), self.resolve_target.is_some(), rect, filter);
+
+        let read_framebuffer = match &self.resolve_target {
+            Some(resolve_target) if params.read_resolved => {
+                self.resolve();
+                resolve_target.framebuffer_id()
+            },
+            _ => self.handle.id()
+        };
+
+        let ([src_x0, src_y0, src_x1, src_y1], [dest_x0, dest_y0, dest_x1, dest_y1]) = (params.source, params.dest);
+
+        unsafe {
+            glBindFramebuffer(GL_READ_FRAMEBUFFER, read_framebuffer);
+            glBindFramebuffer(GL_DRAW_FRAMEBUFFER, draw_framebuffer);
+
+            glBlitFramebuffer(src_x0, src_y0, src_x1, src_y1,
+                dest_x0, dest_y0, dest_x1, dest_y1,
+                params.mask, params.filter);
+
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Regenerates the color texture's mipmaps from what was drawn, resolving first when multisampled.
+    /// For effects reading lower levels, like bloom downsampling. Set a mipmap min filter on the texture to sample them.
+    pub fn generate_mipmaps(&self) {
+        self.resolve();
+
+        if let Some(texture) = self.color_texture() {
+            texture.generate_mipmaps();
+        }
+    }
+
+    /// Returns the number of samples per pixel, 0 for framebuffers which aren't multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.color_renderbuffer.is_some()
+    }
+
+    /// Returns true when the driver accepts the attachments, including the resolve target's for
+    /// multisampled framebuffers. Leaves the window bound.
+    pub fn is_complete(&self) -> bool {
+        let status = unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.handle.id());
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+            status
+        };
+
+        status == GL_FRAMEBUFFER_COMPLETE && self.resolve_target.as_ref().is_none_or(|target| target.is_complete())
+    }
+
+    /// Creates the framebuffer with its color attachment, leaving it bound so depth can be attached.
+    fn with_color(width: u32, height: u32) -> Framebuffer {
+        unsafe {
+            let mut fbo: GLuint = 0;
+            glGenFramebuffers(1, &mut fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, fbo);
+
+            let color_texture = Texture::blank(width, height);
+            glFramebufferTexture2D(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_TEXTURE_2D, color_texture.texture_id(), 0);
+
+            Framebuffer {
+                handle: GlHandle::framebuffer(fbo),
+                color_texture: Some(color_texture),
+                depth_texture: None,
+                depth_renderbuffer: None,
+                color_renderbuffer: None,
+                resolve_target: None,
+                samples: 0,
+                width,
+                height
+            }
+        }
+    }
+
+    /// Checks the framebuffer is usable and unbinds it.
+    fn finish(&self) {
+        unsafe {
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                println!("Framebuffer ({}x{}) is incomplete, status: {:#x}", self.width, self.height, status);
+            }
+
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Binds the framebuffer as the render target and sets the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.handle.id());
+            glViewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    /// Binds the window as the render target again. The caller is responsible for restoring the viewport.
+    pub fn unbind(&self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn framebuffer_id(&self) -> u32 {
+        self.handle.id()
+    }
+
+    /// The framebuffer a multisampled one resolves into, which holds the readable color.
+    pub(crate) fn resolve_target_id(&self) -> Option<u32> {
+        self.resolve_target.as_ref().map(|target| target.framebuffer_id())
+    }
+
+    /// Returns the color texture. For multisampled framebuffers this is the resolve target's texture,
+    /// which holds what was drawn as of the last `resolve`.
+    pub fn color_texture(&self) -> Option<&Texture> {
+        match &self.resolve_target {
+            Some(resolve_target) => resolve_target.color_texture(),
+            None => self.color_texture.as_ref()
+        }
+    }
+
+    /// Returns the depth texture for framebuffers created with `with_depth_texture`.
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// The framebuffer and its attachments are freed when the last handle is dropped.
+impl ResourceDestroy for Framebuffer {}
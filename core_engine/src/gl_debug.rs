@@ -0,0 +1,171 @@
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ogl33::*;
+
+// KHR_debug enums, which ogl33 doesn't include since they're newer than GL 3.3.
+const GL_DEBUG_OUTPUT: GLenum = 0x92E0;
+const GL_DEBUG_OUTPUT_SYNCHRONOUS: GLenum = 0x8242;
+const GL_DEBUG_TYPE_ERROR: GLenum = 0x824C;
+const GL_DEBUG_SEVERITY_HIGH: GLenum = 0x9146;
+const GL_DEBUG_SEVERITY_MEDIUM: GLenum = 0x9147;
+const GL_DEBUG_SEVERITY_LOW: GLenum = 0x9148;
+const GL_DEBUG_SEVERITY_NOTIFICATION: GLenum = 0x826B;
+
+type GlDebugProc = extern "system" fn(source: GLenum, message_type: GLenum, id: GLuint, severity: GLenum,
+    length: GLsizei, message: *const GLchar, user_param: *mut std::ffi::c_void);
+type GlDebugMessageCallback = unsafe extern "system" fn(callback: GlDebugProc, user_param: *const std::ffi::c_void);
+
+/// Set when the driver lacks KHR_debug, so engine GL calls check glGetError instead.
+static CHECK_ERRORS: AtomicBool = AtomicBool::new(false);
+static PANIC_ON_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// How serious a driver message is, as reported by KHR_debug.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    /// Informational, ex: where a buffer was placed in memory.
+    Notification,
+    Low,
+    Medium,
+    High
+}
+
+impl DebugSeverity {
+    pub fn from_gl(severity: GLenum) -> DebugSeverity {
+        match severity {
+            GL_DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            GL_DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            GL_DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            GL_DEBUG_SEVERITY_NOTIFICATION => DebugSeverity::Notification,
+            _ => DebugSeverity::Medium
+        }
+    }
+}
+
+/// Where GL errors are being reported from, see `enable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugOutput {
+    /// The driver reports messages through KHR_debug as each call is made.
+    Callback,
+    /// The driver lacks KHR_debug, so glGetError is checked after each engine GL call.
+    ErrorChecks
+}
+
+/// Returns the name of a glGetError code.
+pub fn gl_error_name(error: GLenum) -> &'static str {
+    match error {
+        GL_NO_ERROR => "GL_NO_ERROR",
+        GL_INVALID_ENUM => "GL_INVALID_ENUM",
+        GL_INVALID_VALUE => "GL_INVALID_VALUE",
+        GL_INVALID_OPERATION => "GL_INVALID_OPERATION",
+        GL_INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        GL_OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        _ => "unknown GL error"
+    }
+}
+
+/// Starts reporting GL errors and driver warnings. Needs a debug context to receive more than errors,
+/// see `WindowConfig::gl_debug`, which the engine enables this for.
+///
+/// With KHR_debug, messages are logged from a callback the driver calls synchronously, so the stack
+/// points at the offending call. Otherwise every engine GL wrapper checks glGetError afterwards and
+/// logs its call site, which stalls the pipeline and can easily halve the frame rate.
+/// Neither costs anything when this isn't called: error checks are one relaxed atomic load.
+pub fn enable() -> DebugOutput {
+    let message_callback = crate::gl_proc_address("glDebugMessageCallback");
+
+    if !khr_debug_supported() || message_callback.is_null() {
+        CHECK_ERRORS.store(true, Ordering::Relaxed);
+        println!("KHR_debug isn't supported, checking glGetError after engine GL calls");
+        return DebugOutput::ErrorChecks;
+    }
+
+    unsafe {
+        let message_callback = std::mem::transmute::<*const std::ffi::c_void, GlDebugMessageCallback>(message_callback);
+
+        glEnable(GL_DEBUG_OUTPUT);
+        glEnable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+        message_callback(debug_callback, std::ptr::null());
+    }
+
+    DebugOutput::Callback
+}
+
+/// When set, GL errors panic in debug builds instead of only being logged, stopping at the offending call.
+/// Release builds always just log. A panic can't unwind through the driver, so errors reported by the
+/// debug callback abort after printing the panic, run with RUST_BACKTRACE=1 to see the call.
+pub fn set_panic_on_error(panic_on_error: bool) {
+    PANIC_ON_ERROR.store(panic_on_error, Ordering::Relaxed);
+}
+
+/// Checks glGetError after an engine GL call when error checks replace the debug callback.
+/// The call site of the caller is logged with the error, so mark wrappers calling this `#[track_caller]`.
+#[track_caller]
+#[inline]
+pub(crate) fn check_errors(call: &str) {
+    if CHECK_ERRORS.load(Ordering::Relaxed) {
+        check_errors_at(call, Location::caller());
+    }
+}
+
+#[cold]
+fn check_errors_at(call: &str, location: &Location) {
+    loop {
+        let error = unsafe { glGetError() };
+
+        if error == GL_NO_ERROR {
+            break;
+        }
+
+        report(true, &format!("{} after {} at {}", gl_error_name(error), call, location));
+    }
+}
+
+/// Formats a message from the driver for the log.
+pub(crate) fn format_debug_message(severity: DebugSeverity, is_error: bool, id: GLuint, message: &str) -> String {
+    let kind = if is_error { "error" } else { "message" };
+    format!("[GL {:?} {} {}] {}", severity, kind, id, message.trim_end())
+}
+
+fn report(is_error: bool, message: &str) {
+    println!("{}", message);
+
+    #[cfg(debug_assertions)]
+    if is_error && PANIC_ON_ERROR.load(Ordering::Relaxed) {
+        panic!("{}", message);
+    }
+}
+
+extern "system" fn debug_callback(_source: GLenum, message_type: GLenum, id: GLuint, severity: GLenum,
+    length: GLsizei, message: *const GLchar, _user_param: *mut std::ffi::c_void) {
+    let text = unsafe {
+        match length < 0 {
+            true => std::ffi::CStr::from_ptr(message).to_string_lossy().into_owned(),
+            false => String::from_utf8_lossy(std::slice::from_raw_parts(message as *const u8, length as usize)).into_owned()
+        }
+    };
+
+    let severity = DebugSeverity::from_gl(severity);
+    let is_error = message_type == GL_DEBUG_TYPE_ERROR;
+
+    // Notifications describe normal driver behavior and arrive every frame on some drivers.
+    if severity == DebugSeverity::Notification && !is_error {
+        return;
+    }
+
+    report(is_error, &format_debug_message(severity, is_error, id, &text));
+}
+
+/// Returns true when the driver supports KHR_debug.
+fn khr_debug_supported() -> bool {
+    unsafe {
+        let mut extension_count: GLint = 0;
+        glGetIntegerv(GL_NUM_EXTENSIONS, &mut extension_count);
+
+        (0..extension_count.max(0) as GLuint).any(|index| {
+            let name = glGetStringi(GL_EXTENSIONS, index);
+
+            !name.is_null() && std::ffi::CStr::from_ptr(name as *const std::ffi::c_char).to_bytes() == b"GL_KHR_debug"
+        })
+    }
+}
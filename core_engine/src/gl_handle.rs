@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+use ogl33::*;
+
+use crate::bind_cache;
+
+/// The GL object name along with the function which deletes it.
+struct GlObject {
+    id: GLuint,
+    deleter: fn(GLuint)
+}
+
+impl Drop for GlObject {
+    fn drop(&mut self) {
+        // Zero is never a valid object name, so there's nothing to free.
+        if self.id != 0 {
+            (self.deleter)(self.id);
+        }
+    }
+}
+
+/// A shared GL object name. Cloning the handle shares the object rather than copying it,
+/// and the object is deleted once the last clone is dropped.
+/// GL objects are only valid on the thread owning the context, so this is deliberately not Send.
+#[derive(Clone)]
+pub struct GlHandle {
+    object: Rc<GlObject>
+}
+
+impl Default for GlHandle {
+    fn default() -> Self {
+        GlHandle::new(0, |_| {})
+    }
+}
+
+impl GlHandle {
+    /// Wraps an object name which is freed by calling the deleter.
+    pub fn new(id: GLuint, deleter: fn(GLuint)) -> Self {
+        GlHandle {
+            object: Rc::new(GlObject { id, deleter })
+        }
+    }
+
+    pub fn texture(id: GLuint) -> Self {
+        GlHandle::new(id, |id| {
+            bind_cache::forget_texture(id);
+            unsafe { glDeleteTextures(1, &id) }
+        })
+    }
+
+    pub fn program(id: GLuint) -> Self {
+        GlHandle::new(id, |id| {
+            bind_cache::forget_program(id);
+            unsafe { glDeleteProgram(id) }
+        })
+    }
+
+    pub fn vertex_array(id: GLuint) -> Self {
+        GlHandle::new(id, |id| {
+            bind_cache::forget_vertex_array(id);
+            unsafe { glDeleteVertexArrays(1, &id) }
+        })
+    }
+
+    pub fn buffer(id: GLuint) -> Self {
+        GlHandle::new(id, |id| unsafe { glDeleteBuffers(1, &id) })
+    }
+
+    pub fn framebuffer(id: GLuint) -> Self {
+        GlHandle::new(id, |id| unsafe { glDeleteFramebuffers(1, &id) })
+    }
+
+    pub fn renderbuffer(id: GLuint) -> Self {
+        GlHandle::new(id, |id| unsafe { glDeleteRenderbuffers(1, &id) })
+    }
+
+    pub fn query(id: GLuint) -> Self {
+        GlHandle::new(id, |id| unsafe { glDeleteQueries(1, &id) })
+    }
+
+    /// Returns true when both handles refer to the same GL object.
+    pub fn ptr_eq(&self, other: &GlHandle) -> bool {
+        Rc::ptr_eq(&self.object, &other.object)
+    }
+
+    /// Returns the GL name of the object.
+    pub fn id(&self) -> GLuint {
+        self.object.id
+    }
+}
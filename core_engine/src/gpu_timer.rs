@@ -0,0 +1,202 @@
+use std::cell::Cell;
+
+use ogl33::*;
+
+use crate::frame_time::{FrameStats, FrameTimeAverage};
+use crate::gl_handle::GlHandle;
+
+/// How quickly the reported GPU times follow changes, see FrameTimeAverage.
+const GPU_TIME_SMOOTHING: f32 = 0.05;
+
+/// Returns true when the driver can record GPU timestamps. Some drivers expose the queries with zero
+/// counter bits, which means the timestamps are meaningless.
+pub fn timer_queries_supported() -> bool {
+    let mut counter_bits: GLint = 0;
+
+    unsafe {
+        glGetQueryiv(GL_TIMESTAMP, GL_QUERY_COUNTER_BITS, &mut counter_bits);
+    }
+
+    counter_bits > 0
+}
+
+/// Converts a pair of GPU timestamps in nanoseconds to milliseconds.
+/// A counter which wrapped between the two reads as zero rather than a huge time.
+pub(crate) fn timestamp_delta_ms(start: GLuint64, end: GLuint64) -> f32 {
+    (end.saturating_sub(start) as f64 / 1_000_000.0) as f32
+}
+
+/// Measures how long the GPU takes to run the commands issued between `begin` and `end`.
+///
+/// Works like OcclusionQuery: results arrive a frame or two late, so two pairs of queries are used in turn
+/// and results are only read once available, which keeps the readback from stalling the frame.
+/// Timestamps are recorded rather than GL_TIME_ELAPSED, since elapsed queries can't nest and the
+/// frame timer runs around the pipeline timers.
+pub struct GpuTimer {
+    starts: [GlHandle; 2],
+    ends: [GlHandle; 2],
+    /// The pair the next begin writes. After end this is the previous frame's pair.
+    write_index: usize,
+    /// True for pairs which have been issued but not read yet.
+    pending: [Cell<bool>; 2],
+    /// The smoothed elapsed time, in seconds like the CPU frame time.
+    elapsed: Cell<FrameTimeAverage>,
+    has_result: Cell<bool>,
+    active: bool
+}
+
+impl GpuTimer {
+    /// Creates a timer, or None when the driver doesn't support timer queries.
+    pub fn new() -> Option<Self> {
+        match timer_queries_supported() {
+            true => Some(GpuTimer::create()),
+            false => None
+        }
+    }
+
+    /// Creates a timer without checking for support.
+    fn create() -> Self {
+        let mut ids: [GLuint; 4] = [0; 4];
+
+        unsafe {
+            glGenQueries(4, ids.as_mut_ptr());
+        }
+
+        GpuTimer {
+            starts: [GlHandle::query(ids[0]), GlHandle::query(ids[1])],
+            ends: [GlHandle::query(ids[2]), GlHandle::query(ids[3])],
+            write_index: 0,
+            pending: [Cell::new(false), Cell::new(false)],
+            elapsed: Cell::new(FrameTimeAverage::new(GPU_TIME_SMOOTHING)),
+            has_result: Cell::new(false),
+            active: false
+        }
+    }
+
+    /// Records the time the GPU reaches the commands issued after this.
+    pub fn begin(&mut self) {
+        #[cfg(debug_assertions)]
+        assert!(!self.active, "GpuTimer::begin called twice without end");
+
+        // The pair about to be reused is from two frames ago. Keep its result if it's finished, otherwise drop it.
+        self.read_if_available(self.write_index);
+
+        unsafe {
+            glQueryCounter(self.starts[self.write_index].id(), GL_TIMESTAMP);
+        }
+
+        self.active = true;
+    }
+
+    /// Records the time the GPU finishes the commands issued since begin.
+    pub fn end(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        unsafe {
+            glQueryCounter(self.ends[self.write_index].id(), GL_TIMESTAMP);
+        }
+
+        self.pending[self.write_index].set(true);
+        self.write_index = (self.write_index + 1) % 2;
+        self.active = false;
+    }
+
+    /// Returns the smoothed GPU time in milliseconds, or None until the first result arrives.
+    pub fn elapsed_ms(&self) -> Option<f32> {
+        self.read_if_available(self.write_index);
+
+        match self.has_result.get() {
+            true => Some(self.elapsed.get().average() * 1000.0),
+            false => None
+        }
+    }
+
+    /// Reads the pair's timestamps when both have arrived, without waiting for them.
+    fn read_if_available(&self, index: usize) {
+        if !self.pending[index].get() {
+            return;
+        }
+
+        let (start_id, end_id) = (self.starts[index].id(), self.ends[index].id());
+        let mut available: GLuint = 0;
+
+        // Queries finish in order, so the end being available means the start is too.
+        unsafe {
+            glGetQueryObjectuiv(end_id, GL_QUERY_RESULT_AVAILABLE, &mut available);
+        }
+
+        if available != 0 {
+            let (mut start, mut end): (GLuint64, GLuint64) = (0, 0);
+
+            unsafe {
+                glGetQueryObjectui64v(start_id, GL_QUERY_RESULT, &mut start);
+                glGetQueryObjectui64v(end_id, GL_QUERY_RESULT, &mut end);
+            }
+
+            let mut elapsed = self.elapsed.get();
+            elapsed.add(timestamp_delta_ms(start, end) / 1000.0);
+
+            self.elapsed.set(elapsed);
+            self.has_result.set(true);
+            self.pending[index].set(false);
+        }
+    }
+}
+
+/// Times the whole frame and each render graph node for `GameManager::frame_stats`.
+/// Timers are created on the first frame, since checking for support needs a GL context.
+#[derive(Default)]
+pub(crate) struct GpuFrameTimers {
+    /// None until the first frame, then whether the driver supports timer queries.
+    supported: Option<bool>,
+    frame: Option<GpuTimer>,
+    /// Timers by render graph node index.
+    pipelines: Vec<Option<GpuTimer>>
+}
+
+impl GpuFrameTimers {
+    pub(crate) fn begin_frame(&mut self, node_count: usize) {
+        let supported = *self.supported.get_or_insert_with(timer_queries_supported);
+
+        if !supported {
+            return;
+        }
+
+        self.pipelines.resize_with(node_count, || None);
+
+        self.frame.get_or_insert_with(GpuTimer::create).begin();
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        if let Some(frame) = &mut self.frame {
+            frame.end();
+        }
+    }
+
+    pub(crate) fn begin_pipeline(&mut self, index: usize) {
+        if let Some(timer) = self.pipelines.get_mut(index) {
+            timer.get_or_insert_with(GpuTimer::create).begin();
+        }
+    }
+
+    pub(crate) fn end_pipeline(&mut self, index: usize) {
+        if let Some(Some(timer)) = self.pipelines.get_mut(index) {
+            timer.end();
+        }
+    }
+
+    /// Forgets the pipeline timers, for when the graph's nodes are replaced and their indices change.
+    pub(crate) fn clear_pipelines(&mut self) {
+        self.pipelines.clear();
+    }
+
+    /// Fills in the GPU times of the stats.
+    pub(crate) fn write_stats(&self, stats: &mut FrameStats) {
+        stats.gpu_ms = self.frame.as_ref().and_then(GpuTimer::elapsed_ms);
+        stats.pipeline_gpu_ms = self.pipelines.iter()
+            .map(|timer| timer.as_ref().and_then(GpuTimer::elapsed_ms))
+            .collect();
+    }
+}
@@ -1,10 +1,42 @@
 pub mod texture;
+pub mod texture_array;
+pub mod atlas;
+pub mod animation;
+pub mod framebuffer;
 pub mod mesh;
 pub mod shader_program;
+pub mod shader_warmup;
 pub mod engine;
 pub mod resource_manager;
+pub mod resource_paths;
 pub mod render_pipeline;
+pub mod render_state;
+pub mod font;
+pub mod camera;
+pub mod color;
+pub mod accumulator;
+pub mod frame_time;
+pub mod sprite_batch;
+pub mod gl_handle;
+pub mod bind_cache;
+pub mod gl_debug;
+pub mod debug_render;
+pub mod debug_overlay;
+pub mod debug_draw;
+pub mod screenshot;
+pub mod save;
+pub mod scheduler;
+pub mod ui;
+pub mod post_process;
+pub mod outline_pipeline;
+pub mod occlusion_query;
+pub mod gpu_timer;
+pub mod physics;
+pub mod particles;
+pub mod particle_emitter;
+pub mod audio;
+mod tests;
 
 pub use window::window::*;
 pub use window::Key;
-pub use window::MouseButton;
\ No newline at end of file
+pub use window::{MouseButton, MouseButtonLeft, MouseButtonRight, MouseButtonMiddle, MouseButtonBack, MouseButtonForward};
\ No newline at end of file
@@ -0,0 +1,643 @@
+use std::fmt::Display;
+use std::mem::size_of;
+
+use glmath::glmath::{Vec2f, Vec3f};
+use ogl33::*;
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::lexer::JsonLexer;
+use serializers::json::parser::{JsonNode, parse_json};
+
+use crate::camera::Camera2D;
+use crate::bind_cache;
+use crate::gl_debug;
+use crate::gl_handle::GlHandle;
+use crate::resource_manager::ResourceDestroy;
+
+pub mod skinned;
+
+/// Axis aligned bounding box around the positions of a mesh.
+/// 2D meshes leave the z extents at zero.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3f,
+    pub max: Vec3f
+}
+
+impl Aabb {
+    /// Computes the bounds of a flat position buffer with the given number of dimensions per vertex.
+    /// Only the first three dimensions are considered.
+    pub fn from_positions(data: &[f32], dimensions: u32) -> Aabb {
+        let dimensions = dimensions as usize;
+
+        if dimensions == 0 || data.len() < dimensions {
+            return Aabb::default();
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+
+        for vertex in data.chunks_exact(dimensions) {
+            for (axis, value) in vertex.iter().take(3).enumerate() {
+                min[axis] = f32::min(min[axis], *value);
+                max[axis] = f32::max(max[axis], *value);
+            }
+        }
+
+        // Unused axes stay flat at zero.
+        for axis in dimensions..3 {
+            min[axis] = 0.0;
+            max[axis] = 0.0;
+        }
+
+        Aabb {
+            min: Vec3f::new(min[0], min[1], min[2]),
+            max: Vec3f::new(max[0], max[1], max[2])
+        }
+    }
+
+    /// Returns the extents of the box along each axis.
+    pub fn size(&self) -> Vec3f {
+        self.max - self.min
+    }
+
+    /// Returns the point in the middle of the box.
+    pub fn center(&self) -> Vec3f {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the diameter in pixels the box covers on screen when drawn at the given
+    /// world position and uniform scale.
+    pub fn screen_diameter(&self, position: Vec2f, scale: f32, camera: &Camera2D) -> f32 {
+        let min = Vec2f::new(self.min.x, self.min.y) * scale + position;
+        let max = Vec2f::new(self.max.x, self.max.y) * scale + position;
+
+        let screen_min = camera.world_to_screen(min);
+        let screen_max = camera.world_to_screen(max);
+        let diagonal = screen_max - screen_min;
+
+        (diagonal.x * diagonal.x + diagonal.y * diagonal.y).sqrt()
+    }
+}
+
+/// A set of meshes of decreasing detail. Each level is paired with the smallest
+/// screen-space diameter (in pixels) at which it should still be used.
+pub struct LodMesh<M = Mesh2D> {
+    /// Sorted from the largest minimum diameter (most detailed) to the smallest.
+    lods: Vec<(f32, M)>
+}
+
+impl<M> LodMesh<M> {
+    pub fn new(mut lods: Vec<(f32, M)>) -> Self {
+        #[cfg(debug_assertions)]
+        assert!(!lods.is_empty(), "A LodMesh needs at least one level");
+
+        lods.sort_by(|a, b| b.0.total_cmp(&a.0));
+        LodMesh { lods }
+    }
+
+    /// Returns the most detailed level whose minimum diameter fits within the screen size.
+    /// Objects smaller than every threshold use the least detailed level.
+    pub fn select_lod(&self, screen_size: f32) -> &M {
+        for (min_screen_size, mesh) in self.lods.iter() {
+            if screen_size >= *min_screen_size {
+                return mesh;
+            }
+        }
+
+        &self.lods[self.lods.len() - 1].1
+    }
+}
+
+impl LodMesh<Mesh2D> {
+    /// Picks a level from how large the most detailed mesh appears through the camera.
+    pub fn select_for_camera(&self, position: Vec2f, scale: f32, camera: &Camera2D) -> &Mesh2D {
+        let screen_size = self.lods[0].1.bounds().screen_diameter(position, scale, camera);
+        self.select_lod(screen_size)
+    }
+}
+
+/// Errors from loading a mesh description.
+#[derive(Debug)]
+pub enum MeshError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// The file isn't valid json.
+    Parse(String),
+    /// The json is valid but doesn't describe a usable mesh.
+    InvalidData(String)
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::Io(err) => write!(f, "Unable to read mesh: {}", err),
+            MeshError::Parse(path) => write!(f, "Mesh file {} is not valid json", path),
+            MeshError::InvalidData(reason) => write!(f, "Invalid mesh: {}", reason)
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl From<std::io::Error> for MeshError {
+    fn from(err: std::io::Error) -> Self {
+        MeshError::Io(err)
+    }
+}
+
+/// Returns the number of vertices in a buffer of `len` floats with `components` floats per vertex.
+/// Fails when components isn't between 1 and 4, or the floats can't be split evenly into vertices,
+/// which would otherwise leave a partial vertex that silently misrenders.
+pub fn buffer_vertex_count(len: usize, components: usize) -> Result<usize, MeshError> {
+    if components == 0 || components > 4 {
+        return Err(MeshError::InvalidData(format!("components must be between 1 and 4, found {}", components)));
+    }
+
+    if !len.is_multiple_of(components) {
+        return Err(MeshError::InvalidData(format!("{} vertex values can't be split into {} components", len, components)));
+    }
+
+    Ok(len / components)
+}
+
+/// Vertex data read from a mesh description, before it's uploaded.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<f32>,
+    /// Number of floats per vertex.
+    pub components: u32,
+    pub indices: Option<Vec<u32>>
+}
+
+impl MeshData {
+    /// Reads a mesh description of the form
+    /// { "vertices": [x, y, ...], "components": 2, "indices": [0, 1, 2, ...] }
+    /// where indices are optional.
+    pub fn from_json(node: &JsonNode) -> Result<MeshData, MeshError> {
+        let vertices = node.pointer("vertices").and_then(Vec::<f32>::from_json)
+            .ok_or_else(|| MeshError::InvalidData("expected a \"vertices\" array of numbers".to_string()))?;
+
+        let components = node.pointer("components").and_then(u32::from_json)
+            .ok_or_else(|| MeshError::InvalidData("expected a whole number of \"components\"".to_string()))?;
+
+        let indices = match node.pointer("indices") {
+            Some(indices) => Some(Vec::<u32>::from_json(indices)
+                .ok_or_else(|| MeshError::InvalidData("expected \"indices\" to be an array of whole numbers".to_string()))?),
+            None => None
+        };
+
+        let vertex_count = buffer_vertex_count(vertices.len(), components as usize)? as u32;
+
+        if vertex_count == 0 {
+            return Err(MeshError::InvalidData("expected at least one vertex".to_string()));
+        }
+
+        if let Some(indices) = &indices {
+            if let Some(index) = indices.iter().find(|index| **index >= vertex_count) {
+                return Err(MeshError::InvalidData(format!("index {} is out of range of {} vertices", index, vertex_count)));
+            }
+        }
+
+        Ok(MeshData { vertices, components, indices })
+    }
+
+    /// Two triangles covering -1..1 on both axes, the quad sprites and full screen passes are drawn with.
+    pub fn quad() -> MeshData {
+        MeshData {
+            vertices: vec![
+                -1.0, -1.0,
+                -1.0, 1.0,
+                1.0, 1.0,
+                1.0, 1.0,
+                1.0, -1.0,
+                -1.0, -1.0
+            ],
+            components: 2,
+            indices: None
+        }
+    }
+
+    /// A circle of radius 1 around the origin, as a fan of triangles from the center to each segment
+    /// of the rim. Meshes draw triangle lists, so the fan is indexed. At least 3 segments are used.
+    pub fn circle(segments: u32) -> MeshData {
+        let segments = segments.max(3);
+        let mut vertices = vec![0.0, 0.0];
+
+        for segment in 0..segments {
+            let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+            vertices.extend_from_slice(&[angle.cos(), angle.sin()]);
+        }
+
+        // The center is vertex 0, and the last triangle wraps back to the first rim vertex.
+        let indices = (0..segments)
+            .flat_map(|segment| [0, segment + 1, (segment + 1) % segments + 1])
+            .collect();
+
+        MeshData { vertices, components: 2, indices: Some(indices) }
+    }
+}
+
+/// How a mesh's vertices are joined into shapes when it's drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveMode {
+    /// Every three vertices are a filled triangle.
+    #[default]
+    Triangles,
+    /// Every two vertices are a line.
+    Lines,
+    /// Each vertex is joined to the one before it.
+    LineStrip
+}
+
+impl PrimitiveMode {
+    pub(crate) fn gl_mode(&self) -> GLenum {
+        match self {
+            PrimitiveMode::Triangles => GL_TRIANGLES,
+            PrimitiveMode::Lines => GL_LINES,
+            PrimitiveMode::LineStrip => GL_LINE_STRIP
+        }
+    }
+}
+
+/// A mesh drawn as a list of triangles, or lines, see `set_primitive_mode`. Clones share the same vertex
+/// array and buffers, which are deleted when the last clone is dropped.
+#[derive(Default, Clone)]
+pub struct Mesh2D {
+    draw_count: i32,
+    primitive_mode: PrimitiveMode,
+    vao: GlHandle,
+    vbos: Vec<GlHandle>,
+    vbo_dimensions: Vec<u32>,
+    /// Bounds of the position attribute, computed when the first buffer is uploaded.
+    bounds: Aabb,
+    /// Element buffer, when the mesh is drawn from indices.
+    ebo: Option<GlHandle>,
+    index_count: i32
+}
+
+impl Mesh2D {
+    pub fn new() -> Mesh2D {
+        unsafe {
+            let mut vao: GLuint = 0;
+            glGenVertexArrays(1, &mut vao);
+
+            Mesh2D { 
+                draw_count: -1, 
+                primitive_mode: PrimitiveMode::Triangles,
+                vao: GlHandle::vertex_array(vao),
+                vbos: Vec::<GlHandle>::default(), 
+                vbo_dimensions: Vec::<u32>::default(),
+                bounds: Aabb::default(),
+                ebo: None,
+                index_count: 0
+            }
+        }
+    }
+
+    /// Loads a mesh from a json description, see `MeshData::from_json` for the format.
+    pub fn from_json(path: &str) -> Result<Mesh2D, MeshError> {
+        let mut lexer = JsonLexer::new(path)?;
+        let node = parse_json(&mut lexer).ok_or_else(|| MeshError::Parse(path.to_string()))?;
+
+        Ok(Mesh2D::from_data(MeshData::from_json(&node)?))
+    }
+
+    /// Creates the -1..1 quad, see `MeshData::quad`.
+    pub fn generate_quad() -> Mesh2D {
+        Mesh2D::from_data(MeshData::quad())
+    }
+
+    /// Creates a circle of radius 1 around the origin with the given number of rim segments, see `MeshData::circle`.
+    pub fn generate_circle(segments: u32) -> Mesh2D {
+        Mesh2D::from_data(MeshData::circle(segments))
+    }
+
+    /// Uploads vertex data into a new mesh.
+    pub fn from_data(data: MeshData) -> Mesh2D {
+        let mut mesh = Mesh2D::new();
+        mesh.add_float_buffer(data.vertices, data.components);
+
+        if let Some(indices) = data.indices {
+            mesh.set_indices(indices);
+        }
+
+        mesh
+    }
+
+    /// Draws the mesh from the given vertex indices instead of in vertex order.
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
+        unsafe {
+            bind_cache::bind_vertex_array(self.vao.id());
+
+            let mut ebo: GLuint = 0;
+            glGenBuffers(1, &mut ebo);
+            glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, ebo);
+            glBufferData(GL_ELEMENT_ARRAY_BUFFER, (indices.len() * size_of::<u32>()) as isize,
+                indices.as_ptr() as *const c_void, GL_STATIC_DRAW);
+
+            // The element buffer binding is part of the vao, so unbind the vao first.
+            bind_cache::bind_vertex_array(0);
+            glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, 0);
+
+            self.ebo = Some(GlHandle::buffer(ebo));
+            self.index_count = indices.len() as i32;
+        }
+    }
+
+    pub fn set_primitive_mode(&mut self, primitive_mode: PrimitiveMode) {
+        self.primitive_mode = primitive_mode;
+    }
+
+    pub fn get_primitive_mode(&self) -> PrimitiveMode {
+        self.primitive_mode
+    }
+
+    /// Returns the number of vertices drawn, the fewest of any per vertex buffer.
+    pub fn vertex_count(&self) -> usize {
+        self.draw_count.max(0) as usize
+    }
+
+    /// Returns the bounds of the mesh positions.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Adds a new float buffer to the VBO.
+    /// Dimension are the number passed to the shader per render call. 
+    /// 3 dims = a 3d vector.
+    /// Attribute indices are assigned sequentially, so the nth buffer added is bound to location n.
+    /// Use `add_float_buffer_at` when the shader declares its own locations.
+    /// The first buffer added is treated as the position attribute when computing bounds.
+    /// Panics when the data can't be split into vertices, see `try_add_float_buffer`.
+    #[track_caller]
+    pub fn add_float_buffer(&mut self, data: Vec<f32>, dimensions: u32) {
+        let location = self.vbos.len() as u32;
+        self.add_float_buffer_at(location, data, dimensions as usize);
+    }
+
+    /// Adds a new float buffer bound to the given attribute location,
+    /// matching a `layout(location = N)` in the shader.
+    /// Panics when the data can't be split into vertices, see `try_add_float_buffer_at`.
+    #[track_caller]
+    pub fn add_float_buffer_at(&mut self, location: u32, data: Vec<f32>, components: usize) {
+        if let Err(err) = self.try_add_float_buffer_at(location, data, components) {
+            panic!("Mesh2D::add_float_buffer_at: {}", err);
+        }
+    }
+
+    /// Like `add_float_buffer`, but returns an error instead of panicking when the buffer's length
+    /// isn't a whole number of vertices, ex: for vertex data loaded at runtime.
+    pub fn try_add_float_buffer(&mut self, data: Vec<f32>, dimensions: u32) -> Result<(), MeshError> {
+        let location = self.vbos.len() as u32;
+        self.try_add_float_buffer_at(location, data, dimensions as usize)
+    }
+
+    /// Like `add_float_buffer_at`, but returns an error instead of panicking when the buffer's length
+    /// isn't a whole number of vertices. Nothing is uploaded when it fails.
+    pub fn try_add_float_buffer_at(&mut self, location: u32, data: Vec<f32>, components: usize) -> Result<(), MeshError> {
+        let vertex_count = buffer_vertex_count(data.len(), components)? as i32;
+        let dimensions = components as u32;
+
+        if self.vbos.is_empty() {
+            self.bounds = Aabb::from_positions(&data, dimensions);
+        }
+
+        // Set draw count to what we expect for a 2D mesh. We would hope that all attributes have
+        // the same draw count, but we have to use them min.
+        if self.draw_count != -1 {
+            // In debug mode, we need to notify if all attributes aren't the same size.
+            #[cfg(debug_assertions)]
+            if vertex_count != self.draw_count {
+                println!("Mesh attribute {} has {} vertices, the mesh has {}", location, vertex_count, self.draw_count);
+            }
+
+            self.draw_count = i32::min(self.draw_count, vertex_count);
+        }
+        else {
+            self.draw_count = vertex_count;
+        }
+
+        // Create and bind the vbo.
+        unsafe { 
+            bind_cache::bind_vertex_array(self.vao.id());
+
+            let mut vbo: GLuint = 0;
+            glGenBuffers(1, &mut vbo);
+            glBindBuffer(GL_ARRAY_BUFFER, vbo);
+
+            // Write data to vbo.
+            let mut size = (self.draw_count as u32 * dimensions) as isize;
+            size *= size_of::<f32>() as isize;
+
+            glBufferData(GL_ARRAY_BUFFER, size,
+                data.as_ptr() as *const c_void, GL_STATIC_DRAW);
+
+            bind_float_attribute(&mut GlVertexAttribs, location, dimensions);
+
+            // Unbind the buffer and append to list of vbos.
+            glBindBuffer(GL_ARRAY_BUFFER, 0);
+
+            self.vbos.push(GlHandle::buffer(vbo));
+            self.vbo_dimensions.push(dimensions);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a float buffer holding one value per instance rather than per vertex, ex: an offset per tile,
+    /// read by `render_instanced`. Instance buffers don't change the vertex count or bounds.
+    pub fn add_instance_float_buffer(&mut self, location: u32, data: Vec<f32>, components: usize) {
+        #[cfg(debug_assertions)]
+        assert_eq!(data.len() % components, 0);
+
+        self.add_attribute_buffer(&data, components, |attribs| bind_instance_attribute(attribs, location, components as u32, false));
+    }
+
+    /// Adds an integer buffer holding one value per instance, ex: the layer of a `TextureArray` each tile samples.
+    /// The values stay integers in the shader, so they're declared as int or ivecN.
+    pub fn add_instance_int_buffer(&mut self, location: u32, data: Vec<i32>, components: usize) {
+        #[cfg(debug_assertions)]
+        assert_eq!(data.len() % components, 0);
+
+        self.add_attribute_buffer(&data, components, |attribs| bind_instance_attribute(attribs, location, components as u32, true));
+    }
+
+    /// Adds an empty float buffer holding one value per instance, for data rewritten every frame with
+    /// `update_instance_float_buffer`, ex: particle positions. Returns the buffer to pass to the update.
+    pub fn add_dynamic_instance_float_buffer(&mut self, location: u32, components: usize) -> usize {
+        self.add_attribute_buffer::<f32>(&[], components, |attribs| bind_instance_attribute(attribs, location, components as u32, false));
+        self.vbos.len() - 1
+    }
+
+    /// Replaces the contents of a buffer added by `add_dynamic_instance_float_buffer`.
+    /// The old storage is orphaned rather than overwritten, so the upload doesn't wait on draws still reading it.
+    #[track_caller]
+    pub fn update_instance_float_buffer(&self, buffer: usize, data: &[f32]) {
+        self.stream_buffer(buffer, data);
+        gl_debug::check_errors("Mesh2D::update_instance_float_buffer");
+    }
+
+    /// Adds an empty float buffer holding one value per vertex, for meshes rebuilt every frame with
+    /// `update_float_buffer`, ex: debug lines. Returns the buffer to pass to the update.
+    pub fn add_dynamic_float_buffer(&mut self, location: u32, components: usize) -> usize {
+        self.add_attribute_buffer::<f32>(&[], components, |attribs| bind_float_attribute(attribs, location, components as u32));
+
+        if self.draw_count == -1 {
+            self.draw_count = 0;
+        }
+
+        self.vbos.len() - 1
+    }
+
+    /// Replaces the contents of a buffer added by `add_dynamic_float_buffer`. The mesh draws as many
+    /// vertices as the last update held, so every dynamic buffer of a mesh is updated with the same count.
+    #[track_caller]
+    pub fn update_float_buffer(&mut self, buffer: usize, data: &[f32]) {
+        self.stream_buffer(buffer, data);
+        self.draw_count = (data.len() / self.vbo_dimensions[buffer] as usize) as i32;
+
+        gl_debug::check_errors("Mesh2D::update_float_buffer");
+    }
+
+    /// Orphans a buffer's storage and uploads the data into new storage.
+    fn stream_buffer(&self, buffer: usize, data: &[f32]) {
+        unsafe {
+            glBindBuffer(GL_ARRAY_BUFFER, self.vbos[buffer].id());
+            glBufferData(GL_ARRAY_BUFFER, std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const c_void, GL_STREAM_DRAW);
+            glBindBuffer(GL_ARRAY_BUFFER, 0);
+        }
+    }
+
+    /// Uploads a buffer of 4 byte values, then points its attribute at it.
+    fn add_attribute_buffer<T>(&mut self, data: &[T], components: usize, bind: impl FnOnce(&mut GlVertexAttribs)) {
+        unsafe {
+            bind_cache::bind_vertex_array(self.vao.id());
+
+            let mut vbo: GLuint = 0;
+            glGenBuffers(1, &mut vbo);
+            glBindBuffer(GL_ARRAY_BUFFER, vbo);
+            glBufferData(GL_ARRAY_BUFFER, std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const c_void, GL_STATIC_DRAW);
+
+            bind(&mut GlVertexAttribs);
+
+            glBindBuffer(GL_ARRAY_BUFFER, 0);
+            bind_cache::bind_vertex_array(0);
+
+            self.vbos.push(GlHandle::buffer(vbo));
+            self.vbo_dimensions.push(components as u32);
+        }
+    }
+
+    /// Draws the mesh once per instance, with instance buffers advancing once per instance.
+    #[track_caller]
+    pub fn render_instanced(&self, instance_count: i32) {
+        unsafe {
+            glDisable(GL_CULL_FACE);
+
+            bind_cache::bind_vertex_array(self.vao.id());
+            bind_cache::count_draw_call();
+
+            match self.ebo {
+                Some(_) => glDrawElementsInstanced(self.primitive_mode.gl_mode(), self.index_count, GL_UNSIGNED_INT, std::ptr::null(), instance_count),
+                None => glDrawArraysInstanced(self.primitive_mode.gl_mode(), 0, self.draw_count, instance_count)
+            }
+
+            gl_debug::check_errors("Mesh2D::render_instanced");
+
+            glEnable(GL_CULL_FACE);
+            glCullFace(GL_BACK);
+        }
+    }
+}
+
+/// The GL calls which point an attribute at the bound array buffer.
+/// Lets tests check which locations are used without a GL context.
+pub(crate) trait VertexAttribs {
+    fn enable_vertex_attrib_array(&mut self, location: u32);
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32);
+    /// Points at 32 bit integers which stay integers in the shader.
+    fn vertex_attrib_int_pointer(&mut self, location: u32, components: i32);
+    fn vertex_attrib_divisor(&mut self, location: u32, divisor: u32);
+}
+
+struct GlVertexAttribs;
+
+impl VertexAttribs for GlVertexAttribs {
+    fn enable_vertex_attrib_array(&mut self, location: u32) {
+        unsafe {
+            glEnableVertexAttribArray(location);
+        }
+    }
+
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32) {
+        unsafe {
+            glVertexAttribPointer(location, components, 
+                GL_FLOAT, GL_FALSE, 0, 0 as *const c_void);
+        }
+    }
+
+    fn vertex_attrib_int_pointer(&mut self, location: u32, components: i32) {
+        unsafe {
+            glVertexAttribIPointer(location, components, GL_INT, 0, 0 as *const c_void);
+        }
+    }
+
+    fn vertex_attrib_divisor(&mut self, location: u32, divisor: u32) {
+        unsafe {
+            glVertexAttribDivisor(location, divisor);
+        }
+    }
+}
+
+/// Points the attribute at the given location to the bound array buffer of tightly packed floats.
+pub(crate) fn bind_float_attribute<A: VertexAttribs>(attribs: &mut A, location: u32, dimensions: u32) {
+    attribs.enable_vertex_attrib_array(location);
+    attribs.vertex_attrib_pointer(location, dimensions as i32);
+}
+
+/// Points the attribute at the given location to the bound array buffer, advancing once per instance.
+/// Integer attributes hold 32 bit ints, otherwise floats.
+pub(crate) fn bind_instance_attribute<A: VertexAttribs>(attribs: &mut A, location: u32, components: u32, integer: bool) {
+    attribs.enable_vertex_attrib_array(location);
+
+    if integer {
+        attribs.vertex_attrib_int_pointer(location, components as i32);
+    }
+    else {
+        attribs.vertex_attrib_pointer(location, components as i32);
+    }
+
+    attribs.vertex_attrib_divisor(location, 1);
+}
+
+/// The vao and vbos are freed when the last handle is dropped.
+impl ResourceDestroy for Mesh2D {}
+
+pub trait DrawableMesh {
+    fn render(&self);
+}
+
+impl DrawableMesh for Mesh2D {
+    #[track_caller]
+    fn render(&self) {
+        unsafe {
+            // Blending and depth testing come from the pipeline's RenderState.
+            glDisable(GL_CULL_FACE);
+
+            bind_cache::bind_vertex_array(self.vao.id());
+            bind_cache::count_draw_call();
+
+            match self.ebo {
+                Some(_) => glDrawElements(self.primitive_mode.gl_mode(), self.index_count, GL_UNSIGNED_INT, std::ptr::null()),
+                None => glDrawArrays(self.primitive_mode.gl_mode(), 0, self.draw_count)
+            }
+
+            gl_debug::check_errors("Mesh2D::render");
+
+            glEnable(GL_CULL_FACE);
+            glCullFace(GL_BACK);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::mem::size_of;
+
+use glmath::glmath::Mat44f;
+use ogl33::*;
+
+use crate::bind_cache;
+use crate::gl_handle::GlHandle;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::resource_manager::ResourceDestroy;
+use crate::shader_program::ShaderProgram;
+
+/// Maximum number of bones in a pose. Shaders should declare the palette with the same size:
+/// layout (std140) uniform BonePalette { mat4 bones[64]; };
+pub const MAX_BONES: usize = 64;
+
+/// Name of the uniform block holding the bone matrices.
+pub const BONE_PALETTE_BLOCK: &str = "BonePalette";
+
+/// Uniform buffer binding point the bone palette is attached to.
+pub const BONE_PALETTE_BINDING: u32 = 0;
+
+/// A single joint of a skeleton.
+#[derive(Debug, Default, Clone)]
+pub struct Bone {
+    pub name: String,
+    /// Index of the parent bone, or None for a root. Parents always come before their children.
+    pub parent: Option<usize>
+}
+
+/// The bone hierarchy of a skinned mesh along with its rest pose.
+#[derive(Debug, Default, Clone)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+    /// Transforms from model space into the local space of each bone in the rest pose.
+    pub inverse_bind_poses: Vec<Mat44f>
+}
+
+impl Skeleton {
+    pub fn new(bones: Vec<Bone>, inverse_bind_poses: Vec<Mat44f>) -> Self {
+        #[cfg(debug_assertions)]
+        assert_eq!(bones.len(), inverse_bind_poses.len());
+
+        Skeleton { bones, inverse_bind_poses }
+    }
+
+    /// Converts the local transform of every bone into model space by walking down the hierarchy.
+    pub fn model_poses(&self, local_poses: &[Mat44f]) -> Vec<Mat44f> {
+        let mut model_poses = Vec::<Mat44f>::with_capacity(self.bones.len());
+
+        for (index, bone) in self.bones.iter().enumerate() {
+            let local = local_poses.get(index).copied().unwrap_or(Mat44f::IDENTITY);
+
+            let model = match bone.parent {
+                Some(parent) => model_poses[parent] * local,
+                None => local
+            };
+
+            model_poses.push(model);
+        }
+
+        model_poses
+    }
+
+    /// Returns the matrices to upload for a pose: each model space bone transform
+    /// multiplied by the inverse of its rest pose.
+    pub fn skinning_matrices(&self, local_poses: &[Mat44f]) -> Vec<Mat44f> {
+        self.model_poses(local_poses).iter()
+            .zip(self.inverse_bind_poses.iter())
+            .map(|(model, inverse_bind)| *model * *inverse_bind)
+            .collect()
+    }
+}
+
+/// A mesh whose vertices are blended between up to four bones in the vertex shader.
+/// The bone indices and weights are added as the two attributes after the mesh's own buffers.
+pub struct SkinnedMesh {
+    mesh: Mesh2D,
+    pub skeleton: Skeleton,
+    bone_indices: Vec<[u8; 4]>,
+    bone_weights: Vec<[f32; 4]>,
+    palette: GlHandle
+}
+
+impl SkinnedMesh {
+    /// Adds the bone attributes to a mesh which already holds its other vertex buffers.
+    pub fn new(mut mesh: Mesh2D, skeleton: Skeleton, bone_indices: Vec<[u8; 4]>, bone_weights: Vec<[f32; 4]>) -> Self {
+        #[cfg(debug_assertions)]
+        assert_eq!(bone_indices.len(), bone_weights.len());
+
+        unsafe {
+            bind_cache::bind_vertex_array(mesh.vao.id());
+
+            // Bone indices stay integers in the shader, so they use the I variant of the attribute pointer.
+            let mut vbo: GLuint = 0;
+            glGenBuffers(1, &mut vbo);
+            glBindBuffer(GL_ARRAY_BUFFER, vbo);
+
+            let index = mesh.vbos.len() as GLuint;
+            glEnableVertexAttribArray(index);
+            glBufferData(GL_ARRAY_BUFFER, (bone_indices.len() * size_of::<[u8; 4]>()) as isize,
+                bone_indices.as_ptr() as *const c_void, GL_STATIC_DRAW);
+            glVertexAttribIPointer(index, 4, GL_UNSIGNED_BYTE, 0, 0 as *const c_void);
+
+            glBindBuffer(GL_ARRAY_BUFFER, 0);
+            bind_cache::bind_vertex_array(0);
+
+            mesh.vbos.push(GlHandle::buffer(vbo));
+            mesh.vbo_dimensions.push(4);
+        }
+
+        mesh.add_float_buffer(bone_weights.iter().flatten().copied().collect(), 4);
+
+        let mut palette: GLuint = 0;
+
+        unsafe {
+            glGenBuffers(1, &mut palette);
+            glBindBuffer(GL_UNIFORM_BUFFER, palette);
+            glBufferData(GL_UNIFORM_BUFFER, (MAX_BONES * size_of::<Mat44f>()) as isize,
+                std::ptr::null(), GL_DYNAMIC_DRAW);
+            glBindBuffer(GL_UNIFORM_BUFFER, 0);
+        }
+
+        SkinnedMesh {
+            mesh,
+            skeleton,
+            bone_indices,
+            bone_weights,
+            palette: GlHandle::buffer(palette)
+        }
+    }
+
+    pub fn get_bone_indices(&self) -> &Vec<[u8; 4]> {
+        &self.bone_indices
+    }
+
+    pub fn get_bone_weights(&self) -> &Vec<[f32; 4]> {
+        &self.bone_weights
+    }
+
+    /// Uploads a palette of skinning matrices (see `Skeleton::skinning_matrices`) to the
+    /// BonePalette uniform block of the program. Matrices past MAX_BONES are ignored.
+    pub fn upload_pose(&self, matrices: &[Mat44f], program: &ShaderProgram) {
+        let count = usize::min(matrices.len(), MAX_BONES);
+
+        #[cfg(debug_assertions)]
+        if matrices.len() > MAX_BONES {
+            println!("Pose has {} bones, only the first {} are uploaded", matrices.len(), MAX_BONES);
+        }
+
+        unsafe {
+            let block_name = std::ffi::CString::new(BONE_PALETTE_BLOCK).unwrap();
+            let block_index = glGetUniformBlockIndex(program.program_id(), block_name.as_ptr() as *const i8);
+
+            // GL_INVALID_INDEX, the program doesn't use the palette.
+            if block_index == u32::MAX {
+                return;
+            }
+
+            glUniformBlockBinding(program.program_id(), block_index, BONE_PALETTE_BINDING);
+
+            // Mat44 is column major, matching the std140 layout of a mat4 array.
+            glBindBuffer(GL_UNIFORM_BUFFER, self.palette.id());
+            glBufferSubData(GL_UNIFORM_BUFFER, 0, (count * size_of::<Mat44f>()) as isize,
+                matrices.as_ptr() as *const c_void);
+            glBindBuffer(GL_UNIFORM_BUFFER, 0);
+
+            glBindBufferBase(GL_UNIFORM_BUFFER, BONE_PALETTE_BINDING, self.palette.id());
+        }
+    }
+}
+
+/// The mesh and palette are freed when the last handle is dropped.
+impl ResourceDestroy for SkinnedMesh {}
+
+impl DrawableMesh for SkinnedMesh {
+    fn render(&self) {
+        self.mesh.render();
+    }
+}
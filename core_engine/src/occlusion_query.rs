@@ -0,0 +1,143 @@
+use std::cell::Cell;
+
+use ogl33::*;
+
+use crate::gl_handle::GlHandle;
+
+/// Which of the two queries is written next, and which ended most recently.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct QuerySlots {
+    write_index: usize
+}
+
+impl QuerySlots {
+    /// The query the next begin_query writes, which ended two frames ago.
+    pub(crate) fn write_index(&self) -> usize {
+        self.write_index
+    }
+
+    /// The query ended most recently. Between frames, and during one until end_query, that's the previous frame's.
+    pub(crate) fn latest_index(&self) -> usize {
+        (self.write_index + 1) % 2
+    }
+
+    /// Moves on once the written query has ended.
+    pub(crate) fn advance(&mut self) {
+        self.write_index = (self.write_index + 1) % 2;
+    }
+}
+
+/// Counts the samples of the draw calls between `begin_query` and `end_query` which pass the depth test,
+/// so expensive objects can be skipped when they were hidden last frame.
+///
+/// GL finishes queries some time after they're issued, and waiting for one stalls until the GPU catches up.
+/// Two queries are used in turn, so while one frame's query is in flight the previous frame's result is read.
+/// Results are only read once they're available, so nothing here ever blocks.
+pub struct OcclusionQuery {
+    queries: [GlHandle; 2],
+    slots: QuerySlots,
+    /// True for queries which have been issued but not read yet.
+    pending: [Cell<bool>; 2],
+    /// The most recent result which has been read.
+    last_sample_count: Cell<Option<u32>>,
+    active: bool
+}
+
+impl Default for OcclusionQuery {
+    fn default() -> Self {
+        OcclusionQuery::new()
+    }
+}
+
+impl OcclusionQuery {
+    pub fn new() -> Self {
+        let mut ids: [GLuint; 2] = [0; 2];
+
+        unsafe {
+            glGenQueries(2, ids.as_mut_ptr());
+        }
+
+        OcclusionQuery {
+            queries: [GlHandle::query(ids[0]), GlHandle::query(ids[1])],
+            slots: QuerySlots::default(),
+            pending: [Cell::new(false), Cell::new(false)],
+            last_sample_count: Cell::new(None),
+            active: false
+        }
+    }
+
+    /// Starts counting samples. Only one query of a kind can be active at a time.
+    pub fn begin_query(&mut self) {
+        #[cfg(debug_assertions)]
+        assert!(!self.active, "begin_query called twice without end_query");
+
+        // The query about to be reused is from two frames ago, so it's almost always finished.
+        // Keep its result if so, otherwise it's dropped rather than waited on.
+        self.read_if_available(self.slots.write_index());
+
+        unsafe {
+            glBeginQuery(GL_SAMPLES_PASSED, self.queries[self.slots.write_index()].id());
+        }
+
+        self.active = true;
+    }
+
+    /// Stops counting samples.
+    pub fn end_query(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        unsafe {
+            glEndQuery(GL_SAMPLES_PASSED);
+        }
+
+        self.pending[self.slots.write_index()].set(true);
+        self.slots.advance();
+        self.active = false;
+    }
+
+    /// Returns true when the previous frame's result is ready, or a result has already been read.
+    pub fn result_available(&self) -> bool {
+        self.read_if_available(self.slots.latest_index());
+        self.last_sample_count.get().is_some()
+    }
+
+    /// Returns the number of samples which passed in the latest finished query, or 0 before any has finished.
+    pub fn sample_count(&self) -> u32 {
+        self.read_if_available(self.slots.latest_index());
+        self.last_sample_count.get().unwrap_or(0)
+    }
+
+    /// Returns false only when the latest finished query saw no samples pass.
+    /// Objects are treated as visible until a result arrives, so nothing pops in late.
+    pub fn is_visible(&self) -> bool {
+        self.read_if_available(self.slots.latest_index());
+        self.last_sample_count.get().map(|count| count > 0).unwrap_or(true)
+    }
+
+    /// Reads the query's result when it has one, without waiting for it.
+    fn read_if_available(&self, index: usize) {
+        if !self.pending[index].get() {
+            return;
+        }
+
+        let id = self.queries[index].id();
+        let mut available: GLuint = 0;
+
+        unsafe {
+            glGetQueryObjectuiv(id, GL_QUERY_RESULT_AVAILABLE, &mut available);
+        }
+
+        if available != 0 {
+            let mut sample_count: GLuint = 0;
+
+            unsafe {
+                glGetQueryObjectuiv(id, GL_QUERY_RESULT, &mut sample_count);
+            }
+
+            self.last_sample_count.set(Some(sample_count));
+            self.pending[index].set(false);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use glmath::glmath::{Vec2f, Vec4f, Mat44f};
+use window::window::MouseKeyboardInputControl;
+
+use crate::camera::Camera2D;
+use crate::color::Color;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::render_pipeline::RenderPipelineHandler;
+use crate::render_state::{RenderState, StencilState, CompareFunc, StencilOp};
+use crate::shader_program::{ShaderProgram, ShaderUniforms, UniformSet};
+use crate::texture::Texture;
+
+/// The stencil value written under the outlined objects.
+const OUTLINE_STENCIL_REF: i32 = 1;
+
+/// An object drawn with an outline around it.
+#[derive(Clone)]
+pub struct OutlineTarget {
+    pub texture: Texture,
+    /// Center of the object.
+    pub position: Vec2f,
+    /// Half the width and height of the object.
+    pub scale: Vec2f
+}
+
+/// The render state of each outline pass.
+///
+/// The first pass draws the objects, marking the pixels they cover in the stencil buffer.
+/// The second pass draws the objects grown by the outline width in a solid color, only where
+/// the stencil isn't marked, which leaves just the rim. Marked pixels fail the second test and
+/// are zeroed, so the stencil is clean again for the next frame without a clear.
+pub fn outline_pass_states() -> [RenderState; 2] {
+    let mark = RenderState {
+        stencil: Some(StencilState {
+            func: CompareFunc::Always,
+            reference: OUTLINE_STENCIL_REF,
+            pass: StencilOp::Replace,
+            ..StencilState::default()
+        }),
+        ..RenderState::default()
+    };
+
+    let rim = RenderState {
+        stencil: Some(StencilState {
+            func: CompareFunc::NotEqual,
+            reference: OUTLINE_STENCIL_REF,
+            fail: StencilOp::Zero,
+            ..StencilState::default()
+        }),
+        ..RenderState::default()
+    };
+
+    [mark, rim]
+}
+
+/// Draws objects with a solid outline around them, for highlighting hovered or selected objects.
+/// The window or render target needs a stencil buffer.
+///
+/// The object shader is expected to match the GUI shader, and the outline shader to have the same
+/// vertex inputs with a vec4 "outlineColor" uniform, like "GUIShader.vert" with "Outline.frag".
+pub struct OutlineRenderPipeline {
+    object_shader: ShaderProgram,
+    outline_shader: ShaderProgram,
+    /// Should span -1..1 on both axes, like the sprite batch quad.
+    mesh: Mesh2D,
+    targets: Vec<OutlineTarget>,
+    outline_color: Color,
+    /// Thickness of the outline in world units.
+    outline_width: f32,
+    view_projection: Mat44f,
+    priority: i32
+}
+
+impl OutlineRenderPipeline {
+    pub fn new(object_shader: ShaderProgram, outline_shader: ShaderProgram, mesh: Mesh2D) -> Self {
+        OutlineRenderPipeline {
+            object_shader,
+            outline_shader,
+            mesh,
+            targets: Vec::<OutlineTarget>::default(),
+            outline_color: Color::WHITE,
+            outline_width: 0.01,
+            view_projection: Mat44f::IDENTITY,
+            // Highlights draw over the scene by default.
+            priority: 1
+        }
+    }
+
+    pub fn add_target(&mut self, target: OutlineTarget) {
+        self.targets.push(target);
+    }
+
+    pub fn clear_targets(&mut self) {
+        self.targets.clear();
+    }
+
+    pub fn get_targets_mut(&mut self) -> &mut Vec<OutlineTarget> {
+        &mut self.targets
+    }
+
+    pub fn set_outline_color(&mut self, color: Color) {
+        self.outline_color = color;
+    }
+
+    pub fn get_outline_color(&self) -> Color {
+        self.outline_color
+    }
+
+    pub fn set_outline_width(&mut self, width: f32) {
+        self.outline_width = width;
+    }
+
+    pub fn get_outline_width(&self) -> f32 {
+        self.outline_width
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    /// Draws every target with the bound shader, growing each by the given amount on every side.
+    fn draw_targets(&self, shader: &ShaderProgram, grow: f32, bind_textures: bool) {
+        let location_pos = shader.get_uniform_location("pos");
+        let location_scale = shader.get_uniform_location("scale");
+
+        for target in self.targets.iter() {
+            if bind_textures {
+                target.texture.bind(0);
+            }
+
+            shader.load_vec2(location_pos, target.position);
+            shader.load_vec2(location_scale, target.scale + Vec2f::new(grow, grow));
+            self.mesh.render();
+        }
+    }
+}
+
+impl RenderPipelineHandler for OutlineRenderPipeline {
+    fn init(&mut self) {
+        self.object_shader.bind();
+        self.object_shader.load_uniforms(UniformSet::new().set_int("guiTexture", 0));
+    }
+
+    fn prepare(&self) {
+        let mut uniforms = UniformSet::new();
+        uniforms.set_mat4("viewProjection", self.view_projection);
+
+        for shader in [&self.object_shader, &self.outline_shader] {
+            shader.bind();
+            shader.load_uniforms(&uniforms);
+        }
+    }
+
+    fn update(&mut self, _input: &Box<dyn MouseKeyboardInputControl>) {}
+
+    /// The engine draws the passes one at a time with their own render states,
+    /// this is only used when the pipeline is rendered directly.
+    fn execute(&self) {
+        self.execute_pass(0);
+        self.execute_pass(1);
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.view_projection = camera.view_projection();
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn pass_count(&self) -> usize {
+        2
+    }
+
+    fn pass_render_state(&self, pass: usize) -> RenderState {
+        outline_pass_states()[pass]
+    }
+
+    fn execute_pass(&self, pass: usize) {
+        match pass {
+            0 => {
+                self.object_shader.bind();
+                self.draw_targets(&self.object_shader, 0.0, true);
+            },
+            _ => {
+                let color = self.outline_color;
+
+                self.outline_shader.bind();
+                self.outline_shader.load_uniforms(UniformSet::new().set_vec4("outlineColor", Vec4f::new(color.r, color.g, color.b, color.a)));
+                self.draw_targets(&self.outline_shader, self.outline_width, false);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use glmath::glmath::{Vec2f, Vec4f};
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+use crate::mesh::Mesh2D;
+use crate::particles::{ParticleInstanceBuffers, ParticleSystem};
+use crate::render_state::{BlendMode, RenderState};
+
+/// Values which can be blended linearly, for keyframes.
+pub trait Interpolate: Copy {
+    /// Returns a at 0, b at 1, and the blend of the two between.
+    fn interpolate(a: Self, b: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Interpolate for Vec4f {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a * (1.0 - t) + b * t
+    }
+}
+
+/// Values at points through a particle's life, from 0 when it spawns to 1 when it dies, blended
+/// linearly between keys. Before the first key and after the last one the nearest key's value is used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T: Interpolate> {
+    /// Sorted by time.
+    keys: Vec<(f32, T)>
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    /// The same value for the whole life of a particle.
+    pub fn constant(value: T) -> Self {
+        Keyframes { keys: vec![(0.0, value)] }
+    }
+
+    /// Creates keyframes from (time, value) pairs in any order.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Keyframes { keys }
+    }
+
+    /// Adds a key, ex: `Keyframes::constant(white).with_key(1.0, transparent)` to fade out.
+    pub fn with_key(mut self, time: f32, value: T) -> Self {
+        let index = self.keys.partition_point(|key| key.0 <= time);
+        self.keys.insert(index, (time, value));
+        self
+    }
+
+    /// Returns the value at the point through a particle's life. Panics when there are no keys.
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.keys.partition_point(|key| key.0 <= time);
+
+        match (next.checked_sub(1).map(|index| self.keys[index]), self.keys.get(next)) {
+            (Some((start, from)), Some(&(end, to))) => T::interpolate(from, to, (time - start) / (end - start)),
+            (Some((_, value)), None) | (None, Some(&(_, value))) => value,
+            (None, None) => panic!("Keyframes::sample called without any keys")
+        }
+    }
+}
+
+/// How an emitter's particles are spawned, move, and look.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterParams {
+    /// Particles spawned per second while emitting. Zero only spawns bursts.
+    pub rate: f32,
+    /// Lowest and highest lifetime of a particle in seconds, picked at random between.
+    pub lifetime: (f32, f32),
+    /// Lowest and highest speed a particle starts with.
+    pub speed: (f32, f32),
+    /// Center of the cone particles are sent out in, in radians counter clockwise from +x.
+    pub direction: f32,
+    /// Half the angle of the cone in radians. PI sends particles out in every direction.
+    pub spread: f32,
+    /// Acceleration applied to every particle, ex: gravity.
+    pub acceleration: Vec2f,
+    pub color: Keyframes<Vec4f>,
+    /// Half the width of a particle's quad.
+    pub size: Keyframes<f32>,
+    /// How the particles blend with what's behind them, see `ParticleEmitter::render_state`.
+    pub blend: BlendMode
+}
+
+impl Default for EmitterParams {
+    fn default() -> Self {
+        EmitterParams {
+            rate: 0.0,
+            lifetime: (1.0, 1.0),
+            speed: (1.0, 1.0),
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: std::f32::consts::PI,
+            acceleration: Vec2f::ZERO,
+            color: Keyframes::constant(Vec4f::new(1.0, 1.0, 1.0, 1.0)),
+            size: Keyframes::constant(1.0),
+            blend: BlendMode::Additive
+        }
+    }
+}
+
+/// Spawns particles into a ParticleSystem and animates their color and size over their lifetime.
+///
+/// Emitters are owned by game code and advanced with `update` each frame. The system's capacity is a hard
+/// cap on live particles: spawns beyond it are dropped, and dead particles free their slots for the next
+/// ones without reallocating. Emitters created `with_seed` spawn the same particles every run.
+pub struct ParticleEmitter {
+    params: EmitterParams,
+    particles: ParticleSystem,
+    rng: SmallRng,
+    position: Vec2f,
+    emitting: bool,
+    /// Fraction of a particle owed by the spawn rate, carried between frames.
+    spawn_debt: f32
+}
+
+impl ParticleEmitter {
+    /// Creates an emitter holding at most max_particles live particles, seeded randomly.
+    pub fn new(params: EmitterParams, max_particles: usize) -> Self {
+        ParticleEmitter::with_rng(params, max_particles, SmallRng::from_entropy())
+    }
+
+    /// Creates an emitter whose random spawn parameters follow from the seed.
+    pub fn with_seed(params: EmitterParams, max_particles: usize, seed: u64) -> Self {
+        ParticleEmitter::with_rng(params, max_particles, SmallRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(params: EmitterParams, max_particles: usize, rng: SmallRng) -> Self {
+        let mut particles = ParticleSystem::new(max_particles);
+        particles.set_acceleration(params.acceleration);
+
+        ParticleEmitter {
+            params,
+            particles,
+            rng,
+            position: Vec2f::ZERO,
+            emitting: true,
+            spawn_debt: 0.0
+        }
+    }
+
+    /// Sets where new particles spawn. Particles already spawned keep moving on their own.
+    pub fn set_position(&mut self, position: Vec2f) {
+        self.position = position;
+    }
+
+    pub fn get_position(&self) -> Vec2f {
+        self.position
+    }
+
+    /// Starts or stops spawning at the emitter's rate. Bursts spawn either way.
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    pub fn params(&self) -> &EmitterParams {
+        &self.params
+    }
+
+    /// Changes affect particles spawned from then on, except the acceleration and keyframes which apply
+    /// to every live particle.
+    pub fn params_mut(&mut self) -> &mut EmitterParams {
+        &mut self.params
+    }
+
+    pub fn particles(&self) -> &ParticleSystem {
+        &self.particles
+    }
+
+    /// Spawns up to count particles at once, returning how many fit under the cap.
+    pub fn burst(&mut self, count: usize) -> usize {
+        let spawned = (0..count).take_while(|_| self.spawn_one()).count();
+        self.apply_appearance();
+
+        spawned
+    }
+
+    /// Moves the particles, removes dead ones, and spawns new ones at the emitter's rate.
+    pub fn update(&mut self, delta_time: f32) {
+        self.particles.set_acceleration(self.params.acceleration);
+        self.particles.update(delta_time);
+
+        if self.emitting && self.params.rate > 0.0 {
+            self.spawn_debt += self.params.rate * delta_time;
+
+            while self.spawn_debt >= 1.0 {
+                self.spawn_debt -= 1.0;
+
+                if !self.spawn_one() {
+                    // Full, the particles owed are dropped rather than spawned in a burst later.
+                    self.spawn_debt = 0.0;
+                }
+            }
+        }
+
+        self.apply_appearance();
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_debt = 0.0;
+    }
+
+    /// The render state the particles should be drawn with.
+    pub fn render_state(&self) -> RenderState {
+        RenderState::with_blend(self.params.blend)
+    }
+
+    /// Uploads the particles and draws the mesh once per particle, see `ParticleSystem::render`.
+    pub fn render(&self, mesh: &Mesh2D, buffers: ParticleInstanceBuffers) {
+        self.particles.render(mesh, buffers);
+    }
+
+    fn spawn_one(&mut self) -> bool {
+        let lifetime = random_in(&mut self.rng, self.params.lifetime);
+        let speed = random_in(&mut self.rng, self.params.speed);
+        let spread = self.params.spread.abs();
+        let angle = self.params.direction + random_in(&mut self.rng, (-spread, spread));
+        let velocity = Vec2f::new(angle.cos(), angle.sin()) * speed;
+
+        self.particles.spawn_sized(self.position, velocity, lifetime,
+            self.params.color.sample(0.0), self.params.size.sample(0.0))
+    }
+
+    fn apply_appearance(&mut self) {
+        let (color, size) = (&self.params.color, &self.params.size);
+
+        self.particles.update_appearance(|age, particle_color, particle_size| {
+            *particle_color = color.sample(age);
+            *particle_size = size.sample(age);
+        });
+    }
+}
+
+/// Picks a value between the bounds, or the lower bound when they're equal or reversed.
+fn random_in(rng: &mut SmallRng, (min, max): (f32, f32)) -> f32 {
+    match min < max {
+        true => rng.gen_range(min..max),
+        false => min
+    }
+}
@@ -0,0 +1,204 @@
+use glmath::glmath::{Vec2f, Vec4f};
+
+use crate::mesh::Mesh2D;
+
+/// Live particles stored as a struct of arrays: every attribute has its own contiguous buffer, indexed
+/// by particle. Positions and colors are already laid out the way instance buffers want them, so they're
+/// uploaded straight from the arrays without gathering them out of particle structs first.
+///
+/// Dead particles are swap-removed, so the live particles stay packed at the front of every array
+/// and the order of particles changes as they die.
+pub struct ParticleSystem {
+    positions: Vec<Vec2f>,
+    velocities: Vec<Vec2f>,
+    /// Seconds each particle has left to live.
+    lifetimes: Vec<f32>,
+    /// Seconds each particle lived for in total, to work out how far through its life it is.
+    durations: Vec<f32>,
+    colors: Vec<Vec4f>,
+    /// Half the width of each particle's quad.
+    sizes: Vec<f32>,
+    max_particles: usize,
+    /// Acceleration applied to every particle, ex: gravity.
+    acceleration: Vec2f
+}
+
+/// Instance buffers of a mesh which a ParticleSystem uploads into, see `ParticleSystem::attach_to_mesh`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParticleInstanceBuffers {
+    positions: usize,
+    colors: usize,
+    sizes: Option<usize>
+}
+
+impl ParticleSystem {
+    /// Creates an empty system holding at most max_particles. Spawns beyond that are dropped.
+    pub fn new(max_particles: usize) -> Self {
+        ParticleSystem {
+            positions: Vec::with_capacity(max_particles),
+            velocities: Vec::with_capacity(max_particles),
+            lifetimes: Vec::with_capacity(max_particles),
+            durations: Vec::with_capacity(max_particles),
+            colors: Vec::with_capacity(max_particles),
+            sizes: Vec::with_capacity(max_particles),
+            max_particles,
+            acceleration: Vec2f::new(0.0, 0.0)
+        }
+    }
+
+    pub fn set_acceleration(&mut self, acceleration: Vec2f) {
+        self.acceleration = acceleration;
+    }
+
+    /// Adds a particle living for `lifetime` seconds. Returns false when the system is full.
+    pub fn spawn(&mut self, position: Vec2f, velocity: Vec2f, lifetime: f32, color: Vec4f) -> bool {
+        self.spawn_sized(position, velocity, lifetime, color, 1.0)
+    }
+
+    /// Like `spawn`, with the half width of the particle's quad.
+    pub fn spawn_sized(&mut self, position: Vec2f, velocity: Vec2f, lifetime: f32, color: Vec4f, size: f32) -> bool {
+        if self.positions.len() >= self.max_particles || lifetime <= 0.0 {
+            return false;
+        }
+
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.lifetimes.push(lifetime);
+        self.durations.push(lifetime);
+        self.colors.push(color);
+        self.sizes.push(size);
+
+        true
+    }
+
+    /// Moves every particle and removes the ones whose lifetime ran out.
+    pub fn update(&mut self, delta_time: f32) {
+        let acceleration = self.acceleration * delta_time;
+
+        for (position, velocity) in self.positions.iter_mut().zip(self.velocities.iter_mut()) {
+            *velocity += acceleration;
+            *position += *velocity * delta_time;
+        }
+
+        for lifetime in self.lifetimes.iter_mut() {
+            *lifetime -= delta_time;
+        }
+
+        // Backwards, so a particle swapped into a removed slot has already been checked.
+        for index in (0..self.lifetimes.len()).rev() {
+            if self.lifetimes[index] <= 0.0 {
+                self.positions.swap_remove(index);
+                self.velocities.swap_remove(index);
+                self.lifetimes.swap_remove(index);
+                self.durations.swap_remove(index);
+                self.colors.swap_remove(index);
+                self.sizes.swap_remove(index);
+            }
+        }
+    }
+
+    /// Returns the number of live particles.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn max_particles(&self) -> usize {
+        self.max_particles
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.velocities.clear();
+        self.lifetimes.clear();
+        self.durations.clear();
+        self.colors.clear();
+        self.sizes.clear();
+    }
+
+    /// Calls the function with how far through its life each particle is, from 0 when spawned to 1
+    /// when it dies, to change its color and size, ex: fading particles out as they age.
+    pub fn update_appearance(&mut self, mut appearance: impl FnMut(f32, &mut Vec4f, &mut f32)) {
+        let particles = self.lifetimes.iter()
+            .zip(self.durations.iter())
+            .zip(self.colors.iter_mut().zip(self.sizes.iter_mut()));
+
+        for ((lifetime, duration), (color, size)) in particles {
+            appearance(1.0 - lifetime / duration, color, size);
+        }
+    }
+
+    pub fn positions(&self) -> &[Vec2f] {
+        &self.positions
+    }
+
+    pub fn velocities(&self) -> &[Vec2f] {
+        &self.velocities
+    }
+
+    pub fn lifetimes(&self) -> &[f32] {
+        &self.lifetimes
+    }
+
+    pub fn colors(&self) -> &[Vec4f] {
+        &self.colors
+    }
+
+    pub fn sizes(&self) -> &[f32] {
+        &self.sizes
+    }
+
+    /// Adds instance buffers for the positions (vec2) and colors (vec4) to the mesh drawn for each particle,
+    /// at the given attribute locations.
+    pub fn attach_to_mesh(mesh: &mut Mesh2D, position_location: u32, color_location: u32) -> ParticleInstanceBuffers {
+        ParticleInstanceBuffers {
+            positions: mesh.add_dynamic_instance_float_buffer(position_location, 2),
+            colors: mesh.add_dynamic_instance_float_buffer(color_location, 4),
+            sizes: None
+        }
+    }
+
+    /// Like `attach_to_mesh`, with a float instance buffer for the sizes.
+    pub fn attach_sized_to_mesh(mesh: &mut Mesh2D, position_location: u32, color_location: u32, size_location: u32) -> ParticleInstanceBuffers {
+        ParticleInstanceBuffers {
+            sizes: Some(mesh.add_dynamic_instance_float_buffer(size_location, 1)),
+            ..ParticleSystem::attach_to_mesh(mesh, position_location, color_location)
+        }
+    }
+
+    /// Uploads the live particles into the mesh's instance buffers.
+    pub fn upload(&self, mesh: &Mesh2D, buffers: ParticleInstanceBuffers) {
+        mesh.update_instance_float_buffer(buffers.positions, vec2_floats(&self.positions));
+        mesh.update_instance_float_buffer(buffers.colors, vec4_floats(&self.colors));
+
+        if let Some(sizes) = buffers.sizes {
+            mesh.update_instance_float_buffer(sizes, &self.sizes);
+        }
+    }
+
+    /// Uploads the particles and draws the mesh once per particle. The shader must be bound.
+    pub fn render(&self, mesh: &Mesh2D, buffers: ParticleInstanceBuffers) {
+        if self.is_empty() {
+            return;
+        }
+
+        self.upload(mesh, buffers);
+        mesh.render_instanced(self.len() as i32);
+    }
+}
+
+/// Views the vectors as their components, x0 y0 x1 y1 and so on.
+pub fn vec2_floats(vectors: &[Vec2f]) -> &[f32] {
+    // Vec2f is repr(C) with two f32 fields, so a slice of them is a slice of twice as many floats.
+    unsafe { std::slice::from_raw_parts(vectors.as_ptr() as *const f32, vectors.len() * 2) }
+}
+
+/// Views the vectors as their components, x0 y0 z0 w0 x1 and so on.
+pub fn vec4_floats(vectors: &[Vec4f]) -> &[f32] {
+    // Vec4f is repr(C) with four f32 fields.
+    unsafe { std::slice::from_raw_parts(vectors.as_ptr() as *const f32, vectors.len() * 4) }
+}
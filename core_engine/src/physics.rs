@@ -0,0 +1,45 @@
+use glmath::glmath::Vec2f;
+
+/// A point mass moving continuously, for games which aren't grid based.
+/// This only integrates motion, collisions are left to the game.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Body2D {
+    pub position: Vec2f,
+    /// Units per second.
+    pub velocity: Vec2f,
+    /// Units per second squared, ex: gravity.
+    pub acceleration: Vec2f,
+    /// How quickly the velocity decays, the velocity is scaled by e^(-drag) each second. 0 for none.
+    pub drag: f32
+}
+
+impl Body2D {
+    /// Creates a body at rest at the given position.
+    pub fn new(position: Vec2f) -> Self {
+        Body2D {
+            position,
+            ..Body2D::default()
+        }
+    }
+
+    pub fn set_drag(&mut self, drag: f32) {
+        self.drag = drag;
+    }
+
+    /// Changes the velocity instantly, ex: for a jump.
+    pub fn apply_impulse(&mut self, impulse: Vec2f) {
+        self.velocity += impulse;
+    }
+
+    /// Advances the body by dt seconds with semi-implicit Euler: the velocity is updated first,
+    /// then moves the body. This stays stable at fixed steps where explicit Euler gains energy.
+    pub fn integrate(&mut self, dt: f32) {
+        self.velocity += self.acceleration * dt;
+
+        if self.drag > 0.0 {
+            self.velocity *= (-self.drag * dt).exp();
+        }
+
+        self.position += self.velocity * dt;
+    }
+}
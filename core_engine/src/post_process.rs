@@ -0,0 +1,268 @@
+use glmath::glmath::Vec2f;
+use ogl33::*;
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::parser::{JsonNode, JsonValueOps};
+
+use crate::framebuffer::Framebuffer;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::resource_manager::ResourceManager;
+use crate::shader_program::{ShaderProgram, ShaderUniforms};
+
+/// A post processing effect as described in the app config, before its shader is looked up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostEffectDesc {
+    /// Name of the shader in the shader registry.
+    pub shader: String,
+    /// Extra float uniforms set each time the effect runs, sorted by name.
+    pub uniforms: Vec<(String, f32)>
+}
+
+/// Reads the effect list from the config. Each entry is either the name of a registered shader,
+/// or an object of the form { "shader": "shader_post_crt", "uniforms": { "curvature": 6.0 } }.
+pub fn parse_post_effects(node: &JsonNode) -> Vec<PostEffectDesc> {
+    let mut effects = Vec::<PostEffectDesc>::new();
+
+    let effect_nodes = match node {
+        JsonNode::Array(effect_nodes) => effect_nodes,
+        _ => {
+            println!("Expected an array of effects for post_processing");
+            return effects;
+        }
+    };
+
+    for effect_node in effect_nodes.iter() {
+        match effect_node {
+            JsonNode::String(shader) => {
+                effects.push(PostEffectDesc { shader: shader.get().clone(), uniforms: vec![] });
+            },
+            JsonNode::Object(effect_object) => {
+                let shader = match effect_object.get("shader").and_then(String::from_json) {
+                    Some(shader) => shader,
+                    None => {
+                        println!("Expected a \"shader\" name in post processing effect");
+                        continue;
+                    }
+                };
+
+                let mut uniforms = Vec::<(String, f32)>::new();
+
+                if let Some(JsonNode::Object(uniforms_object)) = effect_object.get("uniforms") {
+                    for (name, value) in uniforms_object.iter() {
+                        match f32::from_json(value) {
+                            Some(value) => uniforms.push((name.clone(), value)),
+                            None => println!("Expected a number for uniform {} of post processing effect {}", name, shader)
+                        }
+                    }
+                }
+
+                uniforms.sort_by(|a, b| a.0.cmp(&b.0));
+                effects.push(PostEffectDesc { shader, uniforms });
+            },
+            _ => println!("Expected a shader name or object in post_processing")
+        }
+    }
+
+    effects
+}
+
+/// A full screen pass. The shader samples the previous result from the "screenTexture" sampler,
+/// and receives the target size in pixels through the vec2 "resolution" uniform.
+/// "FullscreenQuad.vert" is a suitable vertex shader.
+#[derive(Clone)]
+pub struct PostEffect {
+    shader: ShaderProgram,
+    uniforms: Vec<(i32, f32)>,
+    location_screen_texture: i32,
+    location_resolution: i32
+}
+
+impl PostEffect {
+    pub fn new(shader: ShaderProgram, uniforms: &[(String, f32)]) -> Self {
+        let uniforms = uniforms.iter()
+            .map(|(name, value)| (shader.get_uniform_location(name), *value))
+            .collect();
+
+        PostEffect {
+            location_screen_texture: shader.get_uniform_location("screenTexture"),
+            location_resolution: shader.get_uniform_location("resolution"),
+            shader,
+            uniforms
+        }
+    }
+
+    /// Creates the effect from its config description, looking its shader up in the registry.
+    pub fn from_desc(desc: &PostEffectDesc, shaders: &ResourceManager<ShaderProgram>) -> Option<Self> {
+        shaders.get_registry(&desc.shader)
+            .map(|shader| PostEffect::new(shader.clone(), &desc.uniforms))
+    }
+
+    /// Changes an extra uniform, adding it when the effect didn't set it before.
+    pub fn set_uniform(&mut self, name: &str, value: f32) {
+        let location = self.shader.get_uniform_location(name);
+
+        match self.uniforms.iter_mut().find(|(uniform_location, _)| *uniform_location == location) {
+            Some(uniform) => uniform.1 = value,
+            None => self.uniforms.push((location, value))
+        }
+    }
+
+    fn apply(&self, width: i32, height: i32) {
+        self.shader.bind();
+        self.shader.load_int(self.location_screen_texture, 0);
+        self.shader.load_vec2(self.location_resolution, Vec2f::new(width as f32, height as f32));
+
+        for (location, value) in self.uniforms.iter() {
+            self.shader.load_float(*location, *value);
+        }
+    }
+}
+
+/// Runs a chain of full screen effects over the rendered scene.
+///
+/// While the chain is active the scene is rendered into an offscreen target, then each effect reads
+/// the previous result and writes the next, with the last effect drawing to the window. When the chain
+/// is empty or disabled the scene renders straight to the window and nothing extra is done.
+#[derive(Default)]
+pub struct PostProcessChain {
+    effects: Vec<PostEffect>,
+    disabled: bool,
+    /// The scene target followed by up to two ping-pong targets for the effects between the first and last.
+    /// Created the first time the chain runs and recreated when the window is resized.
+    targets: Vec<Framebuffer>,
+    target_size: (i32, i32),
+    /// MSAA samples of the target the scene renders into.
+    samples: u32,
+    quad: Option<Mesh2D>
+}
+
+impl PostProcessChain {
+    pub fn new() -> Self {
+        PostProcessChain::default()
+    }
+
+    pub fn add_effect(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    pub fn get_effects_mut(&mut self) -> &mut Vec<PostEffect> {
+        &mut self.effects
+    }
+
+    /// Removes every effect and frees the offscreen targets.
+    pub fn clear(&mut self) {
+        self.effects.clear();
+        self.targets.clear();
+        self.target_size = (0, 0);
+    }
+
+    /// Renders the scene into a multisampled target with the given samples per pixel, resolved before the first effect.
+    /// 0 disables multisampling.
+    pub fn set_samples(&mut self, samples: u32) {
+        if samples != self.samples {
+            self.samples = samples;
+            self.targets.clear();
+        }
+    }
+
+    pub fn get_samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.disabled = !enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+
+    /// Turns the chain on or off, returning whether it's now enabled.
+    pub fn toggle(&mut self) -> bool {
+        self.disabled = !self.disabled;
+        !self.disabled
+    }
+
+    /// True when the scene should be redirected through the chain.
+    pub fn is_active(&self) -> bool {
+        !self.disabled && !self.effects.is_empty()
+    }
+
+    /// Redirects rendering into the chain's first target, returning false when the chain isn't active.
+    /// The targets are resized to match the framebuffer, and cleared.
+    pub fn begin(&mut self, framebuffer_size: (i32, i32)) -> bool {
+        // A minimized window has nothing to render into.
+        if !self.is_active() || framebuffer_size.0 <= 0 || framebuffer_size.1 <= 0 {
+            return false;
+        }
+
+        // The last effect draws to the window, so a single effect only needs the scene target.
+        let target_count = 1 + usize::min(self.effects.len() - 1, 2);
+
+        if self.target_size != framebuffer_size || self.targets.len() != target_count {
+            let (width, height) = (framebuffer_size.0 as u32, framebuffer_size.1 as u32);
+
+            self.targets = vec![Framebuffer::multisampled(width, height, self.samples)];
+            self.targets.extend((1..target_count).map(|_| Framebuffer::new(width, height)));
+            self.target_size = framebuffer_size;
+        }
+
+        if self.quad.is_none() {
+            self.quad = Some(Mesh2D::generate_quad());
+        }
+
+        self.targets[0].bind();
+
+        unsafe {
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT | GL_STENCIL_BUFFER_BIT);
+        }
+
+        true
+    }
+
+    /// Binds the target the scene renders into again after drawing elsewhere: the chain's first target
+    /// when begin returned true, otherwise the window.
+    pub(crate) fn bind_scene_target(&self, post_processing: bool) {
+        match self.targets.first() {
+            Some(target) if post_processing => target.bind(),
+            _ => unsafe {
+                glBindFramebuffer(GL_FRAMEBUFFER, 0);
+            }
+        }
+    }
+
+    /// Runs every effect, with the last one drawing to the window.
+    /// Blending and depth testing should be disabled first.
+    pub fn end(&self) {
+        let quad = match &self.quad {
+            Some(quad) => quad,
+            None => return
+        };
+
+        let (width, height) = self.target_size;
+
+        // Only the scene is multisampled, effects read its resolved texture.
+        self.targets[0].resolve();
+
+        for (index, effect) in self.effects.iter().enumerate() {
+            // Effects after the first read from whichever ping-pong target the previous one wrote.
+            let source = match index {
+                0 => &self.targets[0],
+                _ => &self.targets[1 + (index - 1) % 2]
+            };
+
+            if index + 1 == self.effects.len() {
+                source.unbind();
+            }
+            else {
+                self.targets[1 + index % 2].bind();
+            }
+
+            if let Some(texture) = source.color_texture() {
+                texture.bind(0);
+            }
+
+            effect.apply(width, height);
+            quad.render();
+        }
+    }
+}
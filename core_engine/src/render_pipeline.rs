@@ -1,5 +1,32 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use ogl33::*;
 use window::window::MouseKeyboardInputControl;
 
+use crate::audio::AudioEvent;
+use crate::camera::Camera2D;
+use crate::framebuffer::Framebuffer;
+use crate::render_state::{Rect2i, RenderState};
+use crate::shader_program::{ShaderProgram, ShaderUniforms};
+
+pub mod effects;
+
+/// The first texture unit render graph inputs are bound to. Units below are left to the pipelines.
+pub const RENDER_GRAPH_INPUT_UNIT: i32 = 8;
+
+/// Which group a pipeline renders in. Every opaque pipeline renders before the transparent ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderPass {
+    /// Draws without blending. Opaque pipelines render front to back, nearest sort key first.
+    Opaque,
+    /// Blends with what's already drawn. Transparent pipelines render back to front, farthest sort key
+    /// first, so overlapping translucent sprites blend over what's behind them.
+    #[default]
+    Transparent
+}
+
 /// Renders an individual stage in the render pipeline with its own isolated 
 /// state. An object implementing this should be given to the engine at
 /// the application launch time. This handles the program's execution flow.
@@ -13,4 +40,376 @@ pub trait RenderPipelineHandler {
     fn prepare(&self);
     fn update(&mut self, input: &Box<dyn MouseKeyboardInputControl>);
     fn execute(&self);
+
+    /// Called by the engine before each frame with the game camera.
+    /// Pipelines drawing in world space should keep its view projection matrix and upload it in prepare.
+    fn set_camera(&mut self, _camera: &Camera2D) {}
+
+    /// Coarse draw order of the pipeline within its render pass. Pipelines with a higher priority render
+    /// later, so they draw on top of lower ones. Equal priorities are ordered by sort key.
+    /// Layers within a SpriteBatch order draws inside a single pipeline.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// The pass the pipeline renders in. Defaults to Transparent, since pipelines alpha blend by default.
+    fn render_pass(&self) -> RenderPass {
+        RenderPass::default()
+    }
+
+    /// Distance of the pipeline's contents from the camera, larger is farther away. Orders pipelines
+    /// of the same pass and priority: opaque ones front to back, transparent ones back to front.
+    fn sort_key(&self) -> f32 {
+        0.0
+    }
+
+    /// The blend state the engine applies before the pipeline renders. Defaults to straight alpha blending.
+    fn render_state(&self) -> RenderState {
+        RenderState::default()
+    }
+
+    /// Number of passes the pipeline draws each frame. Pipelines with more than one pass are drawn by
+    /// calling prepare once, then execute_pass for each pass after applying its render state.
+    fn pass_count(&self) -> usize {
+        1
+    }
+
+    /// The render state applied before the given pass. Defaults to render_state for every pass.
+    fn pass_render_state(&self, _pass: usize) -> RenderState {
+        self.render_state()
+    }
+
+    /// Draws a single pass of a multi-pass pipeline.
+    fn execute_pass(&self, _pass: usize) {
+        self.execute();
+    }
+
+    /// Audio the pipeline wants played, collected by the engine once per frame after rendering.
+    /// Pipelines should return each event once, clearing whatever they queued.
+    fn on_audio(&self) -> Vec<AudioEvent> {
+        vec![]
+    }
+
+    /// The shader whose sampler uniforms receive the pipeline's render graph inputs.
+    /// Pipelines without inputs don't need to provide one.
+    fn input_shader(&self) -> Option<&ShaderProgram> {
+        None
+    }
+}
+
+/// Identifies a node of a render graph for as long as it's in the graph. Unlike indices, ids don't
+/// change when other nodes are removed, and aren't reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineId(u64);
+
+/// A pipeline in a render graph, with the framebuffer slots it reads and writes.
+pub struct RenderGraphNode {
+    pipeline: Box<dyn RenderPipelineHandler>,
+    /// Each input is a slot name and the sampler uniform its color texture is bound to.
+    inputs: Vec<(String, String)>,
+    /// The slot the pipeline draws into. None draws to the window.
+    output: Option<String>,
+    /// Limits the pipeline's drawing to a rect of its target, replacing the scissor of its render state.
+    scissor: Option<Rect2i>
+}
+
+impl RenderGraphNode {
+    /// Creates a node drawing to the window with no inputs.
+    pub fn new(pipeline: Box<dyn RenderPipelineHandler>) -> Self {
+        RenderGraphNode {
+            pipeline,
+            inputs: Vec::<(String, String)>::default(),
+            output: None,
+            scissor: None
+        }
+    }
+
+    /// Binds the color texture of the slot to the sampler uniform before the pipeline renders.
+    /// The node runs after every node writing the slot.
+    pub fn with_input(mut self, slot: &str, uniform: &str) -> Self {
+        self.inputs.push((slot.to_string(), uniform.to_string()));
+        self
+    }
+
+    /// Draws the pipeline into the slot's framebuffer instead of the window.
+    pub fn with_output(mut self, slot: &str) -> Self {
+        self.output = Some(slot.to_string());
+        self
+    }
+
+    /// Limits the pipeline's drawing to a rect of its target, ex: one player's half of a split screen.
+    pub fn with_scissor(mut self, rect: Rect2i) -> Self {
+        self.scissor = Some(rect);
+        self
+    }
+
+    /// Sets the rect the engine enables the scissor test with before the pipeline executes.
+    /// None disables the scissor test, unless the pipeline's render state sets a scissor of its own.
+    pub fn set_scissor(&mut self, rect: Option<Rect2i>) {
+        self.scissor = rect;
+    }
+
+    pub fn scissor(&self) -> Option<Rect2i> {
+        self.scissor
+    }
+
+    pub fn get_pipeline(&self) -> &dyn RenderPipelineHandler {
+        self.pipeline.as_ref()
+    }
+
+    pub fn get_pipeline_mut(&mut self) -> &mut Box<dyn RenderPipelineHandler> {
+        &mut self.pipeline
+    }
+
+    pub fn into_pipeline(self) -> Box<dyn RenderPipelineHandler> {
+        self.pipeline
+    }
+
+    pub fn inputs(&self) -> &[(String, String)] {
+        &self.inputs
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderGraphError {
+    /// The nodes read each other's outputs, directly or through other nodes, so none of them can run first.
+    Cycle(Vec<usize>)
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderGraphError::Cycle(nodes) => write!(f, "render graph nodes {:?} depend on each other", nodes)
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+/// Pipelines connected through named framebuffer slots.
+///
+/// A node reading a slot always runs after the nodes writing it, and the slot's color texture is
+/// bound to the node's input uniform before it renders, so multi-pass effects don't rely on priorities
+/// lining up. Nodes which don't depend on each other run in priority order, then in the order they were added.
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<RenderGraphNode>,
+    /// The id of each node, by index.
+    ids: Vec<PipelineId>,
+    next_id: u64,
+    slots: HashMap<String, Framebuffer>
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        RenderGraph::default()
+    }
+
+    /// Adds a node, returning its index.
+    pub fn add_node(&mut self, node: RenderGraphNode) -> usize {
+        self.nodes.push(node);
+        self.ids.push(PipelineId(self.next_id));
+        self.next_id += 1;
+        self.nodes.len() - 1
+    }
+
+    /// Removes the node, returning it. The nodes after it move down an index, keeping their order.
+    pub fn remove_node(&mut self, id: PipelineId) -> Option<RenderGraphNode> {
+        let index = self.index_of(id)?;
+        self.ids.remove(index);
+
+        Some(self.nodes.remove(index))
+    }
+
+    /// Returns the id of the node at the index.
+    pub fn get_id(&self, index: usize) -> Option<PipelineId> {
+        self.ids.get(index).copied()
+    }
+
+    /// Returns the current index of the node, or None when it's been removed.
+    pub fn index_of(&self, id: PipelineId) -> Option<usize> {
+        self.ids.iter().position(|node_id| *node_id == id)
+    }
+
+    /// Adds a pipeline drawing to the window with no inputs, returning its index.
+    pub fn add_pipeline(&mut self, pipeline: Box<dyn RenderPipelineHandler>) -> usize {
+        self.add_node(RenderGraphNode::new(pipeline))
+    }
+
+    /// Sets the framebuffer behind a slot. Slots are cleared the first time they're drawn to each frame.
+    pub fn add_slot(&mut self, name: &str, framebuffer: Framebuffer) {
+        self.slots.insert(name.to_string(), framebuffer);
+    }
+
+    pub fn get_slot(&self, name: &str) -> Option<&Framebuffer> {
+        self.slots.get(name)
+    }
+
+    pub fn get_node(&self, index: usize) -> Option<&RenderGraphNode> {
+        self.nodes.get(index)
+    }
+
+    pub fn get_node_mut(&mut self, index: usize) -> Option<&mut RenderGraphNode> {
+        self.nodes.get_mut(index)
+    }
+
+    pub fn nodes_mut(&mut self) -> std::slice::IterMut<'_, RenderGraphNode> {
+        self.nodes.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Compares the order two nodes render in when neither depends on the other: opaque before transparent,
+    /// then lowest priority first, then by sort key in the direction of the pass, then the order they were added.
+    fn draw_order(&self, a: usize, b: usize) -> Ordering {
+        let (first, second) = (self.nodes[a].pipeline.as_ref(), self.nodes[b].pipeline.as_ref());
+        let depth_order = match first.render_pass() {
+            RenderPass::Opaque => first.sort_key().total_cmp(&second.sort_key()),
+            RenderPass::Transparent => second.sort_key().total_cmp(&first.sort_key())
+        };
+
+        first.render_pass().cmp(&second.render_pass())
+            .then(first.priority().cmp(&second.priority()))
+            .then(depth_order)
+            .then(a.cmp(&b))
+    }
+
+    /// Sorts the nodes so each runs after the nodes writing its inputs.
+    /// Of the nodes ready to run, opaque pipelines go first, then the lowest priority, then by sort key,
+    /// see `RenderPipelineHandler::sort_key`. Ties keep the order they were added.
+    pub fn execution_order(&self) -> Result<Vec<usize>, RenderGraphError> {
+        let node_count = self.nodes.len();
+
+        // dependents[a] holds the nodes reading a slot node a writes.
+        let mut dependents = vec![Vec::<usize>::new(); node_count];
+        let mut remaining_inputs = vec![0usize; node_count];
+
+        for (reader, node) in self.nodes.iter().enumerate() {
+            for (slot, _) in node.inputs.iter() {
+                for (writer, other) in self.nodes.iter().enumerate() {
+                    if other.output.as_ref() == Some(slot) && !dependents[writer].contains(&reader) {
+                        dependents[writer].push(reader);
+                        remaining_inputs[reader] += 1;
+                    }
+                }
+            }
+        }
+
+        let mut ready: Vec<usize> = (0..node_count).filter(|node| remaining_inputs[*node] == 0).collect();
+        let mut order = Vec::<usize>::with_capacity(node_count);
+
+        while let Some(next) = ready.iter().copied().min_by(|a, b| self.draw_order(*a, *b)) {
+            ready.retain(|node| *node != next);
+            order.push(next);
+
+            for dependent in dependents[next].iter() {
+                remaining_inputs[*dependent] -= 1;
+
+                if remaining_inputs[*dependent] == 0 {
+                    ready.push(*dependent);
+                }
+            }
+        }
+
+        if order.len() != node_count {
+            let cycle = (0..node_count).filter(|node| !order.contains(node)).collect();
+            return Err(RenderGraphError::Cycle(cycle));
+        }
+
+        Ok(order)
+    }
+
+    /// The order nodes run in. When the graph has a cycle, the slots are ignored and nodes run in draw order.
+    pub(crate) fn render_order(&self) -> Vec<usize> {
+        match self.execution_order() {
+            Ok(order) => order,
+            Err(_) => {
+                let mut order: Vec<usize> = (0..self.nodes.len()).collect();
+                order.sort_by(|a, b| self.draw_order(*a, *b));
+                order
+            }
+        }
+    }
+
+    /// Collects the audio events of every node, in the order they render.
+    pub fn audio_events(&self) -> Vec<AudioEvent> {
+        self.render_order().into_iter()
+            .flat_map(|index| self.nodes[index].pipeline.on_audio())
+            .collect()
+    }
+
+    /// Binds the node's output slot, clearing it when it hasn't been drawn to yet this frame.
+    /// Returns the size of the slot, or None when the node draws to the window.
+    pub(crate) fn bind_output(&self, index: usize, cleared_slots: &mut Vec<String>) -> Option<(i32, i32)> {
+        let slot = self.nodes[index].output.as_ref()?;
+
+        match self.slots.get(slot) {
+            Some(framebuffer) => {
+                framebuffer.bind();
+
+                if !cleared_slots.contains(slot) {
+                    // Slots start transparent so later nodes can composite them over the scene.
+                    // The window's clear color is set again at the start of the next frame.
+                    unsafe {
+                        glClearColor(0.0, 0.0, 0.0, 0.0);
+                        glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT | GL_STENCIL_BUFFER_BIT);
+                    }
+
+                    cleared_slots.push(slot.clone());
+                }
+
+                Some((framebuffer.width() as i32, framebuffer.height() as i32))
+            },
+            None => {
+                println!("Render graph slot {} doesn't have a framebuffer, drawing to the window", slot);
+                None
+            }
+        }
+    }
+
+    /// Binds the color textures of the node's input slots to its input uniforms.
+    /// The node's shader is left bound.
+    pub(crate) fn bind_inputs(&self, index: usize) {
+        let node = &self.nodes[index];
+
+        if node.inputs.is_empty() {
+            return;
+        }
+
+        let shader = match node.pipeline.input_shader() {
+            Some(shader) => shader,
+            None => {
+                println!("Render graph node {} has inputs but no input shader", index);
+                return;
+            }
+        };
+
+        shader.bind();
+
+        for (input, (slot, uniform)) in node.inputs.iter().enumerate() {
+            let framebuffer = match self.slots.get(slot) {
+                Some(framebuffer) => framebuffer,
+                None => continue
+            };
+
+            // Multisampled slots are resolved before they're read.
+            framebuffer.resolve();
+
+            if let Some(texture) = framebuffer.color_texture() {
+                let unit = RENDER_GRAPH_INPUT_UNIT + input as i32;
+                texture.bind(unit);
+                shader.load_int(shader.get_uniform_location(uniform), unit);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,433 @@
+use glmath::glmath::{Vec2f, Vec3f};
+use ogl33::*;
+use window::window::MouseKeyboardInputControl;
+
+use crate::framebuffer::Framebuffer;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::render_pipeline::RenderPipelineHandler;
+use crate::render_state::{BlendMode, RenderState};
+use crate::shader_program::{ShaderProgram, ShaderUniforms};
+
+/// The widest blur the shader supports, in texels on each side of the center.
+pub const MAX_BLUR_RADIUS: u32 = 32;
+
+const BLUR_VERTEX_SHADER: &str = "#version 130
+in vec2 position;
+
+out vec2 texCoord;
+
+void main() {
+    gl_Position = vec4(position, 0, 1);
+
+    // Render targets are stored bottom up, so no flip is needed.
+    texCoord = position * .5 + .5;
+}";
+
+/// Samples the center texel and `radius` texels on either side of it along texelStep.
+const BLUR_FRAGMENT_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D blurTexture;
+uniform vec2 texelStep;
+uniform int radius;
+uniform float weights[33];
+
+void main() {
+    vec4 sum = texture(blurTexture, texCoord) * weights[0];
+
+    for (int i = 1; i <= radius; i++) {
+        vec2 offset = texelStep * float(i);
+        sum += (texture(blurTexture, texCoord + offset) + texture(blurTexture, texCoord - offset)) * weights[i];
+    }
+
+    color = sum;
+}";
+
+/// Keeps the part of each pixel brighter than the threshold, scaled down so colors keep their hue.
+const BLOOM_THRESHOLD_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D sceneTexture;
+uniform float threshold;
+
+void main() {
+    vec3 scene = texture(sceneTexture, texCoord).rgb;
+    float brightness = dot(scene, vec3(0.2126, 0.7152, 0.0722));
+
+    color = vec4(scene * (max(brightness - threshold, 0.0) / max(brightness, 0.0001)), 1.0);
+}";
+
+/// Adds the blurred bright parts over the scene.
+const BLOOM_COMPOSITE_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D sceneTexture;
+uniform sampler2D bloomTexture;
+uniform float intensity;
+
+void main() {
+    vec4 scene = texture(sceneTexture, texCoord);
+    color = vec4(scene.rgb + texture(bloomTexture, texCoord).rgb * intensity, scene.a);
+}";
+
+/// Returns the part of a linear color the bloom threshold pass keeps, matching the threshold shader.
+/// Colors at or below the threshold's luminance are dropped, brighter ones keep their hue.
+pub fn bloom_bright_pass(color: Vec3f, threshold: f32) -> Vec3f {
+    let brightness = color.x * 0.2126 + color.y * 0.7152 + color.z * 0.0722;
+    color * ((brightness - threshold).max(0.0) / brightness.max(0.0001))
+}
+
+/// The size of the targets the bright parts of a scene are blurred in. Half the scene's size, since
+/// the blur hides the lost detail and each pass samples a quarter of the texels.
+pub fn bloom_target_size(width: u32, height: u32) -> (u32, u32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+/// Draws into the target, putting the framebuffer binding and viewport back afterwards.
+fn draw_offscreen(target: &Framebuffer, draw: impl FnOnce()) {
+    let mut bound: GLint = 0;
+    let mut viewport: [GLint; 4] = [0; 4];
+
+    unsafe {
+        glGetIntegerv(GL_FRAMEBUFFER_BINDING, &mut bound);
+        glGetIntegerv(GL_VIEWPORT, viewport.as_mut_ptr());
+    }
+
+    target.bind();
+    draw();
+
+    unsafe {
+        glBindFramebuffer(GL_FRAMEBUFFER, bound as GLuint);
+        glViewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+    }
+}
+
+/// Returns the weights of a Gaussian blur for the center texel followed by each texel out to the radius,
+/// which is clamped to MAX_BLUR_RADIUS. Every texel but the center is sampled on both sides, and the
+/// weights are scaled so the whole kernel sums to 1 and the blur keeps the image's brightness.
+pub fn gaussian_kernel(radius: u32, sigma: f32) -> Vec<f32> {
+    let radius = radius.min(MAX_BLUR_RADIUS);
+    // A sigma of zero would divide by zero, and is as sharp as no blur at all.
+    let sigma = sigma.max(f32::EPSILON);
+
+    let mut weights: Vec<f32> = (0..=radius)
+        .map(|offset| f32::exp(-((offset * offset) as f32) / (2.0 * sigma * sigma)))
+        .collect();
+
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+
+    for weight in weights.iter_mut() {
+        *weight /= total;
+    }
+
+    weights
+}
+
+/// Blurs the color texture of a framebuffer into the pipeline's target with a separable Gaussian blur,
+/// for blurred backgrounds, depth of field, and glows.
+///
+/// The first pass blurs the source horizontally into an intermediate framebuffer of the same size,
+/// the second blurs that vertically into whatever the pipeline draws to, costing 2 * radius + 1 samples
+/// per pass rather than per texel of the square. Multisampled sources need to be resolved first.
+pub struct GaussianBlurPipeline {
+    radius: u32,
+    sigma: f32,
+    /// The kernel for the radius and sigma, see `gaussian_kernel`.
+    weights: Vec<f32>,
+    shader: ShaderProgram,
+    quad: Mesh2D,
+    source: Framebuffer,
+    /// Holds the horizontally blurred source between the passes.
+    intermediate: Framebuffer,
+    priority: i32
+}
+
+impl GaussianBlurPipeline {
+    pub fn new(source: Framebuffer, radius: u32, sigma: f32) -> Self {
+        GaussianBlurPipeline {
+            radius: radius.min(MAX_BLUR_RADIUS),
+            sigma,
+            weights: gaussian_kernel(radius, sigma),
+            shader: ShaderProgram::from_source(BLUR_VERTEX_SHADER, BLUR_FRAGMENT_SHADER),
+            quad: Mesh2D::generate_quad(),
+            intermediate: Framebuffer::new(source.width(), source.height()),
+            source,
+            priority: 0
+        }
+    }
+
+    /// Sets how many texels on each side of a texel are blended into it, up to MAX_BLUR_RADIUS.
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius.min(MAX_BLUR_RADIUS);
+        self.weights = gaussian_kernel(self.radius, self.sigma);
+    }
+
+    pub fn get_radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Sets the standard deviation of the blur in texels. Larger values spread the blur further within the radius,
+    /// a radius of around 3 sigma uses the whole curve.
+    pub fn set_sigma(&mut self, sigma: f32) {
+        self.sigma = sigma;
+        self.weights = gaussian_kernel(self.radius, self.sigma);
+    }
+
+    pub fn get_sigma(&self) -> f32 {
+        self.sigma
+    }
+
+    pub fn get_weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Blurs a different framebuffer, resizing the intermediate framebuffer to match it.
+    pub fn set_source(&mut self, source: Framebuffer) {
+        if source.width() != self.intermediate.width() || source.height() != self.intermediate.height() {
+            self.intermediate = Framebuffer::new(source.width(), source.height());
+        }
+
+        self.source = source;
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    /// Draws the bound texture over the bound target, blurred along one axis.
+    fn blur(&self, texel_step: Vec2f) {
+        self.shader.load_vec2(self.shader.get_uniform_location("texelStep"), texel_step);
+        self.quad.render();
+    }
+}
+
+impl RenderPipelineHandler for GaussianBlurPipeline {
+    fn init(&mut self) {}
+
+    fn prepare(&self) {
+        self.shader.bind();
+        self.shader.load_int(self.shader.get_uniform_location("blurTexture"), 0);
+        self.shader.load_int(self.shader.get_uniform_location("radius"), self.radius as i32);
+        self.shader.load_float_array(self.shader.get_uniform_location("weights"), self.weights.clone());
+    }
+
+    fn update(&mut self, _input: &Box<dyn MouseKeyboardInputControl>) {}
+
+    /// The engine draws the passes one at a time, this is only used when the pipeline is rendered directly.
+    fn execute(&self) {
+        self.execute_pass(0);
+        self.execute_pass(1);
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Both passes replace what's below them.
+    fn render_state(&self) -> RenderState {
+        RenderState::with_blend(BlendMode::Opaque)
+    }
+
+    fn pass_count(&self) -> usize {
+        2
+    }
+
+    fn execute_pass(&self, pass: usize) {
+        let (width, height) = (self.intermediate.width() as f32, self.intermediate.height() as f32);
+
+        match pass {
+            0 => {
+                let source = match self.source.color_texture() {
+                    Some(source) => source,
+                    None => return
+                };
+
+                // Drawing into the intermediate framebuffer, so the engine's target and viewport are put back after.
+                draw_offscreen(&self.intermediate, || {
+                    source.bind(0);
+                    self.blur(Vec2f::new(1.0 / width, 0.0));
+                });
+            },
+            _ => {
+                if let Some(horizontal) = self.intermediate.color_texture() {
+                    horizontal.bind(0);
+                    self.blur(Vec2f::new(0.0, 1.0 / height));
+                }
+            }
+        }
+    }
+}
+
+/// Makes the bright parts of a scene glow by bleeding them into their surroundings, for neon and
+/// emissive looks.
+///
+/// The scene is drawn into a framebuffer, usually a render graph slot, and the bloom draws it to its own
+/// target with the glow added. Reading the slot as an input makes the graph run the bloom after
+/// everything drawing the scene:
+///
+/// ```ignore
+/// graph.add_slot("scene", scene.clone());
+/// graph.add_node(RenderGraphNode::new(game_pipeline).with_output("scene"));
+/// graph.add_node(RenderGraphNode::new(Box::new(BloomPipeline::new(scene, 0.8, 1.0))).with_input("scene", "sceneTexture"));
+/// ```
+///
+/// Each frame the pixels brighter than the threshold are copied into a half size framebuffer, blurred
+/// there with a GaussianBlurPipeline, then added over the scene scaled by the intensity.
+pub struct BloomPipeline {
+    threshold: f32,
+    intensity: f32,
+    scene: Framebuffer,
+    /// The bright parts of the scene, and the blur's source.
+    bright: Framebuffer,
+    /// The bright parts after both blur passes.
+    blurred: Framebuffer,
+    blur: GaussianBlurPipeline,
+    threshold_shader: ShaderProgram,
+    composite_shader: ShaderProgram,
+    quad: Mesh2D,
+    priority: i32
+}
+
+impl BloomPipeline {
+    /// Blooms the scene drawn into the framebuffer. Multisampled scenes are resolved by the render graph
+    /// when they're an input slot, otherwise they need resolving first.
+    pub fn new(scene: Framebuffer, threshold: f32, intensity: f32) -> Self {
+        let (width, height) = bloom_target_size(scene.width(), scene.height());
+        let bright = Framebuffer::new(width, height);
+
+        BloomPipeline {
+            threshold,
+            intensity,
+            scene,
+            blur: GaussianBlurPipeline::new(bright.clone(), 8, 4.0),
+            bright,
+            blurred: Framebuffer::new(width, height),
+            threshold_shader: ShaderProgram::from_source(BLUR_VERTEX_SHADER, BLOOM_THRESHOLD_SHADER),
+            composite_shader: ShaderProgram::from_source(BLUR_VERTEX_SHADER, BLOOM_COMPOSITE_SHADER),
+            quad: Mesh2D::generate_quad(),
+            priority: 0
+        }
+    }
+
+    /// Sets the luminance a pixel needs to glow. 1 only blooms colors brighter than white, which
+    /// needs a floating point scene, lower values bloom bright colors of a regular scene.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    pub fn get_threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Sets how strongly the glow is added over the scene. 0 draws the scene unchanged.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+    }
+
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// The blur spreading the glow, whose radius and sigma are in half size texels.
+    pub fn get_blur_mut(&mut self) -> &mut GaussianBlurPipeline {
+        &mut self.blur
+    }
+
+    /// Blooms a different scene, resizing the bloom's framebuffers to match it.
+    pub fn set_source(&mut self, scene: Framebuffer) {
+        let size = bloom_target_size(scene.width(), scene.height());
+
+        if size != (self.bright.width(), self.bright.height()) {
+            self.bright = Framebuffer::new(size.0, size.1);
+            self.blurred = Framebuffer::new(size.0, size.1);
+            self.blur.set_source(self.bright.clone());
+        }
+
+        self.scene = scene;
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+}
+
+impl RenderPipelineHandler for BloomPipeline {
+    fn init(&mut self) {}
+
+    fn prepare(&self) {
+        self.threshold_shader.bind();
+        self.threshold_shader.load_int(self.threshold_shader.get_uniform_location("sceneTexture"), 0);
+        self.threshold_shader.load_float(self.threshold_shader.get_uniform_location("threshold"), self.threshold);
+
+        self.composite_shader.bind();
+        self.composite_shader.load_int(self.composite_shader.get_uniform_location("sceneTexture"), 0);
+        self.composite_shader.load_int(self.composite_shader.get_uniform_location("bloomTexture"), 1);
+        self.composite_shader.load_float(self.composite_shader.get_uniform_location("intensity"), self.intensity);
+    }
+
+    fn update(&mut self, _input: &Box<dyn MouseKeyboardInputControl>) {}
+
+    /// The engine draws the passes one at a time, this is only used when the pipeline is rendered directly.
+    fn execute(&self) {
+        for pass in 0..self.pass_count() {
+            self.execute_pass(pass);
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Every pass replaces what's below it, the composite adds the glow itself.
+    fn render_state(&self) -> RenderState {
+        RenderState::with_blend(BlendMode::Opaque)
+    }
+
+    /// Threshold, horizontal blur, vertical blur, then composite.
+    fn pass_count(&self) -> usize {
+        4
+    }
+
+    fn execute_pass(&self, pass: usize) {
+        let scene = match self.scene.color_texture() {
+            Some(scene) => scene,
+            None => return
+        };
+
+        match pass {
+            0 => draw_offscreen(&self.bright, || {
+                self.threshold_shader.bind();
+                scene.bind(0);
+                self.quad.render();
+            }),
+            1 => {
+                self.blur.prepare();
+                self.blur.execute_pass(0);
+            },
+            2 => draw_offscreen(&self.blurred, || {
+                self.blur.prepare();
+                self.blur.execute_pass(1);
+            }),
+            _ => {
+                if let Some(blurred) = self.blurred.color_texture() {
+                    self.composite_shader.bind();
+                    scene.bind(0);
+                    blurred.bind(1);
+                    self.quad.render();
+                }
+            }
+        }
+    }
+
+    /// Receives the scene slot when the bloom reads it as a render graph input. The scene is sampled
+    /// from the framebuffer the bloom was created with, the input orders the graph.
+    fn input_shader(&self) -> Option<&ShaderProgram> {
+        Some(&self.composite_shader)
+    }
+}
@@ -0,0 +1,390 @@
+use ogl33::*;
+
+/// How the colors a pipeline outputs are combined with what's already in the framebuffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Blending is disabled, the output replaces the destination.
+    Opaque,
+    /// Standard transparency for textures with straight (non-premultiplied) alpha.
+    /// result = src * src.a + dst * (1 - src.a)
+    #[default]
+    Alpha,
+    /// Transparency for textures whose color channels are already multiplied by alpha.
+    /// Avoids the dark fringes straight alpha produces around filtered edges.
+    /// result = src + dst * (1 - src.a)
+    PremultipliedAlpha,
+    /// Adds the output on top of the destination, for glows and particles.
+    /// result = src * src.a + dst
+    Additive,
+    /// Darkens the destination by the output, for shadows and tinting.
+    /// result = src * dst
+    Multiply
+}
+
+impl BlendMode {
+    /// Returns the source and destination blend factors, or None when blending is disabled.
+    pub fn factors(&self) -> Option<(GLenum, GLenum)> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some((GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA)),
+            BlendMode::PremultipliedAlpha => Some((GL_ONE, GL_ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive => Some((GL_SRC_ALPHA, GL_ONE)),
+            BlendMode::Multiply => Some((GL_DST_COLOR, GL_ZERO))
+        }
+    }
+}
+
+/// Comparison used by depth and stencil tests. The incoming value is compared against the stored one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always
+}
+
+impl CompareFunc {
+    pub fn to_gl(&self) -> GLenum {
+        match self {
+            CompareFunc::Never => GL_NEVER,
+            CompareFunc::Less => GL_LESS,
+            CompareFunc::Equal => GL_EQUAL,
+            CompareFunc::LessEqual => GL_LEQUAL,
+            CompareFunc::Greater => GL_GREATER,
+            CompareFunc::NotEqual => GL_NOTEQUAL,
+            CompareFunc::GreaterEqual => GL_GEQUAL,
+            CompareFunc::Always => GL_ALWAYS
+        }
+    }
+}
+
+/// What happens to the stored stencil value after a test.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    #[default]
+    Keep,
+    Zero,
+    /// Writes the reference value.
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert
+}
+
+impl StencilOp {
+    pub fn to_gl(&self) -> GLenum {
+        match self {
+            StencilOp::Keep => GL_KEEP,
+            StencilOp::Zero => GL_ZERO,
+            StencilOp::Replace => GL_REPLACE,
+            StencilOp::Increment => GL_INCR,
+            StencilOp::IncrementWrap => GL_INCR_WRAP,
+            StencilOp::Decrement => GL_DECR,
+            StencilOp::DecrementWrap => GL_DECR_WRAP,
+            StencilOp::Invert => GL_INVERT
+        }
+    }
+}
+
+/// Stencil test settings, used for both front and back faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    /// A fragment passes when `reference & read_mask` compares successfully against `stored & read_mask`.
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    /// Bits of the stored value which may be written.
+    pub write_mask: u32,
+    /// Applied when the stencil test fails.
+    pub fail: StencilOp,
+    /// Applied when the stencil test passes but the depth test fails.
+    pub depth_fail: StencilOp,
+    /// Applied when both tests pass.
+    pub pass: StencilOp
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        StencilState {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: 0xFF,
+            write_mask: 0xFF,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep
+        }
+    }
+}
+
+impl StencilState {
+    /// Sends the test function, write mask, and operations to GL through the calls.
+    pub(crate) fn apply_to(&self, calls: &mut dyn StencilCalls) {
+        calls.stencil_func(self.func.to_gl(), self.reference, self.read_mask);
+        calls.stencil_mask(self.write_mask);
+        calls.stencil_op(self.fail.to_gl(), self.depth_fail.to_gl(), self.pass.to_gl());
+    }
+}
+
+/// The GL calls a stencil state is applied with, so tests can record them instead.
+pub(crate) trait StencilCalls {
+    fn stencil_func(&mut self, func: GLenum, reference: GLint, read_mask: GLuint);
+    fn stencil_mask(&mut self, write_mask: GLuint);
+    fn stencil_op(&mut self, fail: GLenum, depth_fail: GLenum, pass: GLenum);
+}
+
+struct GlStencilCalls;
+
+impl StencilCalls for GlStencilCalls {
+    fn stencil_func(&mut self, func: GLenum, reference: GLint, read_mask: GLuint) {
+        unsafe { glStencilFunc(func, reference, read_mask) }
+    }
+
+    fn stencil_mask(&mut self, write_mask: GLuint) {
+        unsafe { glStencilMask(write_mask) }
+    }
+
+    fn stencil_op(&mut self, fail: GLenum, depth_fail: GLenum, pass: GLenum) {
+        unsafe { glStencilOp(fail, depth_fail, pass) }
+    }
+}
+
+/// A rectangle of whole pixels, with the origin at the bottom left like glScissor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect2i {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Rect2i {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Rect2i { x, y, width, height }
+    }
+}
+
+impl From<Rect2i> for ViewRect {
+    fn from(rect: Rect2i) -> Self {
+        ViewRect::Pixels {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width.min(i32::MAX as u32) as i32,
+            height: rect.height.min(i32::MAX as u32) as i32
+        }
+    }
+}
+
+/// A rectangle of the framebuffer, with the origin at the bottom left like glViewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewRect {
+    Pixels { x: i32, y: i32, width: i32, height: i32 },
+    /// Fractions of the framebuffer size, so the rect follows the window when it's resized.
+    /// ex: the top right quarter is { x: 0.75, y: 0.75, width: 0.25, height: 0.25 }
+    Normalized { x: f32, y: f32, width: f32, height: f32 }
+}
+
+impl ViewRect {
+    /// Resolves the rect into pixels as (x, y, width, height) for a framebuffer of the given size.
+    pub fn to_pixels(&self, framebuffer_width: i32, framebuffer_height: i32) -> (i32, i32, i32, i32) {
+        match *self {
+            ViewRect::Pixels { x, y, width, height } => (x, y, width, height),
+            ViewRect::Normalized { x, y, width, height } => {
+                let fb_width = framebuffer_width as f32;
+                let fb_height = framebuffer_height as f32;
+
+                (
+                    (x * fb_width).round() as i32,
+                    (y * fb_height).round() as i32,
+                    (width * fb_width).round() as i32,
+                    (height * fb_height).round() as i32
+                )
+            }
+        }
+    }
+}
+
+/// The fixed function GL state a pipeline renders with.
+/// The engine applies it before each pipeline executes, and returns to the default state once every pipeline has rendered.
+/// The default suits 2D rendering: straight alpha blending with depth and stencil testing off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    pub depth_test: bool,
+    /// Whether fragments write their depth. Can be turned off while testing stays on,
+    /// to draw transparent geometry against an opaque scene.
+    pub depth_write: bool,
+    pub depth_func: CompareFunc,
+    /// The stencil test, disabled when None.
+    pub stencil: Option<StencilState>,
+    /// The part of the framebuffer drawn into. None covers the whole framebuffer.
+    /// The camera handed to the pipeline is fitted to this rect.
+    pub viewport: Option<ViewRect>,
+    /// Pixels outside this rect are left untouched, disabled when None.
+    pub scissor: Option<ViewRect>
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        RenderState {
+            blend: BlendMode::default(),
+            depth_test: false,
+            depth_write: true,
+            depth_func: CompareFunc::Less,
+            stencil: None,
+            viewport: None,
+            scissor: None
+        }
+    }
+}
+
+impl RenderState {
+    /// Returns the state with its scissor replaced by the rect, or left as is when there's none.
+    pub fn with_scissor_rect(self, scissor: Option<Rect2i>) -> Self {
+        match scissor {
+            Some(rect) => RenderState { scissor: Some(rect.into()), ..self },
+            None => self
+        }
+    }
+
+    /// Returns the state with the stencil test filled in when it doesn't set one of its own.
+    pub fn with_default_stencil(self, stencil: Option<StencilState>) -> Self {
+        RenderState { stencil: self.stencil.or(stencil), ..self }
+    }
+
+    pub fn with_blend(blend: BlendMode) -> Self {
+        RenderState { blend, ..RenderState::default() }
+    }
+
+    /// Opaque rendering with depth testing and writing, for 3D geometry.
+    pub fn depth_tested() -> Self {
+        RenderState {
+            blend: BlendMode::Opaque,
+            depth_test: true,
+            ..RenderState::default()
+        }
+    }
+}
+
+/// Enables or disables a GL capability.
+unsafe fn set_capability(capability: GLenum, enabled: bool) {
+    if enabled {
+        glEnable(capability);
+    }
+    else {
+        glDisable(capability);
+    }
+}
+
+/// Tracks the state last applied to GL so unchanged settings aren't sent again.
+#[derive(Debug, Default)]
+pub struct RenderStateCache {
+    /// None until the first state is applied, since the GL state isn't known before then.
+    current: Option<RenderState>,
+    /// The viewport and scissor rects in pixels, which change with the framebuffer size.
+    current_viewport: Option<(i32, i32, i32, i32)>,
+    current_scissor: Option<(i32, i32, i32, i32)>
+}
+
+impl RenderStateCache {
+    pub fn new() -> Self {
+        RenderStateCache::default()
+    }
+
+    /// Applies the state, only touching GL for the settings which differ from the current state.
+    /// The framebuffer size resolves normalized rects and the default full viewport.
+    pub fn apply(&mut self, state: &RenderState, framebuffer_size: (i32, i32)) {
+        let current = self.current;
+
+        // True when the setting differs from what was last applied, or nothing has been applied yet.
+        let changed = |setting: &dyn Fn(&RenderState) -> bool| {
+            current.map(|current| setting(&current) != setting(state)).unwrap_or(true)
+        };
+
+        unsafe {
+            if changed(&|s| s.blend.factors().is_some()) {
+                set_capability(GL_BLEND, state.blend.factors().is_some());
+            }
+
+            if current.map(|current| current.blend) != Some(state.blend) {
+                if let Some((src, dst)) = state.blend.factors() {
+                    glBlendFunc(src, dst);
+                }
+            }
+
+            if changed(&|s| s.depth_test) {
+                set_capability(GL_DEPTH_TEST, state.depth_test);
+            }
+
+            if changed(&|s| s.depth_write) {
+                glDepthMask(if state.depth_write { GL_TRUE } else { GL_FALSE });
+            }
+
+            if current.map(|current| current.depth_func) != Some(state.depth_func) {
+                glDepthFunc(state.depth_func.to_gl());
+            }
+
+            if changed(&|s| s.stencil.is_some()) {
+                set_capability(GL_STENCIL_TEST, state.stencil.is_some());
+
+                // The write mask also limits glClear, so restore it when the test turns off.
+                if state.stencil.is_none() {
+                    glStencilMask(0xFF);
+                }
+            }
+
+            if let Some(stencil) = state.stencil {
+                let current_stencil = current.and_then(|current| current.stencil);
+
+                if current_stencil != Some(stencil) {
+                    stencil.apply_to(&mut GlStencilCalls);
+                }
+            }
+
+            let viewport = match state.viewport {
+                Some(viewport) => viewport.to_pixels(framebuffer_size.0, framebuffer_size.1),
+                None => (0, 0, framebuffer_size.0, framebuffer_size.1)
+            };
+
+            if self.current_viewport != Some(viewport) {
+                glViewport(viewport.0, viewport.1, viewport.2, viewport.3);
+                self.current_viewport = Some(viewport);
+            }
+
+            if changed(&|s| s.scissor.is_some()) {
+                set_capability(GL_SCISSOR_TEST, state.scissor.is_some());
+            }
+
+            if let Some(scissor) = state.scissor {
+                let scissor = scissor.to_pixels(framebuffer_size.0, framebuffer_size.1);
+
+                if self.current_scissor != Some(scissor) {
+                    glScissor(scissor.0, scissor.1, scissor.2, scissor.3);
+                    self.current_scissor = Some(scissor);
+                }
+            }
+        }
+
+        self.current = Some(*state);
+    }
+
+    /// Forgets the tracked viewport, for after binding a framebuffer which set its own.
+    pub fn invalidate_viewport(&mut self) {
+        self.current_viewport = None;
+    }
+
+    /// Forgets the tracked state, forcing every setting to be applied next time.
+    /// Call this after code outside the engine changes GL state directly.
+    pub fn invalidate(&mut self) {
+        self.current = None;
+        self.current_viewport = None;
+        self.current_scissor = None;
+    }
+}
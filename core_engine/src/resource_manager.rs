@@ -1,20 +1,52 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 /// Essentially a drop trait, but it's called right the game resources are destroyed.
 /// This way we can have joint ownership over graphics objects such as textures, framebuffers, shaders, and models.
 /// Each destroyable resource should be given this trait.
 /// Must have a mutible reference to the object and this should never be called manually.
 /// Add each resource to the global resources hashmap, and it will handle this.
+/// Resources which free themselves on drop (anything holding a GlHandle) can use the default, which does nothing.
 pub trait ResourceDestroy {
-    fn destroy(&mut self);
+    fn destroy(&mut self) {}
 }
 
+#[derive(Debug)]
+pub enum LoadError {
+    /// Reading the directory failed.
+    Io { path: PathBuf, error: std::io::Error },
+    /// The loader couldn't make a resource from the file.
+    Resource { path: String, message: String }
+}
+
+impl LoadError {
+    /// Wraps the error a loader got for the file, ex: `Texture::open(path).map_err(|err| LoadError::resource(path, err))`.
+    pub fn resource(path: &str, error: impl fmt::Display) -> Self {
+        LoadError::Resource { path: path.to_string(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "failed to read {}: {}", path.display(), error),
+            LoadError::Resource { path, message } => write!(f, "failed to load {}: {}", path, message)
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
 /// Structure responsible for managing the resources of a specific type T.
 /// This type can be anything, but the engine should give whatever types it supports
 /// to clints during the loading process.
 pub struct ResourceManager<T: ResourceDestroy> {
     resource_type_name: String,
-    registries: HashMap<String, T>
+    registries: HashMap<String, T>,
+    /// Returned in place of missing registries while use_fallback is set.
+    fallback: Option<T>,
+    use_fallback: bool
 }
 
 /// When the resources are destroyed, call the resource destroy function on each loaded object.
@@ -23,6 +55,10 @@ impl<T: ResourceDestroy> Drop for ResourceManager<T> {
         for (_, registry) in self.registries.iter_mut() {
             registry.destroy();
         }
+
+        if let Some(fallback) = &mut self.fallback {
+            fallback.destroy();
+        }
     }
 }
 
@@ -30,7 +66,9 @@ impl<T: ResourceDestroy> ResourceManager<T> {
     pub fn new(resource_type_name: &str) -> Self {
         ResourceManager {
             resource_type_name: resource_type_name.to_string(),
-            registries: HashMap::<String, T>::default()
+            registries: HashMap::<String, T>::default(),
+            fallback: None,
+            use_fallback: false
         }
     }
 
@@ -38,17 +76,123 @@ impl<T: ResourceDestroy> ResourceManager<T> {
         self.registries.insert(name.to_string(), registry);
     }
 
+    /// Registers a resource for each file directly in the directory with one of the extensions, named after
+    /// the file stem, ex: "tex_snake_body" for tex_snake_body.png. An empty extensions list takes every file.
+    /// Subdirectories aren't searched. Files the loader fails on are logged and skipped, as are files whose
+    /// name is already registered, so existing resources are never replaced.
+    /// Returns the number of names registered.
+    pub fn register_all_from_directory<F>(&mut self, dir: &str, extensions: &[&str], loader: F) -> Result<usize, LoadError>
+        where F: Fn(&str) -> Result<T, LoadError> {
+        let io_error = |error| LoadError::Io { path: PathBuf::from(dir), error };
+
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+
+            if path.is_file() && matches_extension(&path, extensions) {
+                paths.push(path);
+            }
+        }
+
+        // Sorted so files sharing a stem resolve the same way every run, the first one wins.
+        paths.sort();
+
+        let mut registered = 0;
+        for path in paths {
+            let (Some(name), Some(path)) = (path.file_stem().and_then(|stem| stem.to_str()), path.to_str()) else {
+                println!("Skipping {} in {}, its name isn't valid UTF-8", path.display(), self.resource_type_name);
+                continue;
+            };
+
+            if self.contains_registry(name) {
+                println!("Skipping {}, {} already has a registry named {}", path, self.resource_type_name, name);
+                continue;
+            }
+
+            match loader(path) {
+                Ok(registry) => {
+                    self.add_registry(name, registry);
+                    registered += 1;
+                },
+                Err(err) => println!("Unable to register {} in {}: {}", name, self.resource_type_name, err)
+            }
+        }
+
+        Ok(registered)
+    }
+
     pub fn get_registry(&self, name: &str) -> Option<&T> {
         match self.registries.get(name) {
             Some(registry) => Some(registry),
             None => {
-                println!("Unable to find registry ({}) in {}", name, self.resource_type_name);
-                None
+                match &self.fallback {
+                    Some(fallback) if self.use_fallback => {
+                        println!("Unable to find registry ({}) in {}, using the fallback", name, self.resource_type_name);
+                        Some(fallback)
+                    },
+                    _ => {
+                        println!("Unable to find registry ({}) in {}", name, self.resource_type_name);
+                        None
+                    }
+                }
             }
         }
     }
 
+    /// Returns true when a registry with the name exists, without falling back.
+    pub fn contains_registry(&self, name: &str) -> bool {
+        self.registries.contains_key(name)
+    }
+
+    /// Sets the registry returned in place of missing ones, such as a "missing" texture.
+    /// Only used while fallbacks are enabled with set_use_fallback.
+    pub fn set_fallback(&mut self, fallback: T) {
+        if let Some(old_fallback) = &mut self.fallback {
+            old_fallback.destroy();
+        }
+
+        self.fallback = Some(fallback);
+    }
+
+    /// Iterates the registries by name, not including the fallback.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.registries.iter().map(|(name, registry)| (name.as_str(), registry))
+    }
+
+    /// Returns the number of registries, not counting the fallback.
+    pub fn len(&self) -> usize {
+        self.registries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registries.is_empty()
+    }
+
+    pub fn has_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    /// When enabled, get_registry returns the fallback for names which aren't registered.
+    pub fn set_use_fallback(&mut self, use_fallback: bool) {
+        self.use_fallback = use_fallback;
+    }
+
+    pub fn is_using_fallback(&self) -> bool {
+        self.use_fallback
+    }
+
     pub fn get_name(&self) -> &str {
         &self.resource_type_name
     }
+}
+
+fn matches_extension(path: &Path, extensions: &[&str]) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(extension)),
+        None => false
+    }
 }
\ No newline at end of file
@@ -0,0 +1,66 @@
+use std::path::Path;
+
+/// Environment variable listing override directories, separated like PATH, checked before any others.
+pub const RESOURCE_OVERRIDES_VAR: &str = "GAME_RESOURCE_OVERRIDES";
+
+/// Finds resource files relative to the base resource directory, checking override directories first.
+///
+/// Overrides let mods and local development replace bundled resources without editing the base directory.
+/// They're checked in priority order, and a file an override doesn't have falls through to the next,
+/// ending at the base directory. Only the file is overridden, so a shader can replace just its fragment stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourcePaths {
+    base: String,
+    /// Highest priority first.
+    overrides: Vec<String>
+}
+
+impl ResourcePaths {
+    pub fn new(base: &str) -> Self {
+        ResourcePaths {
+            base: base.to_string(),
+            overrides: Vec::new()
+        }
+    }
+
+    /// Creates paths for the base directory with the overrides listed in GAME_RESOURCE_OVERRIDES.
+    pub fn from_env(base: &str) -> Self {
+        let mut paths = ResourcePaths::new(base);
+
+        if let Some(overrides) = std::env::var_os(RESOURCE_OVERRIDES_VAR) {
+            for path in std::env::split_paths(&overrides).filter(|path| !path.as_os_str().is_empty()) {
+                paths.add_override(&path.to_string_lossy());
+            }
+        }
+
+        paths
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Returns the override directories, highest priority first.
+    pub fn overrides(&self) -> &[String] {
+        &self.overrides
+    }
+
+    /// Adds an override directory below the ones already added. Adding a directory twice does nothing.
+    pub fn add_override(&mut self, path: &str) {
+        if !self.overrides.iter().any(|existing| existing == path) {
+            self.overrides.push(path.to_string());
+        }
+    }
+
+    /// Returns the path of the resource in the first override directory holding it, otherwise in the base directory.
+    pub fn resolve(&self, relative_path: &str) -> String {
+        self.overrides.iter()
+            .map(|directory| join(directory, relative_path))
+            .find(|path| Path::new(path).is_file())
+            .unwrap_or_else(|| join(&self.base, relative_path))
+    }
+}
+
+fn join(directory: &str, relative_path: &str) -> String {
+    directory.to_string() + "/" + relative_path
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread::JoinHandle;
+
+/// How many writes can wait for the writer thread before `save_state_async` blocks.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16;
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// Writing or replacing the file failed.
+    Io { path: PathBuf, error: std::io::Error },
+    /// The writer thread stopped, ex: it panicked, so the write can't be queued or confirmed.
+    WriterStopped
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io { path, error } => write!(f, "failed to write {}: {}", path.display(), error),
+            SaveError::WriterStopped => write!(f, "the save writer thread stopped")
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+enum WriteRequest {
+    Write { path: PathBuf, bytes: Vec<u8> },
+    /// Answered once every write queued before it is done, with the errors since the last flush.
+    Flush(Sender<Vec<SaveError>>)
+}
+
+/// Writes files on a background thread, so saving doesn't hitch the frame.
+///
+/// Writes are queued on a bounded channel and run in the order they were queued. When the queue is full,
+/// queuing blocks until the writer catches up, rather than holding an unbounded amount of data in memory.
+/// Each file is written next to its destination and then renamed over it, so a crash mid-write leaves
+/// either the old file or the new one, never half of each.
+///
+/// Dropping the writer finishes the queued writes before returning.
+pub struct SaveWriter {
+    sender: Option<SyncSender<WriteRequest>>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Default for SaveWriter {
+    fn default() -> Self {
+        SaveWriter::new(DEFAULT_QUEUE_CAPACITY)
+    }
+}
+
+impl SaveWriter {
+    /// Starts the writer thread, holding up to `capacity` queued writes.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+
+        let thread = std::thread::Builder::new()
+            .name("save writer".to_string())
+            .spawn(move || run_writer(receiver))
+            .expect("failed to start the save writer thread");
+
+        SaveWriter { sender: Some(sender), thread: Some(thread) }
+    }
+
+    /// Queues the bytes to replace the file at the path and returns without waiting for the write.
+    /// Blocks only while the queue is full. Failures are reported by the next `flush`.
+    pub fn save_state_async(&self, path: impl AsRef<Path>, bytes: Vec<u8>) -> Result<(), SaveError> {
+        self.send(WriteRequest::Write { path: path.as_ref().to_path_buf(), bytes })
+    }
+
+    /// Blocks until every write queued so far has finished.
+    /// Returns the first write which failed since the last flush, the rest are logged.
+    pub fn flush(&self) -> Result<(), SaveError> {
+        let (sender, result) = mpsc::channel();
+        self.send(WriteRequest::Flush(sender))?;
+
+        let mut errors = result.recv().map_err(|_| SaveError::WriterStopped)?.into_iter();
+
+        match errors.next() {
+            Some(error) => {
+                for error in errors {
+                    println!("{}", error);
+                }
+
+                Err(error)
+            },
+            None => Ok(())
+        }
+    }
+
+    /// Finishes the queued writes and stops the writer thread, for shutdown.
+    pub fn shutdown(mut self) -> Result<(), SaveError> {
+        let result = self.flush();
+        self.join();
+
+        result
+    }
+
+    fn send(&self, request: WriteRequest) -> Result<(), SaveError> {
+        match &self.sender {
+            Some(sender) => sender.send(request).map_err(|_| SaveError::WriterStopped),
+            None => Err(SaveError::WriterStopped)
+        }
+    }
+
+    fn join(&mut self) {
+        // Closing the channel ends the writer's loop once the queue is empty.
+        self.sender = None;
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SaveWriter {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn run_writer(receiver: Receiver<WriteRequest>) {
+    let mut errors = Vec::<SaveError>::new();
+
+    for request in receiver {
+        match request {
+            WriteRequest::Write { path, bytes } => {
+                if let Err(error) = write_atomic(&path, &bytes) {
+                    errors.push(SaveError::Io { path, error });
+                }
+            },
+            WriteRequest::Flush(sender) => {
+                // The caller may have given up waiting, in which case the errors are dropped.
+                let _ = sender.send(std::mem::take(&mut errors));
+            }
+        }
+    }
+
+    // Nobody is left to flush, so report what failed since the last one.
+    for error in errors {
+        println!("{}", error);
+    }
+}
+
+/// Replaces the file by writing a temporary file beside it and renaming it into place.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            // Make sure the data is on disk before the rename makes it the real file.
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
@@ -0,0 +1,134 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Refers to a scheduled callback so it can be cancelled. Clones refer to the same callback.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    cancelled: Rc<Cell<bool>>,
+    /// Set once a one-shot callback has fired.
+    finished: Rc<Cell<bool>>
+}
+
+impl TaskHandle {
+    /// Stops the callback from firing again. Does nothing once it has already finished.
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    /// Returns true while the callback is still due to fire.
+    pub fn is_active(&self) -> bool {
+        !self.cancelled.get() && !self.finished.get()
+    }
+}
+
+enum TaskCallback {
+    Once(Box<dyn FnOnce()>),
+    Repeat(Box<dyn FnMut()>)
+}
+
+struct Task {
+    /// Seconds until the callback fires next.
+    remaining: f32,
+    interval: f32,
+    callback: TaskCallback,
+    handle: TaskHandle
+}
+
+/// Runs callbacks after a delay or at a fixed interval, driven by the frame delta time.
+/// The engine advances its scheduler at the start of every update, see `GameManager::get_scheduler_mut`.
+///
+/// Like an Accumulator, a repeating callback catches up after a long frame by firing once for each
+/// interval that passed.
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Calls the callback once, `seconds` from now.
+    pub fn after(&mut self, seconds: f32, callback: impl FnOnce() + 'static) -> TaskHandle {
+        self.add(seconds, seconds, TaskCallback::Once(Box::new(callback)))
+    }
+
+    /// Calls the callback every `seconds`, starting `seconds` from now, until it's cancelled.
+    pub fn every(&mut self, seconds: f32, callback: impl FnMut() + 'static) -> TaskHandle {
+        #[cfg(debug_assertions)]
+        assert!(seconds > 0.0, "Scheduler interval must be positive");
+
+        self.add(seconds, seconds, TaskCallback::Repeat(Box::new(callback)))
+    }
+
+    fn add(&mut self, delay: f32, interval: f32, callback: TaskCallback) -> TaskHandle {
+        let handle = TaskHandle {
+            cancelled: Rc::new(Cell::new(false)),
+            finished: Rc::new(Cell::new(false))
+        };
+
+        self.tasks.push(Task { remaining: delay, interval, callback, handle: handle.clone() });
+        handle
+    }
+
+    /// Advances time, firing every callback which came due. Callbacks fire in the order they were scheduled.
+    pub fn update(&mut self, delta_time: f32) {
+        let mut index = 0;
+
+        while index < self.tasks.len() {
+            let task = &mut self.tasks[index];
+            task.remaining -= delta_time;
+
+            let done = match &mut task.callback {
+                _ if task.handle.cancelled.get() => true,
+                TaskCallback::Once(_) => task.remaining <= 0.0,
+                TaskCallback::Repeat(callback) => {
+                    while task.remaining <= 0.0 && !task.handle.cancelled.get() {
+                        callback();
+
+                        // A zero interval fires once per update rather than looping forever.
+                        if task.interval <= 0.0 {
+                            task.remaining = 0.0;
+                            break;
+                        }
+
+                        task.remaining += task.interval;
+                    }
+
+                    task.handle.cancelled.get()
+                }
+            };
+
+            if done {
+                let task = self.tasks.remove(index);
+
+                if let TaskCallback::Once(callback) = task.callback {
+                    if !task.handle.cancelled.get() {
+                        task.handle.finished.set(true);
+                        callback();
+                    }
+                }
+            }
+            else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Returns the number of callbacks still scheduled, including cancelled ones not yet removed.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Cancels every scheduled callback.
+    pub fn clear(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.handle.cancel();
+        }
+    }
+}
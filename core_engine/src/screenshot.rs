@@ -0,0 +1,157 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use image::RgbaImage;
+use ogl33::*;
+
+use crate::framebuffer::Framebuffer;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// Encoding or writing the PNG failed.
+    Image(image::ImageError),
+    /// There was nothing to capture, ex: the window is minimized.
+    EmptyFramebuffer,
+    /// The thread encoding the PNG stopped without reporting a result.
+    EncoderStopped
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::Image(err) => write!(f, "{}", err),
+            ScreenshotError::EmptyFramebuffer => write!(f, "the framebuffer has no pixels to capture"),
+            ScreenshotError::EncoderStopped => write!(f, "the screenshot encoder stopped before saving")
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+impl From<image::ImageError> for ScreenshotError {
+    fn from(err: image::ImageError) -> Self {
+        ScreenshotError::Image(err)
+    }
+}
+
+/// A screenshot being captured. The result arrives once the frame it was requested in has been read
+/// back and the PNG has been written.
+pub struct PendingScreenshot {
+    path: PathBuf,
+    result: Receiver<Result<PathBuf, ScreenshotError>>
+}
+
+impl PendingScreenshot {
+    /// The file the screenshot is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the result when the screenshot is done, without blocking.
+    pub fn try_result(&self) -> Option<Result<PathBuf, ScreenshotError>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(ScreenshotError::EncoderStopped))
+        }
+    }
+
+    /// Blocks until the screenshot is written. The frame it was requested in must have finished.
+    pub fn wait(self) -> Result<PathBuf, ScreenshotError> {
+        self.result.recv().unwrap_or(Err(ScreenshotError::EncoderStopped))
+    }
+}
+
+/// A screenshot requested but not read back yet, held by the engine until the end of the frame.
+pub(crate) struct ScreenshotRequest {
+    path: PathBuf,
+    sender: mpsc::Sender<Result<PathBuf, ScreenshotError>>
+}
+
+impl ScreenshotRequest {
+    pub(crate) fn new(path: &Path) -> (ScreenshotRequest, PendingScreenshot) {
+        let (sender, result) = mpsc::channel();
+
+        (ScreenshotRequest { path: path.to_path_buf(), sender }, PendingScreenshot { path: path.to_path_buf(), result })
+    }
+
+    /// Saves the image on a worker thread, so encoding doesn't hold up the frame.
+    pub(crate) fn save_in_background(self, image: Option<RgbaImage>) {
+        let ScreenshotRequest { path, sender } = self;
+
+        std::thread::spawn(move || {
+            let result = match image {
+                Some(image) => image.save(&path).map(|_| path).map_err(ScreenshotError::from),
+                None => Err(ScreenshotError::EmptyFramebuffer)
+            };
+
+            // The caller may have dropped its PendingScreenshot, in which case nobody wants the result.
+            let _ = sender.send(result);
+        });
+    }
+}
+
+/// Reverses the order of the rows in place. GL reads images bottom row first, image files start at the top.
+pub(crate) fn flip_rows(pixels: &mut [u8], row_bytes: usize) {
+    if row_bytes == 0 {
+        return;
+    }
+
+    let row_count = pixels.len() / row_bytes;
+
+    for row in 0..row_count / 2 {
+        let (top, bottom) = pixels.split_at_mut((row_count - row - 1) * row_bytes);
+        top[row * row_bytes..(row + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+    }
+}
+
+/// Sets every pixel's alpha to opaque. The alpha the scene leaves in the framebuffer isn't meant to be seen,
+/// and translucent pixels in a saved screenshot would show whatever is behind the image viewer.
+pub(crate) fn make_opaque(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+}
+
+/// Reads the RGBA pixels of the framebuffer into an image, top row first, with alpha set to opaque.
+/// Zero reads the window, whose back buffer holds the frame drawn since the last swap.
+fn read_framebuffer(framebuffer_id: GLuint, width: u32, height: u32) -> Option<RgbaImage> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        glBindFramebuffer(GL_READ_FRAMEBUFFER, framebuffer_id);
+
+        if framebuffer_id == 0 {
+            glReadBuffer(GL_BACK);
+        }
+
+        // Rows of RGBA bytes are always 4 byte aligned, but set it in case something changed it.
+        glPixelStorei(GL_PACK_ALIGNMENT, 4);
+        glReadPixels(0, 0, width as i32, height as i32, GL_RGBA, GL_UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut std::ffi::c_void);
+
+        glBindFramebuffer(GL_READ_FRAMEBUFFER, 0);
+    }
+
+    flip_rows(&mut pixels, width as usize * 4);
+    make_opaque(&mut pixels);
+    RgbaImage::from_raw(width, height, pixels)
+}
+
+/// Reads back the whole window, including any letterbox bars around a virtual resolution.
+pub(crate) fn capture_window(width: u32, height: u32) -> Option<RgbaImage> {
+    read_framebuffer(0, width, height)
+}
+
+/// Reads back the color of an offscreen framebuffer, resolving it first when it's multisampled.
+/// For rendering without a visible window, ex: comparing frames against golden images in tests.
+pub fn capture_framebuffer(framebuffer: &Framebuffer) -> Result<RgbaImage, ScreenshotError> {
+    framebuffer.resolve();
+
+    let framebuffer_id = framebuffer.resolve_target_id().unwrap_or(framebuffer.framebuffer_id());
+    read_framebuffer(framebuffer_id, framebuffer.width(), framebuffer.height()).ok_or(ScreenshotError::EmptyFramebuffer)
+}
@@ -1,9 +1,14 @@
-use std::{collections::HashMap};
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::Rc};
 
 use ::glmath::glmath::Vec2f;
 use glmath::glmath::{Vec3f, Vec4f, Mat22f, Mat33f, Mat44f};
 use ogl33::*;
 
+use crate::bind_cache;
+use crate::color::Color;
+use crate::debug_render;
+use crate::gl_debug;
+use crate::gl_handle::GlHandle;
 use crate::resource_manager::ResourceDestroy;
 
 #[repr(u32)]
@@ -61,19 +66,226 @@ impl Drop for Shader {
     }
 }
 
+/// Vertex shader of the fallback program, matching the inputs and uniforms of "GUIShader.vert".
+const PASSTHROUGH_VERTEX_SHADER: &str = "#version 130
+in vec2 position;
+
+uniform vec2 pos;
+uniform vec2 scale;
+uniform mat4 viewProjection;
+uniform vec2 uvMin = vec2(0, 0);
+uniform vec2 uvMax = vec2(1, 1);
+uniform float rotation = 0.0;
+
+out vec2 texCoord;
+
+void main() {
+    vec2 offset = position * scale;
+    offset = vec2(offset.x * cos(rotation) - offset.y * sin(rotation), offset.x * sin(rotation) + offset.y * cos(rotation));
+
+    gl_Position = viewProjection * vec4(offset + pos, 0, 1);
+
+    vec2 corner = position * .5 + .5;
+    texCoord = mix(uvMin, uvMax, vec2(corner.x, 1 - corner.y));
+}";
+
+/// Fragment shader of the fallback program, outputs the texture as is.
+const PASSTHROUGH_FRAGMENT_SHADER: &str = "#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D guiTexture;
+
+void main() {
+    color = texture(guiTexture, texCoord);
+}";
+
+/// The first word of every SPIR-V module.
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// GL_SHADER_BINARY_FORMAT_SPIR_V_ARB, from ARB_gl_spirv.
+const GL_SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+
+/// GL_COMPLETION_STATUS_KHR, from KHR_parallel_shader_compile.
+const GL_COMPLETION_STATUS: GLenum = 0x91B1;
+
+type GlMaxShaderCompilerThreads = unsafe extern "system" fn(count: GLuint);
+type GlShaderBinary = unsafe extern "system" fn(count: GLsizei, shaders: *const GLuint, binary_format: GLenum,
+    binary: *const std::ffi::c_void, length: GLsizei);
+type GlSpecializeShader = unsafe extern "system" fn(shader: GLuint, entry_point: *const GLchar,
+    constant_count: GLuint, constant_indices: *const GLuint, constant_values: *const GLuint);
+
+thread_local! {
+    /// Whether the driver compiles on background threads, so completion can be polled without blocking.
+    static PARALLEL_COMPILE: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The driver lacks ARB_gl_spirv, or doesn't expose its functions.
+    SpirvUnsupported,
+    /// The data isn't a SPIR-V module, ex: its length isn't a whole number of words.
+    InvalidSpirv(&'static str),
+    /// A shader failed to compile or specialize, with the driver's info log.
+    Compile { stage: &'static str, log: String },
+    /// The program failed to link, with the driver's info log.
+    Link(String)
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::SpirvUnsupported => write!(f, "SPIR-V shaders aren't supported (ARB_gl_spirv is missing)"),
+            ShaderError::InvalidSpirv(reason) => write!(f, "invalid SPIR-V module: {}", reason),
+            ShaderError::Compile { stage, log } => write!(f, "{} shader failed to compile: {}", stage, log),
+            ShaderError::Link(log) => write!(f, "shader program failed to link: {}", log)
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Checks the data looks like a SPIR-V module: whole 32 bit words starting with the magic number.
+pub(crate) fn validate_spirv(spirv: &[u8]) -> Result<(), ShaderError> {
+    if spirv.len() < 20 {
+        return Err(ShaderError::InvalidSpirv("shorter than the 5 word header"));
+    }
+
+    if !spirv.len().is_multiple_of(4) {
+        return Err(ShaderError::InvalidSpirv("length isn't a multiple of 4 bytes"));
+    }
+
+    let magic = [spirv[0], spirv[1], spirv[2], spirv[3]];
+    if u32::from_ne_bytes(magic) != SPIRV_MAGIC {
+        return Err(ShaderError::InvalidSpirv("missing the magic number, or the wrong byte order"));
+    }
+
+    Ok(())
+}
+
+/// Returns true when the driver supports ARB_gl_spirv.
+fn spirv_supported() -> bool {
+    extension_supported(b"GL_ARB_gl_spirv")
+}
+
+/// Returns true when the driver lists the extension, ex: b"GL_ARB_gl_spirv".
+fn extension_supported(extension: &[u8]) -> bool {
+    unsafe {
+        let mut extension_count: GLint = 0;
+        glGetIntegerv(GL_NUM_EXTENSIONS, &mut extension_count);
+
+        (0..extension_count.max(0) as GLuint).any(|index| {
+            let name = glGetStringi(GL_EXTENSIONS, index);
+
+            !name.is_null() && std::ffi::CStr::from_ptr(name as *const std::ffi::c_char).to_bytes() == extension
+        })
+    }
+}
+
+/// Loads glShaderBinary and glSpecializeShader, which ogl33 doesn't provide.
+fn load_spirv_functions() -> Option<(GlShaderBinary, GlSpecializeShader)> {
+    let shader_binary = crate::gl_proc_address("glShaderBinary");
+    let specialize_shader = [crate::gl_proc_address("glSpecializeShader"), crate::gl_proc_address("glSpecializeShaderARB")]
+        .into_iter()
+        .find(|function| !function.is_null())?;
+
+    if shader_binary.is_null() {
+        return None;
+    }
+
+    unsafe {
+        Some((std::mem::transmute::<*const std::ffi::c_void, GlShaderBinary>(shader_binary),
+            std::mem::transmute::<*const std::ffi::c_void, GlSpecializeShader>(specialize_shader)))
+    }
+}
+
+fn shader_info_log(shader_id: GLuint) -> String {
+    unsafe {
+        let mut length: GLint = 0;
+        glGetShaderiv(shader_id, GL_INFO_LOG_LENGTH, &mut length);
+
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written: GLsizei = 0;
+        glGetShaderInfoLog(shader_id, log.len() as GLsizei, &mut written, log.as_mut_ptr() as *mut GLchar);
+
+        log.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
+    }
+}
+
+fn program_info_log(program_id: GLuint) -> String {
+    unsafe {
+        let mut length: GLint = 0;
+        glGetProgramiv(program_id, GL_INFO_LOG_LENGTH, &mut length);
+
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written: GLsizei = 0;
+        glGetProgramInfoLog(program_id, log.len() as GLsizei, &mut written, log.as_mut_ptr() as *mut GLchar);
+
+        log.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
+    }
+}
+
+/// Creates a shader from a SPIR-V module, specialized with the "main" entry point.
+fn specialize_spirv_shader(functions: (GlShaderBinary, GlSpecializeShader), spirv: &[u8], shader_type: ShaderType) -> Result<Shader, ShaderError> {
+    let (shader_binary, specialize_shader) = functions;
+    let stage = match shader_type {
+        ShaderType::Vertex => "vertex",
+        ShaderType::Fragment => "fragment"
+    };
+
+    unsafe {
+        let shader = Shader { shader_id: glCreateShader(shader_type as u32) };
+
+        shader_binary(1, &shader.shader_id, GL_SHADER_BINARY_FORMAT_SPIR_V,
+            spirv.as_ptr() as *const std::ffi::c_void, spirv.len() as GLsizei);
+        specialize_shader(shader.shader_id, c"main".as_ptr(), 0, std::ptr::null(), std::ptr::null());
+
+        let mut status: GLint = 0;
+        glGetShaderiv(shader.shader_id, GL_COMPILE_STATUS, &mut status);
+
+        if status == 0 {
+            return Err(ShaderError::Compile { stage, log: shader_info_log(shader.shader_id) });
+        }
+
+        Ok(shader)
+    }
+}
+
 #[derive(Default, Clone)]
 /// Holds the entire shader program. Stores the sub-shaders as dependents 
 /// so they aren't dropped too early.
+/// Clones share the same GL program, which is deleted when the last clone is dropped.
 pub struct ShaderProgram {
-    program_id: GLuint,
+    handle: GlHandle,
     attribute_locations: HashMap<String, i32>,
-    current_attribute_location: i32
+    current_attribute_location: i32,
+    /// Uniform locations looked up so far, so each name is only queried from GL once.
+    uniform_locations: RefCell<HashMap<String, i32>>,
+    /// True when the program was built from SPIR-V rather than GLSL source.
+    from_spirv: bool,
+    /// The GLSL vertex shader, kept to build the overdraw variant. None for SPIR-V and wrapped programs.
+    vertex_source: Option<Rc<str>>,
+    /// Built the first time the program is bound in overdraw mode, see `DebugRenderMode::Overdraw`.
+    overdraw_variant: RefCell<Option<OverdrawVariant>>
+}
+
+/// The program's vertex shader linked with a flat color fragment shader.
+#[derive(Clone)]
+struct OverdrawVariant {
+    /// None when the variant failed to link, in which case the program draws normally.
+    handle: Option<GlHandle>,
+    /// Uniform locations of the program mapped to the variant's, since pipelines look locations up once.
+    locations: HashMap<i32, i32>
 }
 
 impl ShaderProgram {
     pub fn open_shaders(vertex_shader_path: &str, fragment_shader_path: &str) -> Result<ShaderProgram, Box<dyn std::error::Error>> {
         unsafe {
-            let vert_shader = Shader::open(vertex_shader_path, ShaderType::Vertex)?;
+            let vertex_source = std::fs::read_to_string(vertex_shader_path)?;
+            let vert_shader = Shader { shader_id: compile_shader(&vertex_source, ShaderType::Vertex) };
             let frag_shader = Shader::open(fragment_shader_path, ShaderType::Fragment)?;
 
             let program_id = glCreateProgram();
@@ -82,29 +294,270 @@ impl ShaderProgram {
             glAttachShader(program_id, vert_shader.shader_id);
             glAttachShader(program_id, frag_shader.shader_id);
             glLinkProgram(program_id);
-            
-            Ok(ShaderProgram { 
-                program_id,
-                attribute_locations: HashMap::<String, i32>::default(),
-                current_attribute_location: 0
-            })
+
+            let mut program = ShaderProgram::from_handle(GlHandle::program(program_id));
+            program.vertex_source = Some(vertex_source.into());
+            Ok(program)
 
             // Here, the two shaders should be dropped and freed.
         }
     }
 
-    pub fn bind(&self) {
+    /// Compiles and links a program from shader source text.
+    pub fn from_source(vertex_shader_source: &str, fragment_shader_source: &str) -> ShaderProgram {
         unsafe {
-            glUseProgram(self.program_id);
+            let vert_shader = Shader { shader_id: compile_shader(vertex_shader_source, ShaderType::Vertex) };
+            let frag_shader = Shader { shader_id: compile_shader(fragment_shader_source, ShaderType::Fragment) };
+
+            let program_id = glCreateProgram();
+
+            glAttachShader(program_id, vert_shader.shader_id);
+            glAttachShader(program_id, frag_shader.shader_id);
+            glLinkProgram(program_id);
+
+            let mut program = ShaderProgram::from_handle(GlHandle::program(program_id));
+            program.vertex_source = Some(vertex_shader_source.into());
+            program
         }
     }
 
-    pub fn unbind(&self) {
+    /// Builds a program from precompiled SPIR-V modules, using ARB_gl_spirv.
+    /// Fails with ShaderError::SpirvUnsupported when the driver lacks the extension,
+    /// see `from_spirv_or_source` to fall back to GLSL.
+    pub fn from_spirv(vert_spirv: &[u8], frag_spirv: &[u8]) -> Result<ShaderProgram, ShaderError> {
+        validate_spirv(vert_spirv)?;
+        validate_spirv(frag_spirv)?;
+
+        if !spirv_supported() {
+            return Err(ShaderError::SpirvUnsupported);
+        }
+
+        let functions = load_spirv_functions().ok_or(ShaderError::SpirvUnsupported)?;
+        let vert_shader = specialize_spirv_shader(functions, vert_spirv, ShaderType::Vertex)?;
+        let frag_shader = specialize_spirv_shader(functions, frag_spirv, ShaderType::Fragment)?;
+
         unsafe {
-            glUseProgram(0);
+            let handle = GlHandle::program(glCreateProgram());
+
+            glAttachShader(handle.id(), vert_shader.shader_id);
+            glAttachShader(handle.id(), frag_shader.shader_id);
+            glLinkProgram(handle.id());
+
+            let mut status: GLint = 0;
+            glGetProgramiv(handle.id(), GL_LINK_STATUS, &mut status);
+
+            if status == 0 {
+                return Err(ShaderError::Link(program_info_log(handle.id())));
+            }
+
+            let mut program = ShaderProgram::from_handle(handle);
+            program.from_spirv = true;
+            Ok(program)
         }
     }
 
+    /// Builds a program from SPIR-V when the driver supports it, otherwise compiles the GLSL source.
+    /// Invalid SPIR-V and compile or link errors aren't hidden by the fallback.
+    pub fn from_spirv_or_source(vert_spirv: &[u8], frag_spirv: &[u8],
+        vertex_shader_source: &str, fragment_shader_source: &str) -> Result<ShaderProgram, ShaderError> {
+        match Self::from_spirv(vert_spirv, frag_spirv) {
+            Err(ShaderError::SpirvUnsupported) => Ok(Self::from_source(vertex_shader_source, fragment_shader_source)),
+            result => result
+        }
+    }
+
+    /// A program which draws its "guiTexture" unchanged, with the same inputs as the GUI shader.
+    /// Used as the fallback when a shader isn't registered.
+    pub fn passthrough() -> ShaderProgram {
+        Self::from_source(PASSTHROUGH_VERTEX_SHADER, PASSTHROUGH_FRAGMENT_SHADER)
+    }
+
+    /// Wraps a program created elsewhere.
+    pub(crate) fn from_handle(handle: GlHandle) -> Self {
+        ShaderProgram {
+            handle,
+            attribute_locations: HashMap::<String, i32>::default(),
+            current_attribute_location: 0,
+            uniform_locations: RefCell::default(),
+            from_spirv: false,
+            vertex_source: None,
+            overdraw_variant: RefCell::default()
+        }
+    }
+
+    /// Lets the driver compile and link shaders created afterwards on up to `threads` background threads,
+    /// using KHR_parallel_shader_compile. Creating programs then returns right away, and `is_link_complete`
+    /// reports when they're done. Returns false when the driver doesn't support it.
+    pub fn set_compiler_threads(threads: u32) -> bool {
+        if !extension_supported(b"GL_KHR_parallel_shader_compile") && !extension_supported(b"GL_ARB_parallel_shader_compile") {
+            return false;
+        }
+
+        let max_threads = [crate::gl_proc_address("glMaxShaderCompilerThreadsKHR"), crate::gl_proc_address("glMaxShaderCompilerThreadsARB")]
+            .into_iter()
+            .find(|function| !function.is_null());
+
+        match max_threads {
+            Some(max_threads) => unsafe {
+                let max_threads = std::mem::transmute::<*const std::ffi::c_void, GlMaxShaderCompilerThreads>(max_threads);
+                max_threads(threads);
+
+                PARALLEL_COMPILE.with(|parallel| parallel.set(threads > 0));
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Returns false while the driver is still compiling or linking the program in the background.
+    /// Always true unless background compilation was enabled with `set_compiler_threads`.
+    pub fn is_link_complete(&self) -> bool {
+        if !PARALLEL_COMPILE.with(|parallel| parallel.get()) {
+            return true;
+        }
+
+        unsafe {
+            let mut status: GLint = 0;
+            glGetProgramiv(self.handle.id(), GL_COMPLETION_STATUS, &mut status);
+            status != 0
+        }
+    }
+
+    /// Waits for the program to finish linking, then returns the compile or link error the driver reported.
+    pub fn link_status(&self) -> Result<(), ShaderError> {
+        unsafe {
+            let mut status: GLint = 0;
+            glGetProgramiv(self.handle.id(), GL_LINK_STATUS, &mut status);
+
+            if status != 0 {
+                return Ok(());
+            }
+
+            // The program keeps its shaders alive, so their logs say which stage failed to compile.
+            let mut shader_ids: [GLuint; 2] = [0; 2];
+            let mut shader_count: GLsizei = 0;
+            glGetAttachedShaders(self.handle.id(), shader_ids.len() as GLsizei, &mut shader_count, shader_ids.as_mut_ptr());
+
+            for &shader_id in shader_ids.iter().take(shader_count.max(0) as usize) {
+                let mut compiled: GLint = 0;
+                glGetShaderiv(shader_id, GL_COMPILE_STATUS, &mut compiled);
+
+                if compiled == 0 {
+                    let mut shader_type: GLint = 0;
+                    glGetShaderiv(shader_id, GL_SHADER_TYPE, &mut shader_type);
+
+                    let stage = match shader_type as GLenum {
+                        GL_VERTEX_SHADER => "vertex",
+                        _ => "fragment"
+                    };
+
+                    return Err(ShaderError::Compile { stage, log: shader_info_log(shader_id) });
+                }
+            }
+
+            Err(ShaderError::Link(program_info_log(self.handle.id())))
+        }
+    }
+
+    /// Returns true when the program was built from SPIR-V, for debug reporting.
+    pub fn is_spirv(&self) -> bool {
+        self.from_spirv
+    }
+
+    /// Returns true when both programs are clones of the same GL program.
+    pub fn shares_handle(&self, other: &ShaderProgram) -> bool {
+        self.handle.ptr_eq(&other.handle)
+    }
+
+    pub fn program_id(&self) -> u32 {
+        self.handle.id()
+    }
+
+    /// Does nothing when the program is already in use.
+    /// In overdraw mode the program's flat variant is bound instead, see `DebugRenderMode::Overdraw`.
+    pub fn bind(&self) {
+        let program_id = match debug_render::overdraw_active() {
+            true => self.overdraw_program_id().unwrap_or(self.handle.id()),
+            false => self.handle.id()
+        };
+
+        bind_cache::use_program(program_id);
+    }
+
+    /// Returns the overdraw variant, building it the first time. None when the program has no vertex source.
+    fn overdraw_program_id(&self) -> Option<GLuint> {
+        let mut variant = self.overdraw_variant.borrow_mut();
+        let variant = variant.get_or_insert_with(|| OverdrawVariant {
+            handle: self.link_overdraw_variant(),
+            locations: HashMap::default()
+        });
+
+        variant.handle.as_ref().map(GlHandle::id)
+    }
+
+    fn link_overdraw_variant(&self) -> Option<GlHandle> {
+        let vertex_source = self.vertex_source.as_ref()?;
+
+        unsafe {
+            let vert_shader = Shader { shader_id: compile_shader(vertex_source, ShaderType::Vertex) };
+            let frag_shader = Shader { shader_id: compile_shader(debug_render::OVERDRAW_FRAGMENT_SHADER, ShaderType::Fragment) };
+            let handle = GlHandle::program(glCreateProgram());
+
+            // Vertex arrays are set up against the program's attribute locations, so the variant needs the same.
+            for (name, location) in &self.attribute_locations {
+                if let Ok(name) = std::ffi::CString::new(name.as_str()) {
+                    glBindAttribLocation(handle.id(), *location as GLuint, name.as_ptr());
+                }
+            }
+
+            glAttachShader(handle.id(), vert_shader.shader_id);
+            glAttachShader(handle.id(), frag_shader.shader_id);
+            glLinkProgram(handle.id());
+
+            let mut status: GLint = 0;
+            glGetProgramiv(handle.id(), GL_LINK_STATUS, &mut status);
+
+            if status == 0 {
+                println!("Overdraw shader failed to link, drawing normally: {}", program_info_log(handle.id()));
+                return None;
+            }
+
+            Some(handle)
+        }
+    }
+
+    /// Counts a uniform load and maps the location looked up from this program to the bound program's,
+    /// which differs in overdraw mode.
+    fn begin_uniform_load(&self, location: i32) -> i32 {
+        bind_cache::count_uniform_call();
+
+        if location < 0 || !debug_render::overdraw_active() {
+            return location;
+        }
+
+        let mut variant = self.overdraw_variant.borrow_mut();
+
+        let (handle, locations) = match variant.as_mut() {
+            Some(OverdrawVariant { handle: Some(handle), locations }) => (handle, locations),
+            _ => return location
+        };
+
+        *locations.entry(location).or_insert_with(|| {
+            let uniform_locations = self.uniform_locations.borrow();
+            let name = uniform_locations.iter().find(|(_, found)| **found == location).map(|(name, _)| name.as_str());
+
+            match name.map(std::ffi::CString::new) {
+                // Uniforms only the fragment shader used don't exist in the variant, and loads to -1 are ignored.
+                Some(Ok(name)) => unsafe { glGetUniformLocation(handle.id(), name.as_ptr()) },
+                _ => -1
+            }
+        })
+    }
+
+    pub fn unbind(&self) {
+        bind_cache::use_program(0);
+    }
+
     /// Creates a new attribute location and returns it for later use.
     pub fn add_attribute(&mut self, attribute_name: &str) -> i32 {
         #[cfg(debug_assertions)]
@@ -117,7 +570,7 @@ impl ShaderProgram {
 
         // Again with this nonsense of converting a u8 ptr to an i8 ptr.
         unsafe {
-            glBindAttribLocation(self.program_id, 
+            glBindAttribLocation(self.handle.id(), 
                 self.current_attribute_location as u32, 
                 attribute_name.as_ptr() as *const i8);
         }
@@ -131,16 +584,107 @@ impl ShaderProgram {
     pub fn get_attribute_location(&self, name: &str) -> Option<&i32> {
         self.attribute_locations.get(name)
     }
-}
 
-impl ResourceDestroy for ShaderProgram {
-    fn destroy(&mut self) {
-        unsafe {
-            glDeleteProgram(self.program_id);
+    /// Asks GL where the linker put an attribute, for attributes not added with add_attribute.
+    /// None when the program has no active attribute of that name.
+    pub fn query_attribute_location(&self, name: &str) -> Option<u32> {
+        let name = std::ffi::CString::new(name).ok()?;
+        let location = unsafe { glGetAttribLocation(self.handle.id(), name.as_ptr()) };
+
+        u32::try_from(location).ok()
+    }
+
+    /// Loads every uniform of the collection into this program, which must be bound.
+    pub fn load_uniforms(&self, uniforms: &UniformSet) {
+        uniforms.load_into(self);
+    }
+
+    /// Reads back the current value of a float uniform, ex: to check what a system loaded.
+    /// Reads stall until the GL has caught up, so keep these out of the frame loop.
+    #[track_caller]
+    pub fn get_uniform_float(&self, location: i32) -> f32 {
+        let [x] = self.get_uniform_floats::<1>(location);
+        x
+    }
+
+    #[track_caller]
+    pub fn get_uniform_vec2(&self, location: i32) -> Vec2f {
+        let [x, y] = self.get_uniform_floats::<2>(location);
+        Vec2f::new(x, y)
+    }
+
+    #[track_caller]
+    pub fn get_uniform_vec3(&self, location: i32) -> Vec3f {
+        let [x, y, z] = self.get_uniform_floats::<3>(location);
+        Vec3f::new(x, y, z)
+    }
+
+    #[track_caller]
+    pub fn get_uniform_vec4(&self, location: i32) -> Vec4f {
+        let [x, y, z, w] = self.get_uniform_floats::<4>(location);
+        Vec4f::new(x, y, z, w)
+    }
+
+    /// Also reads samplers, which hold their texture unit.
+    #[track_caller]
+    pub fn get_uniform_int(&self, location: i32) -> i32 {
+        let mut value = 0;
+
+        if location >= 0 {
+            unsafe {
+                glGetUniformiv(self.handle.id(), location, &mut value);
+            }
+
+            gl_debug::check_errors("glGetUniformiv");
         }
+
+        value
+    }
+
+    #[track_caller]
+    pub fn get_uniform_matrix44(&self, location: i32) -> Mat44f {
+        matrix44_from_uniform(self.get_uniform_floats::<16>(location))
+    }
+
+    /// Reads N floats of the uniform from this program, not the overdraw variant. Zeroes for location -1,
+    /// the same as loads to it being ignored.
+    #[track_caller]
+    fn get_uniform_floats<const N: usize>(&self, location: i32) -> [f32; N] {
+        let mut values = [0.0; N];
+
+        if location >= 0 {
+            unsafe {
+                glGetUniformfv(self.handle.id(), location, values.as_mut_ptr());
+            }
+
+            gl_debug::check_errors("glGetUniformfv");
+        }
+
+        values
     }
 }
 
+/// Texture parameters load_shadow_sampler sets: fragments nearer the light than the stored depth are lit.
+pub(crate) const SHADOW_SAMPLER_PARAMETERS: [(GLenum, GLint); 2] = [
+    (GL_TEXTURE_COMPARE_MODE, GL_COMPARE_REF_TO_TEXTURE as GLint),
+    (GL_TEXTURE_COMPARE_FUNC, GL_LEQUAL as GLint)
+];
+
+/// Rebuilds a matrix from the floats glGetUniformfv returns for it. Matrices are loaded without transposing,
+/// so they come back in the same order as data: each group of four is one of its arrays.
+pub(crate) fn matrix44_from_uniform(values: [f32; 16]) -> Mat44f {
+    let mut data = [[0.0; 4]; 4];
+
+    for (i, row) in data.iter_mut().enumerate() {
+        row.copy_from_slice(&values[i * 4..i * 4 + 4]);
+    }
+
+    Mat44f { data }
+}
+
+/// The program is freed when the last handle is dropped.
+impl ResourceDestroy for ShaderProgram {}
+
 /// Uniform controllers for clients to use from the shader. This encapsulates most
 /// of the shader we don't want implementers to touch.
 pub trait ShaderUniforms {
@@ -156,6 +700,13 @@ pub trait ShaderUniforms {
     // Integers
     fn load_int(&self, location: i32, value: i32);
 
+    // Samplers
+    /// Points a sampler2DShadow uniform at the texture unit, for sampling a depth texture as a shadow map.
+    /// Loaders which don't own GL state only record the unit, the same as load_int.
+    fn load_shadow_sampler(&self, location: i32, unit: u32) {
+        self.load_int(location, unit as i32);
+    }
+
     // Arrays
     fn load_float_array(&self, location: i32, value: Vec<f32>);
     fn load_integer_array(&self, location: i32, value: Vec<i32>);
@@ -168,63 +719,109 @@ pub trait ShaderUniforms {
 
 impl ShaderUniforms for ShaderProgram {
     fn get_uniform_location(&self, uniform_name: &str) -> i32 {
-        unsafe {
+        if let Some(location) = self.uniform_locations.borrow().get(uniform_name) {
+            return *location;
+        }
+
+        let location = unsafe {
             let name_as_c_str = std::ffi::CString::new(uniform_name);
 
-            return match name_as_c_str {
+            match name_as_c_str {
                 Ok(uniform_name) => {
-                    let loc = glGetUniformLocation(self.program_id as u32, 
+                    let loc = glGetUniformLocation(self.handle.id(), 
                         uniform_name.as_ptr() as *const i8);
                     loc 
                 },
                 Err(_) => -1
             }
-        }
+        };
+
+        self.uniform_locations.borrow_mut().insert(uniform_name.to_string(), location);
+        location
     }
 
     fn load_float(&self, location: i32, value: f32) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform1f(location, value);
         }
     }
 
     fn load_vec2(&self, location: i32, value: Vec2f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform2f(location, value.x, value.y);
         }
     }
 
     fn load_vec3(&self, location: i32, value: Vec3f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform3f(location, value.x, value.y, value.z);
         }
     }
 
     fn load_vec4(&self, location: i32, value: Vec4f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform4f(location, value.x, value.y, value.z, value.w);
         }
     }
 
     fn load_int(&self, location: i32, value: i32) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform1i(location, value);
         }
     }
 
+    /// Switches the 2D texture bound to the unit to depth comparisons, so sampling it returns how much of the
+    /// filter footprint is nearer the light than the reference depth, then points the uniform at the unit.
+    /// Bind the depth texture first, ex: `Framebuffer::with_depth_texture`'s. The compare mode stays on the
+    /// texture, so it can't also be read as a regular sampler2D afterwards.
+    #[track_caller]
+    fn load_shadow_sampler(&self, location: i32, unit: u32) {
+        let location = self.begin_uniform_load(location);
+
+        bind_cache::active_texture(unit);
+
+        for (parameter, value) in SHADOW_SAMPLER_PARAMETERS {
+            unsafe {
+                glTexParameteri(GL_TEXTURE_2D, parameter, value);
+            }
+        }
+
+        gl_debug::check_errors("glTexParameteri");
+
+        unsafe {
+            glUniform1i(location, unit as i32);
+        }
+    }
+
     fn load_float_array(&self, location: i32, value: Vec<f32>) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform1fv(location, value.len() as i32, value.as_ptr());
         }
     }
 
     fn load_integer_array(&self, location: i32, value: Vec<i32>) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniform1iv(location, value.len() as i32, value.as_ptr());
         }
     }
 
     fn load_matrix22(&self, location: i32, value: Mat22f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniformMatrix2fv(location, 1, GL_FALSE, 
                 value.data.as_ptr() as *const f32);
@@ -232,6 +829,8 @@ impl ShaderUniforms for ShaderProgram {
     }
 
     fn load_matrix33(&self, location: i32, value: Mat33f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniformMatrix3fv(location, 1, GL_FALSE, 
                 value.data.as_ptr() as *const f32);
@@ -239,9 +838,175 @@ impl ShaderUniforms for ShaderProgram {
     }
 
     fn load_matrix44(&self, location: i32, value: Mat44f) {
+        let location = self.begin_uniform_load(location);
+
         unsafe {
             glUniformMatrix4fv(location, 1, GL_FALSE, 
                 value.data.as_ptr() as *const f32);
         }
     }
-}
\ No newline at end of file
+}
+
+/// A value for one uniform, see `UniformSet`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2(Vec2f),
+    Vec3(Vec3f),
+    Vec4(Vec4f),
+    Int(i32),
+    FloatArray(Vec<f32>),
+    IntArray(Vec<i32>),
+    Mat2(Mat22f),
+    Mat3(Mat33f),
+    Mat4(Mat44f)
+}
+
+/// A collection of uniform values loaded into a program in one call, instead of looking up and
+/// loading each uniform separately.
+/// ex: shader.load_uniforms(UniformSet::new().set_int("guiTexture", 0).set_mat4("viewProjection", view_projection))
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UniformSet {
+    /// Kept in the order they were first set, which is the order they're loaded in.
+    values: Vec<(String, UniformValue)>
+}
+
+impl UniformSet {
+    pub fn new() -> Self {
+        UniformSet::default()
+    }
+
+    /// Sets a uniform, replacing its value if it was already set.
+    pub fn set(&mut self, name: &str, value: UniformValue) -> &mut Self {
+        match self.values.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((name.to_string(), value))
+        }
+
+        self
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) -> &mut Self {
+        self.set(name, UniformValue::Float(value))
+    }
+
+    pub fn set_vec2(&mut self, name: &str, value: Vec2f) -> &mut Self {
+        self.set(name, UniformValue::Vec2(value))
+    }
+
+    pub fn set_vec3(&mut self, name: &str, value: Vec3f) -> &mut Self {
+        self.set(name, UniformValue::Vec3(value))
+    }
+
+    pub fn set_vec4(&mut self, name: &str, value: Vec4f) -> &mut Self {
+        self.set(name, UniformValue::Vec4(value))
+    }
+
+    /// Sets a vec4 to the color's channels, ex: the "tint" of the GUI shader.
+    pub fn set_color(&mut self, name: &str, value: Color) -> &mut Self {
+        self.set_vec4(name, value.into())
+    }
+
+    /// Sets an integer, also used for the texture unit of a sampler.
+    pub fn set_int(&mut self, name: &str, value: i32) -> &mut Self {
+        self.set(name, UniformValue::Int(value))
+    }
+
+    pub fn set_float_array(&mut self, name: &str, value: Vec<f32>) -> &mut Self {
+        self.set(name, UniformValue::FloatArray(value))
+    }
+
+    pub fn set_int_array(&mut self, name: &str, value: Vec<i32>) -> &mut Self {
+        self.set(name, UniformValue::IntArray(value))
+    }
+
+    pub fn set_mat2(&mut self, name: &str, value: Mat22f) -> &mut Self {
+        self.set(name, UniformValue::Mat2(value))
+    }
+
+    pub fn set_mat3(&mut self, name: &str, value: Mat33f) -> &mut Self {
+        self.set(name, UniformValue::Mat3(value))
+    }
+
+    pub fn set_mat4(&mut self, name: &str, value: Mat44f) -> &mut Self {
+        self.set(name, UniformValue::Mat4(value))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UniformValue> {
+        self.values.iter().find(|(existing, _)| existing == name).map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the names of the uniforms the program doesn't have, ex: misspelled, or optimized out of the shader.
+    pub fn missing_uniforms(&self, loader: &dyn ShaderUniforms) -> Vec<String> {
+        self.values.iter()
+            .filter(|(name, _)| loader.get_uniform_location(name) == -1)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Loads every value into the program, which must be bound, failing when any uniform doesn't exist in it.
+    /// The uniforms which do exist are still loaded.
+    /// ex: uniforms.apply(&shader)
+    pub fn apply(&self, program: &dyn ShaderUniforms) -> Result<(), UniformError> {
+        let mut missing = Vec::<String>::new();
+
+        for (name, value) in self.values.iter() {
+            match program.get_uniform_location(name) {
+                -1 => missing.push(name.clone()),
+                location => load_value(program, location, value)
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(UniformError::Missing(missing));
+        }
+
+        Ok(())
+    }
+
+    /// Loads every value with the matching call of the loader, skipping uniforms the program doesn't have.
+    pub fn load_into(&self, loader: &dyn ShaderUniforms) {
+        // Missing uniforms are expected here, ex: a shader not using every uniform of a shared bundle.
+        let _ = self.apply(loader);
+    }
+}
+
+/// Loads a single value at its location.
+fn load_value(loader: &dyn ShaderUniforms, location: i32, value: &UniformValue) {
+    match value {
+        UniformValue::Float(value) => loader.load_float(location, *value),
+        UniformValue::Vec2(value) => loader.load_vec2(location, *value),
+        UniformValue::Vec3(value) => loader.load_vec3(location, *value),
+        UniformValue::Vec4(value) => loader.load_vec4(location, *value),
+        UniformValue::Int(value) => loader.load_int(location, *value),
+        UniformValue::FloatArray(value) => loader.load_float_array(location, value.clone()),
+        UniformValue::IntArray(value) => loader.load_integer_array(location, value.clone()),
+        UniformValue::Mat2(value) => loader.load_matrix22(location, *value),
+        UniformValue::Mat3(value) => loader.load_matrix33(location, *value),
+        UniformValue::Mat4(value) => loader.load_matrix44(location, *value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniformError {
+    /// Uniforms set in a UniformSet which the program doesn't have.
+    Missing(Vec<String>)
+}
+
+impl std::fmt::Display for UniformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UniformError::Missing(names) => write!(f, "the program has no uniforms named {}", names.join(", "))
+        }
+    }
+}
+
+impl std::error::Error for UniformError {}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crate::shader_program::ShaderError;
+
+/// How long to wait between checks on programs the driver is still compiling.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Every shader which failed during warmup, by registry name.
+#[derive(Debug)]
+pub struct ShaderWarmupError {
+    pub failures: Vec<(String, ShaderError)>
+}
+
+impl std::fmt::Display for ShaderWarmupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} shader(s) failed to build:", self.failures.len())?;
+
+        for (name, err) in &self.failures {
+            write!(f, "\n  {}: {}", name, err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderWarmupError {}
+
+/// Waits for every program to finish compiling and linking, calling progress with (finished, total)
+/// as each one does. poll returns None while the driver is still working on a program.
+/// Failures don't stop the warmup, they're all returned together. Returns the number of programs on success.
+pub(crate) fn warmup_programs<'a, P: 'a>(programs: impl IntoIterator<Item = (&'a str, &'a P)>,
+    mut poll: impl FnMut(&P) -> Option<Result<(), ShaderError>>, mut progress: impl FnMut(usize, usize)) -> Result<usize, ShaderWarmupError> {
+    // Sorted so progress and failures come in the same order every run.
+    let mut pending: Vec<(&str, &P)> = programs.into_iter().collect();
+    pending.sort_by(|a, b| a.0.cmp(b.0));
+
+    let total = pending.len();
+    let mut finished = 0;
+    let mut failures = Vec::new();
+
+    while !pending.is_empty() {
+        pending.retain(|(name, program)| {
+            match poll(program) {
+                Some(result) => {
+                    finished += 1;
+                    progress(finished, total);
+
+                    if let Err(err) = result {
+                        failures.push((name.to_string(), err));
+                    }
+
+                    false
+                },
+                None => true
+            }
+        });
+
+        if !pending.is_empty() {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(total)
+    }
+    else {
+        Err(ShaderWarmupError { failures })
+    }
+}
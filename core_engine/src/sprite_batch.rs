@@ -0,0 +1,177 @@
+use glmath::glmath::Vec2f;
+
+use crate::atlas::TextureRegion;
+use crate::color::Color;
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::shader_program::{ShaderProgram, ShaderUniforms};
+use crate::texture::Texture;
+use crate::ui::NineSlice;
+
+/// A single textured quad queued in a sprite batch.
+#[derive(Clone)]
+pub struct Sprite {
+    pub texture: Texture,
+    /// Part of the texture drawn, with v pointing down from the top of the texture.
+    /// (0, 0) to (1, 1) draws the whole texture.
+    pub uv_min: Vec2f,
+    pub uv_max: Vec2f,
+    /// Center of the quad.
+    pub position: Vec2f,
+    /// Half the width and height of the quad.
+    pub scale: Vec2f,
+    /// Sprites on higher layers draw later, so they appear on top of lower layers.
+    pub layer: i32,
+    /// Multiplied with the texture, alpha included. White draws the texture unchanged.
+    pub tint: Color,
+    /// Counter-clockwise rotation about the center of the quad, in radians.
+    pub rotation: f32
+}
+
+/// Sorts sprites by layer, then by texture to reduce texture binds.
+/// The sort is stable: sprites with the same layer and texture keep the order they were drawn in.
+pub(crate) fn sort_sprites(sprites: &mut [Sprite]) {
+    sprites.sort_by_key(|sprite| (sprite.layer, sprite.texture.texture_id()));
+}
+
+/// Collects textured quads and draws them ordered by layer.
+///
+/// Sprites are queued between `begin` and `end`, and `end` sorts them. `render` can then be called
+/// every frame until the next `begin`. The shader is expected to have a vec2 "pos" and "scale" uniform,
+/// like the bundled GUI shader, and to sample its texture from unit 0. Sprites drawn from texture regions
+/// also need vec2 "uvMin" and "uvMax" uniforms, and tinted sprites a vec4 "tint" uniform.
+pub struct SpriteBatch {
+    quad: Mesh2D,
+    sprites: Vec<Sprite>,
+    /// Tint given to the sprites queued from here on.
+    tint: Color,
+    location_pos: i32,
+    location_scale: i32,
+    location_uv_min: i32,
+    location_uv_max: i32,
+    location_tint: i32,
+    location_rotation: i32
+}
+
+impl SpriteBatch {
+    pub fn new(shader: &ShaderProgram) -> Self {
+        SpriteBatch::with_quad(shader, Mesh2D::generate_quad())
+    }
+
+    /// Creates a batch drawing sprites with the given mesh, which should span -1..1 on both axes.
+    pub fn with_quad(shader: &ShaderProgram, quad: Mesh2D) -> Self {
+        SpriteBatch {
+            quad,
+            sprites: Vec::<Sprite>::default(),
+            tint: Color::WHITE,
+            location_pos: shader.get_uniform_location("pos"),
+            location_scale: shader.get_uniform_location("scale"),
+            location_uv_min: shader.get_uniform_location("uvMin"),
+            location_uv_max: shader.get_uniform_location("uvMax"),
+            location_tint: shader.get_uniform_location("tint"),
+            location_rotation: shader.get_uniform_location("rotation")
+        }
+    }
+
+    /// Clears the sprites queued by the previous batch and resets the tint to white.
+    pub fn begin(&mut self) {
+        self.sprites.clear();
+        self.tint = Color::WHITE;
+    }
+
+    /// Sets the tint of the sprites queued from here on, until the next `set_tint` or `begin`.
+    /// A translucent tint fades sprites out, ex: `Color::new(1.0, 1.0, 1.0, 0.5)` draws them half transparent.
+    /// On an sRGB window convert the color with `to_linear` first.
+    pub fn set_tint(&mut self, tint: Color) {
+        self.tint = tint;
+    }
+
+    pub fn get_tint(&self) -> Color {
+        self.tint
+    }
+
+    pub fn draw(&mut self, texture: &Texture, position: Vec2f, scale: Vec2f, layer: i32) {
+        self.draw_region(&TextureRegion::full(texture), position, scale, layer);
+    }
+
+    /// Queues part of a texture, ex: a region of an atlas. Regions of the same atlas share a texture bind.
+    pub fn draw_region(&mut self, region: &TextureRegion, position: Vec2f, scale: Vec2f, layer: i32) {
+        self.draw_region_rotated(region, position, scale, 0.0, layer);
+    }
+
+    /// Queues part of a texture rotated counter-clockwise about its center, in radians.
+    /// The shader needs a float "rotation" uniform, otherwise the sprite is drawn unrotated.
+    pub fn draw_region_rotated(&mut self, region: &TextureRegion, position: Vec2f, scale: Vec2f, rotation: f32, layer: i32) {
+        self.sprites.push(Sprite {
+            texture: region.texture.clone(),
+            uv_min: region.uv_min,
+            uv_max: region.uv_max,
+            position,
+            scale,
+            layer,
+            tint: self.tint,
+            rotation
+        });
+    }
+
+    /// Queues a nine slice filling the rect with the given bottom left corner and size, as up to nine
+    /// sprites, or more when its center tiles.
+    pub fn draw_nine_slice(&mut self, nine_slice: &NineSlice, position: Vec2f, size: Vec2f, layer: i32) {
+        let texture = &nine_slice.texture;
+
+        for quad in nine_slice.quads(position, size) {
+            // Slice quads give the texture coordinates at their bottom left and top right corners,
+            // sprites at their top left and bottom right.
+            self.sprites.push(Sprite {
+                texture: texture.clone(),
+                uv_min: Vec2f::new(quad.uv_min.x, quad.uv_max.y),
+                uv_max: Vec2f::new(quad.uv_max.x, quad.uv_min.y),
+                position: (quad.min + quad.max) * 0.5,
+                scale: (quad.max - quad.min) * 0.5,
+                layer,
+                tint: self.tint,
+                rotation: 0.0
+            });
+        }
+    }
+
+    /// Finishes the batch, sorting the sprites into draw order.
+    pub fn end(&mut self) {
+        sort_sprites(&mut self.sprites);
+    }
+
+    /// Returns the queued sprites in draw order.
+    pub fn get_sprites(&self) -> &Vec<Sprite> {
+        &self.sprites
+    }
+
+    /// Draws the sprites. The shader must already be bound.
+    pub fn render(&self, shader: &ShaderProgram) {
+        let mut bound_texture: Option<u32> = None;
+
+        for sprite in self.sprites.iter() {
+            if bound_texture != Some(sprite.texture.texture_id()) {
+                sprite.texture.bind(0);
+                bound_texture = Some(sprite.texture.texture_id());
+            }
+
+            shader.load_vec2(self.location_pos, sprite.position);
+            shader.load_vec2(self.location_scale, sprite.scale);
+
+            // Shaders which only draw whole textures can leave the uv uniforms out.
+            if self.location_uv_min != -1 {
+                shader.load_vec2(self.location_uv_min, sprite.uv_min);
+                shader.load_vec2(self.location_uv_max, sprite.uv_max);
+            }
+
+            if self.location_tint != -1 {
+                shader.load_vec4(self.location_tint, sprite.tint.into());
+            }
+
+            if self.location_rotation != -1 {
+                shader.load_float(self.location_rotation, sprite.rotation);
+            }
+
+            self.quad.render();
+        }
+    }
+}
@@ -0,0 +1,2290 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use serializers::json::lexer::JsonLexer;
+    use serializers::json::parser::parse_json;
+    use glmath::glmath::{Vec2f, Vec3f, Vec4f, Mat22f, Mat33f, Mat44f, StandardMat, StandardMat44};
+
+    use crate::mesh::{Aabb, LodMesh, MeshData, VertexAttribs, bind_float_attribute, bind_instance_attribute};
+    use crate::texture_array::validate_layer_sizes;
+    use crate::mesh::skinned::{Bone, Skeleton};
+    use crate::camera::{Camera2D, CoordSystem};
+    use crate::gl_handle::GlHandle;
+    use crate::ui::nine_slice::compute_slices;
+    use crate::ui::{NineSlice, SliceBorder, SliceCenter};
+    use crate::outline_pipeline::outline_pass_states;
+    use crate::post_process::{PostEffectDesc, parse_post_effects};
+    use crate::atlas::{AtlasError, TextureAtlas, TextureRegion, parse_atlas_regions, pack_rects};
+    use crate::texture::compressed::{CompressedFormat, CompressedImage, ContainerKind, decode_bc1_block, decode_bc3_block};
+    use crate::texture::packing::{Rect, RectPacker, ShelfPacker, GuillotinePacker, PackingAlgorithm};
+    use crate::animation::{Animation, AnimationFrame, AnimationPlayer, PlayMode};
+    use crate::texture::{Texture, TextureFormat, TextureSampling, TextureFilter, TextureWrap, TextureError, OversizePolicy, row_alignment, validate_pixel_data, checkerboard_pixels, fit_to_max_size};
+    use crate::shader_program::{validate_spirv, ShaderError, ShaderProgram, ShaderUniforms, UniformError, UniformSet, UniformValue};
+    use crate::sprite_batch::{Sprite, sort_sprites};
+    use crate::frame_time::FrameTimeAverage;
+    use crate::bind_cache::{BindState, BindCounter};
+    use crate::accumulator::Accumulator;
+    use crate::physics::Body2D;
+    use crate::audio::{Sound, AudioEvent};
+    use crate::engine::GameManager;
+    use crate::resource_manager::ResourceManager;
+    use crate::screenshot::{flip_rows, make_opaque, ScreenshotError, ScreenshotRequest, capture_framebuffer};
+    use crate::framebuffer::{Framebuffer, FilterMode};
+    use crate::scheduler::Scheduler;
+    use crate::gl_debug::{self, DebugSeverity};
+    use crate::render_pipeline::{RenderPass, RenderPipelineHandler, RenderGraph, RenderGraphNode, RenderGraphError};
+    use crate::color::{Color, srgb_to_linear, linear_to_srgb};
+    use crate::render_state::{ViewRect, Rect2i, RenderState, CompareFunc, StencilOp, StencilState, StencilCalls};
+
+    fn assert_vec2_near(a: Vec2f, b: Vec2f) {
+        assert!((a.x - b.x).abs() < 0.0001 && (a.y - b.y).abs() < 0.0001, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_mesh_bounds() {
+        // The same quad used by the snake pipeline.
+        let vertices = vec![
+            -1.0, -1.0,
+            -1.0, 1.0,
+            1.0, 1.0,
+            1.0, 1.0,
+            1.0, -1.0,
+            -1.0, -1.0
+        ];
+
+        let bounds = Aabb::from_positions(&vertices, 2);
+        assert_eq!(bounds.min, Vec3f::new(-1.0, -1.0, 0.0));
+        assert_eq!(bounds.max, Vec3f::new(1.0, 1.0, 0.0));
+        assert_eq!(bounds.center(), Vec3f::ZERO);
+        assert_eq!(bounds.size(), Vec3f::new(2.0, 2.0, 0.0));
+
+        // Offset 3D positions.
+        let vertices = vec![
+            2.0, 0.5, -3.0,
+            4.0, 1.5, 1.0
+        ];
+
+        let bounds = Aabb::from_positions(&vertices, 3);
+        assert_eq!(bounds.min, Vec3f::new(2.0, 0.5, -3.0));
+        assert_eq!(bounds.max, Vec3f::new(4.0, 1.5, 1.0));
+    }
+
+    #[test]
+    fn test_camera_screen_world_conversion() {
+        let mut camera = Camera2D::new();
+        camera.set_viewport_size(800.0, 600.0);
+
+        // The default camera maps the screen directly onto NDC.
+        assert_vec2_near(camera.screen_to_world(Vec2f::new(400.0, 300.0)), Vec2f::ZERO);
+        assert_vec2_near(camera.screen_to_world(Vec2f::new(0.0, 0.0)), Vec2f::new(-1.0, 1.0));
+        assert_vec2_near(camera.screen_to_ndc(Vec2f::new(200.0, 450.0)), Vec2f::new(-0.5, -0.5));
+
+        // Moving and zooming the camera moves where the center of the screen lands.
+        camera.position = Vec2f::new(2.0, 1.0);
+        camera.zoom = 2.0;
+        camera.rotation = 0.5;
+        assert_vec2_near(camera.screen_to_world(Vec2f::new(400.0, 300.0)), camera.position);
+
+        // Converting back and forth should land on the same point.
+        let world = Vec2f::new(2.3, 0.6);
+        assert_vec2_near(camera.screen_to_world(camera.world_to_screen(world)), world);
+
+        // Following covers the distance over time without overshooting.
+        camera.follow(Vec2f::new(10.0, 1.0), 0.1);
+        assert!(camera.position.x > 2.0 && camera.position.x < 10.0);
+    }
+
+    #[test]
+    fn test_lod_selection() {
+        // Levels are given out of order to make sure they get sorted.
+        let lods = LodMesh::new(vec![(0.0, "low"), (200.0, "high"), (50.0, "medium")]);
+
+        assert_eq!(*lods.select_lod(500.0), "high");
+        assert_eq!(*lods.select_lod(200.0), "high");
+        assert_eq!(*lods.select_lod(199.0), "medium");
+        assert_eq!(*lods.select_lod(10.0), "low");
+
+        // A quad covering the whole 800x600 screen is 1000 pixels across.
+        let mut camera = Camera2D::new();
+        camera.set_viewport_size(800.0, 600.0);
+        let bounds = Aabb::from_positions(&[-1.0, -1.0, 1.0, 1.0], 2);
+        assert!((bounds.screen_diameter(Vec2f::ZERO, 1.0, &camera) - 1000.0).abs() < 0.01);
+        assert!((bounds.screen_diameter(Vec2f::ZERO, 0.5, &camera) - 500.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_dropping_clone_keeps_handle_alive() {
+        static DELETED: AtomicU32 = AtomicU32::new(0);
+
+        let handle = GlHandle::new(7, |id| { DELETED.store(id, Ordering::SeqCst); });
+        let clone = handle.clone();
+        assert_eq!(clone.id(), 7);
+
+        // The original still references the object, so nothing is freed yet.
+        drop(clone);
+        assert_eq!(DELETED.load(Ordering::SeqCst), 0);
+        assert_eq!(handle.id(), 7);
+
+        drop(handle);
+        assert_eq!(DELETED.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn test_aspect_projection_keeps_units_square() {
+        let mut camera = Camera2D::new();
+        camera.resize(200.0, 100.0);
+
+        // The full height is -1..1, and one world unit covers the same pixels on both axes.
+        assert_vec2_near(camera.world_to_screen(Vec2f::new(0.0, 1.0)), Vec2f::new(100.0, 0.0));
+        assert_vec2_near(camera.world_to_screen(Vec2f::new(1.0, 0.0)), Vec2f::new(150.0, 50.0));
+        assert_vec2_near(camera.world_to_screen(Vec2f::new(2.0, -1.0)), Vec2f::new(200.0, 100.0));
+    }
+
+    #[test]
+    fn test_skinning_matrices() {
+        let mut child_rest = Mat44f::IDENTITY;
+        child_rest.translate(Vec3f::new(1.0, 0.0, 0.0));
+
+        let skeleton = Skeleton::new(
+            vec![
+                Bone { name: "root".to_string(), parent: None },
+                Bone { name: "child".to_string(), parent: Some(0) }
+            ],
+            vec![Mat44f::IDENTITY, child_rest.get_inverted()]);
+
+        // Moving the root up carries the child with it, so a vertex on the child moves up too.
+        let mut root_pose = Mat44f::IDENTITY;
+        root_pose.translate(Vec3f::new(0.0, 2.0, 0.0));
+
+        let matrices = skeleton.skinning_matrices(&[root_pose, child_rest]);
+        let vertex = matrices[1] * Vec4f::new(1.0, 0.0, 0.0, 1.0);
+
+        assert_vec2_near(Vec2f::new(vertex.x, vertex.y), Vec2f::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn test_clones_share_one_delete() {
+        static TEXTURE_DELETES: AtomicU32 = AtomicU32::new(0);
+        static PROGRAM_DELETES: AtomicU32 = AtomicU32::new(0);
+
+        let texture = Texture::from_handle(GlHandle::new(3, |_| { TEXTURE_DELETES.fetch_add(1, Ordering::SeqCst); }), 1, 1);
+        let texture_clone = texture.clone();
+        assert!(texture.shares_handle(&texture_clone));
+        assert_eq!(texture.texture_id(), texture_clone.texture_id());
+
+        let program = ShaderProgram::from_handle(GlHandle::new(4, |_| { PROGRAM_DELETES.fetch_add(1, Ordering::SeqCst); }));
+        let program_clone = program.clone();
+        assert!(program.shares_handle(&program_clone));
+        assert_eq!(program.program_id(), program_clone.program_id());
+
+        drop(texture);
+        drop(program);
+        assert_eq!(TEXTURE_DELETES.load(Ordering::SeqCst), 0);
+        assert_eq!(PROGRAM_DELETES.load(Ordering::SeqCst), 0);
+
+        drop(texture_clone);
+        drop(program_clone);
+        assert_eq!(TEXTURE_DELETES.load(Ordering::SeqCst), 1);
+        assert_eq!(PROGRAM_DELETES.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_sprite_layer_order_is_stable() {
+        let texture_a = Texture::from_handle(GlHandle::new(1, |_| {}), 1, 1);
+        let texture_b = Texture::from_handle(GlHandle::new(2, |_| {}), 1, 1);
+
+        let sprite = |texture: &Texture, x: f32, layer: i32| Sprite {
+            texture: texture.clone(),
+            uv_min: Vec2f::new(0.0, 0.0),
+            uv_max: Vec2f::new(1.0, 1.0),
+            position: Vec2f::new(x, 0.0),
+            scale: Vec2f::new(1.0, 1.0),
+            layer,
+            tint: Color::WHITE,
+            rotation: 0.0
+        };
+
+        let mut sprites = vec![
+            sprite(&texture_a, 0.0, 1),
+            sprite(&texture_b, 1.0, 0),
+            sprite(&texture_a, 2.0, 1),
+            sprite(&texture_a, 3.0, 0),
+            sprite(&texture_a, 4.0, 1)
+        ];
+
+        sort_sprites(&mut sprites);
+
+        // Lower layers first, grouped by texture, with equal sprites left in draw order.
+        let order: Vec<f32> = sprites.iter().map(|sprite| sprite.position.x).collect();
+        assert_eq!(order, vec![3.0, 1.0, 0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_accumulator_tick_count() {
+        // Ticks 8 times a second. Binary fractions keep the sums exact.
+        let mut accumulator = Accumulator::new(0.125);
+        let deltas = [0.25, 0.0625, 0.03125, 0.15625, 0.5];
+
+        let mut ticks = 0;
+        for delta in deltas {
+            accumulator.add(delta);
+
+            while accumulator.should_tick() {
+                ticks += 1;
+            }
+        }
+
+        // One second at an eighth of a second per tick.
+        assert_eq!(deltas.iter().sum::<f32>(), 1.0);
+        assert_eq!(ticks, 8);
+        assert_eq!(accumulator.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_viewport_camera_mapping() {
+        // A minimap in the top right quarter of an 800x600 window.
+        let minimap = ViewRect::Normalized { x: 0.75, y: 0.75, width: 0.25, height: 0.25 };
+        let (x, y, width, height) = minimap.to_pixels(800, 600);
+        assert_eq!((x, y, width, height), (600, 450, 200, 150));
+
+        let mut camera = Camera2D::new();
+        camera.fit_viewport(x as f32, (600 - y - height) as f32, width as f32, height as f32);
+
+        // The center of the sub-view is the camera position, both ways.
+        assert_vec2_near(camera.world_to_screen(Vec2f::ZERO), Vec2f::new(700.0, 75.0));
+        assert_vec2_near(camera.screen_to_world(Vec2f::new(700.0, 75.0)), Vec2f::ZERO);
+        assert_vec2_near(camera.screen_to_world(Vec2f::new(700.0, 0.0)), Vec2f::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_mesh_data_from_json() {
+        let parse = |json: &str| MeshData::from_json(&parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap());
+
+        let quad = parse(r#"{ "vertices": [-1, -1, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0], "components": 2, "indices": [0, 1, 2, 2, 3, 0] }"#).unwrap();
+        assert_eq!(quad.vertices, vec![-1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0]);
+        assert_eq!(quad.components, 2);
+        assert_eq!(quad.indices, Some(vec![0, 1, 2, 2, 3, 0]));
+
+        assert!(parse(r#"{ "vertices": [0.0, 0.0], "components": 2 }"#).unwrap().indices.is_none());
+        assert!(parse(r#"{ "vertices": [0.0, 0.0, 1.0], "components": 2 }"#).is_err());
+        assert!(parse(r#"{ "vertices": [0.0, 0.0], "components": 2, "indices": [1] }"#).is_err());
+        assert!(parse(r#"{ "components": 2 }"#).is_err());
+        assert!(parse(r#"{ "vertices": [], "components": 2 }"#).is_err());
+    }
+
+    #[test]
+    fn test_buffer_vertex_count() {
+        use crate::mesh::{buffer_vertex_count, MeshError};
+
+        assert_eq!(buffer_vertex_count(12, 2).unwrap(), 6);
+        assert_eq!(buffer_vertex_count(12, 4).unwrap(), 3);
+        assert_eq!(buffer_vertex_count(0, 3).unwrap(), 0);
+
+        // A partial vertex is rejected with the sizes involved.
+        let err = buffer_vertex_count(7, 2).unwrap_err();
+        assert!(matches!(err, MeshError::InvalidData(_)));
+        assert_eq!(err.to_string(), "Invalid mesh: 7 vertex values can't be split into 2 components");
+
+        assert!(buffer_vertex_count(6, 0).is_err());
+        assert!(buffer_vertex_count(10, 5).is_err());
+    }
+
+    #[test]
+    fn test_nine_slice() {
+        // A 0.25 UV border on a texture drawn 1 unit wide keeps 0.25 unit corners.
+        let slices = compute_slices([0.25; 4], Vec2f::new(1.0, 1.0), Vec2f::new(1.0, 2.0), Vec2f::new(4.0, 2.0));
+
+        // Bottom left corner.
+        assert_eq!(slices[0].min, Vec2f::new(1.0, 2.0));
+        assert_eq!(slices[0].max, Vec2f::new(1.25, 2.25));
+        assert_eq!(slices[0].uv_min, Vec2f::new(0.0, 1.0));
+        assert_eq!(slices[0].uv_max, Vec2f::new(0.25, 0.75));
+
+        // The center stretches to fill the rest.
+        assert_eq!(slices[4].min, Vec2f::new(1.25, 2.25));
+        assert_eq!(slices[4].max, Vec2f::new(4.75, 3.75));
+        assert_eq!(slices[4].uv_min, Vec2f::new(0.25, 0.75));
+        assert_eq!(slices[4].uv_max, Vec2f::new(0.75, 0.25));
+
+        // Top right corner.
+        assert_eq!(slices[8].max, Vec2f::new(5.0, 4.0));
+        assert_eq!(slices[8].uv_max, Vec2f::new(1.0, 0.0));
+
+        // Borders shrink to meet in the middle of a rect smaller than both.
+        let slices = compute_slices([0.5, 0.25, 0.5, 0.75], Vec2f::new(1.0, 1.0), Vec2f::new(0.0, 0.0), Vec2f::new(0.5, 0.5));
+        assert_eq!(slices[0].max, Vec2f::new(0.375, 0.25));
+        assert_eq!(slices[4].min, slices[4].max);
+    }
+
+    #[test]
+    fn test_nine_slice_region() {
+        let texture = Texture::from_handle(GlHandle::new(1, |_| {}), 24, 24);
+        let mut nine_slice = NineSlice::from_region(TextureRegion::full(&texture), (24, 24), SliceBorder::uniform(3));
+
+        // Corners keep their pixel size and the center stretches.
+        let quads = nine_slice.quads(Vec2f::new(0.0, 0.0), Vec2f::new(30.0, 12.0));
+        assert_eq!(quads.len(), 9);
+        assert_eq!(quads[0].min, Vec2f::new(0.0, 0.0));
+        assert_eq!(quads[0].max, Vec2f::new(3.0, 3.0));
+        assert_eq!(quads[0].uv_min, Vec2f::new(0.0, 1.0));
+        assert_eq!(quads[0].uv_max, Vec2f::new(0.125, 0.875));
+        assert_eq!(quads[4].min, Vec2f::new(3.0, 3.0));
+        assert_eq!(quads[4].max, Vec2f::new(27.0, 9.0));
+        assert_eq!(quads[8].max, Vec2f::new(30.0, 12.0));
+        assert_eq!(quads[8].uv_max, Vec2f::new(1.0, 0.0));
+
+        // An 18 pixel center tiles twice across 28 units, the second tile cut short from the right.
+        nine_slice.set_center(SliceCenter::Tile);
+        let quads = nine_slice.quads(Vec2f::new(0.0, 0.0), Vec2f::new(34.0, 24.0));
+        assert_eq!(quads.len(), 10);
+        assert_eq!(quads[4].min, Vec2f::new(3.0, 3.0));
+        assert_eq!(quads[4].max, Vec2f::new(21.0, 21.0));
+        assert_eq!(quads[5].min, Vec2f::new(21.0, 3.0));
+        assert_eq!(quads[5].max, Vec2f::new(31.0, 21.0));
+        assert_eq!(quads[5].uv_min, Vec2f::new(0.125, 0.875));
+        assert!((quads[5].uv_max.x - (0.125 + 0.75 * 10.0 / 18.0)).abs() < 1e-6);
+
+        // Borders shrink to meet in the middle, and the squashed edges and center are skipped.
+        let quads = nine_slice.quads(Vec2f::new(0.0, 0.0), Vec2f::new(4.0, 2.0));
+        assert_eq!(quads.len(), 4);
+        assert_eq!(quads[0].max, Vec2f::new(2.0, 1.0));
+        assert_eq!(quads[3].min, Vec2f::new(2.0, 1.0));
+        assert!(nine_slice.quads(Vec2f::new(0.0, 0.0), Vec2f::new(-1.0, 5.0)).is_empty());
+
+        // Borders are clamped to the region.
+        let nine_slice = NineSlice::from_region(TextureRegion::full(&texture), (24, 24), SliceBorder { left: 20, right: 20, top: 0, bottom: 30 });
+        assert_eq!(nine_slice.border, [0.0, 4.0 / 24.0, 24.0 / 24.0, 20.0 / 24.0]);
+
+        // Texture coordinates stay within a region of an atlas.
+        let region = TextureRegion { texture: texture.clone(), uv_min: Vec2f::new(0.5, 0.25), uv_max: Vec2f::new(1.0, 0.75) };
+        let slices = NineSlice::from_region(region, (12, 12), SliceBorder::uniform(3)).slices(Vec2f::new(0.0, 0.0), Vec2f::new(12.0, 12.0));
+        assert_eq!(slices[0].uv_min, Vec2f::new(0.5, 0.75));
+        assert_eq!(slices[0].uv_max, Vec2f::new(0.625, 0.625));
+        assert_eq!(slices[8].uv_max, Vec2f::new(1.0, 0.25));
+
+        let parse = |json: &str| SliceBorder::from_json(&parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap());
+        assert_eq!(parse("4"), Some(SliceBorder::uniform(4)));
+        assert_eq!(parse(r#"{ "left": 2, "top": 5 }"#), Some(SliceBorder { left: 2, right: 0, top: 5, bottom: 0 }));
+        assert_eq!(parse(r#""wide""#), None);
+    }
+
+    #[derive(Default)]
+    struct RecordedAttribs {
+        enabled: Vec<u32>,
+        pointers: Vec<(u32, i32)>,
+        int_pointers: Vec<(u32, i32)>,
+        divisors: Vec<(u32, u32)>
+    }
+
+    impl VertexAttribs for RecordedAttribs {
+        fn enable_vertex_attrib_array(&mut self, location: u32) {
+            self.enabled.push(location);
+        }
+
+        fn vertex_attrib_pointer(&mut self, location: u32, components: i32) {
+            self.pointers.push((location, components));
+        }
+
+        fn vertex_attrib_int_pointer(&mut self, location: u32, components: i32) {
+            self.int_pointers.push((location, components));
+        }
+
+        fn vertex_attrib_divisor(&mut self, location: u32, divisor: u32) {
+            self.divisors.push((location, divisor));
+        }
+    }
+
+    #[test]
+    fn test_float_buffer_location() {
+        let mut attribs = RecordedAttribs::default();
+        bind_float_attribute(&mut attribs, 3, 2);
+
+        assert_eq!(attribs.enabled, vec![3]);
+        assert_eq!(attribs.pointers, vec![(3, 2)]);
+    }
+
+    #[test]
+    fn test_parse_post_effects() {
+        let json = r#"["shader_post_vignette", { "shader": "shader_post_crt", "uniforms": { "scanlineIntensity": 0.25, "curvature": 6 } }, 5]"#;
+        let effects = parse_post_effects(&parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap());
+
+        assert_eq!(effects, vec![
+            PostEffectDesc { shader: "shader_post_vignette".to_string(), uniforms: vec![] },
+            PostEffectDesc { shader: "shader_post_crt".to_string(), uniforms: vec![
+                ("curvature".to_string(), 6.0),
+                ("scanlineIntensity".to_string(), 0.25)
+            ] }
+        ]);
+    }
+
+    #[test]
+    fn test_outline_pass_states() {
+        let [mark, rim] = outline_pass_states();
+
+        // The first pass marks every covered pixel.
+        let mark_stencil = mark.stencil.unwrap();
+        assert_eq!(mark_stencil.func, CompareFunc::Always);
+        assert_eq!(mark_stencil.pass, StencilOp::Replace);
+
+        // The second only draws outside the marked pixels, and clears the marks it skips.
+        let rim_stencil = rim.stencil.unwrap();
+        assert_eq!(rim_stencil.func, CompareFunc::NotEqual);
+        assert_eq!(rim_stencil.reference, mark_stencil.reference);
+        assert_eq!(rim_stencil.fail, StencilOp::Zero);
+        assert_eq!(rim_stencil.pass, StencilOp::Keep);
+        assert_ne!(rim_stencil.write_mask, 0);
+    }
+
+    #[test]
+    fn test_srgb_color_conversion() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+
+        for channel in [0.0, 0.02, 0.25, 0.5, 0.75, 1.0] {
+            assert!((linear_to_srgb(srgb_to_linear(channel)) - channel).abs() < 1e-5);
+        }
+
+        assert_eq!(Color::from_hex("#FF8000"), Some(Color::new(1.0, 128.0 / 255.0, 0.0, 1.0)));
+        assert_eq!(Color::from_hex("00000080").map(|color| color.a), Some(128.0 / 255.0));
+        assert_eq!(Color::from_hex("#FF80"), None);
+        assert_eq!(Color::from_hex("#GG0000"), None);
+
+        // Alpha is left alone.
+        let linear = Color::new(0.5, 0.5, 0.5, 0.5).to_linear();
+        assert_eq!(linear.a, 0.5);
+        assert!(linear.r < 0.5);
+    }
+
+    #[test]
+    fn test_fallback_resources() {
+        let mut textures = ResourceManager::<Texture>::new("Textures");
+        let brick = Texture::from_handle(GlHandle::new(1, |_| {}), 1, 1);
+        let missing = Texture::from_handle(GlHandle::new(2, |_| {}), 1, 1);
+
+        textures.add_registry("brick", brick.clone());
+        textures.set_fallback(missing.clone());
+
+        // Fallbacks are off until enabled.
+        assert!(textures.get_registry("unknown").is_none());
+
+        textures.set_use_fallback(true);
+        assert!(textures.get_registry("unknown").unwrap().shares_handle(&missing));
+        assert!(textures.get_registry("brick").unwrap().shares_handle(&brick));
+
+        textures.set_use_fallback(false);
+        assert!(textures.get_registry("unknown").is_none());
+    }
+
+    struct PriorityPipeline(i32);
+
+    impl RenderPipelineHandler for PriorityPipeline {
+        fn init(&mut self) {}
+        fn prepare(&self) {}
+        fn update(&mut self, _input: &Box<dyn window::window::MouseKeyboardInputControl>) {}
+        fn execute(&self) {}
+
+        fn priority(&self) -> i32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_render_graph_order() {
+        let mut graph = RenderGraph::new();
+
+        // The composite reads the scene and blur slots, so it runs last despite the lowest priority.
+        let composite = graph.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(-5)))
+            .with_input("scene", "sceneTexture")
+            .with_input("blur", "blurTexture"));
+        let blur = graph.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(0)))
+            .with_input("scene", "screenTexture")
+            .with_output("blur"));
+        let scene = graph.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(3)))
+            .with_output("scene"));
+        let hud = graph.add_pipeline(Box::new(PriorityPipeline(1)));
+
+        assert_eq!(graph.execution_order(), Ok(vec![hud, scene, blur, composite]));
+
+        // Without slots, nodes run by priority then insertion order.
+        let mut flat = RenderGraph::new();
+        let a = flat.add_pipeline(Box::new(PriorityPipeline(1)));
+        let b = flat.add_pipeline(Box::new(PriorityPipeline(0)));
+        let c = flat.add_pipeline(Box::new(PriorityPipeline(1)));
+        assert_eq!(flat.execution_order(), Ok(vec![b, a, c]));
+
+        let mut cyclic = RenderGraph::new();
+        cyclic.add_pipeline(Box::new(PriorityPipeline(0)));
+        cyclic.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(0))).with_input("a", "tex").with_output("b"));
+        cyclic.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(0))).with_input("b", "tex").with_output("a"));
+        assert_eq!(cyclic.execution_order(), Err(RenderGraphError::Cycle(vec![1, 2])));
+    }
+
+    #[test]
+    fn test_remove_render_pipeline() {
+        let mut game_manager = GameManager::with_window(Box::new(ClosableWindow::default()), &window::window::WindowConfig::default());
+
+        let gameplay = game_manager.add_render_pipeline(Box::new(PriorityPipeline(0)));
+        let hud = game_manager.add_render_pipeline(Box::new(PriorityPipeline(1)));
+        assert_ne!(gameplay, hud);
+        assert_eq!(game_manager.get_active_render_pipeline(), Some(gameplay));
+
+        // The removed pipeline comes back, and the ones after it keep their ids.
+        let removed = game_manager.remove_render_pipeline(gameplay).unwrap();
+        assert_eq!(removed.priority(), 0);
+        assert!(game_manager.remove_render_pipeline(gameplay).is_none());
+        assert_eq!(game_manager.get_active_render_pipeline(), None);
+        assert_eq!(game_manager.get_render_graph_mut().index_of(hud), Some(0));
+
+        // The next pipeline added takes over, with a fresh id.
+        let menu = game_manager.add_render_pipeline(Box::new(PriorityPipeline(2)));
+        assert_ne!(menu, gameplay);
+        assert_eq!(game_manager.get_active_render_pipeline(), Some(menu));
+
+        // Removing a pipeline before the active one keeps it active.
+        game_manager.remove_render_pipeline(hud).unwrap();
+        assert_eq!(game_manager.get_active_render_pipeline(), Some(menu));
+        assert_eq!(game_manager.get_render_graph_mut().len(), 1);
+    }
+
+    #[test]
+    fn test_texture_sampling_from_json() {
+        let parse = |json: &str| parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap();
+
+        let defaults = TextureSampling::default();
+
+        // "filter" sets both filters, without mipmaps for magnification.
+        let sampling = TextureSampling::from_json(&parse(r#"{ "path": "a.png", "filter": "nearest_mipmap_linear" }"#), defaults);
+        assert_eq!(sampling.min_filter, TextureFilter::NearestMipmapLinear);
+        assert_eq!(sampling.mag_filter, TextureFilter::Nearest);
+        assert_eq!(sampling.wrap_s, TextureWrap::Repeat);
+
+        let sampling = TextureSampling::from_json(&parse(r#"{ "wrap": "mirrored", "wrap_t": "clamp_to_edge", "mag_filter": "nearest" }"#), defaults);
+        assert_eq!(sampling.wrap_s, TextureWrap::MirroredRepeat);
+        assert_eq!(sampling.wrap_t, TextureWrap::ClampToEdge);
+        assert_eq!(sampling.mag_filter, TextureFilter::Nearest);
+        assert_eq!(sampling.min_filter, defaults.min_filter);
+
+        // Unknown names keep the defaults.
+        assert_eq!(TextureSampling::from_json(&parse(r#"{ "filter": "blurry" }"#), defaults), defaults);
+
+        // Mipmap filters fall back to their base filter when mipmaps are off.
+        let sampling = TextureSampling::from_json(&parse(r#"{ "mipmaps": false, "anisotropy": 8 }"#), defaults);
+        assert_eq!(sampling.effective_min_filter(), TextureFilter::Linear);
+        assert_eq!(sampling.anisotropy, 8.0);
+        assert_eq!(defaults.effective_min_filter(), TextureFilter::LinearMipmapLinear);
+    }
+
+    #[test]
+    fn test_body_constant_acceleration() {
+        let mut body = Body2D::new(Vec2f::new(1.0, 2.0));
+        body.velocity = Vec2f::new(3.0, 0.0);
+        body.acceleration = Vec2f::new(0.0, -9.8);
+
+        let dt = 1.0 / 120.0;
+        let steps = 240;
+
+        for _ in 0..steps {
+            body.integrate(dt);
+        }
+
+        // Semi-implicit Euler overshoots x = x0 + v0 t + a t^2 / 2 by a t dt / 2.
+        let t = dt * steps as f32;
+        let expected_y = 2.0 - 0.5 * 9.8 * t * t;
+        assert!((body.position.x - 7.0).abs() < 1e-3);
+        assert!((body.position.y - expected_y).abs() < 9.8 * t * dt);
+        assert!((body.velocity.y + 9.8 * t).abs() < 1e-3);
+
+        // Drag slows the body down without reversing it.
+        let mut dragged = Body2D::new(Vec2f::new(0.0, 0.0));
+        dragged.velocity = Vec2f::new(10.0, 0.0);
+        dragged.set_drag(2.0);
+        dragged.integrate(0.5);
+        assert!(dragged.velocity.x > 0.0 && dragged.velocity.x < 10.0);
+    }
+
+    /// A window which only tracks whether it was closed, so the manager can run without GL.
+    #[derive(Default)]
+    struct ClosableWindow {
+        closed: bool
+    }
+
+    impl window::window::WindowControl for ClosableWindow {
+        fn update_window(&mut self) -> bool {
+            self.closed
+        }
+
+        fn close_window(&mut self) {
+            self.closed = true;
+        }
+
+        fn should_close(&self) -> bool {
+            self.closed
+        }
+
+        fn set_vsync(&self, _vsync: bool) {}
+
+        fn get_framebuffer_size(&self) -> (i32, i32) {
+            (0, 0)
+        }
+
+        fn get_msaa_samples(&self) -> i32 {
+            0
+        }
+
+        fn set_cursor_mode(&mut self, _mode: window::CursorMode) {}
+
+        fn get_cursor_mode(&self) -> window::CursorMode {
+            window::CursorMode::Normal
+        }
+    }
+
+    #[test]
+    fn test_request_close() {
+        let mut game_manager = GameManager::with_window(Box::new(ClosableWindow::default()), &window::window::WindowConfig::default());
+        assert!(!game_manager.should_close());
+
+        game_manager.request_close();
+        assert!(game_manager.should_close());
+        assert!(game_manager.update());
+    }
+
+    #[test]
+    fn test_in_memory_texture_data() {
+        // A 3 pixel wide RGB image has 9 byte rows, which break GL's default 4 byte row alignment.
+        let mut encoded = Vec::<u8>::new();
+        image::RgbImage::from_pixel(3, 2, image::Rgb([10, 20, 30]))
+            .write_to(&mut std::io::Cursor::new(&mut encoded), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let decoded = image::load_from_memory(&encoded).unwrap();
+        assert_eq!(decoded.color().channel_count(), 3);
+        assert_eq!(decoded.as_bytes().len(), 3 * 3 * 2);
+        assert_eq!(row_alignment(3 * 3), 1);
+
+        assert_eq!(row_alignment(3 * 4), 4);
+        assert_eq!(row_alignment(2 * 4), 8);
+        assert_eq!(row_alignment(3 * 2), 2);
+
+        assert!(validate_pixel_data(3, 2, 4, 24).is_ok());
+        assert!(matches!(validate_pixel_data(3, 2, 4, 23), Err(TextureError::DataSizeMismatch { expected: 24, actual: 23 })));
+        assert!(matches!(validate_pixel_data(0, 2, 4, 0), Err(TextureError::InvalidSize { .. })));
+
+        // Mismatched data is rejected before touching GL.
+        assert!(Texture::from_rgba8(3, 2, &[0u8; 12]).is_err());
+        assert!(matches!(Texture::from_encoded_bytes(&[1, 2, 3]), Err(TextureError::Image(_))));
+    }
+
+    #[test]
+    fn test_texture_formats() {
+        use ogl33::{GL_R8, GL_RED, GL_RGBA16F, GL_RGBA, GL_RG32F, GL_RG, GL_UNSIGNED_BYTE, GL_HALF_FLOAT, GL_FLOAT};
+
+        assert_eq!(TextureFormat::R8.bytes_per_pixel(), 1);
+        assert_eq!(TextureFormat::RGB8.bytes_per_pixel(), 3);
+        assert_eq!(TextureFormat::RGBA16F.bytes_per_pixel(), 8);
+        assert_eq!(TextureFormat::R32F.bytes_per_pixel(), 4);
+        assert_eq!(TextureFormat::RGBA32F.bytes_per_pixel(), 16);
+
+        assert_eq!(TextureFormat::R8.to_gl(), (GL_R8, GL_RED, GL_UNSIGNED_BYTE));
+        assert_eq!(TextureFormat::RGBA16F.to_gl(), (GL_RGBA16F, GL_RGBA, GL_HALF_FLOAT));
+        assert_eq!(TextureFormat::RG32F.to_gl(), (GL_RG32F, GL_RG, GL_FLOAT));
+
+        // The data is checked against the format's pixel size before touching GL.
+        let lut: Vec<u8> = [0.0f32, 0.5, 1.0].iter().flat_map(|value| value.to_ne_bytes()).collect();
+        assert!(matches!(Texture::from_bytes(4, 1, TextureFormat::R32F, &lut),
+            Err(TextureError::DataSizeMismatch { expected: 16, actual: 12 })));
+        assert!(matches!(Texture::from_bytes(2, 1, TextureFormat::RGBA8, &lut), Err(TextureError::DataSizeMismatch { .. })));
+        assert!(matches!(Texture::from_bytes(0, 1, TextureFormat::R8, &[]), Err(TextureError::InvalidSize { .. })));
+    }
+
+    struct NoisyPipeline {
+        priority: i32,
+        events: std::cell::RefCell<Vec<AudioEvent>>
+    }
+
+    impl RenderPipelineHandler for NoisyPipeline {
+        fn init(&mut self) {}
+        fn prepare(&self) {}
+        fn update(&mut self, _input: &Box<dyn window::window::MouseKeyboardInputControl>) {}
+        fn execute(&self) {}
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+
+        fn on_audio(&self) -> Vec<AudioEvent> {
+            self.events.borrow_mut().drain(..).collect()
+        }
+    }
+
+    #[test]
+    fn test_audio_events() {
+        let eat = std::sync::Arc::new(Sound::new(1, "sounds/eat.wav"));
+        let play_eat = AudioEvent::Play { sound: eat.clone(), volume: 0.5 };
+
+        let mut graph = RenderGraph::new();
+        graph.add_pipeline(Box::new(NoisyPipeline { priority: 1, events: std::cell::RefCell::new(vec![AudioEvent::StopAll]) }));
+        graph.add_pipeline(Box::new(PriorityPipeline(0)));
+        graph.add_pipeline(Box::new(NoisyPipeline { priority: 0, events: std::cell::RefCell::new(vec![play_eat.clone()]) }));
+
+        // Events are collected in render order, and each is only returned once.
+        assert_eq!(graph.audio_events(), vec![play_eat, AudioEvent::StopAll]);
+        assert!(graph.audio_events().is_empty());
+    }
+
+    #[test]
+    fn test_generated_texture_pixels() {
+        let magenta = Color::rgb(1.0, 0.0, 1.0).to_rgba8();
+        let black = Color::BLACK.to_rgba8();
+
+        assert_eq!(magenta, [255, 0, 255, 255]);
+        assert_eq!(Color::new(2.0, -1.0, 0.5, 1.0).to_rgba8(), [255, 0, 128, 255]);
+
+        // Odd sizes still alternate along each row and column.
+        let pixels = checkerboard_pixels(3, magenta, black);
+        assert_eq!(pixels.len(), 3 * 3 * 4);
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 3 + x) * 4..(y * 3 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), magenta);
+        assert_eq!(pixel(1, 0), black);
+        assert_eq!(pixel(2, 0), magenta);
+        assert_eq!(pixel(0, 1), black);
+        assert_eq!(pixel(1, 1), magenta);
+        assert_eq!(pixel(2, 2), magenta);
+    }
+
+    #[test]
+    fn test_oversized_image_fits_max_size() {
+        // Stands in for a low end GPU with a max texture size of 16.
+        let max_size = 16;
+        let oversized = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(64, 32, image::Rgba([255, 0, 0, 255])));
+
+        let fitted = fit_to_max_size(oversized.clone(), max_size, OversizePolicy::Downscale).unwrap();
+        assert_eq!(image::GenericImageView::dimensions(&fitted), (16, 8));
+        assert_eq!(fitted.color().channel_count(), 4);
+        assert_eq!(fitted.as_bytes().len(), 16 * 8 * 4);
+
+        // Very thin images keep at least one pixel.
+        let thin = image::DynamicImage::ImageRgb8(image::RgbImage::new(100, 1));
+        let fitted = fit_to_max_size(thin, max_size, OversizePolicy::Downscale).unwrap();
+        assert_eq!(image::GenericImageView::dimensions(&fitted), (16, 1));
+
+        assert!(matches!(fit_to_max_size(oversized, max_size, OversizePolicy::Reject),
+            Err(TextureError::TooLarge { width: 64, height: 32, max_size: 16 })));
+
+        // Images which fit are untouched either way.
+        let small = image::DynamicImage::ImageRgba8(image::RgbaImage::new(16, 4));
+        assert_eq!(image::GenericImageView::dimensions(&fit_to_max_size(small, max_size, OversizePolicy::Reject).unwrap()), (16, 4));
+
+        assert_eq!(Texture::oversize_policy(), OversizePolicy::Downscale);
+        Texture::set_oversize_policy(OversizePolicy::Reject);
+        assert_eq!(Texture::oversize_policy(), OversizePolicy::Reject);
+        Texture::set_oversize_policy(OversizePolicy::Downscale);
+    }
+
+    #[test]
+    fn test_atlas_regions() {
+        let parse = |json: &str| parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap();
+
+        let atlas = parse(r#"{ "image": "sprites.png", "regions": [
+            { "name": "head", "x": 0, "y": 0, "width": 16, "height": 16 },
+            { "name": "body", "x": 16, "y": 0, "width": 16, "height": 32 }
+        ] }"#);
+        let regions = parse_atlas_regions(&atlas, 32, 32).unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions["body"], Rect::new(16, 0, 16, 32));
+
+        // The body reaches past the bottom of a 32x16 image.
+        assert!(matches!(parse_atlas_regions(&atlas, 32, 16), Err(AtlasError::RegionOutOfBounds { ref name, .. }) if name == "body"));
+
+        let duplicate = parse(r#"{ "regions": [
+            { "name": "head", "x": 0, "y": 0, "width": 1, "height": 1 },
+            { "name": "head", "x": 1, "y": 0, "width": 1, "height": 1 }
+        ] }"#);
+        assert!(matches!(parse_atlas_regions(&duplicate, 2, 2), Err(AtlasError::DuplicateRegion(ref name)) if name == "head"));
+
+        let missing_field = parse(r#"{ "regions": [{ "name": "head", "x": 0, "y": 0, "width": 1 }] }"#);
+        assert!(matches!(parse_atlas_regions(&missing_field, 2, 2), Err(AtlasError::InvalidData(_))));
+
+        // Quad corners map to the region with v pointing down the texture.
+        let region = TextureRegion { texture: Texture::default(), uv_min: Vec2f::new(0.25, 0.0), uv_max: Vec2f::new(0.5, 0.5) };
+        assert_eq!(region.quad_uvs(), vec![0.25, 0.5, 0.25, 0.0, 0.5, 0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_shelf_packing() {
+        let sizes = [(16, 16), (8, 32), (16, 8), (30, 4)];
+        let (positions, width, height) = pack_rects(&sizes, 1, 4096, PackingAlgorithm::Shelf).unwrap();
+
+        assert!(width.is_power_of_two() && height <= width);
+
+        // Every rect, padded, lies inside the atlas without overlapping another.
+        let rects: Vec<(u32, u32, u32, u32)> = sizes.iter().zip(positions.iter())
+            .map(|((w, h), (x, y))| (*x, *y, w + 1, h + 1))
+            .collect();
+
+        for (index, a) in rects.iter().enumerate() {
+            assert!(a.0 + a.2 <= width && a.1 + a.3 <= height);
+
+            for b in rects.iter().skip(index + 1) {
+                assert!(a.0 + a.2 <= b.0 || b.0 + b.2 <= a.0 || a.1 + a.3 <= b.1 || b.1 + b.3 <= a.1, "{:?} overlaps {:?}", a, b);
+            }
+        }
+
+        // The tallest image starts the first shelf.
+        assert_eq!(positions[1], (0, 0));
+
+        assert!(pack_rects(&[(40, 40)], 0, 32, PackingAlgorithm::Shelf).is_none());
+        assert_eq!(pack_rects(&[], 0, 32, PackingAlgorithm::Shelf), Some((vec![], 1, 1)));
+    }
+
+    fn test_animation(durations: &[f32], mode: PlayMode) -> Animation {
+        let frames = durations.iter()
+            .map(|duration| AnimationFrame { region: TextureRegion::full(&Texture::default()), duration: *duration })
+            .collect();
+
+        Animation::new(frames, mode)
+    }
+
+    #[test]
+    fn test_animation_frame_boundaries() {
+        // Frames start at 0, 0.25, 0.75, and the cycle ends at 1.
+        let durations = [0.25, 0.5, 0.25];
+
+        let once = test_animation(&durations, PlayMode::Once);
+        assert_eq!(once.cycle_duration(), 1.0);
+        let frames: Vec<usize> = [0.0, 0.125, 0.25, 0.5, 0.75, 0.875, 1.0, 5.0].iter().map(|time| once.frame_at(*time)).collect();
+        assert_eq!(frames, vec![0, 0, 1, 1, 2, 2, 2, 2]);
+
+        let looping = test_animation(&durations, PlayMode::Loop);
+        let frames: Vec<usize> = [0.0, 0.25, 0.75, 1.0, 1.25, 2.75, 3.0].iter().map(|time| looping.frame_at(*time)).collect();
+        assert_eq!(frames, vec![0, 1, 2, 0, 1, 2, 0]);
+
+        // Ping pong plays 0 1 2 1 without repeating the ends, so the cycle is 1.5 seconds.
+        let ping_pong = test_animation(&durations, PlayMode::PingPong);
+        assert_eq!(ping_pong.cycle_duration(), 1.5);
+        let frames: Vec<usize> = [0.0, 0.25, 0.75, 1.0, 1.25, 1.5, 1.75].iter().map(|time| ping_pong.frame_at(*time)).collect();
+        assert_eq!(frames, vec![0, 1, 2, 1, 1, 0, 1]);
+
+        // Two frames simply alternate.
+        let two = test_animation(&[0.5, 0.5], PlayMode::PingPong);
+        assert_eq!([0.0, 0.5, 1.0].map(|time| two.frame_at(time)), [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_animation_player_events() {
+        let mut player = AnimationPlayer::new(test_animation(&[0.25, 0.25], PlayMode::Once));
+
+        player.update(0.0);
+        assert!(player.just_started() && !player.just_finished());
+        assert_eq!(player.current_frame(), 0);
+
+        player.update(0.25);
+        assert!(!player.just_started() && !player.just_finished());
+        assert_eq!(player.current_frame(), 1);
+
+        // Finishing lands exactly on the end, and holds the last frame.
+        player.update(0.25);
+        assert!(player.just_finished() && !player.is_playing());
+        assert_eq!(player.current_frame(), 1);
+
+        player.update(0.25);
+        assert!(!player.just_finished());
+
+        // Looping reports finished once per cycle, keeping the time past the end.
+        let mut looping = AnimationPlayer::new(test_animation(&[0.25, 0.25], PlayMode::Loop));
+        looping.update(0.375);
+        assert!(looping.just_started() && !looping.just_finished());
+        looping.update(0.25);
+        assert!(looping.just_finished() && looping.is_playing());
+        assert_eq!(looping.time(), 0.125);
+        assert_eq!(looping.current_frame(), 0);
+
+        looping.play();
+        looping.update(0.0);
+        assert!(looping.just_started());
+        assert!(looping.current_region().is_some());
+
+        // Without frames there's nothing to show, rather than a panic.
+        let mut empty = AnimationPlayer::new(test_animation(&[], PlayMode::Loop));
+        empty.update(0.25);
+        assert!(empty.current_region().is_none());
+    }
+
+    #[test]
+    fn test_animation_from_json() {
+        let parse = |json: &str| parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap();
+
+        let mut regions = std::collections::HashMap::new();
+        regions.insert("idle".to_string(), Rect::new(0, 0, 16, 16));
+        regions.insert("strip".to_string(), Rect::new(0, 16, 64, 32));
+        let atlas = TextureAtlas::new(Texture::default(), 64, 64, regions);
+
+        let grid = Animation::from_json(&parse(r#"{ "grid": { "region": "strip", "width": 16, "height": 16, "count": 4, "row": 1 },
+            "durations": [0.1, 0.2, 0.1, 0.2], "mode": "ping_pong" }"#), &atlas).unwrap();
+        assert_eq!(grid.mode(), PlayMode::PingPong);
+        assert_eq!(grid.frames().len(), 4);
+        assert_vec2_near(grid.frames()[1].region.uv_min, Vec2f::new(0.25, 0.5));
+        assert_vec2_near(grid.frames()[1].region.uv_max, Vec2f::new(0.5, 0.75));
+        assert_eq!(grid.frames()[3].duration, 0.2);
+
+        let named = Animation::from_json(&parse(r#"{ "frames": ["idle", "idle"], "duration": 0.5 }"#), &atlas).unwrap();
+        assert_eq!(named.mode(), PlayMode::Loop);
+        assert_eq!(named.cycle_duration(), 1.0);
+
+        // A fifth frame runs off the end of the strip.
+        assert!(matches!(Animation::from_json(&parse(r#"{ "grid": { "region": "strip", "width": 16, "height": 16, "count": 5 }, "duration": 0.1 }"#), &atlas),
+            Err(AtlasError::RegionOutOfBounds { .. })));
+        assert!(Animation::from_json(&parse(r#"{ "frames": ["walk"], "duration": 0.1 }"#), &atlas).is_err());
+        assert!(Animation::from_json(&parse(r#"{ "frames": ["idle"], "durations": [0.1, 0.1] }"#), &atlas).is_err());
+        assert!(Animation::from_json(&parse(r#"{ "frames": ["idle"], "duration": 0.1, "mode": "bounce" }"#), &atlas).is_err());
+    }
+
+    #[test]
+    fn test_texture_array_layers() {
+        let layer = |path: &str, width: u32, height: u32| (path.to_string(), (width, height));
+
+        assert_eq!(validate_layer_sizes(&[layer("grass.png", 16, 16), layer("water.png", 16, 16)]).unwrap(), (16, 16));
+        assert!(matches!(validate_layer_sizes(&[]), Err(TextureError::NoLayers)));
+
+        // The error names the first file which doesn't match.
+        let err = validate_layer_sizes(&[layer("grass.png", 16, 16), layer("water.png", 16, 16), layer("rock.png", 32, 16)]).unwrap_err();
+        assert!(matches!(err, TextureError::LayerSizeMismatch { ref path, expected: (16, 16), actual: (32, 16) } if path == "rock.png"));
+        assert!(err.to_string().contains("rock.png"));
+
+        // Layer indices are integer attributes advancing once per instance.
+        let mut attribs = RecordedAttribs::default();
+        bind_instance_attribute(&mut attribs, 2, 1, true);
+        bind_instance_attribute(&mut attribs, 3, 2, false);
+
+        assert_eq!(attribs.enabled, vec![2, 3]);
+        assert_eq!(attribs.int_pointers, vec![(2, 1)]);
+        assert_eq!(attribs.pointers, vec![(3, 2)]);
+        assert_eq!(attribs.divisors, vec![(2, 1), (3, 1)]);
+    }
+
+    /// Packs every size into a bin, returning None as soon as one doesn't fit.
+    fn pack_all(packer: &mut dyn RectPacker, sizes: &[(u32, u32)], bin: (u32, u32)) -> Option<Vec<Rect>> {
+        let rects: Option<Vec<Rect>> = sizes.iter().map(|(width, height)| packer.pack(*width, *height)).collect();
+
+        if let Some(rects) = &rects {
+            for (index, a) in rects.iter().enumerate() {
+                assert!(a.x + a.width <= bin.0 && a.y + a.height <= bin.1, "{:?} is outside the bin", a);
+                assert!(rects.iter().skip(index + 1).all(|b| !a.overlaps(b)), "{:?} overlaps another rect", a);
+            }
+        }
+
+        rects
+    }
+
+    #[test]
+    fn test_rect_packers() {
+        // A tall rect leaves a 4 wide column, which shelves can only use for a single row.
+        let sizes = [(6, 10), (4, 4), (4, 4), (4, 2)];
+
+        assert!(pack_all(&mut ShelfPacker::new(10, 10), &sizes, (10, 10)).is_none());
+
+        let rects = pack_all(&mut GuillotinePacker::new(10, 10), &sizes, (10, 10)).unwrap();
+        assert_eq!(rects[0], Rect::new(0, 0, 6, 10));
+        assert_eq!(rects[3], Rect::new(6, 8, 4, 2));
+
+        // Shelves fill rows left to right.
+        let rects = pack_all(&mut ShelfPacker::new(8, 8), &[(4, 4), (4, 2), (4, 4)], (8, 8)).unwrap();
+        assert_eq!(rects, vec![Rect::new(0, 0, 4, 4), Rect::new(4, 0, 4, 2), Rect::new(0, 4, 4, 4)]);
+
+        // The bin is filled exactly, then nothing else fits.
+        let mut guillotine = GuillotinePacker::new(8, 8);
+        assert!(pack_all(&mut guillotine, &[(4, 8), (4, 4), (4, 4)], (8, 8)).is_some());
+        assert!(guillotine.pack(1, 1).is_none());
+
+        // The atlas packs with the guillotine packer unless told otherwise.
+        assert_eq!(PackingAlgorithm::default(), PackingAlgorithm::Guillotine);
+        let sizes = [(16, 16), (8, 32), (16, 8), (30, 4)];
+        let (positions, width, height) = pack_rects(&sizes, 1, 4096, PackingAlgorithm::default()).unwrap();
+        let rects: Vec<Rect> = sizes.iter().zip(positions).map(|((w, h), (x, y))| Rect::new(x, y, w + 1, h + 1)).collect();
+        assert!(rects.iter().all(|rect| rect.x + rect.width <= width && rect.y + rect.height <= height));
+    }
+
+    #[test]
+    fn test_glyph_placement() {
+        use crate::font::{GlyphPlacer, ATLAS_PAGE_SIZE, GLYPH_PADDING};
+
+        // Glyphs sit side by side on a shelf, each inside its own padding.
+        let mut placer = GlyphPlacer::new();
+        assert_eq!(placer.place(10, 20), Some((0, GLYPH_PADDING, GLYPH_PADDING)));
+        assert_eq!(placer.place(6, 12), Some((0, 10 + GLYPH_PADDING * 3, GLYPH_PADDING)));
+        assert_eq!(placer.page_count(), 1);
+
+        // Four quarter page glyphs fill a page, so the next one starts another.
+        let quarter = ATLAS_PAGE_SIZE / 2 - GLYPH_PADDING * 2;
+        let mut placer = GlyphPlacer::new();
+        let pages: Vec<usize> = (0..5).map(|_| placer.place(quarter, quarter).unwrap().0).collect();
+        assert_eq!(pages, vec![0, 0, 0, 0, 1]);
+        assert_eq!(placer.place(quarter, quarter), Some((1, ATLAS_PAGE_SIZE / 2 + GLYPH_PADDING, GLYPH_PADDING)));
+
+        // A glyph larger than a page fails without adding an empty page.
+        assert_eq!(placer.place(ATLAS_PAGE_SIZE, 1), None);
+        assert_eq!(placer.page_count(), 2);
+    }
+
+    #[test]
+    fn test_font_atlas_per_size() {
+        use crate::font::{Font, GlyphAtlas, GlyphInfo};
+
+        let font_data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../res/fonts/DejaVuSansMono.ttf")).unwrap();
+        let mut font = Font::from_bytes(font_data).unwrap();
+
+        // Seed each size with an already rasterized glyph, so laying out doesn't need a GL context.
+        let small = GlyphInfo { page: Some(0), uv_min: Vec2f::ZERO, uv_max: Vec2f::new(0.25, 0.25), offset: Vec2f::new(1.0, 0.0), size: Vec2f::new(8.0, 10.0) };
+        let large = GlyphInfo { page: Some(2), uv_min: Vec2f::new(0.5, 0.5), uv_max: Vec2f::new(1.0, 1.0), offset: Vec2f::new(2.0, 0.0), size: Vec2f::new(16.0, 20.0) };
+
+        for (pixel_size, info) in [(16, small), (32, large)] {
+            font.atlases.entry(pixel_size).or_insert_with(|| GlyphAtlas::new(pixel_size)).glyphs.insert('A', info);
+        }
+
+        // Each size reads its own cached glyph.
+        let quads = font.layout_text("A A", 16);
+        assert_eq!(quads.len(), 2);
+        assert!(quads.iter().all(|quad| quad.page == 0 && quad.uv_max == small.uv_max && quad.max - quad.min == small.size));
+        assert_eq!(quads[0].min, small.offset);
+
+        let quads = font.layout_text("A", 32);
+        assert_eq!(quads.len(), 1);
+        assert_eq!((quads[0].page, quads[0].uv_min, quads[0].min), (2, large.uv_min, large.offset));
+
+        // A monospace font advances the same for every glyph, scaled with the pixel size.
+        let small_advance = font.layout_text("AA", 16)[1].min.x - small.offset.x;
+        let large_advance = font.layout_text("AA", 32)[1].min.x - large.offset.x;
+        assert!((large_advance - small_advance * 2.0).abs() < 1e-3);
+
+        // A new size gets its own atlas, which spaces don't add pages to.
+        assert!(font.layout_text(" ", 24).is_empty());
+        assert_eq!(font.atlases.len(), 3);
+        assert!(font.page_texture(24, 0).is_none());
+    }
+
+    #[test]
+    fn test_occlusion_query_slots() {
+        use crate::occlusion_query::QuerySlots;
+
+        let mut slots = QuerySlots::default();
+        let mut written = Vec::new();
+
+        for _frame in 0..3 {
+            let write = slots.write_index();
+
+            // Until this frame's query ends, the latest is the one written last frame.
+            if let Some(last_frame) = written.last() {
+                assert_eq!(slots.latest_index(), *last_frame);
+            }
+
+            written.push(write);
+            slots.advance();
+            assert_eq!(slots.latest_index(), write);
+        }
+
+        // The two queries are used in turn, so each is reused two frames after it was written.
+        assert_eq!(written, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_frame_time_average() {
+        let mut frame_time = FrameTimeAverage::new(0.2);
+        assert_eq!(frame_time.average(), 0.0);
+        assert_eq!(frame_time.fps(), 0.0);
+
+        // The first frame is taken as is.
+        frame_time.add(0.05);
+        assert_eq!(frame_time.average(), 0.05);
+
+        // Steady 60 fps frames with some jitter converge towards 1/60.
+        for frame in 0..200 {
+            let jitter = if frame % 2 == 0 { 0.002 } else { -0.002 };
+            frame_time.add(1.0 / 60.0 + jitter);
+        }
+        assert!((frame_time.average() - 1.0 / 60.0).abs() < 0.0005, "average was {}", frame_time.average());
+        assert!((frame_time.fps() - 60.0).abs() < 2.0, "fps was {}", frame_time.fps());
+
+        // A single slow frame only moves the average part of the way.
+        let before = frame_time.average();
+        frame_time.add(0.1);
+        assert!((frame_time.average() - (before + (0.1 - before) * 0.2)).abs() < 1e-6);
+
+        frame_time.reset();
+        assert_eq!(frame_time.average(), 0.0);
+    }
+
+    /// Records each uniform call instead of issuing it to GL. Locations are assigned in order of first lookup,
+    /// except for unknown uniforms, which are located at -1 like uniforms a program doesn't have.
+    #[derive(Default)]
+    struct RecordedUniforms {
+        unknown: Vec<&'static str>,
+        locations: std::cell::RefCell<Vec<String>>,
+        calls: std::cell::RefCell<Vec<(&'static str, i32)>>
+    }
+
+    impl RecordedUniforms {
+        fn record(&self, call: &'static str, location: i32) {
+            self.calls.borrow_mut().push((call, location));
+        }
+    }
+
+    impl ShaderUniforms for RecordedUniforms {
+        fn get_uniform_location(&self, uniform_name: &str) -> i32 {
+            if self.unknown.contains(&uniform_name) {
+                return -1;
+            }
+
+            let mut locations = self.locations.borrow_mut();
+
+            match locations.iter().position(|name| name == uniform_name) {
+                Some(location) => location as i32,
+                None => {
+                    locations.push(uniform_name.to_string());
+                    locations.len() as i32 - 1
+                }
+            }
+        }
+
+        fn load_float(&self, location: i32, _value: f32) { self.record("float", location) }
+        fn load_vec2(&self, location: i32, _value: Vec2f) { self.record("vec2", location) }
+        fn load_vec3(&self, location: i32, _value: Vec3f) { self.record("vec3", location) }
+        fn load_vec4(&self, location: i32, _value: Vec4f) { self.record("vec4", location) }
+        fn load_int(&self, location: i32, _value: i32) { self.record("int", location) }
+        fn load_float_array(&self, location: i32, _value: Vec<f32>) { self.record("float_array", location) }
+        fn load_integer_array(&self, location: i32, _value: Vec<i32>) { self.record("int_array", location) }
+        fn load_matrix22(&self, location: i32, _value: Mat22f) { self.record("mat2", location) }
+        fn load_matrix33(&self, location: i32, _value: Mat33f) { self.record("mat3", location) }
+        fn load_matrix44(&self, location: i32, _value: Mat44f) { self.record("mat4", location) }
+    }
+
+    #[test]
+    fn test_shader_uniforms_load_calls() {
+        let mut uniforms = UniformSet::new();
+        uniforms.set_int("guiTexture", 0)
+            .set_vec2("pos", Vec2f::new(1.0, 2.0))
+            .set_mat4("viewProjection", Mat44f::IDENTITY)
+            .set_float("time", 0.5)
+            .set_vec3("light", Vec3f::new(0.0, 1.0, 0.0))
+            .set_vec4("tint", Vec4f::new(1.0, 1.0, 1.0, 1.0))
+            .set_float_array("weights", vec![0.25, 0.5])
+            .set_int_array("layers", vec![0, 1])
+            .set_mat2("rotation", Mat22f::default())
+            .set_mat3("normal", Mat33f::default());
+
+        // Setting a uniform again replaces its value, keeping its place.
+        uniforms.set_vec2("pos", Vec2f::new(3.0, 4.0));
+        assert_eq!(uniforms.len(), 10);
+        assert_eq!(uniforms.get("pos"), Some(&UniformValue::Vec2(Vec2f::new(3.0, 4.0))));
+        assert_eq!(uniforms.get("missing"), None);
+
+        uniforms.set_color("tint", Color::new(0.5, 1.0, 0.5, 0.25));
+        assert_eq!(uniforms.get("tint"), Some(&UniformValue::Vec4(Vec4f::new(0.5, 1.0, 0.5, 0.25))));
+        assert_eq!(uniforms.len(), 10);
+
+        let loader = RecordedUniforms::default();
+        uniforms.load_into(&loader);
+
+        assert_eq!(loader.locations.borrow().clone(), vec!["guiTexture", "pos", "viewProjection", "time", "light", "tint", "weights", "layers", "rotation", "normal"]);
+        assert_eq!(loader.calls.borrow().clone(), vec![
+            ("int", 0), ("vec2", 1), ("mat4", 2), ("float", 3), ("vec3", 4),
+            ("vec4", 5), ("float_array", 6), ("int_array", 7), ("mat2", 8), ("mat3", 9)
+        ]);
+
+        // An empty collection loads nothing.
+        let loader = RecordedUniforms::default();
+        UniformSet::new().load_into(&loader);
+        assert!(loader.calls.borrow().is_empty());
+
+        // Loaders without GL state record a shadow sampler's unit like any other sampler.
+        loader.load_shadow_sampler(3, 1);
+        assert_eq!(loader.calls.borrow().clone(), vec![("int", 3)]);
+    }
+
+    /// Builds a DDS file holding the levels, described by a FourCC or, for "DX10", a DXGI format.
+    fn dds_file(width: u32, height: u32, four_cc: &[u8; 4], dxgi_format: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![0u8; 128];
+        bytes[0..4].copy_from_slice(b"DDS ");
+        bytes[4..8].copy_from_slice(&124u32.to_le_bytes());
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&(levels.len() as u32).to_le_bytes());
+        bytes[80..84].copy_from_slice(&4u32.to_le_bytes());
+        bytes[84..88].copy_from_slice(four_cc);
+
+        if four_cc == b"DX10" {
+            let dx10 = [dxgi_format, 3, 0, 1, 0];
+            bytes.extend(dx10.iter().flat_map(|word| word.to_le_bytes()));
+        }
+
+        bytes.extend(levels.iter().flatten());
+        bytes
+    }
+
+    /// Builds a little endian KTX 1 file holding the levels in the given GL format.
+    fn ktx_file(width: u32, height: u32, gl_format: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+        let header = [0x04030201, 0, 1, 0, gl_format, 0x1908, width, height, 0, 0, 1, levels.len() as u32, 4];
+        bytes.extend(header.iter().flat_map(|word: &u32| word.to_le_bytes()));
+
+        // Four bytes of key value data, which must be skipped.
+        bytes.extend([1, 2, 3, 4]);
+
+        for level in levels {
+            bytes.extend((level.len() as u32).to_le_bytes());
+            bytes.extend(level);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_compressed_containers() {
+        // An 8x8 BC1 image with its full mip chain: 2x2 blocks, then a single block for 4x4, 2x2 and 1x1.
+        let levels = vec![vec![1u8; 32], vec![2u8; 8], vec![3u8; 8], vec![4u8; 8]];
+
+        let dds = dds_file(8, 8, b"DXT1", 0, &levels);
+        assert_eq!(ContainerKind::detect("background.png", &dds[..12]), Some(ContainerKind::Dds));
+        let img = CompressedImage::parse(ContainerKind::Dds, &dds).unwrap();
+        assert_eq!((img.format, img.srgb, img.width, img.height), (CompressedFormat::Bc1, false, 8, 8));
+        assert_eq!(img.levels, levels);
+        assert_eq!(img.level_dimensions(3), (1, 1));
+        assert_eq!(img.byte_size(), 56);
+        assert_eq!(img.uncompressed_size().unwrap(), (64 + 16 + 4 + 1) * 4);
+
+        // DX10 headers give the format as a DXGI format, here BC7 sRGB.
+        let bc7_levels = vec![vec![5u8; 16]];
+        let img = CompressedImage::parse(ContainerKind::Dds, &dds_file(4, 4, b"DX10", 99, &bc7_levels)).unwrap();
+        assert_eq!((img.format, img.srgb), (CompressedFormat::Bc7, true));
+
+        // KTX stores each level behind its size. A 6x6 image rounds up to 2x2 blocks.
+        let bc3_levels = vec![vec![6u8; 64], vec![7u8; 16], vec![8u8; 16]];
+        let ktx = ktx_file(6, 6, 0x83F3, &bc3_levels);
+        assert_eq!(ContainerKind::detect("background", &ktx[..12]), Some(ContainerKind::Ktx));
+        let mut img = CompressedImage::parse(ContainerKind::Ktx, &ktx).unwrap();
+        assert_eq!((img.format, img.width, img.height), (CompressedFormat::Bc3, 6, 6));
+        assert_eq!(img.levels, bc3_levels);
+
+        // Oversized images drop their largest levels.
+        assert!(img.drop_levels_above(3));
+        assert_eq!((img.width, img.height, img.levels.len()), (3, 3, 2));
+        assert!(!img.clone().drop_levels_above(0));
+
+        // Detection falls back to the extension, and other files are left to the image decoders.
+        assert_eq!(ContainerKind::detect("art/sky.DDS", b"garbage"), Some(ContainerKind::Dds));
+        assert_eq!(ContainerKind::detect("art/sky.ktx", &[]), Some(ContainerKind::Ktx));
+        assert_eq!(ContainerKind::detect("art/sky.png", &[0x89, b'P', b'N', b'G']), None);
+
+        // Malformed files fail with a message.
+        let truncated = &dds[..dds.len() - 1];
+        assert!(matches!(CompressedImage::parse(ContainerKind::Dds, truncated), Err(TextureError::InvalidContainer(_))));
+        assert!(matches!(CompressedImage::parse(ContainerKind::Dds, &dds_file(8, 8, b"DXT3", 0, &levels)), Err(TextureError::InvalidContainer(_))));
+        assert!(matches!(CompressedImage::parse(ContainerKind::Ktx, &dds), Err(TextureError::InvalidContainer(_))));
+        assert!(matches!(CompressedImage::parse(ContainerKind::Ktx, &ktx_file(4, 4, 0x8058, &bc7_levels)), Err(TextureError::InvalidContainer(_))));
+        assert!(matches!(CompressedImage::parse(ContainerKind::Dds, &dds_file(0, 8, b"DXT1", 0, &levels)), Err(TextureError::InvalidSize { .. })));
+
+        // Sizes that overflow are rejected instead of wrapping.
+        assert!(matches!(CompressedFormat::Bc7.level_size(u32::MAX, u32::MAX), Err(TextureError::InvalidContainer(_))));
+        assert!(matches!(CompressedImage::parse(ContainerKind::Dds, &dds_file(u32::MAX, u32::MAX, b"DXT5", 0, &levels)), Err(TextureError::InvalidContainer(_))));
+        let huge = CompressedImage { format: CompressedFormat::Bc1, srgb: false, width: u32::MAX, height: u32::MAX, levels: vec![Vec::new(); 32] };
+        assert!(matches!(huge.uncompressed_size(), Err(TextureError::InvalidContainer(_))));
+    }
+
+    #[test]
+    fn test_block_decompression() {
+        // Pure red and pure blue endpoints, with the four palette entries in the first row.
+        let red = 0xF800u16.to_le_bytes();
+        let blue = 0x001Fu16.to_le_bytes();
+        let bc1 = [red[0], red[1], blue[0], blue[1], 0b11_10_01_00, 0, 0, 0];
+
+        let texels = decode_bc1_block(&bc1, false);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 255]);
+        assert_eq!(texels[2], [170, 0, 85, 255]);
+        assert_eq!(texels[3], [85, 0, 170, 255]);
+        assert_eq!(texels[4], [255, 0, 0, 255]);
+
+        // With the endpoints swapped the block has three colors and transparent black.
+        let bc1_alpha = [blue[0], blue[1], red[0], red[1], 0b11_10_01_00, 0, 0, 0];
+        let texels = decode_bc1_block(&bc1_alpha, false);
+        assert_eq!(texels[2], [127, 0, 127, 255]);
+        assert_eq!(texels[3], [0, 0, 0, 0]);
+
+        // BC3 alpha: the first texel uses alpha0, the second alpha1, and the third the first interpolated value.
+        let mut bc3 = [0u8; 16];
+        bc3[0] = 255;
+        bc3[1] = 0;
+        bc3[2] = 0b10_001_000;
+        bc3[8..16].copy_from_slice(&bc1);
+        let texels = decode_bc3_block(&bc3);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 0]);
+        assert_eq!(texels[2][3], 218);
+
+        // Images which aren't a multiple of the block size are cropped.
+        let img = CompressedImage { format: CompressedFormat::Bc1, srgb: false, width: 5, height: 3, levels: vec![[bc1, bc1].concat()] };
+        let pixels = img.decompress().unwrap();
+        assert_eq!(pixels.dimensions(), (5, 3));
+        assert_eq!(pixels.get_pixel(4, 0).0, [255, 0, 0, 255]);
+        assert_eq!(pixels.get_pixel(3, 0).0, [85, 0, 170, 255]);
+        assert_eq!(pixels.get_pixel(3, 2).0, [255, 0, 0, 255]);
+
+        let bc7 = CompressedImage { format: CompressedFormat::Bc7, srgb: false, width: 4, height: 4, levels: vec![vec![0; 16]] };
+        assert!(matches!(bc7.decompress(), Err(TextureError::UnsupportedCompression { format: CompressedFormat::Bc7, .. })));
+    }
+
+    #[test]
+    fn test_texture_dimensions() {
+        let texture = Texture::from_handle(GlHandle::new(1, |_| {}), 640, 360);
+        assert_eq!(texture.dimensions(), (640, 360));
+
+        // Clones share the size along with the GL texture.
+        assert_eq!(texture.clone().dimensions(), (640, 360));
+
+        // Textures which were never created have no size.
+        assert_eq!(Texture::default().dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_shader_uniforms_apply() {
+        let mut uniforms = UniformSet::new();
+        uniforms.set_mat4("viewProjection", Mat44f::IDENTITY)
+            .set_int("guiTexture", 0)
+            .set_vec4("tint", Vec4f::new(1.0, 0.5, 0.5, 1.0))
+            .set_float_array("weights", vec![0.5, 0.25, 0.25]);
+
+        let program = RecordedUniforms::default();
+        assert_eq!(uniforms.apply(&program), Ok(()));
+        assert_eq!(program.calls.borrow().clone(), vec![("mat4", 0), ("int", 1), ("vec4", 2), ("float_array", 3)]);
+
+        // Uniforms the program doesn't have are reported, while the rest still load.
+        let program = RecordedUniforms { unknown: vec!["tint", "weights"], ..Default::default() };
+        assert_eq!(uniforms.missing_uniforms(&program), vec!["tint".to_string(), "weights".to_string()]);
+        assert_eq!(uniforms.apply(&program), Err(UniformError::Missing(vec!["tint".to_string(), "weights".to_string()])));
+        assert_eq!(program.calls.borrow().clone(), vec![("mat4", 0), ("int", 1)]);
+    }
+
+    #[test]
+    fn test_bind_state_skips_redundant_binds() {
+        let mut state = BindState::default();
+
+        // Two pipelines drawing three sprites each, all with the same texture, alternating every frame.
+        for _ in 0..2 {
+            for program in [1, 2] {
+                assert!(state.use_program(program));
+                state.bind_vertex_array(5);
+
+                for _ in 0..3 {
+                    state.active_texture(0);
+                    state.bind_texture(10);
+                }
+            }
+        }
+
+        let stats = state.stats();
+        assert_eq!(stats.programs, BindCounter { requested: 4, issued: 4 });
+        assert_eq!(stats.vertex_arrays, BindCounter { requested: 4, issued: 1 });
+        assert_eq!(stats.textures, BindCounter { requested: 12, issued: 1 });
+        assert_eq!(stats.textures.skipped(), 11);
+
+        // Each unit keeps its own texture.
+        assert!(state.active_texture(1));
+        assert!(state.bind_texture(10));
+        assert!(state.active_texture(0));
+        assert!(!state.bind_texture(10));
+
+        // Deleting a bound texture or vertex array binds zero in its place, so a new object with the same name binds.
+        state.forget_texture(10);
+        assert!(state.bind_texture(10));
+        state.forget_vertex_array(5);
+        assert!(!state.bind_vertex_array(0));
+        assert!(state.bind_vertex_array(5));
+
+        // Deleted programs and invalidated state are unknown, so the next bind is always issued.
+        state.forget_program(2);
+        assert!(state.use_program(2));
+        assert!(!state.use_program(2));
+
+        state.invalidate();
+        assert!(state.use_program(2));
+        assert!(state.bind_vertex_array(5));
+        assert!(state.active_texture(0));
+        assert!(state.bind_texture(10));
+
+        // Invalidating keeps the counters, which are reset separately.
+        assert!(state.stats().programs.requested > 0);
+        state.reset_stats();
+        assert_eq!(state.stats(), Default::default());
+    }
+
+    #[test]
+    fn test_validate_spirv() {
+        let mut module: Vec<u8> = [0x0723_0203u32, 0x0001_0000, 0, 1, 0]
+            .iter()
+            .flat_map(|word| word.to_ne_bytes())
+            .collect();
+        assert!(validate_spirv(&module).is_ok());
+
+        assert!(matches!(validate_spirv(&module[..16]), Err(ShaderError::InvalidSpirv(_))));
+        assert!(matches!(validate_spirv(b"#version 330 core\nvoid main() {}"), Err(ShaderError::InvalidSpirv(_))));
+
+        module.push(0);
+        assert!(matches!(validate_spirv(&module), Err(ShaderError::InvalidSpirv(_))));
+
+        module.pop();
+        module[..4].reverse();
+        assert!(matches!(validate_spirv(&module), Err(ShaderError::InvalidSpirv(_))));
+    }
+
+    struct PassPipeline {
+        pass: RenderPass,
+        sort_key: f32,
+        priority: i32
+    }
+
+    impl PassPipeline {
+        fn boxed(pass: RenderPass, sort_key: f32, priority: i32) -> Box<PassPipeline> {
+            Box::new(PassPipeline { pass, sort_key, priority })
+        }
+    }
+
+    impl RenderPipelineHandler for PassPipeline {
+        fn init(&mut self) {}
+        fn prepare(&self) {}
+        fn update(&mut self, _input: &Box<dyn window::window::MouseKeyboardInputControl>) {}
+        fn execute(&self) {}
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+
+        fn render_pass(&self) -> RenderPass {
+            self.pass
+        }
+
+        fn sort_key(&self) -> f32 {
+            self.sort_key
+        }
+    }
+
+    #[test]
+    fn test_render_pass_order() {
+        let mut graph = RenderGraph::new();
+
+        let near_glass = graph.add_pipeline(PassPipeline::boxed(RenderPass::Transparent, 1.0, 0));
+        let far_wall = graph.add_pipeline(PassPipeline::boxed(RenderPass::Opaque, 10.0, 0));
+        let far_glass = graph.add_pipeline(PassPipeline::boxed(RenderPass::Transparent, 5.0, 0));
+        let near_wall = graph.add_pipeline(PassPipeline::boxed(RenderPass::Opaque, 2.0, 0));
+        let hud = graph.add_pipeline(PassPipeline::boxed(RenderPass::Transparent, 50.0, 1));
+        let floor = graph.add_pipeline(PassPipeline::boxed(RenderPass::Opaque, 2.0, 0));
+
+        // Opaque front to back, then transparent back to front. Priority still orders within a pass,
+        // and equal keys keep the order they were added.
+        assert_eq!(graph.execution_order(), Ok(vec![near_wall, floor, far_wall, far_glass, near_glass, hud]));
+
+        // Slots still come first: the opaque pipeline reads a transparent one's output.
+        let mut dependent = RenderGraph::new();
+        let composite = dependent.add_node(RenderGraphNode::new(PassPipeline::boxed(RenderPass::Opaque, 0.0, 0))
+            .with_input("scene", "sceneTexture"));
+        let scene = dependent.add_node(RenderGraphNode::new(PassPipeline::boxed(RenderPass::Transparent, 0.0, 0))
+            .with_output("scene"));
+        assert_eq!(dependent.execution_order(), Ok(vec![scene, composite]));
+    }
+
+    #[test]
+    fn test_screenshot_rows_and_results() {
+        // Three rows of two RGBA pixels, bottom row first as GL reads them.
+        let mut pixels: Vec<u8> = (0..24).collect();
+        flip_rows(&mut pixels, 8);
+        assert_eq!(&pixels[..8], &[16, 17, 18, 19, 20, 21, 22, 23]);
+        assert_eq!(&pixels[8..16], &[8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(&pixels[16..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let mut even: Vec<u8> = vec![1, 1, 2, 2];
+        flip_rows(&mut even, 2);
+        assert_eq!(even, vec![2, 2, 1, 1]);
+
+        // Whatever alpha the scene left behind is saved as opaque.
+        let mut translucent: Vec<u8> = vec![10, 20, 30, 0, 40, 50, 60, 128];
+        make_opaque(&mut translucent);
+        assert_eq!(translucent, vec![10, 20, 30, 255, 40, 50, 60, 255]);
+
+        // Nothing to read back, ex: a minimized window.
+        let (request, pending) = ScreenshotRequest::new(std::path::Path::new("unused.png"));
+        assert_eq!(pending.path(), std::path::Path::new("unused.png"));
+        request.save_in_background(None);
+        assert!(matches!(pending.wait(), Err(ScreenshotError::EmptyFramebuffer)));
+
+        let path = std::env::temp_dir().join(format!("screenshot_test_{}.png", std::process::id()));
+        let (request, pending) = ScreenshotRequest::new(&path);
+        let mut image = image::RgbaImage::new(2, 1);
+        image.put_pixel(1, 0, image::Rgba([255, 0, 0, 255]));
+        request.save_in_background(Some(image));
+
+        assert_eq!(pending.wait().unwrap(), path);
+        let saved = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(saved.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_generated_meshes() {
+        let quad = MeshData::quad();
+        assert_eq!(quad.components, 2);
+        assert_eq!(quad.vertices.len(), 12);
+        assert_eq!(Aabb::from_positions(&quad.vertices, 2), Aabb { min: Vec3f::new(-1.0, -1.0, 0.0), max: Vec3f::new(1.0, 1.0, 0.0) });
+
+        let circle = MeshData::circle(8);
+        let indices = circle.indices.clone().unwrap();
+        assert_eq!(circle.vertices.len(), (8 + 1) * 2);
+        assert_eq!(indices.len(), 8 * 3);
+        assert_eq!(&indices[..3], &[0, 1, 2]);
+        assert_eq!(&indices[21..], &[0, 8, 1]);
+
+        // The center is at the origin and every rim vertex is at radius 1.
+        assert_eq!(&circle.vertices[..2], &[0.0, 0.0]);
+        for rim in circle.vertices[2..].chunks(2) {
+            assert!(((rim[0] * rim[0] + rim[1] * rim[1]).sqrt() - 1.0).abs() < 1e-5);
+        }
+
+        assert_eq!(MeshData::circle(1).indices.unwrap().len(), 3 * 3);
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let mut scheduler = Scheduler::new();
+        let once_count = Rc::new(Cell::new(0));
+        let repeat_count = Rc::new(Cell::new(0));
+        let cancelled_count = Rc::new(Cell::new(0));
+
+        let counter = once_count.clone();
+        let once = scheduler.after(0.5, move || counter.set(counter.get() + 1));
+        let counter = repeat_count.clone();
+        let repeat = scheduler.every(0.25, move || counter.set(counter.get() + 1));
+        let counter = cancelled_count.clone();
+        let cancelled = scheduler.after(0.1, move || counter.set(counter.get() + 1));
+        cancelled.cancel();
+
+        // 1.25 seconds of frames, in steps which add up exactly in floating point.
+        for _ in 0..10 {
+            scheduler.update(0.125);
+        }
+
+        assert_eq!(once_count.get(), 1);
+        assert!(!once.is_active());
+        assert_eq!(repeat_count.get(), 5);
+        assert!(repeat.is_active());
+        assert_eq!(cancelled_count.get(), 0);
+        assert_eq!(scheduler.len(), 1);
+
+        // A long frame catches up on every interval that passed.
+        scheduler.update(1.0);
+        assert_eq!(repeat_count.get(), 9);
+
+        repeat.cancel();
+        scheduler.update(1.0);
+        assert_eq!(repeat_count.get(), 9);
+        assert!(scheduler.is_empty());
+        assert_eq!(once_count.get(), 1);
+    }
+
+    #[test]
+    fn test_gl_debug_messages() {
+        assert_eq!(gl_debug::gl_error_name(ogl33::GL_INVALID_OPERATION), "GL_INVALID_OPERATION");
+        assert_eq!(gl_debug::gl_error_name(0xFFFF), "unknown GL error");
+
+        assert_eq!(DebugSeverity::from_gl(0x9146), DebugSeverity::High);
+        assert_eq!(DebugSeverity::from_gl(0x826B), DebugSeverity::Notification);
+        assert!(DebugSeverity::High > DebugSeverity::Low);
+
+        assert_eq!(gl_debug::format_debug_message(DebugSeverity::High, true, 1282, "Invalid operation.\n"),
+            "[GL High error 1282] Invalid operation.");
+
+        // Without debug output enabled, checks don't touch GL, which isn't loaded in tests.
+        gl_debug::check_errors("glDrawArrays");
+    }
+
+    #[test]
+    fn test_frame_stats() {
+        assert_eq!(crate::gpu_timer::timestamp_delta_ms(1_000_000, 3_500_000), 2.5);
+        // A wrapped counter reads as no time rather than a huge one.
+        assert_eq!(crate::gpu_timer::timestamp_delta_ms(5, 1), 0.0);
+
+        let stats = crate::frame_time::FrameStats {
+            cpu_ms: 16.0,
+            gpu_ms: Some(6.0),
+            pipeline_gpu_ms: vec![Some(2.0), Some(1.5)]
+        };
+        assert_eq!(stats.pipeline_gpu_ms(1), Some(1.5));
+        assert_eq!(stats.pipeline_gpu_ms(2), None);
+        assert_eq!(stats.other_gpu_ms(), Some(2.5));
+
+        // Unmeasured times stay unknown rather than counting as zero.
+        let unsupported = crate::frame_time::FrameStats { cpu_ms: 16.0, gpu_ms: None, pipeline_gpu_ms: Vec::new() };
+        assert_eq!(unsupported.other_gpu_ms(), None);
+
+        let partial = crate::frame_time::FrameStats { pipeline_gpu_ms: vec![Some(2.0), None], ..stats };
+        assert_eq!(partial.other_gpu_ms(), None);
+    }
+
+    #[test]
+    fn test_save_writer() {
+        let directory = std::env::temp_dir().join(format!("core_engine_save_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let save_writer = crate::save::SaveWriter::new(2);
+
+        // More writes than the queue holds, with later writes to a path replacing earlier ones.
+        for index in 0..5 {
+            save_writer.save_state_async(directory.join(format!("save_{}.json", index % 3)), format!("{{\"slot\":{}}}", index).into_bytes()).unwrap();
+        }
+
+        save_writer.flush().unwrap();
+
+        assert_eq!(std::fs::read_to_string(directory.join("save_0.json")).unwrap(), "{\"slot\":3}");
+        assert_eq!(std::fs::read_to_string(directory.join("save_1.json")).unwrap(), "{\"slot\":4}");
+        assert_eq!(std::fs::read_to_string(directory.join("save_2.json")).unwrap(), "{\"slot\":2}");
+        assert!(!directory.join("save_0.json.tmp").exists());
+
+        // A failed write is reported by the next flush, and the flush after it is clean again.
+        let blocked = directory.join("save_0.json").join("nested.json");
+        save_writer.save_state_async(&blocked, b"{}".to_vec()).unwrap();
+        assert!(matches!(save_writer.flush(), Err(crate::save::SaveError::Io { path, .. }) if path == blocked));
+        assert!(save_writer.flush().is_ok());
+
+        save_writer.save_state_async(directory.join("last.json"), b"[]".to_vec()).unwrap();
+        save_writer.shutdown().unwrap();
+        assert_eq!(std::fs::read(directory.join("last.json")).unwrap(), b"[]");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_debug_render_mode_states() {
+        use crate::debug_render::DebugRenderMode;
+        use crate::render_state::{BlendMode, RenderState};
+
+        let state = RenderState {
+            blend: BlendMode::Opaque,
+            depth_test: true,
+            viewport: Some(ViewRect::Normalized { x: 0.0, y: 0.0, width: 0.5, height: 1.0 }),
+            ..RenderState::default()
+        };
+
+        assert_eq!(DebugRenderMode::Normal.adjust_render_state(state), state);
+        assert_eq!(DebugRenderMode::Wireframe.adjust_render_state(state), state);
+
+        // Overdraw adds every fragment, but still draws into the pipeline's viewport.
+        let overdraw = DebugRenderMode::Overdraw.adjust_render_state(state);
+        assert_eq!(overdraw.blend, BlendMode::Additive);
+        assert!(!overdraw.depth_test && !overdraw.depth_write);
+        assert_eq!(overdraw.viewport, state.viewport);
+        assert!(!crate::debug_render::overdraw_active());
+    }
+
+    #[test]
+    fn test_particle_system() {
+        use crate::particles::{ParticleSystem, vec2_floats, vec4_floats};
+
+        let mut particles = ParticleSystem::new(3);
+        particles.set_acceleration(Vec2f::new(0.0, -2.0));
+
+        assert!(particles.spawn(Vec2f::new(0.0, 0.0), Vec2f::new(1.0, 0.0), 1.0, Vec4f::new(1.0, 0.0, 0.0, 1.0)));
+        assert!(particles.spawn(Vec2f::new(5.0, 5.0), Vec2f::new(0.0, 0.0), 0.25, Vec4f::new(0.0, 1.0, 0.0, 1.0)));
+        assert!(particles.spawn(Vec2f::new(-1.0, 0.0), Vec2f::new(0.0, 1.0), 2.0, Vec4f::new(0.0, 0.0, 1.0, 1.0)));
+        // Full, and particles without a lifetime are never added.
+        assert!(!particles.spawn(Vec2f::new(0.0, 0.0), Vec2f::new(0.0, 0.0), 1.0, Vec4f::new(1.0, 1.0, 1.0, 1.0)));
+
+        particles.update(0.5);
+
+        // The second particle died and the last one was swapped into its slot, in every array.
+        assert_eq!(particles.len(), 2);
+        assert_eq!(particles.lifetimes(), &[0.5, 1.5]);
+        assert_eq!(particles.velocities(), &[Vec2f::new(1.0, -1.0), Vec2f::new(0.0, 0.0)]);
+        assert_eq!(particles.positions(), &[Vec2f::new(0.5, -0.5), Vec2f::new(-1.0, 0.0)]);
+        assert_eq!(particles.colors()[1], Vec4f::new(0.0, 0.0, 1.0, 1.0));
+
+        // The arrays are uploaded as is, one vec2 or vec4 per particle.
+        assert_eq!(vec2_floats(particles.positions()), &[0.5, -0.5, -1.0, 0.0]);
+        assert_eq!(vec4_floats(particles.colors()), &[1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0]);
+
+        particles.update(2.0);
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn test_blit_params() {
+        use crate::framebuffer::blit_params;
+
+        // Scaling into a rect offset in the destination copies only color, with the chosen filter.
+        let params = blit_params((8, 8), false, [10, 20, 3, 4], FilterMode::Linear);
+        assert_eq!((params.source, params.dest), ([0, 0, 8, 8], [10, 20, 13, 24]));
+        assert_eq!((params.mask, params.filter), (ogl33::GL_COLOR_BUFFER_BIT, ogl33::GL_LINEAR));
+        assert!(!params.read_resolved);
+
+        // Multisampled color is read directly at its own size, and resolved first when scaled.
+        assert!(!blit_params((8, 8), true, [0, 0, 8, 8], FilterMode::Nearest).read_resolved);
+        assert!(blit_params((8, 8), true, [0, 0, 4, 8], FilterMode::Nearest).read_resolved);
+        assert_eq!(blit_params((8, 8), true, [0, 0, 4, 8], FilterMode::Nearest).filter, ogl33::GL_NEAREST);
+    }
+
+    /// Needs a display for the GL context. Run with `cargo test -- --ignored test_framebuffer_blit`.
+    #[test]
+    #[ignore]
+    fn test_framebuffer_blit() {
+        let config = window::window::WindowConfig { xres: 64, yres: 64, title: "blit".to_string(), ..Default::default() };
+        let _window = window::window::GraphicsWindow::new(&config);
+
+        let source = Framebuffer::new(8, 8);
+        source.bind();
+
+        unsafe {
+            ogl33::glClearColor(1.0, 0.5, 0.0, 1.0);
+            ogl33::glClear(ogl33::GL_COLOR_BUFFER_BIT);
+        }
+
+        // Downsampling a single color keeps it whichever filter is used.
+        for (filter, size) in [(FilterMode::Nearest, 8), (FilterMode::Linear, 3)] {
+            let dest = Framebuffer::new(size, size);
+            source.blit_to(&dest, filter);
+
+            let image = capture_framebuffer(&dest).unwrap();
+            assert_eq!(image.dimensions(), (size, size));
+            assert!(image.pixels().all(|pixel| pixel.0 == [255, 128, 0, 255] || pixel.0 == [255, 127, 0, 255]));
+        }
+    }
+
+    /// Needs a display for the GL context. Run with `cargo test -- --ignored test_multisampled_resolve`.
+    #[test]
+    #[ignore]
+    fn test_multisampled_resolve() {
+        let config = window::window::WindowConfig { xres: 64, yres: 64, title: "msaa".to_string(), ..Default::default() };
+        let _window = window::window::GraphicsWindow::new(&config);
+
+        let multisampled = Framebuffer::multisampled(8, 8, 4);
+        assert!(multisampled.is_multisampled());
+        assert!(multisampled.samples() > 1);
+        assert!(multisampled.is_complete());
+
+        multisampled.bind();
+
+        unsafe {
+            ogl33::glClearColor(0.0, 1.0, 0.0, 1.0);
+            ogl33::glClear(ogl33::GL_COLOR_BUFFER_BIT);
+        }
+
+        // Capturing resolves into the single sampled target and reads that.
+        assert!(multisampled.resolve_target_id().is_some());
+        let image = capture_framebuffer(&multisampled).unwrap();
+        assert_eq!(image.dimensions(), (8, 8));
+        assert!(image.pixels().all(|pixel| pixel.0 == [0, 255, 0, 255]));
+    }
+
+    /// Needs a display for the GL context. Run with `cargo test -- --ignored test_uniform_readback`.
+    #[test]
+    #[ignore]
+    fn test_uniform_readback() {
+        let config = window::window::WindowConfig { xres: 64, yres: 64, title: "uniforms".to_string(), ..Default::default() };
+        let _window = window::window::GraphicsWindow::new(&config);
+
+        let shader = ShaderProgram::from_source(
+            "#version 330 core\nuniform vec2 offset; uniform int layer; void main() { gl_Position = vec4(offset, float(layer), 1.0); }",
+            "#version 330 core\nout vec4 color; void main() { color = vec4(1.0); }");
+        shader.bind();
+
+        let offset = shader.get_uniform_location("offset");
+        let layer = shader.get_uniform_location("layer");
+
+        shader.load_vec2(offset, Vec2f::new(0.25, -3.5));
+        shader.load_int(layer, 7);
+
+        assert_eq!(shader.get_uniform_vec2(offset), Vec2f::new(0.25, -3.5));
+        assert_eq!(shader.get_uniform_int(layer), 7);
+        // Missing uniforms read back as zero, the same as loads to them being ignored.
+        assert_eq!(shader.get_uniform_float(shader.get_uniform_location("missing")), 0.0);
+    }
+
+    /// Needs a display for the GL context. Run with `cargo test -- --ignored test_shadow_sampler`.
+    #[test]
+    #[ignore]
+    fn test_shadow_sampler() {
+        use crate::shader_program::SHADOW_SAMPLER_PARAMETERS;
+
+        let config = window::window::WindowConfig { xres: 64, yres: 64, title: "shadow".to_string(), ..Default::default() };
+        let _window = window::window::GraphicsWindow::new(&config);
+
+        let shader = ShaderProgram::from_source(
+            "#version 330 core\nvoid main() { gl_Position = vec4(0.0, 0.0, 0.0, 1.0); }",
+            "#version 330 core\nuniform sampler2DShadow shadowMap; out vec4 color; void main() { color = vec4(texture(shadowMap, vec3(0.5))); }");
+        shader.bind();
+
+        let shadow_map = Framebuffer::with_depth_texture(8, 8);
+        shadow_map.depth_texture().unwrap().bind(2);
+
+        // ShaderProgram's override switches the bound depth texture to comparisons, then points the uniform at it.
+        let location = shader.get_uniform_location("shadowMap");
+        shader.load_shadow_sampler(location, 2);
+        assert_eq!(shader.get_uniform_int(location), 2);
+
+        for (parameter, value) in SHADOW_SAMPLER_PARAMETERS {
+            let mut current = 0;
+            unsafe {
+                ogl33::glGetTexParameteriv(ogl33::GL_TEXTURE_2D, parameter, &mut current);
+            }
+
+            assert_eq!(current, value);
+        }
+    }
+
+    #[test]
+    fn test_uniform_matrix_order() {
+        use crate::shader_program::matrix44_from_uniform;
+
+        // Floats come back in the order the matrix was uploaded, one array of data per four.
+        let values: [f32; 16] = std::array::from_fn(|i| i as f32);
+        let matrix = matrix44_from_uniform(values);
+        assert_eq!(matrix.data[0], [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(matrix.data[1][0], 4.0);
+        assert_eq!(matrix.data[3], [12.0, 13.0, 14.0, 15.0]);
+
+        // Reading back what load_matrix44 uploads gives the same matrix.
+        let mut uploaded = Mat44f::IDENTITY;
+        uploaded.data[3] = [0.5, -2.0, 1.0, 1.0];
+        let floats: [f32; 16] = std::array::from_fn(|i| uploaded.data[i / 4][i % 4]);
+        assert_eq!(matrix44_from_uniform(floats).data, uploaded.data);
+    }
+
+    #[test]
+    fn test_resource_override_paths() {
+        let root = std::env::temp_dir().join(format!("core_engine_override_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let (base, first, second) = (root.join("res"), root.join("mod_a"), root.join("mod_b"));
+        for (directory, files) in [(&base, &["shaders/sprite.frag", "textures/head.png"][..]), (&first, &["shaders/sprite.frag"]), (&second, &["shaders/sprite.frag", "textures/head.png"])] {
+            for file in files {
+                std::fs::create_dir_all(directory.join(file).parent().unwrap()).unwrap();
+                std::fs::write(directory.join(file), b"").unwrap();
+            }
+        }
+
+        let path = |directory: &std::path::PathBuf| directory.to_string_lossy().into_owned();
+        let mut game_manager = GameManager::with_window(Box::new(ClosableWindow::default()), &window::window::WindowConfig::default());
+
+        let mut paths = crate::resource_paths::ResourcePaths::new(&path(&base));
+        assert_eq!(paths.resolve("textures/head.png"), path(&base) + "/textures/head.png");
+
+        // The first override holding a file shadows the base, missing files fall through to the next directory.
+        paths.add_override(&path(&first));
+        paths.add_override(&path(&second));
+        paths.add_override(&path(&first));
+        assert_eq!(paths.overrides().len(), 2);
+        assert_eq!(paths.resolve("shaders/sprite.frag"), path(&first) + "/shaders/sprite.frag");
+        assert_eq!(paths.resolve("textures/head.png"), path(&second) + "/textures/head.png");
+        assert_eq!(paths.resolve("textures/tail.png"), path(&base) + "/textures/tail.png");
+
+        // The manager resolves through the same overrides.
+        game_manager.add_resource_override_path(&path(&second));
+        assert_eq!(game_manager.resource_path("shaders/sprite.frag"), path(&second) + "/shaders/sprite.frag");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_register_all_from_directory() {
+        use crate::resource_manager::{LoadError, ResourceDestroy};
+
+        struct TextResource(String);
+        impl ResourceDestroy for TextResource {}
+
+        let root = std::env::temp_dir().join(format!("core_engine_register_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+
+        for (file, contents) in [("tex_snake_body.png", "body"), ("tex_snake_head.PNG", "head"), ("broken.png", ""),
+            ("notes.txt", "notes"), ("tex_snake_body.txt", "other body"), ("nested/tex_food.png", "food")] {
+            std::fs::write(root.join(file), contents).unwrap();
+        }
+
+        let loader = |path: &str| {
+            let contents = std::fs::read_to_string(path).map_err(|err| LoadError::resource(path, err))?;
+
+            match contents.is_empty() {
+                true => Err(LoadError::resource(path, "empty file")),
+                false => Ok(TextResource(contents))
+            }
+        };
+
+        // Only matching files directly in the directory are loaded, and failures are skipped.
+        let mut textures = ResourceManager::new("Textures");
+        let dir = root.to_string_lossy().into_owned();
+        assert_eq!(textures.register_all_from_directory(&dir, &["png"], loader).unwrap(), 2);
+        assert_eq!(textures.len(), 2);
+        assert_eq!(textures.get_registry("tex_snake_body").unwrap().0, "body");
+        assert_eq!(textures.get_registry("tex_snake_head").unwrap().0, "head");
+        assert!(!textures.contains_registry("broken") && !textures.contains_registry("tex_food"));
+
+        let mut everything = ResourceManager::new("Everything");
+        assert_eq!(everything.register_all_from_directory(&dir, &[], loader).unwrap(), 3);
+        assert!(everything.contains_registry("notes"));
+        assert_eq!(everything.get_registry("tex_snake_body").unwrap().0, "body");
+
+        // Names that are already registered are left alone.
+        assert_eq!(textures.register_all_from_directory(&dir, &[], loader).unwrap(), 1);
+        assert_eq!(textures.len(), 3);
+        assert_eq!(textures.get_registry("tex_snake_body").unwrap().0, "body");
+
+        assert!(matches!(textures.register_all_from_directory(&root.join("missing").to_string_lossy(), &[], loader),
+            Err(LoadError::Io { .. })));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_debug_overlay_stats() {
+        use crate::debug_overlay::{FrameHistory, FRAME_HISTORY_LEN, DebugOverlayStats, frame_time_color, frame_time_bar_height, overlay_text};
+
+        let mut history = FrameHistory::default();
+        assert_eq!(history.iter().count(), 0);
+
+        for frame in 0..FRAME_HISTORY_LEN + 5 {
+            history.push(frame as f32);
+        }
+
+        // The oldest frames are dropped once the history is full, and the rest come back oldest first.
+        assert_eq!(history.len(), FRAME_HISTORY_LEN);
+        assert_eq!(history.iter().next(), Some(5.0));
+        assert_eq!(history.iter().last(), Some((FRAME_HISTORY_LEN + 4) as f32));
+
+        assert_ne!(frame_time_color(10.0), frame_time_color(20.0));
+        assert_ne!(frame_time_color(20.0), frame_time_color(40.0));
+        assert_eq!(frame_time_color(40.0), frame_time_color(100.0));
+
+        assert_eq!(frame_time_bar_height(0.0), 0.0);
+        assert!(frame_time_bar_height(10.0) < frame_time_bar_height(20.0));
+        // Long frames are clipped to the top of the graph.
+        assert_eq!(frame_time_bar_height(100.0), frame_time_bar_height(1000.0));
+
+        let mut stats = DebugOverlayStats {
+            fps: 60.0,
+            frame: crate::frame_time::FrameStats { cpu_ms: 16.67, gpu_ms: Some(4.0), pipeline_gpu_ms: vec![Some(3.0), None] },
+            resource_counts: vec![("Textures".to_string(), 4), ("Shaders".to_string(), 2)],
+            ..DebugOverlayStats::default()
+        };
+        stats.binds.draw_calls = 12;
+        stats.binds.uniform_calls = 40;
+
+        let text = overlay_text(&stats);
+        assert!(text.starts_with("60 fps  16.67 ms\ngpu 4.00 ms\n  pipeline 0 3.00 ms\n"));
+        assert!(text.contains("draws 12  uniforms 40"));
+        assert!(text.ends_with("textures 4  shaders 2"));
+
+        stats.frame.gpu_ms = None;
+        assert!(overlay_text(&stats).contains("gpu n/a"));
+    }
+
+    #[test]
+    fn test_coordinate_systems() {
+        let to_ndc = |camera: &Camera2D, point: Vec2f| {
+            let clip = camera.view_projection() * Vec4f::new(point.x, point.y, 0.0, 1.0);
+            Vec2f::new(clip.x / clip.w, clip.y / clip.w)
+        };
+
+        let mut camera = Camera2D::new();
+        camera.resize(800.0, 600.0);
+        assert_eq!(camera.get_coordinate_system(), CoordSystem::CenterNdc);
+
+        // The origin is the center, and the top of the view is y = 1.
+        assert_vec2_near(to_ndc(&camera, Vec2f::ZERO), Vec2f::ZERO);
+        assert_vec2_near(to_ndc(&camera, Vec2f::new(0.0, 1.0)), Vec2f::new(0.0, 1.0));
+
+        camera.set_coordinate_system(CoordSystem::TopLeftPixels);
+
+        // Pixels map from the top left corner to the bottom right one, y down.
+        assert_vec2_near(to_ndc(&camera, Vec2f::ZERO), Vec2f::new(-1.0, 1.0));
+        assert_vec2_near(to_ndc(&camera, Vec2f::new(800.0, 600.0)), Vec2f::new(1.0, -1.0));
+        assert_vec2_near(to_ndc(&camera, Vec2f::new(400.0, 150.0)), Vec2f::new(0.0, 0.5));
+        assert_vec2_near(camera.world_to_screen(Vec2f::new(200.0, 100.0)), Vec2f::new(200.0, 100.0));
+
+        // Resizing keeps the coordinate system.
+        camera.resize(400.0, 300.0);
+        assert_vec2_near(to_ndc(&camera, Vec2f::new(400.0, 300.0)), Vec2f::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn test_scissor_rect() {
+        let rect = Rect2i::new(10, 20, 300, 200);
+        assert_eq!(ViewRect::from(rect).to_pixels(800, 600), (10, 20, 300, 200));
+
+        // A node's scissor replaces its render state's, and no scissor keeps the state as is.
+        let state = RenderState::default().with_scissor_rect(Some(rect));
+        assert_eq!(state.scissor, Some(ViewRect::Pixels { x: 10, y: 20, width: 300, height: 200 }));
+        assert_eq!(state.with_scissor_rect(None), state);
+        assert_eq!(RenderState::default().with_scissor_rect(None).scissor, None);
+
+        // Any pipeline can be scissored, the rect is kept by its node.
+        let mut graph = RenderGraph::new();
+        let plain = graph.add_node(RenderGraphNode::new(Box::new(PriorityPipeline(0))).with_scissor(rect));
+        let unscissored = graph.add_pipeline(Box::new(PriorityPipeline(1)));
+        assert_eq!(graph.get_node(plain).unwrap().scissor(), Some(rect));
+        assert_eq!(graph.get_node(unscissored).unwrap().scissor(), None);
+
+        graph.get_node_mut(plain).unwrap().set_scissor(None);
+        assert_eq!(graph.get_node(plain).unwrap().scissor(), None);
+    }
+
+    #[test]
+    fn test_particle_emitter() {
+        use crate::particle_emitter::{EmitterParams, Keyframes, ParticleEmitter};
+
+        let sizes = Keyframes::new(vec![(1.0, 0.0), (0.0, 2.0)]);
+        assert_eq!(sizes.sample(-1.0), 2.0);
+        assert_eq!(sizes.sample(0.25), 1.5);
+        assert_eq!(sizes.sample(2.0), 0.0);
+
+        let fade = Keyframes::constant(Vec4f::new(1.0, 1.0, 1.0, 1.0)).with_key(1.0, Vec4f::new(1.0, 1.0, 1.0, 0.0));
+        assert_eq!(fade.sample(0.5), Vec4f::new(1.0, 1.0, 1.0, 0.5));
+
+        let params = EmitterParams {
+            lifetime: (1.0, 2.0),
+            speed: (1.0, 3.0),
+            direction: 0.0,
+            spread: 0.5,
+            color: fade,
+            size: sizes,
+            ..EmitterParams::default()
+        };
+
+        // Emitters with the same seed spawn the same particles.
+        let mut emitter = ParticleEmitter::with_seed(params.clone(), 8, 42);
+        let mut replay = ParticleEmitter::with_seed(params, 8, 42);
+        emitter.set_position(Vec2f::new(1.0, 1.0));
+        replay.set_position(Vec2f::new(1.0, 1.0));
+
+        // The burst is capped by the live particle limit.
+        assert_eq!(emitter.burst(10), 8);
+        assert_eq!(replay.burst(10), 8);
+        assert_eq!(emitter.particles().velocities(), replay.particles().velocities());
+        assert_eq!(emitter.particles().lifetimes(), replay.particles().lifetimes());
+
+        for velocity in emitter.particles().velocities() {
+            let speed = velocity.x.hypot(velocity.y);
+            assert!((1.0..=3.0).contains(&speed));
+            // Inside the cone around +x.
+            assert!(velocity.y.atan2(velocity.x).abs() <= 0.5 + 1e-5);
+        }
+
+        // Fresh particles start at the first keys, and fade as they age.
+        assert!(emitter.particles().sizes().iter().all(|size| *size == 2.0));
+        emitter.update(0.5);
+        assert!(emitter.particles().colors().iter().all(|color| color.w < 1.0 && color.w > 0.0));
+
+        // Once the first particles die their slots are reused by the rate.
+        emitter.params_mut().rate = 10.0;
+        emitter.update(1.6);
+        assert_eq!(emitter.particles().len(), 8);
+        emitter.set_emitting(false);
+        emitter.update(2.0);
+        assert!(emitter.particles().is_empty());
+    }
+
+    #[test]
+    fn test_gaussian_kernel() {
+        use crate::render_pipeline::effects::{gaussian_kernel, MAX_BLUR_RADIUS};
+
+        let weights = gaussian_kernel(4, 2.0);
+        assert_eq!(weights.len(), 5);
+
+        // Every side texel is sampled twice, and the whole kernel keeps the image's brightness.
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(weights.windows(2).all(|pair| pair[0] > pair[1]));
+
+        // A wider sigma spreads the weight further from the center.
+        assert!(gaussian_kernel(4, 4.0)[0] < weights[0]);
+
+        // No radius, or no sigma, leaves the image unchanged.
+        assert_eq!(gaussian_kernel(0, 2.0), vec![1.0]);
+        assert!((gaussian_kernel(3, 0.0)[0] - 1.0).abs() < 1e-6);
+
+        assert_eq!(gaussian_kernel(100, 10.0).len(), MAX_BLUR_RADIUS as usize + 1);
+    }
+
+    #[test]
+    fn test_debug_draw_queue() {
+        use crate::debug_draw::{DebugDraw, grid_lines, CIRCLE_SEGMENTS, MAX_GRID_LINES};
+        use crate::color::Color;
+
+        // A disabled handle drops everything.
+        let disabled = DebugDraw::disabled();
+        disabled.line(Vec2f::ZERO, Vec2f::new(1.0, 0.0), Color::WHITE);
+        assert!(!disabled.is_enabled());
+
+        // Clones queue into the same frame.
+        let debug_draw = DebugDraw::enabled();
+        let clone = debug_draw.clone();
+        debug_draw.line(Vec2f::ZERO, Vec2f::new(1.0, 0.0), Color::rgb(1.0, 0.0, 0.0));
+        clone.rect(Vec2f::new(-1.0, -1.0), Vec2f::new(1.0, 1.0), Color::WHITE);
+        clone.circle(Vec2f::ZERO, 0.5, Color::WHITE);
+
+        let shapes = debug_draw.shapes.as_ref().unwrap();
+        assert_eq!(shapes.borrow().line_count(), 1 + 4 + CIRCLE_SEGMENTS as usize);
+        assert_eq!(&shapes.borrow().positions[..4], &[0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(&shapes.borrow().colors[..8], &[1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0]);
+
+        // The rectangle is closed and the circle ends where it starts.
+        let positions = shapes.borrow().positions.clone();
+        assert_eq!(&positions[4..6], &positions[18..20]);
+        assert!((positions[20] - positions[positions.len() - 2]).abs() < 1e-5);
+
+        // Grids wait for the view, then fill it.
+        debug_draw.grid_at(Vec2f::new(0.5, 0.5), 1.0, Color::WHITE);
+        let before = shapes.borrow().line_count();
+        shapes.borrow_mut().expand_grids(Vec2f::new(-2.0, -1.0), Vec2f::new(2.0, 1.0));
+        assert_eq!(shapes.borrow().line_count(), before + 4 + 2);
+
+        shapes.borrow_mut().clear();
+        assert_eq!(shapes.borrow().line_count(), 0);
+
+        let lines = grid_lines(Vec2f::ZERO, 0.5, Vec2f::new(-0.6, -0.2), Vec2f::new(0.6, 0.2));
+        assert_eq!(lines, vec![
+            (Vec2f::new(-0.5, -0.2), Vec2f::new(-0.5, 0.2)),
+            (Vec2f::new(0.0, -0.2), Vec2f::new(0.0, 0.2)),
+            (Vec2f::new(0.5, -0.2), Vec2f::new(0.5, 0.2)),
+            (Vec2f::new(-0.6, 0.0), Vec2f::new(0.6, 0.0))
+        ]);
+
+        // Grids too fine to read, or without a spacing, are skipped.
+        assert!(grid_lines(Vec2f::ZERO, 1.0 / MAX_GRID_LINES as f32, Vec2f::new(-1.0, -1.0), Vec2f::new(1.0, 1.0)).is_empty());
+        assert!(grid_lines(Vec2f::ZERO, 0.0, Vec2f::new(-1.0, -1.0), Vec2f::new(1.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_bloom_bright_pass() {
+        use crate::render_pipeline::effects::{bloom_bright_pass, bloom_target_size};
+
+        // Colors at or below the threshold don't glow.
+        assert_eq!(bloom_bright_pass(Vec3f::new(0.5, 0.5, 0.5), 0.8), Vec3f::ZERO);
+        assert_eq!(bloom_bright_pass(Vec3f::ZERO, 0.0), Vec3f::ZERO);
+
+        // Brighter colors keep their hue, scaled by how far past the threshold they are.
+        let glow = bloom_bright_pass(Vec3f::new(2.0, 1.0, 0.0), 0.5);
+        assert!((glow.x - glow.y * 2.0).abs() < 1e-5 && glow.z == 0.0);
+        assert!(glow.x < 2.0 && glow.x > 0.0);
+
+        // A threshold of zero keeps the whole color.
+        let white = bloom_bright_pass(Vec3f::new(1.0, 1.0, 1.0), 0.0);
+        assert!((white.x - 1.0).abs() < 1e-5);
+
+        assert_eq!(bloom_target_size(1920, 1080), (960, 540));
+        assert_eq!(bloom_target_size(1, 0), (1, 1));
+    }
+
+    #[test]
+    fn test_shader_warmup() {
+        use crate::shader_warmup::warmup_programs;
+        use crate::resource_manager::ResourceDestroy;
+
+        /// Stands in for a program the driver finishes after a number of polls, possibly with an error.
+        struct PendingProgram {
+            polls_left: Cell<u32>,
+            error: Option<&'static str>
+        }
+
+        impl ResourceDestroy for PendingProgram {}
+
+        let poll = |program: &PendingProgram| {
+            if program.polls_left.get() > 0 {
+                program.polls_left.set(program.polls_left.get() - 1);
+                return None;
+            }
+
+            match program.error {
+                Some(log) => Some(Err(ShaderError::Compile { stage: "fragment", log: log.to_string() })),
+                None => Some(Ok(()))
+            }
+        };
+
+        let mut shaders = ResourceManager::new("Shaders");
+        shaders.add_registry("shader_game", PendingProgram { polls_left: Cell::new(0), error: None });
+        shaders.add_registry("shader_post", PendingProgram { polls_left: Cell::new(3), error: None });
+        shaders.add_registry("shader_broken", PendingProgram { polls_left: Cell::new(1), error: Some("0:4: 'colr' undeclared") });
+        shaders.add_registry("shader_typo", PendingProgram { polls_left: Cell::new(0), error: Some("0:1: syntax error") });
+
+        // Every shader is waited on, even after one fails, and the failures come back together.
+        let mut reported = Vec::new();
+        let err = warmup_programs(shaders.iter(), poll, |finished, total| reported.push((finished, total))).unwrap_err();
+
+        assert_eq!(reported, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+        assert!(shaders.iter().all(|(_, program)| program.polls_left.get() == 0));
+
+        let failed: Vec<&str> = err.failures.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(failed, vec!["shader_typo", "shader_broken"]);
+        assert!(err.to_string().starts_with("2 shader(s) failed to build:"));
+        assert!(err.to_string().contains("shader_broken: fragment shader failed to compile: 0:4: 'colr' undeclared"));
+
+        let mut working = ResourceManager::new("Shaders");
+        working.add_registry("shader_game", PendingProgram { polls_left: Cell::new(2), error: None });
+        assert_eq!(warmup_programs(working.iter(), poll, |_, _| {}).unwrap(), 1);
+        assert_eq!(warmup_programs(ResourceManager::<PendingProgram>::new("Shaders").iter(), poll, |_, _| {}).unwrap(), 0);
+    }
+
+    /// Records the stencil calls instead of sending them to GL.
+    #[derive(Default)]
+    struct RecordedStencilCalls {
+        calls: Vec<String>
+    }
+
+    impl StencilCalls for RecordedStencilCalls {
+        fn stencil_func(&mut self, func: u32, reference: i32, read_mask: u32) {
+            self.calls.push(format!("glStencilFunc({:#x}, {}, {:#x})", func, reference, read_mask));
+        }
+
+        fn stencil_mask(&mut self, write_mask: u32) {
+            self.calls.push(format!("glStencilMask({:#x})", write_mask));
+        }
+
+        fn stencil_op(&mut self, fail: u32, depth_fail: u32, pass: u32) {
+            self.calls.push(format!("glStencilOp({:#x}, {:#x}, {:#x})", fail, depth_fail, pass));
+        }
+    }
+
+    #[test]
+    fn test_stencil_calls() {
+        use ogl33::{GL_ALWAYS, GL_EQUAL, GL_KEEP, GL_REPLACE, GL_ZERO};
+
+        // Drawing the panel shape marks its pixels with 1.
+        let mark = StencilState { pass: StencilOp::Replace, reference: 1, ..StencilState::default() };
+        let mut recorded = RecordedStencilCalls::default();
+        mark.apply_to(&mut recorded);
+
+        assert_eq!(recorded.calls, vec![
+            format!("glStencilFunc({:#x}, 1, 0xff)", GL_ALWAYS),
+            "glStencilMask(0xff)".to_string(),
+            format!("glStencilOp({:#x}, {:#x}, {:#x})", GL_KEEP, GL_KEEP, GL_REPLACE)
+        ]);
+
+        // Drawing inside it only passes on marked pixels, and leaves the mask alone.
+        let inside = StencilState { func: CompareFunc::Equal, reference: 1, write_mask: 0, fail: StencilOp::Zero, ..StencilState::default() };
+        let mut recorded = RecordedStencilCalls::default();
+        inside.apply_to(&mut recorded);
+
+        assert_eq!(recorded.calls, vec![
+            format!("glStencilFunc({:#x}, 1, 0xff)", GL_EQUAL),
+            "glStencilMask(0x0)".to_string(),
+            format!("glStencilOp({:#x}, {:#x}, {:#x})", GL_ZERO, GL_KEEP, GL_KEEP)
+        ]);
+
+        // The engine's stencil test only fills in for pipelines which don't set their own.
+        assert_eq!(RenderState::default().with_default_stencil(Some(inside)).stencil, Some(inside));
+        assert_eq!(RenderState { stencil: Some(mark), ..RenderState::default() }.with_default_stencil(Some(inside)).stencil, Some(mark));
+        assert_eq!(RenderState::default().with_default_stencil(None).stencil, None);
+
+        let mut game_manager = GameManager::with_window(Box::new(ClosableWindow::default()), &window::window::WindowConfig::default());
+        assert_eq!(game_manager.get_stencil_test(), None);
+        game_manager.set_stencil_test(Some(inside));
+        assert_eq!(game_manager.get_stencil_test(), Some(inside));
+    }
+}
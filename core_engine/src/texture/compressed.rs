@@ -0,0 +1,454 @@
+use ogl33::*;
+
+use super::TextureError;
+
+// S3TC and BPTC are extensions to GL 3.3, so ogl33 doesn't define their formats.
+const GL_COMPRESSED_RGBA_S3TC_DXT1_EXT: GLenum = 0x83F1;
+const GL_COMPRESSED_RGB_S3TC_DXT1_EXT: GLenum = 0x83F0;
+const GL_COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83F3;
+const GL_COMPRESSED_SRGB_S3TC_DXT1_EXT: GLenum = 0x8C4C;
+const GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: GLenum = 0x8C4D;
+const GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: GLenum = 0x8C4F;
+const GL_COMPRESSED_RGBA_BPTC_UNORM: GLenum = 0x8E8C;
+const GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM: GLenum = 0x8E8D;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const KTX_MAGIC: &[u8; 12] = &[0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+/// Size of the DDS magic and header, and of the extended header following it for DX10 formats.
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const KTX_HEADER_SIZE: usize = 64;
+
+/// Block compression formats, which store each 4x4 block of pixels in a fixed number of bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    /// 8 bytes per block, RGB with 1 bit alpha. Also known as DXT1.
+    Bc1,
+    /// 16 bytes per block, BC1 color with smooth alpha. Also known as DXT5.
+    Bc3,
+    /// 16 bytes per block, high quality RGBA.
+    Bc7
+}
+
+impl CompressedFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompressedFormat::Bc1 => "BC1",
+            CompressedFormat::Bc3 => "BC3",
+            CompressedFormat::Bc7 => "BC7"
+        }
+    }
+
+    pub fn block_bytes(self) -> usize {
+        match self {
+            CompressedFormat::Bc1 => 8,
+            CompressedFormat::Bc3 | CompressedFormat::Bc7 => 16
+        }
+    }
+
+    /// Returns the number of bytes of an image of the given size, which is rounded up to whole blocks.
+    /// Sizes too large to address are an InvalidContainer error, since they only come from malformed headers.
+    pub fn level_size(self, width: u32, height: u32) -> Result<usize, TextureError> {
+        let blocks_wide = width.div_ceil(4).max(1) as usize;
+        let blocks_high = height.div_ceil(4).max(1) as usize;
+
+        blocks_wide.checked_mul(blocks_high)
+            .and_then(|blocks| blocks.checked_mul(self.block_bytes()))
+            .ok_or_else(|| invalid(format!("a {}x{} image is too large", width, height)))
+    }
+
+    /// BC1 is uploaded with alpha, so blocks using its transparent color stay transparent.
+    pub fn to_gl(self, srgb: bool) -> GLenum {
+        match (self, srgb) {
+            (CompressedFormat::Bc1, false) => GL_COMPRESSED_RGBA_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc1, true) => GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc3, false) => GL_COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            (CompressedFormat::Bc3, true) => GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            (CompressedFormat::Bc7, false) => GL_COMPRESSED_RGBA_BPTC_UNORM,
+            (CompressedFormat::Bc7, true) => GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+        }
+    }
+
+    /// Reads a GL internal format, as stored by KTX files, returning the format and whether it's sRGB.
+    fn from_gl(format: GLenum) -> Option<(Self, bool)> {
+        match format {
+            GL_COMPRESSED_RGB_S3TC_DXT1_EXT | GL_COMPRESSED_RGBA_S3TC_DXT1_EXT => Some((CompressedFormat::Bc1, false)),
+            GL_COMPRESSED_SRGB_S3TC_DXT1_EXT | GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT => Some((CompressedFormat::Bc1, true)),
+            GL_COMPRESSED_RGBA_S3TC_DXT5_EXT => Some((CompressedFormat::Bc3, false)),
+            GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT => Some((CompressedFormat::Bc3, true)),
+            GL_COMPRESSED_RGBA_BPTC_UNORM => Some((CompressedFormat::Bc7, false)),
+            GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM => Some((CompressedFormat::Bc7, true)),
+            _ => None
+        }
+    }
+
+    /// Reads a DXGI format, as stored by the DX10 header of DDS files.
+    fn from_dxgi(format: u32) -> Option<(Self, bool)> {
+        match format {
+            71 => Some((CompressedFormat::Bc1, false)),
+            72 => Some((CompressedFormat::Bc1, true)),
+            77 => Some((CompressedFormat::Bc3, false)),
+            78 => Some((CompressedFormat::Bc3, true)),
+            98 => Some((CompressedFormat::Bc7, false)),
+            99 => Some((CompressedFormat::Bc7, true)),
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Display for CompressedFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Returns true when the driver lists the format among the compressed formats it accepts.
+pub fn is_format_supported(format: CompressedFormat) -> bool {
+    unsafe {
+        let mut count: GLint = 0;
+        glGetIntegerv(GL_NUM_COMPRESSED_TEXTURE_FORMATS, &mut count);
+
+        if count <= 0 {
+            return false;
+        }
+
+        let mut formats = vec![0 as GLint; count as usize];
+        glGetIntegerv(GL_COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr());
+
+        // Drivers tend to list only the linear variants, so those stand in for sRGB too.
+        formats.contains(&(format.to_gl(false) as GLint))
+    }
+}
+
+/// File formats holding compressed texture data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Dds,
+    /// KTX version 1.
+    Ktx
+}
+
+impl ContainerKind {
+    /// The number of bytes detect needs to recognize a file.
+    pub const MAGIC_LEN: usize = 12;
+
+    /// Detects the container from the first bytes of a file, falling back to the extension of its path.
+    /// Returns None for anything else, which is left to the image decoders.
+    pub fn detect(path: &str, header: &[u8]) -> Option<Self> {
+        if header.starts_with(DDS_MAGIC) {
+            return Some(ContainerKind::Dds);
+        }
+
+        if header.starts_with(KTX_MAGIC) {
+            return Some(ContainerKind::Ktx);
+        }
+
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "dds" => Some(ContainerKind::Dds),
+            "ktx" => Some(ContainerKind::Ktx),
+            _ => None
+        }
+    }
+}
+
+/// Block compressed pixels with their mip chain, as stored in a DDS or KTX file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    /// True when the file marks the data as sRGB.
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    /// The data of each mip level, starting with the full size image.
+    pub levels: Vec<Vec<u8>>
+}
+
+impl CompressedImage {
+    /// Reads the contents of a container file. Only 2D textures holding BC1, BC3, or BC7 data are accepted.
+    pub fn parse(kind: ContainerKind, bytes: &[u8]) -> Result<Self, TextureError> {
+        match kind {
+            ContainerKind::Dds => parse_dds(bytes),
+            ContainerKind::Ktx => parse_ktx(bytes)
+        }
+    }
+
+    /// Returns the size of a mip level, halving each level down to a minimum of 1.
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) {
+        mip_dimensions(self.width, self.height, level)
+    }
+
+    /// Returns the number of bytes of every mip level, the memory the texture takes on the GPU.
+    pub fn byte_size(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    /// Returns the number of bytes the same mip chain would take as uncompressed RGBA.
+    pub fn uncompressed_size(&self) -> Result<usize, TextureError> {
+        (0..self.levels.len())
+            .map(|level| self.level_dimensions(level))
+            .try_fold(0usize, |total, (width, height)| {
+                (width as usize).checked_mul(height as usize)
+                    .and_then(|pixels| pixels.checked_mul(4))
+                    .and_then(|bytes| total.checked_add(bytes))
+            })
+            .ok_or_else(|| invalid(format!("a {}x{} image is too large", self.width, self.height)))
+    }
+
+    /// Drops the largest mip levels until the image fits within max_size.
+    /// Returns false when even the smallest level stored is too large.
+    pub(crate) fn drop_levels_above(&mut self, max_size: u32) -> bool {
+        let fitting = (0..self.levels.len())
+            .find(|level| { let (width, height) = self.level_dimensions(*level); width <= max_size && height <= max_size });
+
+        match fitting {
+            Some(level) => {
+                let (width, height) = self.level_dimensions(level);
+                self.levels.drain(..level);
+                self.width = width;
+                self.height = height;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Decodes the full size level to RGBA, for GPUs which don't support the format.
+    /// BC7 has no CPU decoder.
+    pub fn decompress(&self) -> Result<image::RgbaImage, TextureError> {
+        let decode_block: fn(&[u8]) -> [[u8; 4]; 16] = match self.format {
+            CompressedFormat::Bc1 => |block| decode_bc1_block(block, false),
+            CompressedFormat::Bc3 => decode_bc3_block,
+            CompressedFormat::Bc7 => return Err(TextureError::UnsupportedCompression {
+                format: self.format,
+                reason: "the GPU doesn't support it and there's no CPU decoder for it"
+            })
+        };
+
+        let mut pixels = image::RgbaImage::new(self.width, self.height);
+        let blocks_wide = self.width.div_ceil(4).max(1);
+
+        for (index, block) in self.levels[0].chunks_exact(self.format.block_bytes()).enumerate() {
+            let (block_x, block_y) = (index as u32 % blocks_wide * 4, index as u32 / blocks_wide * 4);
+
+            for (texel, color) in decode_block(block).iter().enumerate() {
+                let (x, y) = (block_x + texel as u32 % 4, block_y + texel as u32 / 4);
+
+                // Blocks on the right and bottom edges cover pixels past the image.
+                if x < self.width && y < self.height {
+                    pixels.put_pixel(x, y, image::Rgba(*color));
+                }
+            }
+        }
+
+        Ok(pixels)
+    }
+}
+
+fn mip_dimensions(width: u32, height: u32, level: usize) -> (u32, u32) {
+    let shift = level.min(31) as u32;
+
+    ((width >> shift).max(1), (height >> shift).max(1))
+}
+
+fn invalid(message: impl Into<String>) -> TextureError {
+    TextureError::InvalidContainer(message.into())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, TextureError> {
+    bytes.get(offset..offset.saturating_add(4))
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .ok_or_else(|| invalid("the file ends within its header"))
+}
+
+/// Copies out the data of a mip level.
+fn read_level(bytes: &[u8], offset: usize, size: usize, level: usize) -> Result<Vec<u8>, TextureError> {
+    bytes.get(offset..offset.checked_add(size).ok_or_else(|| too_large(level))?)
+        .map(|data| data.to_vec())
+        .ok_or_else(|| invalid(format!("the file ends within mip level {}", level)))
+}
+
+fn too_large(level: usize) -> TextureError {
+    invalid(format!("mip level {} runs past the largest addressable offset", level))
+}
+
+fn check_size(width: u32, height: u32) -> Result<(), TextureError> {
+    if width == 0 || height == 0 {
+        return Err(TextureError::InvalidSize { width, height });
+    }
+
+    Ok(())
+}
+
+/// Reads a DDS file. Formats are given either by a DXT1/DXT5 FourCC, or by the DXGI format of a DX10 header.
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, TextureError> {
+    const DDPF_FOURCC: u32 = 0x4;
+    const DDS_DIMENSION_TEXTURE2D: u32 = 3;
+
+    if bytes.len() < DDS_HEADER_SIZE || !bytes.starts_with(DDS_MAGIC) {
+        return Err(invalid("not a DDS file"));
+    }
+
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let level_count = read_u32(bytes, 28)?.max(1) as usize;
+    let pixel_format_flags = read_u32(bytes, 80)?;
+    let four_cc = &bytes[84..88];
+
+    check_size(width, height)?;
+
+    if pixel_format_flags & DDPF_FOURCC == 0 {
+        return Err(invalid("DDS files must hold BC1, BC3, or BC7 data, found uncompressed pixels"));
+    }
+
+    let (format, srgb, data_start) = match four_cc {
+        b"DXT1" => (CompressedFormat::Bc1, false, DDS_HEADER_SIZE),
+        b"DXT5" => (CompressedFormat::Bc3, false, DDS_HEADER_SIZE),
+        b"DX10" => {
+            let dxgi_format = read_u32(bytes, DDS_HEADER_SIZE)?;
+            let dimension = read_u32(bytes, DDS_HEADER_SIZE + 4)?;
+            let array_size = read_u32(bytes, DDS_HEADER_SIZE + 12)?;
+
+            if dimension != DDS_DIMENSION_TEXTURE2D || array_size > 1 {
+                return Err(invalid("only 2D DDS textures are supported"));
+            }
+
+            let (format, srgb) = CompressedFormat::from_dxgi(dxgi_format)
+                .ok_or_else(|| invalid(format!("unsupported DXGI format {}, expected BC1, BC3, or BC7", dxgi_format)))?;
+
+            (format, srgb, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+        },
+        other => return Err(invalid(format!("unsupported DDS format {}, expected DXT1, DXT5, or a DX10 header",
+            String::from_utf8_lossy(other))))
+    };
+
+    // Levels follow each other without padding.
+    let mut offset = data_start;
+    let mut levels = Vec::with_capacity(level_count.min(32));
+
+    for level in 0..level_count {
+        let (level_width, level_height) = mip_dimensions(width, height, level);
+        let size = format.level_size(level_width, level_height)?;
+
+        levels.push(read_level(bytes, offset, size, level)?);
+        offset = offset.checked_add(size).ok_or_else(|| too_large(level))?;
+    }
+
+    Ok(CompressedImage { format, srgb, width, height, levels })
+}
+
+/// Reads a little endian KTX 1 file.
+fn parse_ktx(bytes: &[u8]) -> Result<CompressedImage, TextureError> {
+    const KTX_ENDIANNESS: u32 = 0x04030201;
+
+    if bytes.len() < KTX_HEADER_SIZE || !bytes.starts_with(KTX_MAGIC) {
+        return Err(invalid("not a KTX 1 file"));
+    }
+
+    if read_u32(bytes, 12)? != KTX_ENDIANNESS {
+        return Err(invalid("big endian KTX files aren't supported"));
+    }
+
+    let internal_format = read_u32(bytes, 28)?;
+    let width = read_u32(bytes, 36)?;
+    let height = read_u32(bytes, 40)?;
+    let depth = read_u32(bytes, 44)?;
+    let array_elements = read_u32(bytes, 48)?;
+    let faces = read_u32(bytes, 52)?;
+    let level_count = read_u32(bytes, 56)?.max(1) as usize;
+    let key_value_bytes = read_u32(bytes, 60)? as usize;
+
+    check_size(width, height)?;
+
+    if depth > 1 || array_elements > 0 || faces != 1 {
+        return Err(invalid("only 2D KTX textures are supported"));
+    }
+
+    let (format, srgb) = CompressedFormat::from_gl(internal_format)
+        .ok_or_else(|| invalid(format!("unsupported KTX format 0x{:04X}, expected BC1, BC3, or BC7", internal_format)))?;
+
+    // Each level is preceded by its size, and padded to 4 bytes.
+    let mut offset = KTX_HEADER_SIZE.checked_add(key_value_bytes).ok_or_else(|| invalid("the key value data is too large"))?;
+    let mut levels = Vec::with_capacity(level_count.min(32));
+
+    for level in 0..level_count {
+        let (level_width, level_height) = mip_dimensions(width, height, level);
+        let size = format.level_size(level_width, level_height)?;
+        let stored_size = read_u32(bytes, offset)? as usize;
+
+        if stored_size != size {
+            return Err(invalid(format!("mip level {} holds {} bytes, expected {}", level, stored_size, size)));
+        }
+
+        let data_start = offset.checked_add(4).ok_or_else(|| too_large(level))?;
+        levels.push(read_level(bytes, data_start, size, level)?);
+        offset = data_start.checked_add(size)
+            .and_then(|end| end.checked_next_multiple_of(4))
+            .ok_or_else(|| too_large(level))?;
+    }
+
+    Ok(CompressedImage { format, srgb, width, height, levels })
+}
+
+/// Expands a 5:6:5 color to 8 bits per channel.
+fn rgb565(color: u16) -> [u8; 3] {
+    let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+
+    [((r << 3) | (r >> 2)) as u8, ((g << 2) | (g >> 4)) as u8, ((b << 3) | (b >> 2)) as u8]
+}
+
+/// Decodes the 16 texels of a BC1 block, row by row. BC3 blocks always use four colors,
+/// while BC1 blocks with the first color not above the second use three and transparent black.
+pub(crate) fn decode_bc1_block(block: &[u8], four_colors: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let (c0, c1) = (rgb565(color0), rgb565(color1));
+    let mix = |weight0: u16, weight1: u16, total: u16| -> [u8; 4] {
+        let channel = |index: usize| ((c0[index] as u16 * weight0 + c1[index] as u16 * weight1) / total) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+
+    let palette = if four_colors || color0 > color1 {
+        [[c0[0], c0[1], c0[2], 255], [c1[0], c1[1], c1[2], 255], mix(2, 1, 3), mix(1, 2, 3)]
+    }
+    else {
+        [[c0[0], c0[1], c0[2], 255], [c1[0], c1[1], c1[2], 255], mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+
+    std::array::from_fn(|texel| palette[(indices >> (texel * 2)) as usize & 3])
+}
+
+/// Decodes the 16 texels of a BC3 block: interpolated alpha followed by a BC1 color block.
+pub(crate) fn decode_bc3_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let (alpha0, alpha1) = (block[0] as u16, block[1] as u16);
+    let indices = block[2..8].iter().rev().fold(0u64, |bits, byte| (bits << 8) | *byte as u64);
+
+    let mut palette = [0u8; 8];
+    palette[0] = alpha0 as u8;
+    palette[1] = alpha1 as u8;
+
+    if alpha0 > alpha1 {
+        for step in 1..7 {
+            palette[step as usize + 1] = (((7 - step) * alpha0 + step * alpha1) / 7) as u8;
+        }
+    }
+    else {
+        for step in 1..5 {
+            palette[step as usize + 1] = (((5 - step) * alpha0 + step * alpha1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut texels = decode_bc1_block(&block[8..16], true);
+
+    for (texel, color) in texels.iter_mut().enumerate() {
+        color[3] = palette[(indices >> (texel * 3)) as usize & 7];
+    }
+
+    texels
+}
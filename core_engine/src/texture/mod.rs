@@ -0,0 +1,819 @@
+use std::cell::Cell;
+
+use image::GenericImageView;
+use ogl33::*;
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::parser::JsonNode;
+
+use crate::bind_cache;
+use crate::color::Color;
+use crate::gl_debug;
+use crate::gl_handle::GlHandle;
+use crate::resource_manager::ResourceDestroy;
+
+pub mod packing;
+pub mod compressed;
+
+use compressed::{CompressedFormat, CompressedImage, ContainerKind};
+
+/// From EXT_texture_filter_anisotropic, which GL 3.3 doesn't include.
+pub(crate) const GL_TEXTURE_MAX_ANISOTROPY_EXT: GLenum = 0x84FE;
+const GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT: GLenum = 0x84FF;
+
+/// Returns the highest anisotropy the driver supports, or 1 when anisotropic filtering isn't available.
+pub fn max_anisotropy() -> f32 {
+    unsafe {
+        let mut extension_count: GLint = 0;
+        glGetIntegerv(GL_NUM_EXTENSIONS, &mut extension_count);
+
+        let supported = (0..extension_count.max(0) as GLuint).any(|index| {
+            let name = glGetStringi(GL_EXTENSIONS, index);
+
+            !name.is_null() && matches!(std::ffi::CStr::from_ptr(name as *const std::ffi::c_char).to_bytes(),
+                b"GL_EXT_texture_filter_anisotropic" | b"GL_ARB_texture_filter_anisotropic")
+        });
+
+        if !supported {
+            return 1.0;
+        }
+
+        let mut max_anisotropy: GLfloat = 1.0;
+        glGetFloatv(GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_anisotropy);
+        max_anisotropy.max(1.0)
+    }
+}
+
+/// Returns the largest width or height of a texture the GPU accepts.
+pub fn max_texture_size() -> u32 {
+    let mut max_size: GLint = 0;
+
+    unsafe {
+        glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut max_size);
+    }
+
+    max_size.max(0) as u32
+}
+
+/// What happens when an image loaded from a file or memory is larger than the GPU's max texture size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Scales the image down to fit, keeping its aspect ratio.
+    #[default]
+    Downscale,
+    /// Fails the load with TextureError::TooLarge.
+    Reject
+}
+
+thread_local! {
+    /// Textures are created on the thread owning the GL context, so the policy is kept per thread.
+    static OVERSIZE_POLICY: Cell<OversizePolicy> = Cell::new(OversizePolicy::default());
+
+    /// Whether compressed textures the GPU doesn't support are decompressed instead of failing to load.
+    static DECOMPRESS_UNSUPPORTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Applies the oversize policy to an image for a GPU with the given max texture size.
+/// Images which already fit are returned unchanged.
+pub(crate) fn fit_to_max_size(img: image::DynamicImage, max_size: u32, policy: OversizePolicy) -> Result<image::DynamicImage, TextureError> {
+    let (width, height) = img.dimensions();
+
+    if width <= max_size && height <= max_size {
+        return Ok(img);
+    }
+
+    match policy {
+        OversizePolicy::Reject => Err(TextureError::TooLarge { width, height, max_size }),
+        OversizePolicy::Downscale => {
+            let scale = max_size as f64 / width.max(height) as f64;
+            let scaled = |size: u32| ((size as f64 * scale).round() as u32).clamp(1, max_size);
+
+            println!("{}x{} image exceeds the max texture size of {}, downscaling", width, height, max_size);
+            Ok(img.resize_exact(scaled(width), scaled(height), image::imageops::FilterType::Triangle))
+        }
+    }
+}
+
+/// How texels are blended when a texture is drawn smaller (min) or larger (mag) than its size.
+/// Pixel art should use Nearest to stay crisp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear
+}
+
+impl TextureFilter {
+    pub fn to_gl(self) -> GLenum {
+        match self {
+            TextureFilter::Nearest => GL_NEAREST,
+            TextureFilter::Linear => GL_LINEAR,
+            TextureFilter::NearestMipmapNearest => GL_NEAREST_MIPMAP_NEAREST,
+            TextureFilter::LinearMipmapNearest => GL_LINEAR_MIPMAP_NEAREST,
+            TextureFilter::NearestMipmapLinear => GL_NEAREST_MIPMAP_LINEAR,
+            TextureFilter::LinearMipmapLinear => GL_LINEAR_MIPMAP_LINEAR
+        }
+    }
+
+    /// Parses the config name of the filter, ex: "nearest" or "linear_mipmap_linear".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nearest" => Some(TextureFilter::Nearest),
+            "linear" => Some(TextureFilter::Linear),
+            "nearest_mipmap_nearest" => Some(TextureFilter::NearestMipmapNearest),
+            "linear_mipmap_nearest" => Some(TextureFilter::LinearMipmapNearest),
+            "nearest_mipmap_linear" => Some(TextureFilter::NearestMipmapLinear),
+            "linear_mipmap_linear" => Some(TextureFilter::LinearMipmapLinear),
+            _ => None
+        }
+    }
+
+    pub fn uses_mipmaps(self) -> bool {
+        !matches!(self, TextureFilter::Nearest | TextureFilter::Linear)
+    }
+
+    /// The filter without mipmapping. Magnification never uses mipmaps, so mag filters are reduced to this.
+    pub fn without_mipmaps(self) -> Self {
+        match self {
+            TextureFilter::Nearest | TextureFilter::NearestMipmapNearest | TextureFilter::NearestMipmapLinear => TextureFilter::Nearest,
+            _ => TextureFilter::Linear
+        }
+    }
+}
+
+/// What happens to texture coordinates outside of 0..1 on an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat
+}
+
+impl TextureWrap {
+    pub fn to_gl(self) -> GLenum {
+        match self {
+            TextureWrap::ClampToEdge => GL_CLAMP_TO_EDGE,
+            TextureWrap::Repeat => GL_REPEAT,
+            TextureWrap::MirroredRepeat => GL_MIRRORED_REPEAT
+        }
+    }
+
+    /// Parses the config name of the wrap mode: "clamp_to_edge", "repeat", or "mirrored".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clamp_to_edge" => Some(TextureWrap::ClampToEdge),
+            "repeat" => Some(TextureWrap::Repeat),
+            "mirrored" => Some(TextureWrap::MirroredRepeat),
+            _ => None
+        }
+    }
+}
+
+/// The filters, wrap modes, and mipmapping of a texture.
+///
+/// Mipmaps stop scaled down sprites from shimmering, but they're only read by the mipmap min filters.
+/// Pixel art using the "nearest" filter never samples them, so turning mipmaps on globally leaves it crisp,
+/// it only costs the extra memory. Use "nearest_mipmap_nearest" for pixel art which is also drawn small.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureSampling {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    /// Generate mipmaps when the texture is uploaded. Without them, mipmap min filters fall back to their base filter.
+    pub mipmaps: bool,
+    /// Maximum degree of anisotropic filtering, 1 for none. Clamped to what the driver supports,
+    /// and ignored when anisotropic filtering isn't available.
+    pub anisotropy: f32
+}
+
+/// Smooth, mipmapped, and repeating, which is how textures were always loaded.
+impl Default for TextureSampling {
+    fn default() -> Self {
+        TextureSampling {
+            min_filter: TextureFilter::LinearMipmapLinear,
+            mag_filter: TextureFilter::Linear,
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            mipmaps: true,
+            anisotropy: 1.0
+        }
+    }
+}
+
+impl TextureSampling {
+    /// The min filter actually used, without mipmaps when they aren't generated.
+    pub fn effective_min_filter(&self) -> TextureFilter {
+        if self.mipmaps { self.min_filter } else { self.min_filter.without_mipmaps() }
+    }
+
+    /// Reads the sampling options of a texture entry, starting from the defaults.
+    /// "filter" sets both filters and "wrap" both axes, while "min_filter", "mag_filter", "wrap_s", and "wrap_t"
+    /// set one. "mipmaps" and "anisotropy" set the rest.
+    /// ex: { "filter": "nearest", "wrap_s": "repeat", "wrap_t": "clamp_to_edge", "mipmaps": false }
+    pub fn from_json(node: &JsonNode, defaults: TextureSampling) -> TextureSampling {
+        let mut sampling = defaults;
+
+        let object = match node {
+            JsonNode::Object(object) => object,
+            _ => return sampling
+        };
+
+        let filter = |key: &str| -> Option<TextureFilter> {
+            let name = object.get(key).and_then(String::from_json)?;
+            let filter = TextureFilter::from_name(&name);
+
+            if filter.is_none() {
+                println!("Unknown texture filter {} for {}", name, key);
+            }
+
+            filter
+        };
+
+        let wrap = |key: &str| -> Option<TextureWrap> {
+            let name = object.get(key).and_then(String::from_json)?;
+            let wrap = TextureWrap::from_name(&name);
+
+            if wrap.is_none() {
+                println!("Unknown texture wrap mode {} for {}", name, key);
+            }
+
+            wrap
+        };
+
+        if let Some(filter) = filter("filter") {
+            sampling.min_filter = filter;
+            sampling.mag_filter = filter.without_mipmaps();
+        }
+
+        if let Some(min_filter) = filter("min_filter") {
+            sampling.min_filter = min_filter;
+        }
+
+        if let Some(mag_filter) = filter("mag_filter") {
+            sampling.mag_filter = mag_filter.without_mipmaps();
+        }
+
+        if let Some(wrap) = wrap("wrap") {
+            sampling.wrap_s = wrap;
+            sampling.wrap_t = wrap;
+        }
+
+        if let Some(wrap_s) = wrap("wrap_s") {
+            sampling.wrap_s = wrap_s;
+        }
+
+        if let Some(wrap_t) = wrap("wrap_t") {
+            sampling.wrap_t = wrap_t;
+        }
+
+        if let Some(mipmaps) = object.get("mipmaps").and_then(bool::from_json) {
+            sampling.mipmaps = mipmaps;
+        }
+
+        if let Some(anisotropy) = object.get("anisotropy").and_then(f32::from_json) {
+            sampling.anisotropy = anisotropy.max(1.0);
+        }
+
+        sampling
+    }
+}
+
+/// Layout of uncompressed pixel data given to `Texture::from_bytes`, which is also how it's stored on the GPU.
+/// Channels are listed in order, 16F formats take half floats and 32F formats take f32s, both in native byte
+/// order, ex: from `f32::to_ne_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    R16F,
+    RG16F,
+    RGBA16F,
+    R32F,
+    RG32F,
+    RGBA32F
+}
+
+impl TextureFormat {
+    /// Returns the size of a pixel in bytes.
+    pub fn bytes_per_pixel(self) -> u32 {
+        let (channels, channel_bytes) = match self {
+            TextureFormat::R8 => (1, 1),
+            TextureFormat::RG8 => (2, 1),
+            TextureFormat::RGB8 => (3, 1),
+            TextureFormat::RGBA8 => (4, 1),
+            TextureFormat::R16F => (1, 2),
+            TextureFormat::RG16F => (2, 2),
+            TextureFormat::RGBA16F => (4, 2),
+            TextureFormat::R32F => (1, 4),
+            TextureFormat::RG32F => (2, 4),
+            TextureFormat::RGBA32F => (4, 4)
+        };
+
+        channels * channel_bytes
+    }
+
+    /// Returns the internal format, pixel format, and data type passed to glTexImage2D.
+    pub fn to_gl(self) -> (GLenum, GLenum, GLenum) {
+        match self {
+            TextureFormat::R8 => (GL_R8, GL_RED, GL_UNSIGNED_BYTE),
+            TextureFormat::RG8 => (GL_RG8, GL_RG, GL_UNSIGNED_BYTE),
+            TextureFormat::RGB8 => (GL_RGB8, GL_RGB, GL_UNSIGNED_BYTE),
+            TextureFormat::RGBA8 => (GL_RGBA8, GL_RGBA, GL_UNSIGNED_BYTE),
+            TextureFormat::R16F => (GL_R16F, GL_RED, GL_HALF_FLOAT),
+            TextureFormat::RG16F => (GL_RG16F, GL_RG, GL_HALF_FLOAT),
+            TextureFormat::RGBA16F => (GL_RGBA16F, GL_RGBA, GL_HALF_FLOAT),
+            TextureFormat::R32F => (GL_R32F, GL_RED, GL_FLOAT),
+            TextureFormat::RG32F => (GL_RG32F, GL_RG, GL_FLOAT),
+            TextureFormat::RGBA32F => (GL_RGBA32F, GL_RGBA, GL_FLOAT)
+        }
+    }
+}
+
+/// How a texture's pixels are stored on the GPU.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextureStorage {
+    /// 8 bits per channel, the default for textures decoded from images or created in code.
+    #[default]
+    Uncompressed,
+    /// Block compressed data uploaded as is, with the bytes it takes and would take uncompressed.
+    Compressed { format: CompressedFormat, bytes: usize, uncompressed_bytes: usize },
+    /// Block compressed data the GPU doesn't support, decompressed to RGBA when loaded.
+    Decompressed(CompressedFormat)
+}
+
+impl std::fmt::Display for TextureStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const MIB: f64 = 1024.0 * 1024.0;
+
+        match self {
+            TextureStorage::Uncompressed => write!(f, "uncompressed"),
+            TextureStorage::Compressed { format, bytes, uncompressed_bytes } =>
+                write!(f, "{} ({:.2} MiB, {:.2} MiB uncompressed)", format, *bytes as f64 / MIB, *uncompressed_bytes as f64 / MIB),
+            TextureStorage::Decompressed(format) => write!(f, "{} decompressed to RGBA", format)
+        }
+    }
+}
+
+/// A 2D texture. Clones share the same GL texture, which is deleted when the last clone is dropped.
+#[derive(Default, Clone)]
+pub struct Texture {
+    handle: GlHandle,
+    storage: TextureStorage,
+    /// Size of the base level in pixels, zero for textures created outside the engine.
+    width: u32,
+    height: u32
+}
+
+impl Texture {
+    pub fn texture_id(&self) -> u32 {
+        return self.handle.id();
+    }
+
+    /// Wraps a texture created elsewhere, with the size of its base level.
+    pub(crate) fn from_handle(handle: GlHandle, width: u32, height: u32) -> Self {
+        Texture { handle, storage: TextureStorage::Uncompressed, width, height }
+    }
+
+    /// Returns the width and height in pixels of the full size level, after any downscaling to fit the GPU.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns how the pixels are stored, ex: to confirm compressed textures are used.
+    pub fn storage(&self) -> TextureStorage {
+        self.storage
+    }
+
+    /// Returns true when both textures are clones of the same GL texture.
+    pub fn shares_handle(&self, other: &Texture) -> bool {
+        self.handle.ptr_eq(&other.handle)
+    }
+
+    /// Binds the texture to a specified index.
+    /// Does nothing when the texture is already bound to that index.
+    pub fn bind(&self, index: i32) {
+        bind_cache::active_texture(index as u32);
+        bind_cache::bind_texture_2d(self.handle.id());
+    }
+
+    /// Creates an empty RGBA texture which can be filled in later with glTexSubImage2D.
+    pub(crate) fn blank(width: u32, height: u32) -> Self {
+        unsafe {
+            let mut texture: GLuint = 0;
+            glGenTextures(1, &mut texture);
+            bind_cache::bind_texture_2d(texture);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as GLint);
+
+            glTexImage2D(GL_TEXTURE_2D, 0, GL_RGBA as GLint, 
+                width as i32, height as i32, 0, 
+                GL_RGBA, GL_UNSIGNED_BYTE, std::ptr::null());
+            gl_debug::check_errors("glTexImage2D");
+
+            bind_cache::bind_texture_2d(0);
+
+            Texture::from_handle(GlHandle::texture(texture), width, height)
+        }
+    }
+
+    /// Creates a 1x1 texture of a single color, ex: white for drawing untextured, tinted quads.
+    pub fn solid_color(color: Color) -> Self {
+        Self::upload(1, 1, 4, &color.to_rgba8(), false, PIXEL_SAMPLING)
+    }
+
+    /// Creates a size x size texture of alternating one pixel squares, starting with color_a in the first corner.
+    /// Drawn with nearest filtering so the squares stay sharp when scaled up.
+    pub fn checkerboard(size: u32, color_a: Color, color_b: Color) -> Self {
+        let pixels = checkerboard_pixels(size, color_a.to_rgba8(), color_b.to_rgba8());
+
+        Self::upload(size, size, 4, &pixels, false, PIXEL_SAMPLING)
+    }
+
+    /// Creates the classic "missing texture": a 2x2 magenta and black checkerboard.
+    /// Used in place of textures which couldn't be found or loaded.
+    pub fn missing() -> Self {
+        Self::checkerboard(2, Color::rgb(1.0, 0.0, 1.0), Color::BLACK)
+    }
+
+    /// Sets the min and mag filters. Mipmaps are generated when the min filter needs them,
+    /// and mipmapped mag filters are reduced to their base filter.
+    pub fn set_filter(&self, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        unsafe {
+            bind_cache::bind_texture_2d(self.handle.id());
+
+            if min_filter.uses_mipmaps() {
+                glGenerateMipmap(GL_TEXTURE_2D);
+            }
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, min_filter.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, mag_filter.without_mipmaps().to_gl() as GLint);
+            bind_cache::bind_texture_2d(0);
+        }
+    }
+
+    /// Sets the degree of anisotropic filtering, clamped to what the driver supports.
+    /// Does nothing when anisotropic filtering isn't available.
+    pub fn set_anisotropy(&self, anisotropy: f32) {
+        let max_anisotropy = max_anisotropy();
+
+        // Without the extension the parameter doesn't exist.
+        if max_anisotropy <= 1.0 {
+            return;
+        }
+
+        unsafe {
+            bind_cache::bind_texture_2d(self.handle.id());
+            glTexParameterf(GL_TEXTURE_2D, GL_TEXTURE_MAX_ANISOTROPY_EXT, anisotropy.clamp(1.0, max_anisotropy));
+            bind_cache::bind_texture_2d(0);
+        }
+    }
+
+    /// Regenerates the mipmaps from the base level, ex: after rendering into the texture.
+    pub fn generate_mipmaps(&self) {
+        unsafe {
+            bind_cache::bind_texture_2d(self.handle.id());
+            glGenerateMipmap(GL_TEXTURE_2D);
+            bind_cache::bind_texture_2d(0);
+        }
+    }
+
+    /// Sets the wrap mode of the horizontal (s) and vertical (t) axes.
+    pub fn set_wrap(&self, wrap_s: TextureWrap, wrap_t: TextureWrap) {
+        unsafe {
+            bind_cache::bind_texture_2d(self.handle.id());
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, wrap_s.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, wrap_t.to_gl() as GLint);
+            bind_cache::bind_texture_2d(0);
+        }
+    }
+
+    /// Sets the filters, wrap modes, and anisotropy together. Mipmaps are generated when sampling asks for them.
+    pub fn set_sampling(&self, sampling: TextureSampling) {
+        self.set_filter(sampling.effective_min_filter(), sampling.mag_filter);
+        self.set_wrap(sampling.wrap_s, sampling.wrap_t);
+
+        if sampling.anisotropy > 1.0 {
+            self.set_anisotropy(sampling.anisotropy);
+        }
+    }
+
+    /// Sets what happens to images larger than the GPU's max texture size, for textures created afterwards.
+    /// Defaults to downscaling them to fit, since low end GPUs may only support 4096 or less.
+    pub fn set_oversize_policy(policy: OversizePolicy) {
+        OVERSIZE_POLICY.with(|current| current.set(policy));
+    }
+
+    pub fn oversize_policy() -> OversizePolicy {
+        OVERSIZE_POLICY.with(|current| current.get())
+    }
+
+    /// When enabled, compressed textures in formats the GPU doesn't support are decompressed to RGBA
+    /// on the CPU, for textures loaded afterwards. Otherwise they fail with TextureError::UnsupportedCompression.
+    /// Only BC1 and BC3 can be decompressed.
+    pub fn set_decompress_unsupported(decompress: bool) {
+        DECOMPRESS_UNSUPPORTED.with(|current| current.set(decompress));
+    }
+
+    pub fn decompresses_unsupported() -> bool {
+        DECOMPRESS_UNSUPPORTED.with(|current| current.get())
+    }
+
+    /// Loads the texture from a file. DDS and KTX files holding BC1, BC3, or BC7 data are uploaded compressed,
+    /// along with the mip levels they store. Anything else is decoded as an image.
+    pub fn open(texture_path: &str) -> Result<Self, TextureError> {
+        Self::load(texture_path, false, TextureSampling::default())
+    }
+
+    /// Loads a color texture from a file, storing it as sRGB so sampling returns linear colors.
+    /// Data textures (normal maps, noise, etc.) should use `open` instead.
+    pub fn open_srgb(texture_path: &str) -> Result<Self, TextureError> {
+        Self::load(texture_path, true, TextureSampling::default())
+    }
+
+    /// Loads the texture from a file with the given filters and wrap modes, stored as sRGB when srgb is set.
+    pub fn open_with(texture_path: &str, srgb: bool, sampling: TextureSampling) -> Result<Self, TextureError> {
+        Self::load(texture_path, srgb, sampling)
+    }
+
+    /// Creates a texture from tightly packed RGBA pixels, 4 bytes each, starting at the top row.
+    pub fn from_rgba8(width: u32, height: u32, pixels: &[u8]) -> Result<Self, TextureError> {
+        validate_pixel_data(width, height, 4, pixels.len())?;
+
+        Ok(Self::upload(width, height, 4, pixels, false, TextureSampling::default()))
+    }
+
+    /// Creates a texture from tightly packed pixels generated at runtime, ex: noise maps, LUTs, or gradients.
+    /// The data starts at the top row. Uses the default sampling, which can be changed with set_sampling.
+    pub fn from_bytes(width: u32, height: u32, format: TextureFormat, data: &[u8]) -> Result<Self, TextureError> {
+        validate_pixel_data(width, height, format.bytes_per_pixel(), data.len())?;
+
+        Ok(Self::upload_format(width, height, format.to_gl(), format.bytes_per_pixel(), data, TextureSampling::default()))
+    }
+
+    /// Decodes an image file held in memory, ex: a PNG embedded with include_bytes!.
+    pub fn from_encoded_bytes(bytes: &[u8]) -> Result<Self, TextureError> {
+        let img = image::load_from_memory(bytes)?;
+
+        Self::from_image(img, false, TextureSampling::default())
+    }
+
+    /// Replaces part of the texture with tightly packed RGBA pixels, 4 bytes each.
+    /// The region must lie within the texture.
+    pub fn update_region(&self, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) -> Result<(), TextureError> {
+        validate_pixel_data(width, height, 4, pixels.len())?;
+
+        unsafe {
+            bind_cache::bind_texture_2d(self.handle.id());
+            glPixelStorei(GL_UNPACK_ALIGNMENT, row_alignment(width as usize * 4));
+            glTexSubImage2D(GL_TEXTURE_2D, 0, x as i32, y as i32,
+                width as i32, height as i32,
+                GL_RGBA, GL_UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 4);
+            bind_cache::bind_texture_2d(0);
+        }
+
+        Ok(())
+    }
+
+    fn load(texture_path: &str, srgb: bool, sampling: TextureSampling) -> Result<Self, TextureError> {
+        let mut header = [0u8; ContainerKind::MAGIC_LEN];
+        let header_len = std::fs::File::open(texture_path)
+            .and_then(|mut file| std::io::Read::read(&mut file, &mut header))
+            .map_err(image::ImageError::from)?;
+
+        if let Some(container) = ContainerKind::detect(texture_path, &header[..header_len]) {
+            let bytes = std::fs::read(texture_path).map_err(image::ImageError::from)?;
+
+            return Self::from_compressed(CompressedImage::parse(container, &bytes)?, srgb, sampling);
+        }
+
+        let img = image::open(texture_path)?;
+
+        Self::from_image(img, srgb, sampling)
+    }
+
+    /// Uploads block compressed data and its mip levels without decoding it. Data the GPU doesn't support is
+    /// decompressed when enabled with set_decompress_unsupported. Mipmaps can't be generated for compressed
+    /// textures, so mipmapped filters only apply when the data has its own mip levels.
+    pub fn from_compressed(mut img: CompressedImage, srgb: bool, sampling: TextureSampling) -> Result<Self, TextureError> {
+        let srgb = srgb || img.srgb;
+
+        if !compressed::is_format_supported(img.format) {
+            if !Self::decompresses_unsupported() {
+                return Err(TextureError::UnsupportedCompression { format: img.format, reason: "the GPU doesn't support it" });
+            }
+
+            let pixels = img.decompress()?;
+            let mut texture = Self::from_image(image::DynamicImage::ImageRgba8(pixels), srgb, sampling)?;
+            texture.storage = TextureStorage::Decompressed(img.format);
+
+            return Ok(texture);
+        }
+
+        let max_size = max_texture_size();
+
+        if img.width > max_size || img.height > max_size {
+            let (width, height) = (img.width, img.height);
+
+            if Self::oversize_policy() == OversizePolicy::Reject || !img.drop_levels_above(max_size) {
+                return Err(TextureError::TooLarge { width, height, max_size });
+            }
+        }
+
+        let min_filter = if img.levels.len() > 1 { sampling.min_filter } else { sampling.min_filter.without_mipmaps() };
+        let internal_format = img.format.to_gl(srgb);
+        let uncompressed_bytes = img.uncompressed_size()?;
+
+        unsafe {
+            let mut texture: GLuint = 0;
+            glGenTextures(1, &mut texture);
+            bind_cache::bind_texture_2d(texture);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, sampling.wrap_s.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, sampling.wrap_t.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, min_filter.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, sampling.mag_filter.without_mipmaps().to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAX_LEVEL, img.levels.len() as GLint - 1);
+
+            for (level, data) in img.levels.iter().enumerate() {
+                let (width, height) = img.level_dimensions(level);
+
+                glCompressedTexImage2D(GL_TEXTURE_2D, level as GLint, internal_format,
+                    width as i32, height as i32, 0,
+                    data.len() as i32, data.as_ptr() as *const c_void);
+            }
+            gl_debug::check_errors("glCompressedTexImage2D");
+
+            bind_cache::bind_texture_2d(0);
+
+            let mut texture = Texture::from_handle(GlHandle::texture(texture), img.width, img.height);
+            texture.storage = TextureStorage::Compressed {
+                format: img.format,
+                bytes: img.byte_size(),
+                uncompressed_bytes
+            };
+
+            if sampling.anisotropy > 1.0 {
+                texture.set_anisotropy(sampling.anisotropy);
+            }
+
+            Ok(texture)
+        }
+    }
+
+    /// Uploads a decoded image, applying the oversize policy first.
+    /// Images are converted to 8 bit RGBA when they have alpha and 8 bit RGB otherwise, ex: grayscale or 16 bit PNGs.
+    pub(crate) fn from_image(img: image::DynamicImage, srgb: bool, sampling: TextureSampling) -> Result<Self, TextureError> {
+        let img = fit_to_max_size(img, max_texture_size(), Self::oversize_policy())?;
+
+        if img.color().has_alpha() {
+            let pixels = img.to_rgba8();
+            Ok(Self::upload(pixels.width(), pixels.height(), 4, pixels.as_raw(), srgb, sampling))
+        }
+        else {
+            let pixels = img.to_rgb8();
+            Ok(Self::upload(pixels.width(), pixels.height(), 3, pixels.as_raw(), srgb, sampling))
+        }
+    }
+
+    /// Creates the texture from tightly packed 8 bit RGB or RGBA pixels.
+    fn upload(width: u32, height: u32, channels: u32, pixels: &[u8], srgb: bool, sampling: TextureSampling) -> Self {
+        let gl_format = match (channels, srgb) {
+            (4, true) => (GL_SRGB8_ALPHA8, GL_RGBA, GL_UNSIGNED_BYTE),
+            (4, false) => (GL_RGBA, GL_RGBA, GL_UNSIGNED_BYTE),
+            (_, true) => (GL_SRGB8, GL_RGB, GL_UNSIGNED_BYTE),
+            (_, false) => (GL_RGB, GL_RGB, GL_UNSIGNED_BYTE)
+        };
+
+        Self::upload_format(width, height, gl_format, channels, pixels, sampling)
+    }
+
+    /// Creates the texture from tightly packed pixels, given the internal format, pixel format, and data type.
+    fn upload_format(width: u32, height: u32, gl_format: (GLenum, GLenum, GLenum), bytes_per_pixel: u32,
+        pixels: &[u8], sampling: TextureSampling) -> Self {
+        let (internal_format, format, data_type) = gl_format;
+
+        unsafe {
+            let mut texture: GLuint = 0;
+            glGenTextures(1, &mut texture);
+            bind_cache::bind_texture_2d(texture);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, sampling.wrap_s.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, sampling.wrap_t.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, sampling.effective_min_filter().to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, sampling.mag_filter.without_mipmaps().to_gl() as GLint);
+
+            // GL expects rows to start on 4 byte boundaries, which RGB rows of odd widths don't.
+            glPixelStorei(GL_UNPACK_ALIGNMENT, row_alignment((width * bytes_per_pixel) as usize));
+            glTexImage2D(GL_TEXTURE_2D, 0, internal_format as GLint, 
+                width as i32, height as i32, 0, 
+                format, data_type, pixels.as_ptr() as *const c_void);
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 4);
+            gl_debug::check_errors("glTexImage2D");
+
+            if sampling.mipmaps {
+                glGenerateMipmap(GL_TEXTURE_2D);
+            }
+
+            bind_cache::bind_texture_2d(0);
+
+            let texture = Texture::from_handle(GlHandle::texture(texture), width, height);
+
+            if sampling.anisotropy > 1.0 {
+                texture.set_anisotropy(sampling.anisotropy);
+            }
+
+            texture
+        }
+    }
+}
+
+/// Sampling of generated textures, which are meant to be seen pixel for pixel.
+const PIXEL_SAMPLING: TextureSampling = TextureSampling {
+    min_filter: TextureFilter::Nearest,
+    mag_filter: TextureFilter::Nearest,
+    wrap_s: TextureWrap::Repeat,
+    wrap_t: TextureWrap::Repeat,
+    mipmaps: false,
+    anisotropy: 1.0
+};
+
+/// Generates the RGBA pixels of a size x size checkerboard of one pixel squares.
+pub(crate) fn checkerboard_pixels(size: u32, color_a: [u8; 4], color_b: [u8; 4]) -> Vec<u8> {
+    (0..size * size)
+        .flat_map(|index| if (index % size + index / size) % 2 == 0 { color_a } else { color_b })
+        .collect()
+}
+
+/// Returns the largest unpack alignment GL accepts (8, 4, 2, or 1) which rows of the given size in bytes satisfy.
+pub(crate) fn row_alignment(row_bytes: usize) -> GLint {
+    [8, 4, 2].into_iter().find(|alignment| row_bytes % alignment == 0).unwrap_or(1) as GLint
+}
+
+/// Checks that the pixel data holds exactly width * height pixels.
+pub(crate) fn validate_pixel_data(width: u32, height: u32, bytes_per_pixel: u32, len: usize) -> Result<(), TextureError> {
+    let expected = width as usize * height as usize * bytes_per_pixel as usize;
+
+    if width == 0 || height == 0 {
+        return Err(TextureError::InvalidSize { width, height });
+    }
+
+    if len != expected {
+        return Err(TextureError::DataSizeMismatch { expected, actual: len });
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+    /// The pixel data doesn't match the size of the texture.
+    DataSizeMismatch { expected: usize, actual: usize },
+    InvalidSize { width: u32, height: u32 },
+    /// The image is larger than the GPU's max texture size and the oversize policy rejects it.
+    TooLarge { width: u32, height: u32, max_size: u32 },
+    /// A layer of a texture array isn't the same size as the first layer.
+    LayerSizeMismatch { path: String, expected: (u32, u32), actual: (u32, u32) },
+    /// A texture array was given no layers.
+    NoLayers,
+    /// A DDS or KTX file is malformed, or holds data other than BC1, BC3, or BC7.
+    InvalidContainer(String),
+    /// Compressed data which can't be used, ex: the GPU lacks the format and decompressing isn't enabled.
+    UnsupportedCompression { format: CompressedFormat, reason: &'static str }
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Image(err) => write!(f, "{}", err),
+            TextureError::DataSizeMismatch { expected, actual } => write!(f, "expected {} bytes of pixel data, found {}", expected, actual),
+            TextureError::InvalidSize { width, height } => write!(f, "invalid texture size {}x{}", width, height),
+            TextureError::TooLarge { width, height, max_size } => write!(f, "{}x{} exceeds the max texture size of {}", width, height, max_size),
+            TextureError::LayerSizeMismatch { path, expected, actual } =>
+                write!(f, "{} is {}x{}, but every layer must match the first layer's {}x{}", path, actual.0, actual.1, expected.0, expected.1),
+            TextureError::NoLayers => write!(f, "a texture array needs at least one layer"),
+            TextureError::InvalidContainer(message) => write!(f, "invalid compressed texture: {}", message),
+            TextureError::UnsupportedCompression { format, reason } => write!(f, "can't load {} texture: {}", format, reason)
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<image::ImageError> for TextureError {
+    fn from(err: image::ImageError) -> Self {
+        TextureError::Image(err)
+    }
+}
+
+/// The texture is freed when the last handle is dropped.
+impl ResourceDestroy for Texture {}
\ No newline at end of file
@@ -0,0 +1,135 @@
+/// A rectangle of an image in pixels, with the origin at the top left like image files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Returns true when the rectangles share any pixels.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width && other.x < self.x + self.width &&
+            self.y < other.y + other.height && other.y < self.y + self.height
+    }
+}
+
+/// Places rectangles within a fixed size bin, one at a time, without moving those already placed.
+pub trait RectPacker {
+    /// Reserves space for a rectangle, returning where it was placed, or None when it doesn't fit.
+    fn pack(&mut self, width: u32, height: u32) -> Option<Rect>;
+}
+
+/// Which packer an atlas uses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PackingAlgorithm {
+    Shelf,
+    /// Wastes less space than shelves when rectangles vary in height.
+    #[default]
+    Guillotine
+}
+
+impl PackingAlgorithm {
+    pub fn packer(self, width: u32, height: u32) -> Box<dyn RectPacker> {
+        match self {
+            PackingAlgorithm::Shelf => Box::new(ShelfPacker::new(width, height)),
+            PackingAlgorithm::Guillotine => Box::new(GuillotinePacker::new(width, height))
+        }
+    }
+}
+
+/// Places rectangles left to right on the current shelf, starting a new shelf above the tallest
+/// rectangle of the current one once a rectangle doesn't fit in the row.
+/// Fast and simple, and packs well when rectangles are of similar height, ex: glyphs of a font.
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        ShelfPacker {
+            width,
+            height,
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0
+        }
+    }
+}
+
+impl RectPacker for ShelfPacker {
+    fn pack(&mut self, width: u32, height: u32) -> Option<Rect> {
+        if width > self.width || height > self.height {
+            return None;
+        }
+
+        // Start a new shelf when this row is full.
+        if self.shelf_x + width > self.width {
+            self.shelf_y += self.shelf_height;
+            self.shelf_x = 0;
+            self.shelf_height = 0;
+        }
+
+        if self.shelf_y + height > self.height {
+            return None;
+        }
+
+        let rect = Rect::new(self.shelf_x, self.shelf_y, width, height);
+        self.shelf_x += width;
+        self.shelf_height = u32::max(self.shelf_height, height);
+
+        Some(rect)
+    }
+}
+
+/// Keeps a list of free rectangles. Each rectangle is placed in the corner of the free rectangle it fits
+/// most tightly, and the space left over is cut into two new free rectangles.
+pub struct GuillotinePacker {
+    free: Vec<Rect>
+}
+
+impl GuillotinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        GuillotinePacker {
+            free: vec![Rect::new(0, 0, width, height)]
+        }
+    }
+}
+
+impl RectPacker for GuillotinePacker {
+    fn pack(&mut self, width: u32, height: u32) -> Option<Rect> {
+        let requested = Rect::new(0, 0, width, height).area();
+
+        let (index, _) = self.free.iter().enumerate()
+            .filter(|(_, free)| free.width >= width && free.height >= height)
+            .min_by_key(|(_, free)| free.area() - requested)?;
+
+        let free = self.free.swap_remove(index);
+        let (right_width, bottom_height) = (free.width - width, free.height - height);
+
+        // Cut along the shorter leftover side, so the larger leftover stays in one piece.
+        let (right, bottom) = if right_width < bottom_height {
+            (Rect::new(free.x + width, free.y, right_width, height), Rect::new(free.x, free.y + height, free.width, bottom_height))
+        }
+        else {
+            (Rect::new(free.x + width, free.y, right_width, free.height), Rect::new(free.x, free.y + height, width, bottom_height))
+        };
+
+        self.free.extend([right, bottom].into_iter().filter(|rect| rect.area() > 0));
+
+        Some(Rect::new(free.x, free.y, width, height))
+    }
+}
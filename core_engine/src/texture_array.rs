@@ -0,0 +1,131 @@
+use image::GenericImageView;
+use ogl33::*;
+
+use crate::bind_cache;
+use crate::gl_handle::GlHandle;
+use crate::resource_manager::ResourceDestroy;
+use crate::texture::{TextureError, TextureSampling, GL_TEXTURE_MAX_ANISOTROPY_EXT, max_anisotropy};
+
+/// A stack of equally sized 2D textures bound to a single unit, ex: every tile of a tile map,
+/// so a whole map can be drawn in one instanced draw call with a layer index per instance.
+///
+/// Shaders sample it with a sampler2DArray, passing the layer as the third coordinate:
+///
+/// uniform sampler2DArray tiles;
+/// flat in int layer;
+/// ...
+/// color = texture(tiles, vec3(texCoord, layer));
+///
+/// The layer is usually a per instance integer attribute, see `Mesh2D::add_instance_int_buffer`,
+/// declared as `in int layer` in the vertex shader and passed on as a flat varying.
+/// Clones share the same GL texture, which is deleted when the last clone is dropped.
+#[derive(Default, Clone)]
+pub struct TextureArray {
+    handle: GlHandle,
+    width: u32,
+    height: u32,
+    layers: u32
+}
+
+impl TextureArray {
+    /// Loads every file as one layer, in order, stored as sRGB when srgb is set.
+    /// Every image must be the same size as the first.
+    pub fn from_files(paths: &[&str], srgb: bool, sampling: TextureSampling) -> Result<Self, TextureError> {
+        let mut images = Vec::<image::RgbaImage>::new();
+        let mut sizes = Vec::<(String, (u32, u32))>::new();
+
+        for path in paths {
+            let img = image::open(path)?;
+            sizes.push((path.to_string(), img.dimensions()));
+            images.push(img.to_rgba8());
+        }
+
+        let (width, height) = validate_layer_sizes(&sizes)?;
+
+        Ok(Self::upload(width, height, &images, srgb, sampling))
+    }
+
+    /// Creates the array from RGBA images already checked to be width x height.
+    fn upload(width: u32, height: u32, images: &[image::RgbaImage], srgb: bool, sampling: TextureSampling) -> Self {
+        let internal_format = if srgb { GL_SRGB8_ALPHA8 } else { GL_RGBA };
+
+        unsafe {
+            let mut texture: GLuint = 0;
+            glGenTextures(1, &mut texture);
+            glBindTexture(GL_TEXTURE_2D_ARRAY, texture);
+
+            glTexParameteri(GL_TEXTURE_2D_ARRAY, GL_TEXTURE_WRAP_S, sampling.wrap_s.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D_ARRAY, GL_TEXTURE_WRAP_T, sampling.wrap_t.to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D_ARRAY, GL_TEXTURE_MIN_FILTER, sampling.effective_min_filter().to_gl() as GLint);
+            glTexParameteri(GL_TEXTURE_2D_ARRAY, GL_TEXTURE_MAG_FILTER, sampling.mag_filter.without_mipmaps().to_gl() as GLint);
+
+            // Allocate every layer, then fill them one at a time.
+            glTexImage3D(GL_TEXTURE_2D_ARRAY, 0, internal_format as GLint,
+                width as i32, height as i32, images.len() as i32, 0,
+                GL_RGBA, GL_UNSIGNED_BYTE, std::ptr::null());
+
+            for (layer, img) in images.iter().enumerate() {
+                glTexSubImage3D(GL_TEXTURE_2D_ARRAY, 0, 0, 0, layer as i32,
+                    width as i32, height as i32, 1,
+                    GL_RGBA, GL_UNSIGNED_BYTE, img.as_ptr() as *const c_void);
+            }
+
+            if sampling.mipmaps {
+                glGenerateMipmap(GL_TEXTURE_2D_ARRAY);
+            }
+
+            let max_anisotropy = max_anisotropy();
+
+            if sampling.anisotropy > 1.0 && max_anisotropy > 1.0 {
+                glTexParameterf(GL_TEXTURE_2D_ARRAY, GL_TEXTURE_MAX_ANISOTROPY_EXT, sampling.anisotropy.clamp(1.0, max_anisotropy));
+            }
+
+            glBindTexture(GL_TEXTURE_2D_ARRAY, 0);
+
+            TextureArray {
+                handle: GlHandle::texture(texture),
+                width,
+                height,
+                layers: images.len() as u32
+            }
+        }
+    }
+
+    pub fn texture_id(&self) -> u32 {
+        self.handle.id()
+    }
+
+    /// Binds the array to a texture unit, for a sampler2DArray uniform set to the same unit.
+    pub fn bind(&self, unit: i32) {
+        unsafe {
+            bind_cache::active_texture(unit as u32);
+            glBindTexture(GL_TEXTURE_2D_ARRAY, self.handle.id());
+        }
+    }
+
+    /// Size of every layer in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+}
+
+/// Checks that every layer, given as (path, size), matches the size of the first, which is returned.
+pub(crate) fn validate_layer_sizes(sizes: &[(String, (u32, u32))]) -> Result<(u32, u32), TextureError> {
+    let (_, expected) = sizes.first().ok_or(TextureError::NoLayers)?;
+
+    if expected.0 == 0 || expected.1 == 0 {
+        return Err(TextureError::InvalidSize { width: expected.0, height: expected.1 });
+    }
+
+    match sizes.iter().find(|(_, size)| size != expected) {
+        Some((path, actual)) => Err(TextureError::LayerSizeMismatch { path: path.clone(), expected: *expected, actual: *actual }),
+        None => Ok(*expected)
+    }
+}
+
+/// The texture is freed when the last handle is dropped.
+impl ResourceDestroy for TextureArray {}
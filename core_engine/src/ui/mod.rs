@@ -0,0 +1,3 @@
+pub(crate) mod nine_slice;
+
+pub use nine_slice::{NineSlice, SliceBorder, SliceCenter, SliceQuad, MAX_CENTER_TILES};
@@ -0,0 +1,321 @@
+use std::cell::OnceCell;
+
+use glmath::glmath::Vec2f;
+use serializers::json::deserialize::JsonDeserialize;
+use serializers::json::parser::JsonNode;
+
+use crate::atlas::{AtlasError, TextureAtlas, TextureRegion};
+use crate::mesh::{Mesh2D, DrawableMesh};
+use crate::shader_program::{ShaderProgram, ShaderUniforms};
+use crate::texture::Texture;
+
+/// Centers needing more tiles than this are stretched instead, so a tiny tile can't flood the sprite batch.
+pub const MAX_CENTER_TILES: usize = 256;
+
+/// One of the nine pieces of a nine slice, placed in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliceQuad {
+    /// Bottom left corner of the quad.
+    pub min: Vec2f,
+    /// Top right corner of the quad.
+    pub max: Vec2f,
+    /// Texture coordinates at the min and max corners, with v pointing down so (0, 0) is the top left of the image.
+    pub uv_min: Vec2f,
+    pub uv_max: Vec2f
+}
+
+/// Widths of a nine slice's borders in texture pixels, see `NineSlice::from_region`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SliceBorder {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32
+}
+
+impl SliceBorder {
+    /// The same width on every side.
+    pub fn uniform(width: u32) -> Self {
+        SliceBorder { left: width, right: width, top: width, bottom: width }
+    }
+
+    /// Reads either a single width for every side, or { "left": 4, "right": 4, "top": 4, "bottom": 4 }
+    /// where missing sides are 0.
+    pub fn from_json(node: &JsonNode) -> Option<Self> {
+        if let Some(width) = u32::from_json(node) {
+            return Some(SliceBorder::uniform(width));
+        }
+
+        match node {
+            JsonNode::Object(_) => {
+                let side = |name: &str| node.pointer(name).and_then(u32::from_json).unwrap_or(0);
+                Some(SliceBorder { left: side("left"), right: side("right"), top: side("top"), bottom: side("bottom") })
+            },
+            _ => None
+        }
+    }
+}
+
+/// How the middle of a nine slice fills the space between its edges.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SliceCenter {
+    /// One quad stretched over the whole center.
+    #[default]
+    Stretch,
+    /// The center repeated at its texture size from the top left, with the last row and column cut short.
+    Tile
+}
+
+/// A texture which can be drawn at any size without stretching its borders, for dialog boxes,
+/// buttons, and bars.
+///
+/// The texture, or a region of it, is cut into a 3x3 grid by the border widths. The corners keep their
+/// size, the edges stretch along one axis, and the center stretches or tiles, see `SliceCenter`.
+///
+/// Nine slices can be declared next to an atlas, see `NineSlice::from_json`, and drawn either with
+/// `render` or through `SpriteBatch::draw_nine_slice`. Like sprites they're laid out with y up.
+#[derive(Clone)]
+pub struct NineSlice {
+    pub texture: Texture,
+    /// Widths of the top, right, bottom, and left borders in UV space, as fractions of the region.
+    pub border: [f32; 4],
+    /// The world size of the whole region. Borders are drawn at the matching fraction of this size,
+    /// so they stay the same thickness whatever size the slice is rendered at.
+    pub texture_size: Vec2f,
+    /// Texture coordinates of the region the slice is cut from, the whole texture for `new`.
+    uv_min: Vec2f,
+    uv_max: Vec2f,
+    center: SliceCenter,
+    /// Created by the first `render`, slices drawn through a sprite batch never need it.
+    quad: OnceCell<Mesh2D>
+}
+
+impl NineSlice {
+    pub fn new(texture: Texture, border: [f32; 4], texture_size: Vec2f) -> Self {
+        NineSlice {
+            texture,
+            border,
+            texture_size,
+            uv_min: Vec2f::new(0.0, 0.0),
+            uv_max: Vec2f::new(1.0, 1.0),
+            center: SliceCenter::Stretch,
+            quad: OnceCell::new()
+        }
+    }
+
+    /// Creates a nine slice from a region `region_size` pixels large, drawn at one unit per pixel.
+    /// Borders are clamped to fit the region.
+    pub fn from_region(region: TextureRegion, region_size: (u32, u32), border: SliceBorder) -> Self {
+        let (width, height) = region_size;
+        let left = border.left.min(width);
+        let right = border.right.min(width - left);
+        let top = border.top.min(height);
+        let bottom = border.bottom.min(height - top);
+
+        let (pixels_x, pixels_y) = (width.max(1) as f32, height.max(1) as f32);
+
+        NineSlice {
+            uv_min: region.uv_min,
+            uv_max: region.uv_max,
+            ..NineSlice::new(region.texture,
+                [top as f32 / pixels_y, right as f32 / pixels_x, bottom as f32 / pixels_y, left as f32 / pixels_x],
+                Vec2f::new(width as f32, height as f32))
+        }
+    }
+
+    /// Reads a nine slice declaration from an atlas's "nine_slices" object:
+    /// { "region": "panel", "border": 6, "center": "tile", "pixel_size": 0.004 }
+    /// "border" is a width in pixels or per side object, see `SliceBorder::from_json`. "center" is "stretch",
+    /// the default, or "tile". "pixel_size" is the world size of one texture pixel, 1 by default for pixel coordinates.
+    pub fn from_json(node: &JsonNode, atlas: &TextureAtlas) -> Result<Self, AtlasError> {
+        let region_name = node.pointer("region").and_then(String::from_json)
+            .ok_or_else(|| AtlasError::InvalidData("expected a nine slice \"region\" name".to_string()))?;
+        let rect = atlas.pixel_rect(&region_name)
+            .ok_or_else(|| AtlasError::InvalidData(format!("unknown region {}", region_name)))?;
+
+        let border = node.pointer("border").and_then(SliceBorder::from_json)
+            .ok_or_else(|| AtlasError::InvalidData("expected a \"border\" width or { \"left\", \"right\", \"top\", \"bottom\" }".to_string()))?;
+
+        let center = match node.pointer("center").and_then(String::from_json).as_deref() {
+            None | Some("stretch") => SliceCenter::Stretch,
+            Some("tile") => SliceCenter::Tile,
+            Some(other) => return Err(AtlasError::InvalidData(format!("unknown nine slice center {}, expected stretch or tile", other)))
+        };
+
+        let mut slice = NineSlice::from_region(atlas.region_at(rect), (rect.width, rect.height), border);
+        slice.set_center(center);
+
+        if let Some(pixel_size) = node.pointer("pixel_size").and_then(f32::from_json) {
+            slice.texture_size *= pixel_size;
+        }
+
+        Ok(slice)
+    }
+
+    pub fn set_center(&mut self, center: SliceCenter) {
+        self.center = center;
+    }
+
+    pub fn get_center(&self) -> SliceCenter {
+        self.center
+    }
+
+    /// Splits the rect with the given bottom left corner and size into its nine pieces, with texture
+    /// coordinates within the slice's region.
+    pub fn slices(&self, position: Vec2f, size: Vec2f) -> [SliceQuad; 9] {
+        let mut slices = compute_slices(self.border, self.texture_size, position, size);
+        let uv_size = self.uv_max - self.uv_min;
+        let to_region = |uv: Vec2f| Vec2f::new(self.uv_min.x + uv.x * uv_size.x, self.uv_min.y + uv.y * uv_size.y);
+
+        for slice in slices.iter_mut() {
+            slice.uv_min = to_region(slice.uv_min);
+            slice.uv_max = to_region(slice.uv_max);
+        }
+
+        slices
+    }
+
+    /// Splits the rect into quads, skipping pieces squashed to nothing by a small rect, and cutting
+    /// the center into tiles when it tiles.
+    pub fn quads(&self, position: Vec2f, size: Vec2f) -> Vec<SliceQuad> {
+        let size = Vec2f::new(size.x.max(0.0), size.y.max(0.0));
+        let mut quads = Vec::<SliceQuad>::with_capacity(9);
+
+        for (index, slice) in self.slices(position, size).into_iter().enumerate() {
+            if slice.max.x - slice.min.x <= 0.0 || slice.max.y - slice.min.y <= 0.0 {
+                continue;
+            }
+
+            match (index, self.center) {
+                (4, SliceCenter::Tile) => self.tile_center(slice, &mut quads),
+                _ => quads.push(slice)
+            }
+        }
+
+        quads
+    }
+
+    /// Draws the slice filling the rect with the given bottom left corner and size.
+    /// The shader must already be bound, and like "NineSliceShader.vert" needs the vec2 uniforms
+    /// "pos", "scale", "uvMin", and "uvMax", and sample its texture from unit 0.
+    pub fn render(&self, position: Vec2f, size: Vec2f, shader: &ShaderProgram) {
+        let location_pos = shader.get_uniform_location("pos");
+        let location_scale = shader.get_uniform_location("scale");
+        let location_uv_min = shader.get_uniform_location("uvMin");
+        let location_uv_max = shader.get_uniform_location("uvMax");
+
+        let quad = self.quad.get_or_init(|| {
+            // Spans 0..1 so quads are placed by their corner and size.
+            let vertices = vec![
+                0.0, 0.0,
+                0.0, 1.0,
+                1.0, 1.0,
+                1.0, 1.0,
+                1.0, 0.0,
+                0.0, 0.0
+            ];
+
+            let mut quad = Mesh2D::new();
+            quad.add_float_buffer(vertices, 2);
+            quad
+        });
+
+        self.texture.bind(0);
+
+        for slice in self.quads(position, size).iter() {
+            shader.load_vec2(location_pos, slice.min);
+            shader.load_vec2(location_scale, slice.max - slice.min);
+            shader.load_vec2(location_uv_min, slice.uv_min);
+            shader.load_vec2(location_uv_max, slice.uv_max);
+            quad.render();
+        }
+    }
+
+    /// Fills the center quad with tiles of the center's texture size, from its top left corner.
+    fn tile_center(&self, center: SliceQuad, quads: &mut Vec<SliceQuad>) {
+        let [top, right, bottom, left] = self.border;
+        let tile_size = Vec2f::new(
+            (1.0 - left - right) * self.texture_size.x,
+            (1.0 - top - bottom) * self.texture_size.y);
+        let size = center.max - center.min;
+
+        let columns = (size.x / tile_size.x).ceil();
+        let rows = (size.y / tile_size.y).ceil();
+
+        if tile_size.x <= 0.0 || tile_size.y <= 0.0 || columns * rows > MAX_CENTER_TILES as f32 {
+            quads.push(center);
+            return;
+        }
+
+        let uv_size = center.uv_max - center.uv_min;
+
+        for row in 0..rows as usize {
+            let top = center.max.y - row as f32 * tile_size.y;
+            let bottom = (top - tile_size.y).max(center.min.y);
+
+            for column in 0..columns as usize {
+                let left = center.min.x + column as f32 * tile_size.x;
+                let right = (left + tile_size.x).min(center.max.x);
+
+                // Cut tiles keep the top left of the center's texture.
+                let cut = Vec2f::new((right - left) / tile_size.x, (top - bottom) / tile_size.y);
+
+                quads.push(SliceQuad {
+                    min: Vec2f::new(left, bottom),
+                    max: Vec2f::new(right, top),
+                    uv_min: Vec2f::new(center.uv_min.x, center.uv_max.y - uv_size.y * cut.y),
+                    uv_max: Vec2f::new(center.uv_min.x + uv_size.x * cut.x, center.uv_max.y)
+                });
+            }
+        }
+    }
+}
+
+/// Returns the world size of the borders along one axis. When the rect is too small to fit both,
+/// they shrink together so they meet in the middle.
+fn fit_borders(start: f32, end: f32, size: f32) -> (f32, f32) {
+    let total = start + end;
+
+    if total > size && total > 0.0 {
+        let scale = size.max(0.0) / total;
+        (start * scale, end * scale)
+    }
+    else {
+        (start, end)
+    }
+}
+
+pub(crate) fn compute_slices(border: [f32; 4], texture_size: Vec2f, position: Vec2f, size: Vec2f) -> [SliceQuad; 9] {
+    let [top, right, bottom, left] = border;
+
+    let (left_size, right_size) = fit_borders(left * texture_size.x, right * texture_size.x, size.x);
+    let (bottom_size, top_size) = fit_borders(bottom * texture_size.y, top * texture_size.y, size.y);
+
+    // Edges of the columns and rows, left to right and bottom to top.
+    let xs = [position.x, position.x + left_size, position.x + size.x - right_size, position.x + size.x];
+    let ys = [position.y, position.y + bottom_size, position.y + size.y - top_size, position.y + size.y];
+
+    // The matching texture coordinates. v points down, so the bottom row of the rect samples the bottom of the image.
+    let us = [0.0, left, 1.0 - right, 1.0];
+    let vs = [1.0, 1.0 - bottom, top, 0.0];
+
+    let mut slices = [SliceQuad {
+        min: Vec2f::new(0.0, 0.0),
+        max: Vec2f::new(0.0, 0.0),
+        uv_min: Vec2f::new(0.0, 0.0),
+        uv_max: Vec2f::new(0.0, 0.0)
+    }; 9];
+
+    for row in 0..3 {
+        for column in 0..3 {
+            slices[row * 3 + column] = SliceQuad {
+                min: Vec2f::new(xs[column], ys[row]),
+                max: Vec2f::new(xs[column + 1], ys[row + 1]),
+                uv_min: Vec2f::new(us[column], vs[row]),
+                uv_max: Vec2f::new(us[column + 1], vs[row + 1])
+            };
+        }
+    }
+
+    slices
+}
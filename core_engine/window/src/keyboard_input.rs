@@ -1,10 +1,22 @@
 use std::vec;
 
-use glfw::ffi::GLFWwindow;
+use glfw::ffi::{GLFWwindow, glfwGetKeyScancode};
+
+/// Scancodes from 0 up to this are tracked. Every platform GLFW supports stays below it.
+pub const NUM_SCANCODES: usize = 512;
 
 #[repr(i32)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 /// Enumerates every key supported by the window.
+/// The numpad keys are the `Kp` variants.
+///
+/// Not every keyboard or platform reports every key:
+/// - `World1` and `World2` only exist on some non-US layouts.
+/// - `F13` through `F25` are missing from most keyboards, and macOS stops at `F20`.
+/// - `PrintScreen`, `ScrollLock`, `Pause`, and `Menu` are not reported on macOS.
+/// - `KpEqual` only exists on some (mostly Apple) numpads.
+///
+/// Media keys (play, volume, etc.) are handled by the OS and never reach GLFW, so there are no variants for them.
 pub enum Key {
     Space = glfw::ffi::KEY_SPACE,
     Apostrophe = glfw::ffi::KEY_APOSTROPHE,
@@ -130,26 +142,216 @@ pub enum Key {
     Unknown = glfw::ffi::KEY_UNKNOWN,
 }
 
+impl Key {
+    /// Every key but `Unknown`, in the order they're declared.
+    pub const ALL: [Key; 120] = [
+        Key::Space, Key::Apostrophe, Key::Comma, Key::Minus, Key::Period, Key::Slash, Key::Num0, Key::Num1,
+        Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9, Key::Semicolon,
+        Key::Equal, Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K,
+        Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
+        Key::Y, Key::Z, Key::LeftBracket, Key::Backslash, Key::RightBracket, Key::GraveAccent, Key::World1,
+        Key::World2, Key::Escape, Key::Enter, Key::Tab, Key::Backspace, Key::Insert, Key::Delete, Key::Right,
+        Key::Left, Key::Down, Key::Up, Key::PageUp, Key::PageDown, Key::Home, Key::End, Key::CapsLock,
+        Key::ScrollLock, Key::NumLock, Key::PrintScreen, Key::Pause, Key::F1, Key::F2, Key::F3, Key::F4, Key::F5,
+        Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15, Key::F16,
+        Key::F17, Key::F18, Key::F19, Key::F20, Key::F21, Key::F22, Key::F23, Key::F24, Key::F25, Key::Kp0,
+        Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9, Key::KpDecimal,
+        Key::KpDivide, Key::KpMultiply, Key::KpSubtract, Key::KpAdd, Key::KpEnter, Key::KpEqual, Key::LeftShift,
+        Key::LeftControl, Key::LeftAlt, Key::LeftSuper, Key::RightShift, Key::RightControl, Key::RightAlt,
+        Key::RightSuper, Key::Menu
+    ];
+
+    /// Returns the platform specific scancode of the physical key, or None when the keyboard doesn't have it.
+    /// Scancodes identify a position on the keyboard whatever the layout, but differ between platforms,
+    /// so they're meant for input at runtime rather than saving in config files.
+    /// Needs a window to have been created.
+    pub fn to_scancode(self) -> Option<i32> {
+        scancode_with(self, |key| unsafe { glfwGetKeyScancode(key) })
+    }
+
+    /// Returns the key at the scancode's position, or None when no key has it. Needs a window to have been created.
+    pub fn from_scancode(scancode: i32) -> Option<Key> {
+        key_from_scancode_with(scancode, |key| unsafe { glfwGetKeyScancode(key) })
+    }
+
+    /// Looks up a key by its variant name, ex: "F3" or "KpEnter", for keys named in config files.
+    pub fn from_name(name: &str) -> Option<Key> {
+        match name {
+            "Space" => Some(Key::Space),
+            "Apostrophe" => Some(Key::Apostrophe),
+            "Comma" => Some(Key::Comma),
+            "Minus" => Some(Key::Minus),
+            "Period" => Some(Key::Period),
+            "Slash" => Some(Key::Slash),
+            "Num0" => Some(Key::Num0),
+            "Num1" => Some(Key::Num1),
+            "Num2" => Some(Key::Num2),
+            "Num3" => Some(Key::Num3),
+            "Num4" => Some(Key::Num4),
+            "Num5" => Some(Key::Num5),
+            "Num6" => Some(Key::Num6),
+            "Num7" => Some(Key::Num7),
+            "Num8" => Some(Key::Num8),
+            "Num9" => Some(Key::Num9),
+            "Semicolon" => Some(Key::Semicolon),
+            "Equal" => Some(Key::Equal),
+            "A" => Some(Key::A),
+            "B" => Some(Key::B),
+            "C" => Some(Key::C),
+            "D" => Some(Key::D),
+            "E" => Some(Key::E),
+            "F" => Some(Key::F),
+            "G" => Some(Key::G),
+            "H" => Some(Key::H),
+            "I" => Some(Key::I),
+            "J" => Some(Key::J),
+            "K" => Some(Key::K),
+            "L" => Some(Key::L),
+            "M" => Some(Key::M),
+            "N" => Some(Key::N),
+            "O" => Some(Key::O),
+            "P" => Some(Key::P),
+            "Q" => Some(Key::Q),
+            "R" => Some(Key::R),
+            "S" => Some(Key::S),
+            "T" => Some(Key::T),
+            "U" => Some(Key::U),
+            "V" => Some(Key::V),
+            "W" => Some(Key::W),
+            "X" => Some(Key::X),
+            "Y" => Some(Key::Y),
+            "Z" => Some(Key::Z),
+            "LeftBracket" => Some(Key::LeftBracket),
+            "Backslash" => Some(Key::Backslash),
+            "RightBracket" => Some(Key::RightBracket),
+            "GraveAccent" => Some(Key::GraveAccent),
+            "World1" => Some(Key::World1),
+            "World2" => Some(Key::World2),
+            "Escape" => Some(Key::Escape),
+            "Enter" => Some(Key::Enter),
+            "Tab" => Some(Key::Tab),
+            "Backspace" => Some(Key::Backspace),
+            "Insert" => Some(Key::Insert),
+            "Delete" => Some(Key::Delete),
+            "Right" => Some(Key::Right),
+            "Left" => Some(Key::Left),
+            "Down" => Some(Key::Down),
+            "Up" => Some(Key::Up),
+            "PageUp" => Some(Key::PageUp),
+            "PageDown" => Some(Key::PageDown),
+            "Home" => Some(Key::Home),
+            "End" => Some(Key::End),
+            "CapsLock" => Some(Key::CapsLock),
+            "ScrollLock" => Some(Key::ScrollLock),
+            "NumLock" => Some(Key::NumLock),
+            "PrintScreen" => Some(Key::PrintScreen),
+            "Pause" => Some(Key::Pause),
+            "F1" => Some(Key::F1),
+            "F2" => Some(Key::F2),
+            "F3" => Some(Key::F3),
+            "F4" => Some(Key::F4),
+            "F5" => Some(Key::F5),
+            "F6" => Some(Key::F6),
+            "F7" => Some(Key::F7),
+            "F8" => Some(Key::F8),
+            "F9" => Some(Key::F9),
+            "F10" => Some(Key::F10),
+            "F11" => Some(Key::F11),
+            "F12" => Some(Key::F12),
+            "F13" => Some(Key::F13),
+            "F14" => Some(Key::F14),
+            "F15" => Some(Key::F15),
+            "F16" => Some(Key::F16),
+            "F17" => Some(Key::F17),
+            "F18" => Some(Key::F18),
+            "F19" => Some(Key::F19),
+            "F20" => Some(Key::F20),
+            "F21" => Some(Key::F21),
+            "F22" => Some(Key::F22),
+            "F23" => Some(Key::F23),
+            "F24" => Some(Key::F24),
+            "F25" => Some(Key::F25),
+            "Kp0" => Some(Key::Kp0),
+            "Kp1" => Some(Key::Kp1),
+            "Kp2" => Some(Key::Kp2),
+            "Kp3" => Some(Key::Kp3),
+            "Kp4" => Some(Key::Kp4),
+            "Kp5" => Some(Key::Kp5),
+            "Kp6" => Some(Key::Kp6),
+            "Kp7" => Some(Key::Kp7),
+            "Kp8" => Some(Key::Kp8),
+            "Kp9" => Some(Key::Kp9),
+            "KpDecimal" => Some(Key::KpDecimal),
+            "KpDivide" => Some(Key::KpDivide),
+            "KpMultiply" => Some(Key::KpMultiply),
+            "KpSubtract" => Some(Key::KpSubtract),
+            "KpAdd" => Some(Key::KpAdd),
+            "KpEnter" => Some(Key::KpEnter),
+            "KpEqual" => Some(Key::KpEqual),
+            "LeftShift" => Some(Key::LeftShift),
+            "LeftControl" => Some(Key::LeftControl),
+            "LeftAlt" => Some(Key::LeftAlt),
+            "LeftSuper" => Some(Key::LeftSuper),
+            "RightShift" => Some(Key::RightShift),
+            "RightControl" => Some(Key::RightControl),
+            "RightAlt" => Some(Key::RightAlt),
+            "RightSuper" => Some(Key::RightSuper),
+            "Menu" => Some(Key::Menu),
+            _ => None
+        }
+    }
+}
+
+/// Returns the scancode the lookup gives the key, treating negative scancodes as missing.
+pub(crate) fn scancode_with(key: Key, lookup: impl Fn(i32) -> i32) -> Option<i32> {
+    match key {
+        Key::Unknown => None,
+        _ => Some(lookup(key as i32)).filter(|scancode| *scancode >= 0)
+    }
+}
+
+/// Returns the first key the lookup gives the scancode.
+pub(crate) fn key_from_scancode_with(scancode: i32, lookup: impl Fn(i32) -> i32) -> Option<Key> {
+    Key::ALL.iter().copied().find(|key| scancode_with(*key, &lookup) == Some(scancode))
+}
+
+/// A key or mouse button going down or up, queued by the callbacks until the next update.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ButtonEvent {
+    pub button: i32,
+    pub pressed: bool
+}
+
 /// Stores information about pressed keys
 /// Nothing is threaded, so this is safe to do.
 pub struct KeyInputs {
-    pub keys_pressed_frame: Vec<i32>,
-    pub keys_released_frame: Vec<i32>
+    /// In the order glfw reported them, so a key pressed and released between updates is seen as both.
+    pub key_events_frame: Vec<ButtonEvent>,
+    pub scancode_events_frame: Vec<ButtonEvent>
 }
 
 pub static mut KEY_INPUTS: KeyInputs = KeyInputs { 
-    keys_pressed_frame: vec![], 
-    keys_released_frame: vec![] 
+    key_events_frame: vec![], 
+    scancode_events_frame: vec![]
 };
 
-pub extern "C" fn key_callback(_window: *mut GLFWwindow, key: i32, _scancode: i32, action: i32, _mods: i32) {
+pub extern "C" fn key_callback(_window: *mut GLFWwindow, key: i32, scancode: i32, action: i32, _mods: i32) {
+    // Keys GLFW doesn't know are still reported with their scancode.
+    let tracked_key = key >= 0;
+    let tracked_scancode = (0..NUM_SCANCODES as i32).contains(&scancode);
+
     unsafe {
         match action {
-            _ if action == glfw::Action::Press as i32 => {
-                KEY_INPUTS.keys_pressed_frame.push(key);
-            },
-            _ if action == glfw::Action::Release as i32 => {
-                KEY_INPUTS.keys_released_frame.push(key);
+            _ if action == glfw::Action::Press as i32 || action == glfw::Action::Release as i32 => {
+                let pressed = action == glfw::Action::Press as i32;
+
+                if tracked_key {
+                    KEY_INPUTS.key_events_frame.push(ButtonEvent { button: key, pressed });
+                }
+
+                if tracked_scancode {
+                    KEY_INPUTS.scancode_events_frame.push(ButtonEvent { button: scancode, pressed });
+                }
             },
             // The only other option is repeat which we don't have to do
             // anything for.
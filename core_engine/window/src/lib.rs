@@ -1,13 +1,18 @@
 pub mod window;
 mod keyboard_input;
 mod mouse_input;
+mod tests;
 
 pub use keyboard_input::Key;
-pub use mouse_input::MouseButton;
+pub use mouse_input::{MouseButton, CursorMode};
 
 /// Alias to `MouseButton1`, supplied for improved clarity.
 pub use self::MouseButton::Button1 as MouseButtonLeft;
 /// Alias to `MouseButton2`, supplied for improved clarity.
 pub use self::MouseButton::Button2 as MouseButtonRight;
 /// Alias to `MouseButton3`, supplied for improved clarity.
-pub use self::MouseButton::Button3 as MouseButtonMiddle;
\ No newline at end of file
+pub use self::MouseButton::Button3 as MouseButtonMiddle;
+/// Alias to `MouseButton4`, the back side button on most mice.
+pub use self::MouseButton::Button4 as MouseButtonBack;
+/// Alias to `MouseButton5`, the forward side button on most mice.
+pub use self::MouseButton::Button5 as MouseButtonForward;
\ No newline at end of file
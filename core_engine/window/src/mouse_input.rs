@@ -1,6 +1,8 @@
 use std::vec;
 
-use glfw::ffi::GLFWwindow;
+use glfw::ffi::{GLFWwindow, glfwGetWindowSize, glfwGetFramebufferSize};
+
+use crate::keyboard_input::ButtonEvent;
 
 /// Mouse buttons. The `MouseButtonLeft`, `MouseButtonRight`, and
 /// `MouseButtonMiddle` aliases are supplied for convenience.
@@ -20,27 +22,114 @@ pub enum MouseButton {
     Button8 = glfw::ffi::MOUSE_BUTTON_8,
 }
 
+/// How the cursor behaves while it's over the window.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum CursorMode {
+    /// The cursor is visible and moves freely.
+    #[default]
+    Normal,
+    /// The cursor is invisible over the window but otherwise moves freely.
+    Hidden,
+    /// The cursor is hidden and grabbed by the window, for mouse-look and dragging.
+    /// Mouse deltas keep accumulating past the window's edges, using raw motion where the platform supports it.
+    Disabled
+}
+
+impl CursorMode {
+    /// Returns the value passed to glfwSetInputMode for the GLFW_CURSOR mode.
+    pub fn to_glfw(&self) -> i32 {
+        match self {
+            CursorMode::Normal => glfw::ffi::CURSOR_NORMAL,
+            CursorMode::Hidden => glfw::ffi::CURSOR_HIDDEN,
+            CursorMode::Disabled => glfw::ffi::CURSOR_DISABLED
+        }
+    }
+}
+
 /// Stores information about pressed keys
 /// Nothing is threaded, so this is safe to do.
 pub struct MouseButtonInputs {
-    pub buttons_pressed_frame: Vec<i32>,
-    pub buttons_released_frame: Vec<i32>
+    /// In the order glfw reported them, so a button pressed and released between updates is seen as both.
+    pub button_events_frame: Vec<ButtonEvent>
 }
 
-pub static mut MOUSE_BUTTON_INPUTS: MouseButtonInputs = MouseButtonInputs { buttons_pressed_frame: vec![], buttons_released_frame: vec![] };
+pub static mut MOUSE_BUTTON_INPUTS: MouseButtonInputs = MouseButtonInputs { button_events_frame: vec![] };
 
 pub extern "C" fn mouse_callback(_window: *mut GLFWwindow, button: i32, action: i32, _mods: i32) {
     unsafe {
         match action {
             _ if action == glfw::Action::Press as i32 => {
-                MOUSE_BUTTON_INPUTS.buttons_pressed_frame.push(button);
+                MOUSE_BUTTON_INPUTS.button_events_frame.push(ButtonEvent { button, pressed: true });
             },
             _ if action == glfw::Action::Release as i32 => {
-                MOUSE_BUTTON_INPUTS.buttons_released_frame.push(button);
+                MOUSE_BUTTON_INPUTS.button_events_frame.push(ButtonEvent { button, pressed: false });
             },
             // The only other option is repeat which we don't have to do
             // anything for.
             _ => ()
         }
     }
-}
\ No newline at end of file
+}
+
+/// Stores the latest cursor position reported by glfw, in framebuffer pixels.
+pub struct CursorPosition {
+    pub x: f64,
+    pub y: f64,
+    /// Whether the cursor is over the window.
+    pub inside: bool,
+    /// Set when the cursor may have jumped since the last update, ex: it entered the window or the window
+    /// regained focus, so the move isn't reported as a mouse delta.
+    pub jumped: bool
+}
+
+pub static mut CURSOR_POSITION: CursorPosition = CursorPosition { x: 0.0, y: 0.0, inside: false, jumped: false };
+
+pub extern "C" fn cursor_position_callback(window: *mut GLFWwindow, x: f64, y: f64) {
+    unsafe {
+        let (mut window_width, mut window_height, mut framebuffer_width, mut framebuffer_height) = (0, 0, 0, 0);
+        glfwGetWindowSize(window, &mut window_width, &mut window_height);
+        glfwGetFramebufferSize(window, &mut framebuffer_width, &mut framebuffer_height);
+
+        (CURSOR_POSITION.x, CURSOR_POSITION.y) = window_to_framebuffer((x, y),
+            (window_width, window_height), (framebuffer_width, framebuffer_height));
+    }
+}
+
+pub extern "C" fn cursor_enter_callback(_window: *mut GLFWwindow, entered: i32) {
+    unsafe {
+        CURSOR_POSITION.inside = entered != 0;
+        CURSOR_POSITION.jumped |= entered != 0;
+    }
+}
+
+pub extern "C" fn window_focus_callback(_window: *mut GLFWwindow, focused: i32) {
+    unsafe {
+        CURSOR_POSITION.jumped |= focused != 0;
+    }
+}
+
+/// Scrolling accumulated from glfw since the last update, in wheel lines.
+pub struct ScrollOffset {
+    pub x: f64,
+    pub y: f64
+}
+
+pub static mut SCROLL_OFFSET: ScrollOffset = ScrollOffset { x: 0.0, y: 0.0 };
+
+pub extern "C" fn scroll_callback(_window: *mut GLFWwindow, x: f64, y: f64) {
+    unsafe {
+        SCROLL_OFFSET.x += x;
+        SCROLL_OFFSET.y += y;
+    }
+}
+
+/// Scales a cursor position from window coordinates to framebuffer pixels, which differ on scaled displays.
+/// Positions are left as they are while the window is minimized.
+pub(crate) fn window_to_framebuffer(position: (f64, f64), window_size: (i32, i32), framebuffer_size: (i32, i32)) -> (f64, f64) {
+    if window_size.0 <= 0 || window_size.1 <= 0 || framebuffer_size.0 <= 0 || framebuffer_size.1 <= 0 {
+        return position;
+    }
+
+    (position.0 * framebuffer_size.0 as f64 / window_size.0 as f64,
+        position.1 * framebuffer_size.1 as f64 / window_size.1 as f64)
+}
@@ -0,0 +1,363 @@
+#[cfg(test)]
+mod tests {
+    use ogl33::{GL_FRAMEBUFFER_SRGB, GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, GL_STENCIL_BUFFER_BIT};
+
+    use crate::window::{WindowConfig, get_window_hints, get_enabled_capabilities, get_clear_mask, apply_window_icon,
+        get_cursor_input_modes, valid_msaa_samples, MouseKeyboardInput, MouseKeyboardInputControl};
+    use crate::{CursorMode, Key, MouseButtonLeft, MouseButtonRight};
+    use crate::mouse_input::{mouse_callback, cursor_enter_callback, scroll_callback, window_to_framebuffer};
+    use crate::keyboard_input::key_callback;
+    use crate::keyboard_input::{scancode_with, key_from_scancode_with};
+
+    /// Held by tests which feed input through the glfw callbacks, since they share the static input state.
+    static CALLBACK_INPUT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn test_srgb_config() {
+        let mut config = WindowConfig::default();
+
+        assert!(!get_window_hints(&config).contains(&(glfw::ffi::SRGB_CAPABLE, 1)));
+        assert!(get_enabled_capabilities(&config).is_empty());
+
+        config.srgb = true;
+
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::SRGB_CAPABLE, 1)));
+        assert_eq!(get_enabled_capabilities(&config), vec![GL_FRAMEBUFFER_SRGB]);
+    }
+
+    #[test]
+    fn test_stencil_bits_hint() {
+        let mut config = WindowConfig::default();
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::STENCIL_BITS, 8)));
+
+        config.stencil_bits = 0;
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::STENCIL_BITS, 0)));
+    }
+
+    #[test]
+    fn test_gl_debug_hint() {
+        let mut config = WindowConfig::default();
+        assert!(!get_window_hints(&config).contains(&(glfw::ffi::OPENGL_DEBUG_CONTEXT, 1)));
+
+        config.gl_debug = true;
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::OPENGL_DEBUG_CONTEXT, 1)));
+    }
+
+    #[test]
+    fn test_clear_mask() {
+        let mut config = WindowConfig::default();
+        assert_eq!(get_clear_mask(&config), GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+
+        config.clear_depth = false;
+        config.clear_stencil = true;
+        assert_eq!(get_clear_mask(&config), GL_COLOR_BUFFER_BIT | GL_STENCIL_BUFFER_BIT);
+    }
+
+    #[test]
+    fn test_window_icon_from_config() {
+        let icon_path = std::env::temp_dir().join("window_icon_test.png");
+        image::RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255])).save(&icon_path).unwrap();
+
+        let mut config = WindowConfig::default();
+        config.icon_paths = vec![
+            icon_path.to_str().unwrap().to_string(),
+            "missing_icon.png".to_string()
+        ];
+
+        // The missing icon is skipped, and the valid one reaches the window.
+        let mut set_icons = None;
+        apply_window_icon(&config, |icons| set_icons = Some(icons));
+
+        let set_icons = set_icons.unwrap();
+        assert_eq!(set_icons.len(), 1);
+        assert_eq!((set_icons[0].width, set_icons[0].height), (4, 2));
+        assert_eq!(set_icons[0].pixels[0], u32::from_le_bytes([255, 0, 0, 255]));
+
+        // No configured icons means the window keeps the default.
+        let mut called = false;
+        apply_window_icon(&WindowConfig::default(), |_| called = true);
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_cursor_mode() {
+        use glfw::ffi::{CURSOR, CURSOR_NORMAL, CURSOR_HIDDEN, CURSOR_DISABLED, RAW_MOUSE_MOTION};
+
+        assert_eq!(get_cursor_input_modes(CursorMode::Normal, false), vec![(CURSOR, CURSOR_NORMAL)]);
+        assert_eq!(get_cursor_input_modes(CursorMode::Hidden, true), vec![(CURSOR, CURSOR_HIDDEN), (RAW_MOUSE_MOTION, 0)]);
+        assert_eq!(get_cursor_input_modes(CursorMode::Disabled, true), vec![(CURSOR, CURSOR_DISABLED), (RAW_MOUSE_MOTION, 1)]);
+
+        // Deltas are only reported while the cursor is disabled.
+        let mut input = MouseKeyboardInput::new();
+        input.update_cursor((10.0, 10.0));
+        input.update_cursor((20.0, 30.0));
+        assert_eq!((input.get_mouse_dx(), input.get_mouse_dy()), (0, 0));
+
+        // mouse_delta reports them in every mode, as differences between positions.
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (10.0, 20.0));
+
+        // The first update after disabling doesn't report the jump to the current position.
+        input.set_cursor_mode(CursorMode::Disabled);
+        input.update_cursor((100.0, 100.0));
+        assert_eq!((input.get_mouse_dx(), input.get_mouse_dy()), (0, 0));
+
+        // Grabbed, deltas keep coming past the window's edges.
+        input.update_cursor((105.0, 97.0));
+        assert_eq!((input.get_mouse_dx(), input.get_mouse_dy()), (5, -3));
+        input.update_cursor((-2000.5, 97.25));
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (-2105.5, 0.25));
+
+        input.set_cursor_mode(CursorMode::Normal);
+        input.update_cursor((200.0, 200.0));
+        assert_eq!((input.get_mouse_dx(), input.get_mouse_dy()), (0, 0));
+        input.update_cursor((210.0, 200.0));
+        assert_eq!((input.get_mouse_dx(), input.get_mouse_dy()), (0, 0));
+        assert_eq!(input.mouse_delta().x, 10.0);
+    }
+
+    #[test]
+    fn test_mouse_delta_resets() {
+        use crate::mouse_input::{window_focus_callback, CURSOR_POSITION};
+
+        let _lock = CALLBACK_INPUT.lock().unwrap_or_else(|err| err.into_inner());
+        let window = std::ptr::null_mut();
+        let mut input = MouseKeyboardInput::new();
+
+        // The position callback asks glfw for the window's size, so positions are set directly.
+        let move_cursor = |x: f64, y: f64| unsafe {
+            CURSOR_POSITION.x = x;
+            CURSOR_POSITION.y = y;
+        };
+
+        move_cursor(10.0, 10.0);
+        input.update_input();
+        move_cursor(12.0, 10.0);
+        input.update_input();
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (2.0, 0.0));
+
+        // The update after the window regains focus only sets the reference point.
+        move_cursor(500.0, 300.0);
+        window_focus_callback(window, 1);
+        input.update_input();
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (0.0, 0.0));
+
+        move_cursor(504.0, 306.0);
+        input.update_input();
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (4.0, 6.0));
+
+        // Losing focus doesn't reset it.
+        window_focus_callback(window, 0);
+        move_cursor(505.0, 306.0);
+        input.update_input();
+        assert_eq!(input.mouse_delta().x, 1.0);
+
+        // The cursor entering the window does, since it can come back in anywhere.
+        move_cursor(0.0, 0.0);
+        cursor_enter_callback(window, 1);
+        input.update_input();
+        assert_eq!((input.mouse_delta().x, input.mouse_delta().y), (0.0, 0.0));
+
+        cursor_enter_callback(window, 0);
+    }
+
+    #[test]
+    fn test_msaa_samples() {
+        use ogl33::GL_MULTISAMPLE;
+
+        let mut config = WindowConfig::default();
+        assert!(!get_window_hints(&config).iter().any(|(hint, _)| *hint == glfw::ffi::SAMPLES));
+        assert!(!get_enabled_capabilities(&config).contains(&GL_MULTISAMPLE));
+
+        config.msaa_samples = 4;
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::SAMPLES, 4)));
+        assert!(get_enabled_capabilities(&config).contains(&GL_MULTISAMPLE));
+
+        // Unsupported counts fall back to the closest lower supported count.
+        assert_eq!(valid_msaa_samples(6), 4);
+        assert_eq!(valid_msaa_samples(16), 8);
+        assert_eq!(valid_msaa_samples(1), 0);
+
+        config.msaa_samples = 3;
+        assert!(get_window_hints(&config).contains(&(glfw::ffi::SAMPLES, 2)));
+    }
+
+    #[test]
+    fn test_key_from_name() {
+        assert_eq!(Key::from_name("F3"), Some(Key::F3));
+        assert_eq!(Key::from_name("KpEnter"), Some(Key::KpEnter));
+        assert_eq!(Key::from_name("Space"), Some(Key::Space));
+        assert_eq!(Key::from_name("f3"), None);
+        assert_eq!(Key::from_name("Unknown"), None);
+        assert_eq!(Key::from_name(""), None);
+    }
+
+    #[test]
+    fn test_scancode_mapping() {
+        // A layout where the letters are shuffled, and keys past the arrows aren't on the keyboard.
+        let lookup = |key: i32| match key {
+            _ if key == Key::W as i32 => 25,
+            _ if key == Key::Z as i32 => 17,
+            _ if key >= Key::PageUp as i32 => -1,
+            _ => key + 100
+        };
+
+        for key in Key::ALL {
+            match scancode_with(key, lookup) {
+                Some(scancode) => assert_eq!(key_from_scancode_with(scancode, lookup), Some(key)),
+                None => assert!(key as i32 >= Key::PageUp as i32)
+            }
+        }
+
+        assert_eq!(scancode_with(Key::W, lookup), Some(25));
+        assert_eq!(key_from_scancode_with(17, lookup), Some(Key::Z));
+        assert_eq!(scancode_with(Key::Unknown, lookup), None);
+        assert_eq!(key_from_scancode_with(-1, lookup), None);
+        assert_eq!(key_from_scancode_with(99, lookup), None);
+
+        // Nothing is down before any input, and out of range scancodes are never down.
+        let input = MouseKeyboardInput::new();
+        assert!(!input.is_scancode_down(25));
+        assert!(!input.is_scancode_down(-1));
+        assert!(!input.is_scancode_down(100_000));
+    }
+
+    #[test]
+    fn test_mouse_position_and_buttons() {
+        let _lock = CALLBACK_INPUT.lock().unwrap_or_else(|err| err.into_inner());
+        let window = std::ptr::null_mut();
+        let mut input = MouseKeyboardInput::new();
+
+        // Positions are reported in framebuffer pixels, and aren't clamped to the window.
+        assert_eq!(window_to_framebuffer((100.0, 50.0), (800, 600), (1600, 1200)), (200.0, 100.0));
+        assert_eq!(window_to_framebuffer((-10.0, 700.0), (800, 600), (800, 600)), (-10.0, 700.0));
+        assert_eq!(window_to_framebuffer((100.0, 50.0), (0, 0), (0, 0)), (100.0, 50.0));
+
+        input.update_cursor((120.0, 45.0));
+        assert_eq!((input.mouse_position().x, input.mouse_position().y), (120.0, 45.0));
+
+        cursor_enter_callback(window, 1);
+        mouse_callback(window, MouseButtonLeft as i32, glfw::Action::Press as i32, 0);
+        input.update_input();
+        assert!(input.is_cursor_inside());
+        assert!(input.is_mouse_down(MouseButtonLeft));
+        assert!(!input.is_mouse_released(MouseButtonLeft));
+        assert!(input.is_mouse_up(MouseButtonRight));
+
+        // Released is only set for the update the button went up in.
+        cursor_enter_callback(window, 0);
+        mouse_callback(window, MouseButtonLeft as i32, glfw::Action::Release as i32, 0);
+        input.update_input();
+        assert!(!input.is_cursor_inside());
+        assert!(input.is_mouse_up(MouseButtonLeft));
+        assert!(input.is_mouse_released(MouseButtonLeft));
+        assert!(input.is_mouse_clicked(MouseButtonLeft));
+
+        input.update_input();
+        assert!(!input.is_mouse_released(MouseButtonLeft));
+    }
+
+    #[test]
+    fn test_key_and_mouse_released() {
+        let _lock = CALLBACK_INPUT.lock().unwrap_or_else(|err| err.into_inner());
+        let window = std::ptr::null_mut();
+        let mut input = MouseKeyboardInput::new();
+        let press = glfw::Action::Press as i32;
+        let release = glfw::Action::Release as i32;
+
+        key_callback(window, Key::Space as i32, -1, press, 0);
+        input.update_input();
+        assert!(input.is_key_down(Key::Space));
+        assert!(!input.is_key_released(Key::Space));
+
+        key_callback(window, Key::Space as i32, -1, release, 0);
+        input.update_input();
+        assert!(input.is_key_up(Key::Space));
+        assert!(input.is_key_released(Key::Space));
+
+        input.update_input();
+        assert!(!input.is_key_released(Key::Space));
+
+        // A tap between updates is still clicked and released, once.
+        key_callback(window, Key::E as i32, -1, press, 0);
+        key_callback(window, Key::E as i32, -1, release, 0);
+        mouse_callback(window, MouseButtonRight as i32, press, 0);
+        mouse_callback(window, MouseButtonRight as i32, release, 0);
+        input.update_input();
+        assert!(input.is_key_up(Key::E));
+        assert!(input.is_key_clicked(Key::E) && input.is_key_released(Key::E));
+        assert!(input.is_mouse_up(MouseButtonRight));
+        assert!(input.is_mouse_clicked(MouseButtonRight) && input.is_mouse_released(MouseButtonRight));
+
+        input.update_input();
+        assert!(!input.is_key_released(Key::E));
+        assert!(!input.is_mouse_released(MouseButtonRight));
+
+        // Releasing and pressing again between updates leaves the key down, and still reports the release.
+        key_callback(window, Key::E as i32, -1, press, 0);
+        input.update_input();
+        key_callback(window, Key::E as i32, -1, release, 0);
+        key_callback(window, Key::E as i32, -1, press, 0);
+        input.update_input();
+        assert!(input.is_key_down(Key::E));
+        assert!(input.is_key_released(Key::E));
+    }
+
+    #[test]
+    fn test_key_clicked_once_per_press() {
+        let _lock = CALLBACK_INPUT.lock().unwrap_or_else(|err| err.into_inner());
+        let window = std::ptr::null_mut();
+        let mut input = MouseKeyboardInput::new();
+        let (press, repeat, release) = (glfw::Action::Press as i32, glfw::Action::Repeat as i32, glfw::Action::Release as i32);
+
+        // Scripted events, with an update after each batch. Each entry is (events, clicked, down).
+        let script: &[(&[i32], bool, bool)] = &[
+            (&[press], true, true),
+            // Holding the key, through OS key repeats and duplicate presses, never clicks again.
+            (&[repeat, repeat], false, true),
+            (&[], false, true),
+            (&[press], false, true),
+            (&[release], false, false),
+            (&[], false, false),
+            // A press and release between updates clicks once.
+            (&[press, release], true, false),
+            (&[], false, false),
+            // So does tapping twice, since clicked is per update.
+            (&[press, release, press, release], true, false),
+            // Releasing and pressing again while held is a new press.
+            (&[press], true, true),
+            (&[release, press, repeat], true, true),
+            (&[release], false, false)
+        ];
+
+        for (step, (events, clicked, down)) in script.iter().enumerate() {
+            for action in events.iter() {
+                key_callback(window, Key::W as i32, -1, *action, 0);
+            }
+
+            input.update_input();
+            assert_eq!(input.is_key_clicked(Key::W), *clicked, "clicked at step {}", step);
+            assert_eq!(input.is_key_down(Key::W), *down, "down at step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_scroll_delta() {
+        let _lock = CALLBACK_INPUT.lock().unwrap_or_else(|err| err.into_inner());
+        let window = std::ptr::null_mut();
+        let mut input = MouseKeyboardInput::new();
+
+        // Scrolling between updates adds up.
+        scroll_callback(window, 0.0, 1.0);
+        scroll_callback(window, 0.5, 1.0);
+        input.update_input();
+        assert_eq!((input.scroll_delta().x, input.scroll_delta().y), (0.5, 2.0));
+
+        // And is only reported for one update.
+        input.update_input();
+        assert_eq!((input.scroll_delta().x, input.scroll_delta().y), (0.0, 0.0));
+
+        scroll_callback(window, 0.0, -0.25);
+        input.update_input();
+        assert_eq!(input.scroll_delta().y, -0.25);
+    }
+}
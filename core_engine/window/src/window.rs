@@ -1,34 +1,60 @@
 extern crate glfw;
 
-use glfw::{Context, ffi::{glfwGetProcAddress, glfwSetKeyCallback, glfwSetMouseButtonCallback, glfwMakeContextCurrent, glfwSwapInterval, glfwWindowHint, RESIZABLE}, Window};
+use glfw::{Context, PixelImage, ffi::{glfwGetProcAddress, glfwSetKeyCallback, glfwSetMouseButtonCallback, glfwSetCursorPosCallback, glfwSetCursorEnterCallback, glfwSetScrollCallback, glfwSetWindowFocusCallback, glfwSetInputMode, glfwRawMouseMotionSupported, glfwMakeContextCurrent, glfwSwapInterval, glfwWindowHint, RESIZABLE, STENCIL_BITS, SRGB_CAPABLE, SAMPLES, OPENGL_DEBUG_CONTEXT, CURSOR, RAW_MOUSE_MOTION}, Window};
+use glmath::glmath::Vec2f;
 use ogl33::*;
 
 use crate::keyboard_input;
 use crate::mouse_input;
+use crate::mouse_input::CursorMode;
 const NUM_KEYS_INPUT: usize = 500;
 const NUM_MOUSE_BUTTONS: usize = 8;
 
 #[derive(Default, Clone)]
 struct ButtonInputState {
+    /// Latched by the events, so repeated presses without a release in between are ignored.
     down: Vec<bool>,
-    clicked: Vec<bool>
+    /// Set for the update a button went down in, or went up in when clicking on release.
+    clicked: Vec<bool>,
+    /// Set for the update a button went up in.
+    released: Vec<bool>,
+    /// Mouse buttons click when they're let go, like UI buttons, while keys click as soon as they're pressed.
+    click_on_release: bool
 }
 
 impl ButtonInputState {
-    fn update(&mut self, pressed_this_frame: &mut Vec<i32>, released_this_frame: &mut Vec<i32>) {
+    fn new(count: usize, click_on_release: bool) -> Self {
+        ButtonInputState {
+            down: vec![false; count],
+            clicked: vec![false; count],
+            released: vec![false; count],
+            click_on_release
+        }
+    }
+
+    /// Applies the events in order, so a button tapped between updates is both clicked and released for one update.
+    fn update(&mut self, events_this_frame: &mut Vec<keyboard_input::ButtonEvent>) {
         // Used memset for highly optimized resetting of the keys pressed.
         unsafe {
             let p_keys_pressed = self.clicked.as_mut_ptr();
             p_keys_pressed.write_bytes(false as u8, self.clicked.len());
-        }
 
-        while let Some(button) = pressed_this_frame.pop() {
-            self.down[button as usize] = true;
+            let p_keys_released = self.released.as_mut_ptr();
+            p_keys_released.write_bytes(false as u8, self.released.len());
         }
 
-        while let Some(button) = released_this_frame.pop() {
-            self.down[button as usize] = false;
-            self.clicked[button as usize] = true;
+        for event in events_this_frame.drain(..) {
+            let button = event.button as usize;
+
+            if event.pressed && !self.down[button] {
+                self.down[button] = true;
+                self.clicked[button] |= !self.click_on_release;
+            }
+            else if !event.pressed && self.down[button] {
+                self.down[button] = false;
+                self.clicked[button] |= self.click_on_release;
+                self.released[button] = true;
+            }
         }
     }
 }
@@ -41,7 +67,20 @@ pub trait WindowControl {
     /// Sends a message to close the window to the client.
     fn close_window(&mut self);
 
+    /// True once the window has been asked to close, by the user or close_window.
+    fn should_close(&self) -> bool;
+
     fn set_vsync(&self, vsync: bool);
+
+    /// Returns the size of the framebuffer in pixels. This can differ from the window size on high DPI displays.
+    fn get_framebuffer_size(&self) -> (i32, i32);
+
+    /// Returns the number of MSAA samples the window's framebuffer was actually created with, 0 when multisampling is off.
+    fn get_msaa_samples(&self) -> i32;
+
+    /// Changes how the cursor behaves while it's over the window.
+    fn set_cursor_mode(&mut self, mode: CursorMode);
+    fn get_cursor_mode(&self) -> CursorMode;
 }
 
 pub trait MouseKeyboardInputControl {
@@ -50,60 +89,144 @@ pub trait MouseKeyboardInputControl {
 
     /// Returns true if the key state if the key is 'down'
     fn is_key_down(&self, key: keyboard_input::Key) -> bool;
-    /// Returns true if the key state is 'pressed'
+    /// Returns true for the update the key went down in, once per physical press. Key repeats don't click again.
     fn is_key_clicked(&self, key: keyboard_input::Key) -> bool;
     /// Returns true if the key state is 'up'
     fn is_key_up(&self, key: keyboard_input::Key) -> bool {
         !self.is_key_down(key)
     }
+    /// Returns true for the update the key went up in, ex: to fire a charged action on release.
+    fn is_key_released(&self, key: keyboard_input::Key) -> bool;
+
+    /// Returns true if the physical key with the scancode is down, whatever the keyboard layout.
+    /// ex: `Key::W.to_scancode()` for movement on the key in W's place.
+    fn is_scancode_down(&self, scancode: i32) -> bool;
 
     // Returns true if the mouse button state is 'down'
     fn is_mouse_down(&self, button: mouse_input::MouseButton) -> bool;
-    // Returns true if the mouse button state is 'pressed'
+    /// Returns true for the update the mouse button was let go in, once per press, the way UI buttons click.
+    /// Unlike keys, which click as soon as they go down, see `is_key_clicked`.
     fn is_mouse_clicked(&self, button: mouse_input::MouseButton) -> bool;
     // Returns true if the mouse button state is 'released'
     fn is_mouse_up(&self, button: mouse_input::MouseButton) -> bool {
         !self.is_mouse_down(button)
     }
-
-    // Returns the amount the mouse has scrolled in the X axis.
+    /// Returns true for the update the mouse button went up in.
+    fn is_mouse_released(&self, button: mouse_input::MouseButton) -> bool;
+
+    /// Returns the cursor position in framebuffer pixels from the top left of the window, y down, as of
+    /// the last update. The position isn't clamped: while a button is held it keeps following the cursor
+    /// outside the window, so it can be negative or past the window's size. Otherwise it stays where the
+    /// cursor left. Check `is_cursor_inside` before treating it as a point in the window.
+    fn mouse_position(&self) -> Vec2f;
+    /// Returns true when the cursor was over the window at the last update.
+    fn is_cursor_inside(&self) -> bool;
+    /// Returns how far the wheel scrolled during the last update, in lines: one notch of a standard wheel is 1.
+    /// Positive y scrolls up (away from the user) and positive x scrolls right. Trackpads report fractional
+    /// lines, scaled by glfw to roughly match a wheel. Platforms with natural scrolling flip the sign, as they
+    /// do for every other application.
+    fn scroll_delta(&self) -> Vec2f;
+
+    /// Tells the input which cursor mode the window is in. Changing modes resets the mouse delta, so
+    /// grabbing the cursor doesn't report the jump to its new position as movement.
+    fn set_cursor_mode(&mut self, mode: CursorMode);
+
+    /// Returns how far the mouse moved during the last update, in framebuffer pixels with y down.
+    /// While the cursor is disabled (grabbed) the window reads raw motion where the platform supports it, and
+    /// deltas keep accumulating past the window's edges. Otherwise they're differences between cursor positions.
+    /// The update after the cursor mode changes, the window regains focus, or the cursor enters the window
+    /// reports zero rather than a jump.
+    fn mouse_delta(&self) -> Vec2f;
+
+    /// Returns the amount the mouse has moved in the X or Y axis since the last update, in whole framebuffer
+    /// pixels. On high DPI displays these are larger than window coordinates, so the same movement reports
+    /// a bigger delta than it did in screen coordinates. Always 0 unless the cursor is disabled, use
+    /// `mouse_delta` for movement in every cursor mode.
     fn get_mouse_dx(&self) -> i32;
     fn get_mouse_dy(&self) -> i32;
 }
 
 pub struct GraphicsWindow {
-    window: Window
+    window: Window,
+    /// Size of the framebuffer the viewport was last set to.
+    framebuffer_size: (i32, i32),
+    /// Buffers cleared at the start of each frame.
+    clear_mask: GLbitfield,
+    cursor_mode: CursorMode,
+    /// Samples granted by the driver, which may differ from what was requested.
+    msaa_samples: i32
 }
 
 #[derive(Default, Clone)]
 pub struct MouseKeyboardInput {
     keyboard_input: ButtonInputState,
-    mouse_button_input: ButtonInputState
+    scancode_input: ButtonInputState,
+    mouse_button_input: ButtonInputState,
+    cursor_mode: CursorMode,
+    /// Cursor position at the last update, None until the first update after a reset, see `mouse_delta`.
+    last_cursor_position: Option<(f64, f64)>,
+    mouse_delta: (f64, f64),
+    cursor_position: (f64, f64),
+    cursor_inside: bool,
+    scroll_delta: (f64, f64)
 }
 
 impl MouseKeyboardInput {
     pub fn new() -> Self {
         MouseKeyboardInput {
-            keyboard_input: ButtonInputState {
-                down: vec![false; NUM_KEYS_INPUT],
-                clicked: vec![false; NUM_KEYS_INPUT]
-            },
-            mouse_button_input: ButtonInputState { 
-                down: vec![false; NUM_MOUSE_BUTTONS],
-                clicked: vec![false; NUM_MOUSE_BUTTONS]
-            }
+            keyboard_input: ButtonInputState::new(NUM_KEYS_INPUT, false),
+            scancode_input: ButtonInputState::new(keyboard_input::NUM_SCANCODES, false),
+            mouse_button_input: ButtonInputState::new(NUM_MOUSE_BUTTONS, true),
+            cursor_mode: CursorMode::Normal,
+            last_cursor_position: None,
+            mouse_delta: (0.0, 0.0),
+            cursor_position: (0.0, 0.0),
+            cursor_inside: false,
+            scroll_delta: (0.0, 0.0)
         }
     }
+
+    /// Updates the cursor position, and the mouse delta from it, to the position reported this frame.
+    pub(crate) fn update_cursor(&mut self, position: (f64, f64)) {
+        self.cursor_position = position;
+
+        // The first position after a reset only sets the reference point,
+        // otherwise the jump from wherever the cursor was would be reported as movement.
+        self.mouse_delta = match self.last_cursor_position {
+            Some(last) => (position.0 - last.0, position.1 - last.1),
+            None => (0.0, 0.0)
+        };
+
+        self.last_cursor_position = Some(position);
+    }
 }
 
 impl MouseKeyboardInputControl for MouseKeyboardInput {
     fn update_input(&mut self) {
         unsafe {
-            self.keyboard_input.update(&mut keyboard_input::KEY_INPUTS.keys_pressed_frame, 
-                &mut keyboard_input::KEY_INPUTS.keys_released_frame);
+            self.keyboard_input.update(&mut keyboard_input::KEY_INPUTS.key_events_frame);
+            self.scancode_input.update(&mut keyboard_input::KEY_INPUTS.scancode_events_frame);
+            self.mouse_button_input.update(&mut mouse_input::MOUSE_BUTTON_INPUTS.button_events_frame);
+
+            if mouse_input::CURSOR_POSITION.jumped {
+                self.last_cursor_position = None;
+                mouse_input::CURSOR_POSITION.jumped = false;
+            }
+
+            self.update_cursor((mouse_input::CURSOR_POSITION.x, mouse_input::CURSOR_POSITION.y));
+            self.cursor_inside = mouse_input::CURSOR_POSITION.inside;
 
-            self.mouse_button_input.update(&mut mouse_input::MOUSE_BUTTON_INPUTS.buttons_pressed_frame, 
-                &mut mouse_input::MOUSE_BUTTON_INPUTS.buttons_released_frame);
+            // The scrolling is consumed, so each update only reports what happened since the last one.
+            self.scroll_delta = (mouse_input::SCROLL_OFFSET.x, mouse_input::SCROLL_OFFSET.y);
+            mouse_input::SCROLL_OFFSET = mouse_input::ScrollOffset { x: 0.0, y: 0.0 };
+        }
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) {
+        if mode != self.cursor_mode {
+            self.cursor_mode = mode;
+            self.last_cursor_position = None;
+            self.mouse_delta = (0.0, 0.0);
         }
     }
 
@@ -121,6 +244,17 @@ impl MouseKeyboardInputControl for MouseKeyboardInput {
         }
     }
 
+    fn is_key_released(&self, key: keyboard_input::Key) -> bool {
+        self.keyboard_input.released.get(key as usize).copied().unwrap_or(false)
+    }
+
+    fn is_scancode_down(&self, scancode: i32) -> bool {
+        usize::try_from(scancode).ok()
+            .and_then(|scancode| self.scancode_input.down.get(scancode))
+            .copied()
+            .unwrap_or(false)
+    }
+
     fn is_mouse_down(&self, button: mouse_input::MouseButton) -> bool {
         return match self.mouse_button_input.down.get(button as usize) {
             Some(val) => *val,
@@ -135,12 +269,41 @@ impl MouseKeyboardInputControl for MouseKeyboardInput {
         }
     }
 
+    fn is_mouse_released(&self, button: mouse_input::MouseButton) -> bool {
+        return match self.mouse_button_input.released.get(button as usize) {
+            Some(val) => *val,
+            None => false
+        }
+    }
+
+    fn mouse_position(&self) -> Vec2f {
+        Vec2f::new(self.cursor_position.0 as f32, self.cursor_position.1 as f32)
+    }
+
+    fn is_cursor_inside(&self) -> bool {
+        self.cursor_inside
+    }
+
+    fn scroll_delta(&self) -> Vec2f {
+        Vec2f::new(self.scroll_delta.0 as f32, self.scroll_delta.1 as f32)
+    }
+
+    fn mouse_delta(&self) -> Vec2f {
+        Vec2f::new(self.mouse_delta.0 as f32, self.mouse_delta.1 as f32)
+    }
+
     fn get_mouse_dx(&self) -> i32 {
-        0
+        match self.cursor_mode {
+            CursorMode::Disabled => self.mouse_delta.0 as i32,
+            _ => 0
+        }
     }
 
     fn get_mouse_dy(&self) -> i32 {
-        0
+        match self.cursor_mode {
+            CursorMode::Disabled => self.mouse_delta.1 as i32,
+            _ => 0
+        }
     }
 
 }
@@ -150,15 +313,30 @@ impl WindowControl for GraphicsWindow {
         self.window.set_should_close(true);
     }
 
+    fn should_close(&self) -> bool {
+        self.window.should_close()
+    }
+
     fn update_window(&mut self) -> bool {
         // Update input state.
         self.window.glfw.poll_events();
 
+        // Keep the viewport covering the whole window when it's resized.
+        let framebuffer_size = self.window.get_framebuffer_size();
+
+        if framebuffer_size != self.framebuffer_size {
+            self.framebuffer_size = framebuffer_size;
+
+            unsafe {
+                glViewport(0, 0, framebuffer_size.0, framebuffer_size.1);
+            }
+        }
+
         self.window.swap_buffers();
 
         // Clear the window.
         unsafe {
-            glClear(GL_COLOR_BUFFER_BIT);
+            glClear(self.clear_mask);
         }
 
         return self.window.should_close();
@@ -176,6 +354,30 @@ impl WindowControl for GraphicsWindow {
             }
         }
     }
+
+    fn get_framebuffer_size(&self) -> (i32, i32) {
+        self.framebuffer_size
+    }
+
+    fn get_msaa_samples(&self) -> i32 {
+        self.msaa_samples
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) {
+        unsafe {
+            let raw_motion_supported = glfwRawMouseMotionSupported() != 0;
+
+            for (input_mode, value) in get_cursor_input_modes(mode, raw_motion_supported) {
+                glfwSetInputMode(self.window.window_ptr(), input_mode, value);
+            }
+        }
+
+        self.cursor_mode = mode;
+    }
+
+    fn get_cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
 }
 
 fn load_gl_functions() {
@@ -185,11 +387,27 @@ fn load_gl_functions() {
     }
 }
 
-#[derive(Default, Clone, Copy)]
+/// Looks up a GL function which isn't part of GL 3.3, ex: one added by an extension.
+/// Returns null when the driver doesn't provide it. Needs a window's context to be current.
+pub fn gl_proc_address(name: &str) -> *const std::ffi::c_void {
+    match std::ffi::CString::new(name) {
+        Ok(name) => unsafe { glfwGetProcAddress(name.as_ptr()) },
+        Err(_) => std::ptr::null()
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct WindowClearColor {
     pub r: f32,
     pub g: f32,
-    pub b: f32
+    pub b: f32,
+    pub a: f32
+}
+
+impl Default for WindowClearColor {
+    fn default() -> Self {
+        WindowClearColor { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
+    }
 }
 
 #[derive(Clone)]
@@ -200,6 +418,23 @@ pub struct WindowConfig {
     pub title: String,
     pub vsync: bool,
     pub resizable: bool,
+    /// Requests an sRGB capable framebuffer and enables GL_FRAMEBUFFER_SRGB.
+    /// Shaders should then output linear colors; GL converts them to sRGB when writing to the window.
+    pub srgb: bool,
+    /// Clears the depth buffer along with the color at the start of each frame.
+    pub clear_depth: bool,
+    /// Clears the stencil buffer along with the color at the start of each frame.
+    pub clear_stencil: bool,
+    /// Bits per pixel of the window's stencil buffer, 0 for none. 8 is enough for most masking.
+    pub stencil_bits: u32,
+    /// Paths of the images used as the window icon. Giving several sizes lets the OS pick the
+    /// closest match for the title bar and task bar.
+    pub icon_paths: Vec<String>,
+    /// Number of samples per pixel used to smooth edges: 0, 2, 4, or 8. 0 disables multisampling.
+    pub msaa_samples: u32,
+    /// Requests a debug context, so the driver reports errors and warnings as they happen.
+    /// Debug contexts can be noticeably slower, so leave this off outside development.
+    pub gl_debug: bool,
 }
 
 impl Default for WindowConfig {
@@ -211,10 +446,133 @@ impl Default for WindowConfig {
             clear_color: WindowClearColor::default(),
             vsync: true,
             resizable: true,
+            srgb: false,
+            clear_depth: true,
+            clear_stencil: false,
+            stencil_bits: 8,
+            icon_paths: Vec::<String>::new(),
+            msaa_samples: 0,
+            gl_debug: false,
+        }
+    }
+}
+
+/// Returns the GLFW window hints requested by the config as (hint, value) pairs.
+pub fn get_window_hints(config: &WindowConfig) -> Vec<(i32, i32)> {
+    let mut hints = vec![(RESIZABLE, config.resizable as i32), (STENCIL_BITS, config.stencil_bits as i32)];
+
+    if config.srgb {
+        hints.push((SRGB_CAPABLE, 1));
+    }
+
+    let samples = valid_msaa_samples(config.msaa_samples);
+    if samples > 0 {
+        hints.push((SAMPLES, samples as i32));
+    }
+
+    if config.gl_debug {
+        hints.push((OPENGL_DEBUG_CONTEXT, 1));
+    }
+
+    hints
+}
+
+/// Sample counts which may be requested for MSAA.
+pub const MSAA_SAMPLE_COUNTS: [u32; 4] = [0, 2, 4, 8];
+
+/// Returns the sample count to request for the configured one. Unsupported counts fall back to the
+/// closest lower supported count, ex: 6 becomes 4 and 16 becomes 8. The app config warns when it's loaded.
+pub fn valid_msaa_samples(requested: u32) -> u32 {
+    if MSAA_SAMPLE_COUNTS.contains(&requested) {
+        return requested;
+    }
+
+    MSAA_SAMPLE_COUNTS.iter()
+        .copied()
+        .filter(|samples| *samples <= requested)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Decodes the icon images into RGBA pixels. Icons which fail to load are skipped with a warning,
+/// so a bad icon never stops the window from opening.
+pub fn load_window_icons(paths: &[String]) -> Vec<PixelImage> {
+    let mut icons = Vec::<PixelImage>::with_capacity(paths.len());
+
+    for path in paths {
+        match image::open(path) {
+            Ok(icon) => {
+                let icon = icon.to_rgba8();
+
+                icons.push(PixelImage {
+                    width: icon.width(),
+                    height: icon.height(),
+                    // GLFW reads the pixels as bytes in RGBA order.
+                    pixels: icon.pixels().map(|pixel| u32::from_le_bytes(pixel.0)).collect()
+                });
+            },
+            Err(err) => println!("Unable to load window icon {}: {}", path, err)
         }
     }
+
+    icons
+}
+
+/// Loads the configured icons and passes them to set_icon. Nothing is set if no icon loads.
+pub fn apply_window_icon<F: FnOnce(Vec<PixelImage>)>(config: &WindowConfig, set_icon: F) {
+    if config.icon_paths.is_empty() {
+        return;
+    }
+
+    let icons = load_window_icons(&config.icon_paths);
+
+    if !icons.is_empty() {
+        set_icon(icons);
+    }
+}
+
+/// Returns the (mode, value) pairs passed to glfwSetInputMode to put the cursor in the given mode.
+/// Raw mouse motion is turned on while the cursor is disabled when the platform supports it,
+/// so mouse-look isn't affected by pointer acceleration.
+pub fn get_cursor_input_modes(mode: CursorMode, raw_motion_supported: bool) -> Vec<(i32, i32)> {
+    let mut input_modes = vec![(CURSOR, mode.to_glfw())];
+
+    if raw_motion_supported {
+        input_modes.push((RAW_MOUSE_MOTION, (mode == CursorMode::Disabled) as i32));
+    }
+
+    input_modes
 }
 
+/// Returns the buffers cleared at the start of each frame.
+pub fn get_clear_mask(config: &WindowConfig) -> GLbitfield {
+    let mut mask = GL_COLOR_BUFFER_BIT;
+
+    if config.clear_depth {
+        mask |= GL_DEPTH_BUFFER_BIT;
+    }
+
+    if config.clear_stencil {
+        mask |= GL_STENCIL_BUFFER_BIT;
+    }
+
+    mask
+}
+
+/// Returns the GL capabilities which should be enabled once the context is created.
+pub fn get_enabled_capabilities(config: &WindowConfig) -> Vec<GLenum> {
+    let mut capabilities = Vec::<GLenum>::new();
+
+    if config.srgb {
+        capabilities.push(GL_FRAMEBUFFER_SRGB);
+    }
+
+    if valid_msaa_samples(config.msaa_samples) > 0 {
+        capabilities.push(GL_MULTISAMPLE);
+    }
+
+    capabilities
+}
 
 impl GraphicsWindow {
     pub fn new(config: &WindowConfig) -> GraphicsWindow {
@@ -222,16 +580,31 @@ impl GraphicsWindow {
 
         // Set window parameters.
         unsafe {
-            glfwWindowHint(RESIZABLE, 0);
+            for (hint, value) in get_window_hints(config) {
+                glfwWindowHint(hint, value);
+            }
         }
 
-        let (mut window, _events) = glfw
-            .create_window(config.xres, config.yres, &config.title, glfw::WindowMode::Windowed)
-            .expect("Failed to create window.");
+        let mut created = glfw.create_window(config.xres, config.yres, &config.title, glfw::WindowMode::Windowed);
+
+        // Some drivers refuse a multisampled framebuffer, so try again without one before giving up.
+        if created.is_none() && valid_msaa_samples(config.msaa_samples) > 0 {
+            println!("Unable to create a window with {} msaa samples, falling back to no multisampling", config.msaa_samples);
+
+            unsafe {
+                glfwWindowHint(SAMPLES, 0);
+            }
+
+            created = glfw.create_window(config.xres, config.yres, &config.title, glfw::WindowMode::Windowed);
+        }
+
+        let (mut window, _events) = created.expect("Failed to create window.");
 
         window.set_key_polling(true);
         window.make_current();
 
+        apply_window_icon(config, |icons| window.set_icon_from_pixels(icons));
+
         // Add gl context to window.
         load_gl_functions();
 
@@ -242,6 +615,18 @@ impl GraphicsWindow {
 
             let mouse_cb: Option<glfw::ffi::GLFWmousebuttonfun> = Some(mouse_input::mouse_callback);
             glfwSetMouseButtonCallback(window.window_ptr(), mouse_cb);
+
+            let cursor_cb: Option<glfw::ffi::GLFWcursorposfun> = Some(mouse_input::cursor_position_callback);
+            glfwSetCursorPosCallback(window.window_ptr(), cursor_cb);
+
+            let cursor_enter_cb: Option<glfw::ffi::GLFWcursorenterfun> = Some(mouse_input::cursor_enter_callback);
+            glfwSetCursorEnterCallback(window.window_ptr(), cursor_enter_cb);
+
+            let scroll_cb: Option<glfw::ffi::GLFWscrollfun> = Some(mouse_input::scroll_callback);
+            glfwSetScrollCallback(window.window_ptr(), scroll_cb);
+
+            let focus_cb: Option<glfw::ffi::GLFWwindowfocusfun> = Some(mouse_input::window_focus_callback);
+            glfwSetWindowFocusCallback(window.window_ptr(), focus_cb);
         }
 
         // Set the window to behave as specified in the config:
@@ -251,11 +636,30 @@ impl GraphicsWindow {
                 false => glfwSwapInterval(0)
             }
 
-            glClearColor(config.clear_color.r, config.clear_color.g, config.clear_color.b, 1.0);
+            glClearColor(config.clear_color.r, config.clear_color.g, config.clear_color.b, config.clear_color.a);
+
+            for capability in get_enabled_capabilities(config) {
+                glEnable(capability);
+            }
+        }
+
+        let framebuffer_size = window.get_framebuffer_size();
+
+        let mut msaa_samples: GLint = 0;
+        unsafe {
+            glGetIntegerv(GL_SAMPLES, &mut msaa_samples);
+        }
+
+        if msaa_samples != valid_msaa_samples(config.msaa_samples) as i32 {
+            println!("Requested {} msaa samples, but the window was created with {}", config.msaa_samples, msaa_samples);
         }
 
         GraphicsWindow {
-            window
+            window,
+            framebuffer_size,
+            clear_mask: get_clear_mask(config),
+            cursor_mode: CursorMode::Normal,
+            msaa_samples
         }
     }
 }
\ No newline at end of file
@@ -45,6 +45,24 @@ impl<T: Vectorable<T> + PartialOrd + Copy> Mat44<T> {
     };
 }
 
+impl<T: Vectorable<T> + PartialOrd + Copy + 
+    Add<Output = T> + Sub<Output = T> + Div<Output = T> + Neg<Output = T>> Mat44<T> 
+{
+    /// Creates an orthographic projection which maps the given box onto normalized device coordinates.
+    pub fn ortho(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Mat44<T> {
+        let mut mat = Self::IDENTITY;
+
+        mat.data[0][0] = T::TWO / (right - left);
+        mat.data[1][1] = T::TWO / (top - bottom);
+        mat.data[2][2] = -T::TWO / (far - near);
+        mat.data[3][0] = -(right + left) / (right - left);
+        mat.data[3][1] = -(top + bottom) / (top - bottom);
+        mat.data[3][2] = -(far + near) / (far - near);
+
+        mat
+    }
+}
+
 impl<T: PartialOrd + Copy + Display> Display for Mat44<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "[{}, {}, {}]\n[{}, {}, {}]\n[{}, {}, {}]", 
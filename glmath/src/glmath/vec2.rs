@@ -1,6 +1,7 @@
 use std::{ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Neg}, fmt::Display};
 use crate::glmath::*;
 
+#[repr(C)]
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Vec2<T: PartialOrd + Copy> {
     pub x: T,
@@ -143,6 +144,35 @@ impl<T: PartialOrd + Copy + DivAssign> DivAssign<T> for Vec2<T> {
     }
 }
 
+/// Distance and polar coordinate helpers.
+impl<T: PartialOrd + Copy + Vectorable<T> +
+    std::ops::Sub<Output = T> +
+    std::ops::Mul<Output = T> +
+    std::ops::Add<Output = T>> Vec2<T> {
+    /// Computes the distance between two points.
+    pub fn distance(a: Vec2<T>, b: Vec2<T>) -> T {
+        Self::distance_squared(a, b).sqrt()
+    }
+
+    /// Computes the squared distance between two points, for comparing distances without a sqrt.
+    pub fn distance_squared(a: Vec2<T>, b: Vec2<T>) -> T {
+        let dx = a.x - b.x;
+        let dy = a.y - b.y;
+
+        dx * dx + dy * dy
+    }
+
+    /// Computes the angle of the vector in radians from the positive X axis, in -PI..PI.
+    pub fn angle(&self) -> T {
+        T::atan2(self.y, self.x)
+    }
+
+    /// Creates the unit vector at the given angle in radians from the positive X axis.
+    pub fn from_angle(angle: T) -> Vec2<T> {
+        Vec2::<T> { x: angle.cos(), y: angle.sin() }
+    }
+}
+
 impl<T: PartialOrd + Copy +
     std::ops::Div<Output = T>> TwoDimSwizzle<T> for Vec2<T> {
     fn x(&self) -> &T {
@@ -2,6 +2,7 @@ use std::{ops::{Add, AddAssign, Neg, Sub, SubAssign, Mul, MulAssign, Rem}, fmt::
 
 use crate::glmath::*;
 
+#[repr(C)]
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Vec3<T: PartialOrd + Copy> {
     pub x: T,
@@ -1,6 +1,7 @@
 use std::{ops::{Add, MulAssign, AddAssign, SubAssign, Sub, Neg}, fmt::Display};
 use crate::glmath::*;
 
+#[repr(C)]
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Vec4<T: PartialOrd + Copy> {
     pub x: T,
@@ -206,4 +206,34 @@ mod tests {
         // Idt * scale matrix = scale matrix.
         assert_eq!(Mat44f::IDENTITY * scale_mat, scale_mat);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_mat44_ortho() {
+        // A box of -1 to 1 on every axis is already in NDC.
+        assert_eq!(Mat44f::ortho(-1.0, 1.0, -1.0, 1.0, 1.0, -1.0), Mat44f::IDENTITY);
+
+        // The corners of the box map to the corners of NDC.
+        let ortho = Mat44f::ortho(0.0, 200.0, 0.0, 100.0, -1.0, 1.0);
+        assert_eq!(ortho * Vec4f::new(0.0, 0.0, 0.0, 1.0), Vec4f::new(-1.0, -1.0, 0.0, 1.0));
+        assert_eq!(ortho * Vec4f::new(200.0, 100.0, 0.0, 1.0), Vec4f::new(1.0, 1.0, 0.0, 1.0));
+        assert_eq!(ortho * Vec4f::new(100.0, 50.0, 0.0, 1.0), Vec4f::new(0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_vec2_distance_and_angle() {
+        let a = Vec2f::new(1.0, 2.0);
+        let b = Vec2f::new(4.0, 6.0);
+
+        assert_eq!(Vec2f::distance_squared(a, b), 25.0);
+        assert_eq!(Vec2f::distance(a, b), 5.0);
+        assert_eq!(Vec2f::distance(b, a), 5.0);
+
+        assert_eq!(Vec2f::X.angle(), 0.0);
+        assert!((Vec2f::Y.angle() - PI / 2.0).abs() < 1e-6);
+        assert!((Vec2f::new(-1.0, 0.0).angle() - PI).abs() < 1e-6);
+
+        let unit = Vec2f::from_angle(PI / 3.0);
+        assert!((unit.length() - 1.0).abs() < 1e-6);
+        assert!((unit.angle() - PI / 3.0).abs() < 1e-6);
+    }
+}
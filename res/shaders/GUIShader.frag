@@ -4,7 +4,9 @@ out vec4 color;
 in vec2 texCoord;
 
 uniform sampler2D guiTexture;
+// Multiplied with the texture, alpha included.
+uniform vec4 tint = vec4(1, 1, 1, 1);
 
 void main() {
-    color = texture(guiTexture, texCoord);
+    color = texture(guiTexture, texCoord) * tint;
 }
\ No newline at end of file
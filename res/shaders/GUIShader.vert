@@ -3,12 +3,23 @@ in vec2 position;
 
 uniform vec2 pos;
 uniform vec2 scale;
+uniform mat4 viewProjection;
+// Part of the texture drawn, with v pointing down from the top of the image.
+uniform vec2 uvMin = vec2(0, 0);
+uniform vec2 uvMax = vec2(1, 1);
+// Counter-clockwise rotation about the center of the quad, in radians.
+uniform float rotation = 0.0;
 
 out vec2 texCoord;
 
 void main() {
-    gl_Position = vec4((position.x * scale.x) + pos.x, (position.y * scale.y) + pos.y, 0, 1);
+    vec2 corner_offset = position * scale;
+    float c = cos(rotation);
+    float s = sin(rotation);
+    corner_offset = vec2(corner_offset.x * c - corner_offset.y * s, corner_offset.x * s + corner_offset.y * c);
 
-    texCoord = position * .5 + .5;
-	texCoord.y = -texCoord.y;
+    gl_Position = viewProjection * vec4(corner_offset + pos, 0, 1);
+
+    vec2 corner = position * .5 + .5;
+    texCoord = mix(uvMin, uvMax, vec2(corner.x, 1 - corner.y));
 }
\ No newline at end of file
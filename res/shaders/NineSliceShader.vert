@@ -0,0 +1,17 @@
+#version 130
+in vec2 position;
+
+uniform vec2 pos;
+uniform vec2 scale;
+uniform vec2 uvMin;
+uniform vec2 uvMax;
+uniform mat4 viewProjection;
+
+out vec2 texCoord;
+
+void main() {
+    gl_Position = viewProjection * vec4((position.x * scale.x) + pos.x, (position.y * scale.y) + pos.y, 0, 1);
+
+    // The quad spans 0..1, with v pointing down in the texture.
+    texCoord = mix(uvMin, uvMax, position);
+}
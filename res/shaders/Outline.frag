@@ -0,0 +1,8 @@
+#version 130
+out vec4 color;
+
+uniform vec4 outlineColor;
+
+void main() {
+    color = outlineColor;
+}
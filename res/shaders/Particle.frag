@@ -0,0 +1,11 @@
+#version 130
+out vec4 color;
+
+in vec4 fragColor;
+in vec2 local;
+
+void main() {
+    // A soft round dot, fading out towards the edge of the quad.
+    float falloff = 1.0 - smoothstep(0.3, 1.0, length(local));
+    color = vec4(fragColor.rgb, fragColor.a * falloff);
+}
\ No newline at end of file
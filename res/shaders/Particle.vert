@@ -0,0 +1,18 @@
+#version 130
+in vec2 position;
+// Per particle, see ParticleSystem::attach_sized_to_mesh.
+in vec2 particlePosition;
+in vec4 particleColor;
+in float particleSize;
+
+uniform mat4 viewProjection;
+
+out vec4 fragColor;
+out vec2 local;
+
+void main() {
+    gl_Position = viewProjection * vec4(position * particleSize + particlePosition, 0, 1);
+
+    fragColor = particleColor;
+    local = position;
+}
\ No newline at end of file
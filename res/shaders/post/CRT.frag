@@ -0,0 +1,33 @@
+#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D screenTexture;
+uniform vec2 resolution;
+
+// How strongly the screen bulges. Higher values are flatter.
+uniform float curvature;
+// How dark the gaps between scanlines are, from 0 to 1.
+uniform float scanlineIntensity;
+
+void main() {
+    // Bend the coordinates outward from the center like a curved tube.
+    vec2 centered = texCoord * 2.0 - 1.0;
+    vec2 offset = centered.yx / max(curvature, 0.001);
+    centered += centered * offset * offset;
+    vec2 uv = centered * .5 + .5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        color = vec4(0, 0, 0, 1);
+        return;
+    }
+
+    vec3 scene = texture(screenTexture, uv).rgb;
+
+    // One dark line every other pixel row.
+    float scanline = sin(uv.y * resolution.y * 3.14159) * .5 + .5;
+    scene *= 1.0 - scanlineIntensity * scanline;
+
+    color = vec4(scene, 1);
+}
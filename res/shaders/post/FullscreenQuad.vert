@@ -0,0 +1,11 @@
+#version 130
+in vec2 position;
+
+out vec2 texCoord;
+
+void main() {
+    gl_Position = vec4(position, 0, 1);
+
+    // Render targets are stored bottom up, so no flip is needed.
+    texCoord = position * .5 + .5;
+}
@@ -0,0 +1,20 @@
+#version 130
+out vec4 color;
+
+in vec2 texCoord;
+
+uniform sampler2D screenTexture;
+
+// Distance from the center where darkening starts, with the corners at about 0.7.
+uniform float radius;
+// How dark the corners get, from 0 to 1.
+uniform float strength;
+
+void main() {
+    vec3 scene = texture(screenTexture, texCoord).rgb;
+
+    float distance = length(texCoord - .5);
+    float vignette = smoothstep(radius, radius + .35, distance);
+
+    color = vec4(scene * (1.0 - vignette * strength), 1);
+}
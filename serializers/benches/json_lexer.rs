@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, black_box, Criterion, Throughput};
+use serializers::json::lexer::{JsonLexer, Token, TokenType};
+
+/// Size of the synthetic document, about 1 MB.
+const TARGET_SIZE: usize = 1024 * 1024;
+
+/// Documents lexed at once by the multi-threaded variant.
+const THREAD_COUNT: usize = 4;
+
+/// A small deterministic generator so every run lexes the same document.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+/// Appends a value of varied type, nesting objects and arrays up to the given depth.
+fn write_value(json: &mut String, rng: &mut Lcg, depth: u32) {
+    let kind = if depth == 0 { rng.next() % 5 } else { rng.next() % 7 };
+
+    match kind {
+        0 => json.push_str(&(rng.next() as i64 - (1 << 30)).to_string()),
+        1 => json.push_str(&format!("{:.4}", (rng.next() % 100000) as f64 / 7.0 - 5000.0)),
+        2 => json.push_str(&format!("\"value_{}\"", rng.next() % 10000)),
+        3 => json.push_str(["true", "false"][(rng.next() % 2) as usize]),
+        4 => json.push_str("null"),
+        5 => {
+            json.push('[');
+
+            for index in 0..rng.next() % 8 {
+                if index > 0 {
+                    json.push_str(", ");
+                }
+
+                write_value(json, rng, depth - 1);
+            }
+
+            json.push(']');
+        },
+        _ => {
+            json.push_str("{\n");
+
+            for index in 0..rng.next() % 6 {
+                if index > 0 {
+                    json.push_str(",\n");
+                }
+
+                json.push_str(&format!("    \"key_{}\": ", index));
+                write_value(json, rng, depth - 1);
+            }
+
+            json.push_str("\n}");
+        }
+    }
+}
+
+/// Generates an array of nested objects and arrays of about TARGET_SIZE bytes.
+fn synthetic_json() -> String {
+    let mut rng = Lcg(0x5eed);
+    let mut json = String::with_capacity(TARGET_SIZE + 4096);
+    json.push('[');
+
+    while json.len() < TARGET_SIZE {
+        if json.len() > 1 {
+            json.push_str(",\n");
+        }
+
+        write_value(&mut json, &mut rng, 4);
+    }
+
+    json.push(']');
+    json
+}
+
+/// Lexes the whole document, returning the number of tokens and the byte offset lexing stopped at.
+fn lex_all(lexer: &mut JsonLexer) -> (usize, usize) {
+    let mut token = Token::default();
+    let mut token_count = 0;
+
+    lexer.reset();
+
+    loop {
+        lexer.next_token(&mut token);
+
+        if let TokenType::Undefined = token.get_type() {
+            return (token_count, token.get_position());
+        }
+
+        token_count += 1;
+    }
+}
+
+fn bench_json_lexer(c: &mut Criterion) {
+    let json = synthetic_json();
+    let mut lexer = JsonLexer::from_raw_json(&json).unwrap();
+    let (token_count, end) = lex_all(&mut lexer);
+
+    // The generator should only produce valid json, so lexing only stops at the end of the document.
+    assert_eq!(end, json.len());
+
+    // Criterion reports one throughput per group, so bytes and tokens get their own groups.
+    let mut bytes_group = c.benchmark_group("json_lexer_bytes");
+    bytes_group.warm_up_time(Duration::from_secs(3));
+    bytes_group.throughput(Throughput::Bytes(json.len() as u64));
+    bytes_group.bench_function("single_thread", |b| b.iter(|| black_box(lex_all(&mut lexer))));
+    bytes_group.finish();
+
+    let mut tokens_group = c.benchmark_group("json_lexer_tokens");
+    tokens_group.warm_up_time(Duration::from_secs(3));
+    tokens_group.throughput(Throughput::Elements(token_count as u64));
+    tokens_group.bench_function("single_thread", |b| b.iter(|| black_box(lex_all(&mut lexer))));
+    tokens_group.finish();
+
+    // Lexers share nothing, so separate documents lexed on separate threads should scale with the cores.
+    let mut lexers: Vec<JsonLexer> = (0..THREAD_COUNT).map(|_| JsonLexer::from_raw_json(&json).unwrap()).collect();
+
+    let mut threaded_group = c.benchmark_group("json_lexer_threaded");
+    threaded_group.warm_up_time(Duration::from_secs(3));
+    threaded_group.throughput(Throughput::Bytes((json.len() * THREAD_COUNT) as u64));
+    threaded_group.bench_function(format!("{}_threads", THREAD_COUNT), |b| b.iter(|| {
+        std::thread::scope(|scope| {
+            for lexer in lexers.iter_mut() {
+                scope.spawn(move || black_box(lex_all(lexer)));
+            }
+        });
+    }));
+    threaded_group.finish();
+}
+
+criterion_group!(benches, bench_json_lexer);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+//! Derives `JsonSerialize` and `JsonDeserialize` for enums.
+//!
+//! Unit variants are written as their name, ex: `"Hard"`. Variants holding data are written as an
+//! object tagged with the variant's name under "type". Struct variants add each field by name,
+//! tuple variants with one field add it as "value", and tuple variants with more fields add them
+//! as a "values" array:
+//!
+//! { "type": "Custom", "speed": 1.5 }, { "type": "Seeded", "value": 42 }, { "type": "Start", "values": [1, 2] }
+//!
+//! A unit variant also reads from a tagged object with no other keys. Variants with empty fields,
+//! ex: `Reset()` or `Reset {}`, are treated as unit variants.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Fields, Ident, LitStr};
+
+#[proc_macro_derive(JsonSerialize)]
+pub fn derive_json_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match enum_data(&input) {
+        Ok(data) => serialize_enum(&input, data).into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+#[proc_macro_derive(JsonDeserialize)]
+pub fn derive_json_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match enum_data(&input) {
+        Ok(data) => deserialize_enum(&input, data).into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+fn enum_data(input: &DeriveInput) -> syn::Result<&DataEnum> {
+    match &input.data {
+        Data::Enum(data) => Ok(data),
+        _ => Err(syn::Error::new_spanned(&input.ident, "json derives only support enums"))
+    }
+}
+
+/// Returns the pattern matching a variant without fields, which also constructs it, or None when it has fields.
+fn fieldless_pattern(name: &Ident, variant_name: &Ident, fields: &Fields) -> Option<TokenStream2> {
+    match fields {
+        Fields::Unit => Some(quote! { #name::#variant_name }),
+        Fields::Unnamed(fields) if fields.unnamed.is_empty() => Some(quote! { #name::#variant_name() }),
+        Fields::Named(fields) if fields.named.is_empty() => Some(quote! { #name::#variant_name {} }),
+        _ => None
+    }
+}
+
+/// Names bound to the fields of a variant, in order. Fields aren't bound under their own names so
+/// they can't shadow the generated code's locals, ex: a field named `writer`.
+fn tuple_bindings(count: usize) -> Vec<Ident> {
+    (0..count).map(|index| format_ident!("field_{}", index)).collect()
+}
+
+fn serialize_enum(input: &DeriveInput, data: &DataEnum) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let arms = data.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let tag = LitStr::new(&variant_name.to_string(), Span::call_site());
+
+        if let Some(pattern) = fieldless_pattern(name, variant_name, &variant.fields) {
+            return quote! {
+                #pattern => ::serializers::json::serialize::write_json_string(#tag, writer)?,
+            };
+        }
+
+        match &variant.fields {
+            Fields::Unit => unreachable!("unit variants are fieldless"),
+            Fields::Named(fields) => {
+                let field_names: Vec<&Ident> = fields.named.iter().filter_map(|field| field.ident.as_ref()).collect();
+                let keys = field_names.iter().map(|field| LitStr::new(&field.to_string(), Span::call_site()));
+                let bindings = tuple_bindings(field_names.len());
+
+                quote! {
+                    #name::#variant_name { #(#field_names: #bindings),* } => {
+                        writer.write_all(b"{\"type\":")?;
+                        ::serializers::json::serialize::write_json_string(#tag, writer)?;
+                        #(
+                            writer.write_all(b",")?;
+                            ::serializers::json::serialize::write_json_string(#keys, writer)?;
+                            writer.write_all(b":")?;
+                            ::serializers::json::serialize::JsonSerialize::write_json(#bindings, writer)?;
+                        )*
+                        writer.write_all(b"}")?;
+                    },
+                }
+            },
+            Fields::Unnamed(fields) => {
+                let bindings = tuple_bindings(fields.unnamed.len());
+
+                let values = match bindings.len() {
+                    1 => {
+                        let value = &bindings[0];
+                        quote! {
+                            writer.write_all(b",\"value\":")?;
+                            ::serializers::json::serialize::JsonSerialize::write_json(#value, writer)?;
+                        }
+                    },
+                    _ => {
+                        let (first, rest) = bindings.split_first().expect("tuple variants have at least one field");
+                        quote! {
+                            writer.write_all(b",\"values\":[")?;
+                            ::serializers::json::serialize::JsonSerialize::write_json(#first, writer)?;
+                            #(
+                                writer.write_all(b",")?;
+                                ::serializers::json::serialize::JsonSerialize::write_json(#rest, writer)?;
+                            )*
+                            writer.write_all(b"]")?;
+                        }
+                    }
+                };
+
+                quote! {
+                    #name::#variant_name(#(#bindings),*) => {
+                        writer.write_all(b"{\"type\":")?;
+                        ::serializers::json::serialize::write_json_string(#tag, writer)?;
+                        #values
+                        writer.write_all(b"}")?;
+                    },
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::serializers::json::serialize::JsonSerialize for #name #type_generics #where_clause {
+            fn write_json(&self, writer: &mut dyn ::std::io::Write) -> ::std::result::Result<(), ::serializers::json::serialize::SerializeError> {
+                use ::std::io::Write as _;
+
+                match self {
+                    #(#arms)*
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn deserialize_enum(input: &DeriveInput, data: &DataEnum) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let (unit_tags, unit_values): (Vec<LitStr>, Vec<TokenStream2>) = data.variants.iter()
+        .filter_map(|variant| {
+            let pattern = fieldless_pattern(name, &variant.ident, &variant.fields)?;
+            Some((LitStr::new(&variant.ident.to_string(), Span::call_site()), pattern))
+        })
+        .unzip();
+
+    let tagged_arms = data.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let tag = LitStr::new(&variant_name.to_string(), Span::call_site());
+
+        if let Some(value) = fieldless_pattern(name, variant_name, &variant.fields) {
+            return quote! {
+                #tag => Some(#value),
+            };
+        }
+
+        match &variant.fields {
+            Fields::Unit => unreachable!("unit variants are fieldless"),
+            Fields::Named(fields) => {
+                let field_names: Vec<&Ident> = fields.named.iter().filter_map(|field| field.ident.as_ref()).collect();
+                let keys = field_names.iter().map(|field| LitStr::new(&field.to_string(), Span::call_site()));
+
+                quote! {
+                    #tag => Some(#name::#variant_name {
+                        #(
+                            #field_names: ::serializers::json::deserialize::JsonDeserialize::from_json(__object.get(#keys)?)?,
+                        )*
+                    }),
+                }
+            },
+            Fields::Unnamed(fields) => {
+                let values = match fields.unnamed.len() {
+                    1 => quote! {
+                        ::serializers::json::deserialize::JsonDeserialize::from_json(__object.get("value")?)?
+                    },
+                    count => {
+                        let indices = 0..count;
+                        quote! {
+                            #(
+                                ::serializers::json::deserialize::JsonDeserialize::from_json(__values.get(#indices)?)?
+                            ),*
+                        }
+                    }
+                };
+
+                let values_array = match fields.unnamed.len() {
+                    1 => quote! {},
+                    count => quote! {
+                        let __values = match __object.get("values")? {
+                            ::serializers::json::parser::JsonNode::Array(values) if values.size() == #count => values,
+                            _ => return None
+                        };
+                    }
+                };
+
+                quote! {
+                    #tag => {
+                        #values_array
+                        Some(#name::#variant_name(#values))
+                    },
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::serializers::json::deserialize::JsonDeserialize for #name #type_generics #where_clause {
+            fn from_json(__node: &::serializers::json::parser::JsonNode) -> ::std::option::Option<Self> {
+                use ::serializers::json::parser::JsonValueOps;
+
+                match __node {
+                    ::serializers::json::parser::JsonNode::String(tag) => match tag.get().as_str() {
+                        #(#unit_tags => Some(#unit_values),)*
+                        _ => None
+                    },
+                    ::serializers::json::parser::JsonNode::Object(__object) => {
+                        let __tag = match __object.get("type")? {
+                            ::serializers::json::parser::JsonNode::String(tag) => tag.get().clone(),
+                            _ => return None
+                        };
+
+                        match __tag.as_str() {
+                            #(#tagged_arms)*
+                            _ => None
+                        }
+                    },
+                    _ => None
+                }
+            }
+        }
+    }
+}
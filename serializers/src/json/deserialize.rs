@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::json::parser::{JsonNode, JsonValueOps};
+
+/// Derives JsonDeserialize for enums, reading what the JsonSerialize derive writes.
+pub use serializers_derive::JsonDeserialize;
+
+/// Converts a parsed json node into a rust value.
+/// Returns None when the node holds a different type of value.
+pub trait JsonDeserialize: Sized {
+    fn from_json(node: &JsonNode) -> Option<Self>;
+}
+
+/// Integers accept whole numbers, including floats like 1.0. Floats accept both, since "1" parses as a whole number.
+macro_rules! impl_deserialize_integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonDeserialize for $t {
+                fn from_json(node: &JsonNode) -> Option<Self> {
+                    match node {
+                        // Parse the text directly, so u64 values beyond i64 still read.
+                        JsonNode::RawNumber(text) => text.get().parse::<$t>().ok()
+                            .or_else(|| node.coerce_to_i64().and_then(|value| <$t>::try_from(value).ok())),
+                        _ => node.coerce_to_i64().and_then(|value| <$t>::try_from(value).ok())
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_deserialize_integer!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl JsonDeserialize for f32 {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        f64::from_json(node).map(|value| value as f32)
+    }
+}
+
+impl JsonDeserialize for f64 {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        node.coerce_to_f64()
+    }
+}
+
+impl JsonDeserialize for bool {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        match node {
+            JsonNode::Bool(value) => Some(*value.get()),
+            _ => None
+        }
+    }
+}
+
+/// String nodes hold their text as written in the json, so the escapes are decoded here.
+impl JsonDeserialize for String {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        match node {
+            JsonNode::String(value) => unescape_json_string(value.get()),
+            _ => None
+        }
+    }
+}
+
+/// Decodes the escapes of json string text, the reverse of `escape_json_string`.
+/// Returns None for an unknown escape, or a \u escape which isn't a valid character.
+pub fn unescape_json_string(text: &str) -> Option<String> {
+    if !text.contains('\\') {
+        return Some(text.to_string());
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        let decoded = match chars.next()? {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let code = read_hex_escape(&mut chars)?;
+
+                // Characters outside the basic multilingual plane are written as a surrogate pair.
+                match code {
+                    0xD800..=0xDBFF => {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+
+                        let low = read_hex_escape(&mut chars)?;
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return None;
+                        }
+
+                        char::from_u32(0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00))?
+                    },
+                    _ => char::from_u32(code)?
+                }
+            },
+            _ => return None
+        };
+
+        unescaped.push(decoded);
+    }
+
+    Some(unescaped)
+}
+
+/// Reads the four hex digits of a \u escape.
+fn read_hex_escape(chars: &mut std::str::Chars) -> Option<u32> {
+    let digits: String = chars.take(4).collect();
+
+    match digits.len() == 4 && digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        true => u32::from_str_radix(&digits, 16).ok(),
+        false => None
+    }
+}
+
+/// Arrays deserialize when every element does.
+impl<T: JsonDeserialize> JsonDeserialize for Vec<T> {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        match node {
+            JsonNode::Array(array) => array.iter().map(T::from_json).collect(),
+            _ => None
+        }
+    }
+}
+
+/// Objects deserialize into maps when every value does. Keys are stored as written, so their escapes are decoded.
+impl<T: JsonDeserialize> JsonDeserialize for HashMap<String, T> {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        match node {
+            JsonNode::Object(object) => object.iter()
+                .map(|(key, value)| Some((unescape_json_string(key)?, T::from_json(value)?)))
+                .collect(),
+            _ => None
+        }
+    }
+}
+
+impl JsonDeserialize for JsonNode {
+    fn from_json(node: &JsonNode) -> Option<Self> {
+        Some(node.clone())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io::Error, str::FromStr};
+use std::{fmt::Display, io::Error, str::FromStr};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReserveCode {
@@ -28,6 +28,11 @@ pub enum TokenType {
     Float { 
         value: f64
     },
+    /// A number kept exactly as written, produced instead of Number and Float
+    /// when the lexer preserves raw numbers.
+    RawNumber {
+        text: String
+    },
     Boolean {
         value: bool
     },
@@ -44,22 +49,90 @@ impl Default for TokenType {
     }
 }
 
+/// The kinds of number a json number can be read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericKind {
+    Integer,
+    Float
+}
+
+/// Returns true when the token holds a number which converts to the target kind without losing anything,
+/// ex: 1.0 is compatible with Integer, while 1.5 isn't.
+pub fn is_numeric_compatible(token: &TokenType, target: NumericKind) -> bool {
+    match (token, target) {
+        (TokenType::Number { .. }, NumericKind::Integer) => true,
+        (TokenType::Number { value }, NumericKind::Float) => integer_to_f64(*value).is_some(),
+        (TokenType::Float { value }, NumericKind::Integer) => float_to_i64(*value).is_some(),
+        (TokenType::Float { .. }, NumericKind::Float) => true,
+        (TokenType::RawNumber { text }, NumericKind::Integer) => raw_number_to_i64(text).is_some(),
+        (TokenType::RawNumber { text }, NumericKind::Float) => raw_number_to_f64(text).is_some(),
+        _ => false
+    }
+}
+
+/// Converts an integer to a float when the float holds it exactly, which is up to 2^53.
+pub(crate) fn integer_to_f64(value: i64) -> Option<f64> {
+    const MAX_EXACT: u64 = 1 << f64::MANTISSA_DIGITS;
+
+    if value.unsigned_abs() <= MAX_EXACT { Some(value as f64) } else { None }
+}
+
+/// Converts a float to an integer when it's a whole number within range of i64.
+pub(crate) fn float_to_i64(value: f64) -> Option<i64> {
+    // i64::MAX rounds up to 2^63 as a float, so the upper bound is exclusive.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if value.is_finite() && value.fract() == 0.0 && (-LIMIT..LIMIT).contains(&value) {
+        Some(value as i64)
+    }
+    else {
+        None
+    }
+}
+
+/// Reads the text of a raw number as an integer, accepting floats like "1.0" and "1e3" which are whole numbers.
+pub(crate) fn raw_number_to_i64(text: &str) -> Option<i64> {
+    text.parse::<i64>().ok().or_else(|| text.parse::<f64>().ok().and_then(float_to_i64))
+}
+
+/// Reads the text of a raw number as a float. Integers must be exactly representable.
+pub(crate) fn raw_number_to_f64(text: &str) -> Option<f64> {
+    match text.parse::<i64>() {
+        Ok(value) => integer_to_f64(value),
+        Err(_) => text.parse::<f64>().ok().filter(|value| value.is_finite())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Token {
-    token_type: TokenType
+    token_type: TokenType,
+    /// Byte offset of the start of the token in the json text.
+    position: usize
 }
 
 impl Token {
     pub fn get_type(&self) -> TokenType {
         self.token_type.clone()
     }
+
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?}", self.position, self.token_type)
+    }
 }
 
 pub struct JsonLexer {
     /// All the text in the json file.
     json_text: String,
     /// the current location we are lexing in the file.
-    index: usize
+    index: usize,
+    /// When set, numbers are returned as RawNumber tokens holding their original text.
+    preserve_raw_numbers: bool
 }
 
 /// A lexer for a json file which returns
@@ -72,7 +145,8 @@ impl JsonLexer {
                 Ok(file_text) => file_text,
                 Err(e) => return Err(e)
             },
-            index: 0
+            index: 0,
+            preserve_raw_numbers: false
         })
     }
 
@@ -88,15 +162,61 @@ impl JsonLexer {
                 Ok(json_text) => json_text,
                 Err(_) => return None
             },
-            index: 0 
+            index: 0,
+            preserve_raw_numbers: false
         })
     }
 
+    /// Returns numbers as RawNumber tokens holding the text exactly as it was written, ex: "1.50" stays "1.50",
+    /// so the json can be written back out without normalizing them. Numbers are still validated.
+    /// Off by default.
+    pub fn preserve_raw_numbers(&mut self, preserve: bool) {
+        self.preserve_raw_numbers = preserve;
+    }
+
+    /// Lexes the whole input from the start and returns every token, for inspecting why a document
+    /// failed to parse. Stops at the end of the input, or after the Undefined token where lexing failed.
+    /// The lexer is reset afterwards.
+    pub fn dump_tokens(&mut self) -> Vec<Token> {
+        let tokens = self.collect_all_tokens();
+
+        self.reset();
+        tokens
+    }
+
+    /// Drains the token stream into a Vec, starting from the beginning of the input wherever the lexer was.
+    /// Stops at the end of the input, or after the Undefined token where lexing failed, and leaves the lexer there.
+    /// Useful in tests and for tools which look over every token before parsing.
+    pub fn collect_all_tokens(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::<Token>::new();
+        let mut token = Token::default();
+
+        self.reset();
+
+        loop {
+            self.next_token(&mut token);
+
+            if token.token_type == TokenType::Undefined {
+                // Keep the failure so its position is visible, unless the input simply ended.
+                if token.position < self.json_text.len() {
+                    tokens.push(token);
+                }
+
+                break;
+            }
+
+            tokens.push(token.clone());
+        }
+
+        tokens
+    }
+
     pub fn next_token<'a>(&mut self, token: &'a mut Token) {
         let size = self.json_text.len();
         let json_text = self.json_text.as_bytes();
 
         skip_whitespace(json_text, &mut self.index, size);
+        token.position = self.index;
 
         if is_eof(self.index, size) {
             token.token_type = TokenType::Undefined;
@@ -106,11 +226,17 @@ impl JsonLexer {
         let ch = json_text[self.index] as char;
 
         if ch.is_numeric() || ch == '-' {
+            let number_start = self.index;
+
             // Load number
             if !load_number(json_text, &mut self.index, size, token) {
                 token.token_type = TokenType::Undefined;
                 return;
             }
+
+            if self.preserve_raw_numbers {
+                token.token_type = TokenType::RawNumber { text: self.json_text[number_start..self.index].to_string() };
+            }
         }
         else if is_str_start(ch as u8) {
             // Load string
@@ -136,16 +262,66 @@ fn is_eof(index: usize, size: usize) -> bool {
 }
 
 fn skip_whitespace(json: &[u8], index: &mut usize, size: usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        *index = skip_whitespace_blocks(json, *index, size.min(json.len()));
+    }
+
+    // Finishes the scan on other architectures, and the tail shorter than a block on x86_64.
     while !is_eof(*index, size) && (json[*index]).is_ascii_whitespace() {
         *index += 1;
     }
 }
 
+/// Skips whitespace 16 bytes at a time with SSE2, which every x86_64 cpu supports.
+/// Stops at the first non whitespace byte, or before the last partial block.
+///
+/// memchr and friends search for the first byte which matches, so they can't find the end of a run of
+/// whitespace. Instead each block is compared against every whitespace byte and the matches combined.
+#[cfg(target_arch = "x86_64")]
+fn skip_whitespace_blocks(json: &[u8], mut index: usize, size: usize) -> usize {
+    use std::arch::x86_64::*;
+
+    const BLOCK_SIZE: usize = 16;
+
+    // Tokens are usually separated by a single space, which isn't worth loading a block for.
+    if index + 1 >= size || !json[index].is_ascii_whitespace() || !json[index + 1].is_ascii_whitespace() {
+        return index;
+    }
+
+    // SAFETY: SSE2 is part of the x86_64 baseline, and every load reads 16 bytes before size, which is within json.
+    unsafe {
+        let whitespace = [b' ', b'\n', b'\r', b'\t', 0x0C].map(|byte| _mm_set1_epi8(byte as i8));
+
+        while index + BLOCK_SIZE <= size {
+            let block = _mm_loadu_si128(json.as_ptr().add(index) as *const __m128i);
+            let matches = whitespace.iter()
+                .fold(_mm_setzero_si128(), |matches, byte| _mm_or_si128(matches, _mm_cmpeq_epi8(block, *byte)));
+
+            // One bit per byte, set for whitespace.
+            let mask = _mm_movemask_epi8(matches) as u32;
+
+            if mask != 0xFFFF {
+                return index + mask.trailing_ones() as usize;
+            }
+
+            index += BLOCK_SIZE;
+        }
+    }
+
+    index
+}
+
 fn is_str_start(c: u8) -> bool {
     c == '"' as u8
 }
 
 fn get_integer_num(json: &[u8], index: &mut usize, size: usize) {
+    // A single digit number can end the text.
+    if is_eof(*index, size) {
+        return;
+    }
+
     let mut ch = json[*index];
 
     while (ch as char).is_numeric() && !is_eof(*index, size) {
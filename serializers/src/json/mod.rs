@@ -1,3 +1,5 @@
 pub mod parser;
 pub mod lexer;
+pub mod deserialize;
+pub mod serialize;
 mod tests;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::{collections::{HashMap, hash_map::Keys}, str::FromStr};
 
 use crate::json::lexer::*;
+use crate::json::deserialize::JsonDeserialize;
+use crate::json::serialize::escape_json_string;
 
 use super::lexer;
 
@@ -207,6 +209,8 @@ pub enum JsonNode {
     Array(JsonArray),
     Number(JsonValue<i64>),
     Float(JsonValue<f64>),
+    /// A number holding the text it was written with, from a lexer preserving raw numbers.
+    RawNumber(JsonValue<String>),
     Bool(JsonValue<bool>),
     String(JsonValue<String>),
     #[default]
@@ -220,6 +224,7 @@ impl ToString for JsonNode {
             JsonNode::Array(arr) => arr.to_string(),
             JsonNode::Number(num) => num.to_string(),
             JsonNode::Float(num) => num.to_string(),
+            JsonNode::RawNumber(num) => num.to_string(),
             JsonNode::Bool(val) => val.to_string(),
             JsonNode::String(str) => {
                 let mut as_string = "\"".to_string();
@@ -232,6 +237,178 @@ impl ToString for JsonNode {
     }
 }
 
+impl From<bool> for JsonNode {
+    fn from(value: bool) -> Self {
+        JsonNode::Bool(JsonValue::new(value))
+    }
+}
+
+impl From<i64> for JsonNode {
+    fn from(value: i64) -> Self {
+        JsonNode::Number(JsonValue::new(value))
+    }
+}
+
+impl From<f64> for JsonNode {
+    fn from(value: f64) -> Self {
+        JsonNode::Float(JsonValue::new(value))
+    }
+}
+
+/// String nodes hold their text escaped as it appears in json, so the text is escaped here.
+impl From<&str> for JsonNode {
+    fn from(value: &str) -> Self {
+        JsonNode::String(JsonValue::new(escape_json_string(value)))
+    }
+}
+
+impl From<String> for JsonNode {
+    fn from(value: String) -> Self {
+        JsonNode::from(value.as_str())
+    }
+}
+
+impl JsonNode {
+    /// Looks up a node by a '/' separated path of object keys and array indices, following
+    /// JSON Pointer (RFC 6901): "~1" stands for '/' and "~0" for '~' within a key.
+    /// The leading '/' is optional, and an empty path returns this node.
+    /// ex: "snake/tile_size" or "/resources/textures/tex_snake_head"
+    pub fn pointer(&self, path: &str) -> Option<&JsonNode> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+
+        if path.is_empty() {
+            return Some(self);
+        }
+
+        let mut node = self;
+
+        for token in path.split('/') {
+            let key = token.replace("~1", "/").replace("~0", "~");
+
+            node = match node {
+                JsonNode::Object(object) => object.get(&key)?,
+                JsonNode::Array(array) => array.get(key.parse::<usize>().ok()?)?,
+                _ => return None
+            };
+        }
+
+        Some(node)
+    }
+
+    /// Looks up a node like `pointer`, returning a copy of it, or the default when the path is missing
+    /// or holds null. ex: config.pointer_or("game/dev_mode", false.into())
+    pub fn pointer_or(&self, path: &str, default: JsonNode) -> JsonNode {
+        self.pointer(path).cloned().unwrap_or(JsonNode::Null).unwrap_or(default)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonNode::Null)
+    }
+
+    /// Returns the node, or the default when it's null.
+    pub fn unwrap_or(self, default: JsonNode) -> JsonNode {
+        self.unwrap_or_else(|| default)
+    }
+
+    /// Returns the node, or the result of the function when it's null.
+    pub fn unwrap_or_else(self, default: impl FnOnce() -> JsonNode) -> JsonNode {
+        match self {
+            JsonNode::Null => default(),
+            node => node
+        }
+    }
+
+    /// Reads a number as a float, converting integers when the float holds them exactly.
+    /// Returns None for anything that isn't a number.
+    pub fn coerce_to_f64(&self) -> Option<f64> {
+        match self {
+            JsonNode::Float(value) => Some(*value.get()),
+            JsonNode::Number(value) => lexer::integer_to_f64(*value.get()),
+            JsonNode::RawNumber(text) => lexer::raw_number_to_f64(text.get()),
+            _ => None
+        }
+    }
+
+    /// Reads a number as an integer, converting floats which are whole numbers, ex: 1.0 reads as 1 while 1.5 is None.
+    /// Returns None for anything that isn't a number.
+    pub fn coerce_to_i64(&self) -> Option<i64> {
+        match self {
+            JsonNode::Number(value) => Some(*value.get()),
+            JsonNode::Float(value) => lexer::float_to_i64(*value.get()),
+            JsonNode::RawNumber(text) => lexer::raw_number_to_i64(text.get()),
+            _ => None
+        }
+    }
+
+    /// Converts every element of an array, ex: node.as_array_of::<i64>().
+    /// Elements are stored as whatever they were written as, so a mixed array like [1, "two", null] parses
+    /// fine, and is only rejected here when an element can't be converted to T.
+    pub fn as_array_of<T: JsonDeserialize>(&self) -> Result<Vec<T>, ParseError> {
+        match self {
+            JsonNode::Array(array) => array.iter()
+                .map(|element| T::from_json(element).ok_or(ParseError::TypeMismatch))
+                .collect(),
+            _ => Err(ParseError::TypeMismatch)
+        }
+    }
+
+    /// Compares two nodes exactly. Object keys are compared without regard to their order,
+    /// while arrays must have the same elements in the same order.
+    pub fn deep_eq(&self, other: &JsonNode) -> bool {
+        nodes_equal(self, other, None)
+    }
+
+    /// Compares two nodes like deep_eq, but floats only need to be within epsilon of each other.
+    /// Integers and floats with the same value are also considered equal.
+    pub fn approx_eq(&self, other: &JsonNode, epsilon: f64) -> bool {
+        nodes_equal(self, other, Some(epsilon))
+    }
+}
+
+/// Recursively compares two nodes, using epsilon for numbers when given.
+fn nodes_equal(a: &JsonNode, b: &JsonNode, epsilon: Option<f64>) -> bool {
+    match (a, b) {
+        (JsonNode::Object(a), JsonNode::Object(b)) => {
+            a.size() == b.size() && a.iter().all(|(key, a_node)| {
+                match b.get(key) {
+                    Some(b_node) => nodes_equal(a_node, b_node, epsilon),
+                    None => false
+                }
+            })
+        },
+        (JsonNode::Array(a), JsonNode::Array(b)) => {
+            a.size() == b.size() && a.iter().zip(b.iter())
+                .all(|(a_node, b_node)| nodes_equal(a_node, b_node, epsilon))
+        },
+        (JsonNode::Number(a), JsonNode::Number(b)) => a.get() == b.get(),
+        (JsonNode::Bool(a), JsonNode::Bool(b)) => a.get() == b.get(),
+        (JsonNode::String(a), JsonNode::String(b)) => a.get() == b.get(),
+        (JsonNode::Null, JsonNode::Null) => true,
+        (JsonNode::Float(a), JsonNode::Float(b)) if epsilon.is_none() => a.get() == b.get(),
+        // Raw numbers compare by value, so "1.50" equals "1.5".
+        (JsonNode::RawNumber(_), _) | (_, JsonNode::RawNumber(_)) if epsilon.is_none() => {
+            match (number_as_f64(a), number_as_f64(b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => false
+            }
+        },
+        // Whatever is left is either a float compared within epsilon or a type mismatch.
+        _ => match (number_as_f64(a), number_as_f64(b), epsilon) {
+            (Some(a), Some(b), Some(epsilon)) => (a - b).abs() <= epsilon,
+            _ => false
+        }
+    }
+}
+
+fn number_as_f64(node: &JsonNode) -> Option<f64> {
+    match node {
+        JsonNode::Number(value) => Some(*value.get() as f64),
+        JsonNode::Float(value) => Some(*value.get()),
+        JsonNode::RawNumber(text) => text.get().parse::<f64>().ok(),
+        _ => None
+    }
+}
+
 pub fn parse_json(lexer: &mut JsonLexer) -> Option<JsonNode> {
     let mut token = Token::default();
     lexer.reset();
@@ -241,6 +418,42 @@ pub fn parse_json(lexer: &mut JsonLexer) -> Option<JsonNode> {
     parse_node(lexer, &mut token)
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The text isn't valid json.
+    InvalidJson,
+    /// The json is valid, but holds a different type of value than requested.
+    TypeMismatch
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidJson => write!(f, "invalid json"),
+            ParseError::TypeMismatch => write!(f, "json doesn't match the requested type")
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses json text straight into a rust value, ex: parse_into::<Vec<i64>>("[1, 2, 3]").
+/// The text must hold a single value, anything but whitespace after it is InvalidJson.
+pub fn parse_into<T: JsonDeserialize>(text: &str) -> Result<T, ParseError> {
+    let mut lexer = JsonLexer::from_raw_json(text).ok_or(ParseError::InvalidJson)?;
+    let node = parse_json(&mut lexer).ok_or(ParseError::InvalidJson)?;
+
+    // The lexer only returns Undefined at the end of the text when nothing follows the root value.
+    let mut trailing = Token::default();
+    lexer.next_token(&mut trailing);
+
+    if trailing.get_type() != TokenType::Undefined || trailing.get_position() < text.len() {
+        return Err(ParseError::InvalidJson);
+    }
+
+    T::from_json(&node).ok_or(ParseError::TypeMismatch)
+}
+
 fn parse_node(lexer: &mut JsonLexer, token: &mut Token) -> Option<JsonNode> {
     // At this context, we are expecting anything except for syntax tokens.
     return match token.get_type() {
@@ -248,6 +461,7 @@ fn parse_node(lexer: &mut JsonLexer, token: &mut Token) -> Option<JsonNode> {
         TokenType::Reserve { reserve_id: lexer::ReserveCode::OpenBracket } => parse_array(lexer, token),
         TokenType::Number { value } => Some(JsonNode::Number(JsonValue { value })),
         TokenType::Float { value } => Some(JsonNode::Float(JsonValue { value })),
+        TokenType::RawNumber { text } => Some(JsonNode::RawNumber(JsonValue { value: text })),
         TokenType::Boolean { value } => Some(JsonNode::Bool(JsonValue { value })),
         TokenType::String { value } => Some(JsonNode::String(JsonValue { value })),
         TokenType::Null => Some(JsonNode::Null),
@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::json::parser::{JsonNode, JsonValueOps};
+
+/// Derives JsonSerialize for enums, see the serializers_derive crate for the format.
+pub use serializers_derive::JsonSerialize;
+
+#[derive(Debug)]
+pub enum SerializeError {
+    Io(std::io::Error),
+    /// The value has no json representation, ex: NaN or infinite floats.
+    InvalidValue(String),
+    /// A JsonWriter was asked to write something that would make the document invalid,
+    /// ex: a value in an object without a key, or closing an array that isn't open.
+    InvalidStructure(String)
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::Io(err) => write!(f, "{}", err),
+            SerializeError::InvalidValue(value) => write!(f, "{} can't be written as json", value),
+            SerializeError::InvalidStructure(reason) => write!(f, "invalid json structure: {}", reason)
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl From<std::io::Error> for SerializeError {
+    fn from(err: std::io::Error) -> Self {
+        SerializeError::Io(err)
+    }
+}
+
+/// Converts a rust value into json, written straight to the writer so large values
+/// never need to be held in memory as a single string.
+pub trait JsonSerialize {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError>;
+
+    /// Writes the value into a string, for small values.
+    fn to_json_string(&self) -> Result<String, SerializeError> {
+        let mut buffer = Vec::<u8>::new();
+        self.write_json(&mut buffer)?;
+
+        // Everything written is either ascii or copied from valid strings.
+        Ok(String::from_utf8(buffer).expect("json output should be valid utf-8"))
+    }
+}
+
+/// Writes the value as json to a file, replacing it. The output is buffered and streamed,
+/// so the whole json string is never in memory at once.
+pub fn write_to_file<T: JsonSerialize + ?Sized>(value: &T, path: &str) -> Result<(), SerializeError> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    value.write_json(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Returns the text with quotes, backslashes, and control characters escaped, without surrounding quotes.
+/// This is the form string nodes hold their text in.
+pub fn escape_json_string(text: &str) -> String {
+    let mut quoted = Vec::with_capacity(text.len() + 2);
+
+    // Writing to a Vec doesn't fail, and escaping keeps the text valid UTF-8.
+    write_json_string(text, &mut quoted).unwrap();
+    String::from_utf8(quoted[1..quoted.len() - 1].to_vec()).unwrap()
+}
+
+/// Writes a quoted json string, escaping quotes, backslashes, and control characters.
+pub fn write_json_string(text: &str, writer: &mut dyn Write) -> Result<(), SerializeError> {
+    writer.write_all(b"\"")?;
+
+    // Copy the runs between escaped characters in one write each.
+    let mut run_start = 0;
+
+    for (index, ch) in text.char_indices() {
+        let escaped = match ch {
+            '"' => Some("\\\"".to_string()),
+            '\\' => Some("\\\\".to_string()),
+            '\n' => Some("\\n".to_string()),
+            '\r' => Some("\\r".to_string()),
+            '\t' => Some("\\t".to_string()),
+            ch if (ch as u32) < 0x20 => Some(format!("\\u{:04x}", ch as u32)),
+            _ => None
+        };
+
+        if let Some(escaped) = escaped {
+            writer.write_all(&text.as_bytes()[run_start..index])?;
+            writer.write_all(escaped.as_bytes())?;
+            run_start = index + ch.len_utf8();
+        }
+    }
+
+    writer.write_all(&text.as_bytes()[run_start..])?;
+    writer.write_all(b"\"")?;
+
+    Ok(())
+}
+
+macro_rules! impl_serialize_integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonSerialize for $t {
+                fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+                    write!(writer, "{}", self)?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_serialize_integer!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+/// Floats are written with the shortest text which parses back to the same value.
+macro_rules! impl_serialize_float {
+    ($($t:ty),*) => {
+        $(
+            impl JsonSerialize for $t {
+                fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+                    if !self.is_finite() {
+                        return Err(SerializeError::InvalidValue(self.to_string()));
+                    }
+
+                    write!(writer, "{}", self)?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_serialize_float!(f32, f64);
+
+impl JsonSerialize for bool {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        writer.write_all(if *self { b"true" } else { b"false" })?;
+        Ok(())
+    }
+}
+
+impl JsonSerialize for str {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        write_json_string(self, writer)
+    }
+}
+
+impl JsonSerialize for String {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        write_json_string(self, writer)
+    }
+}
+
+/// None is written as null.
+impl<T: JsonSerialize> JsonSerialize for Option<T> {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        match self {
+            Some(value) => value.write_json(writer),
+            None => {
+                writer.write_all(b"null")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T: JsonSerialize> JsonSerialize for [T] {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        writer.write_all(b"[")?;
+
+        for (index, value) in self.iter().enumerate() {
+            if index > 0 {
+                writer.write_all(b",")?;
+            }
+
+            value.write_json(writer)?;
+        }
+
+        writer.write_all(b"]")?;
+        Ok(())
+    }
+}
+
+impl<T: JsonSerialize> JsonSerialize for Vec<T> {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        self.as_slice().write_json(writer)
+    }
+}
+
+/// Maps are written as objects with their keys sorted, so the output doesn't change between runs.
+impl<T: JsonSerialize> JsonSerialize for HashMap<String, T> {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+
+        writer.write_all(b"{")?;
+
+        for (index, key) in keys.into_iter().enumerate() {
+            if index > 0 {
+                writer.write_all(b",")?;
+            }
+
+            write_json_string(key, writer)?;
+            writer.write_all(b":")?;
+            self[key].write_json(writer)?;
+        }
+
+        writer.write_all(b"}")?;
+        Ok(())
+    }
+}
+
+/// Parsed nodes are written back as they were read. Object keys and string nodes hold their text
+/// with the escapes still in place, so they're written without escaping again.
+impl JsonSerialize for JsonNode {
+    fn write_json(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        match self {
+            JsonNode::Object(object) => {
+                writer.write_all(b"{")?;
+
+                for (index, (key, value)) in object.iter().enumerate() {
+                    if index > 0 {
+                        writer.write_all(b",")?;
+                    }
+
+                    write!(writer, "\"{}\":", key)?;
+                    value.write_json(writer)?;
+                }
+
+                writer.write_all(b"}")?;
+            },
+            JsonNode::Array(array) => {
+                writer.write_all(b"[")?;
+
+                for (index, value) in array.iter().enumerate() {
+                    if index > 0 {
+                        writer.write_all(b",")?;
+                    }
+
+                    value.write_json(writer)?;
+                }
+
+                writer.write_all(b"]")?;
+            },
+            JsonNode::Number(value) => value.get().write_json(writer)?,
+            JsonNode::Float(value) => {
+                // Keep whole floats as floats, "2" would be read back as a Number.
+                if value.get().is_finite() && value.get().fract() == 0.0 {
+                    write!(writer, "{:.1}", value.get())?;
+                }
+                else {
+                    value.get().write_json(writer)?;
+                }
+            },
+            JsonNode::RawNumber(value) => writer.write_all(value.get().as_bytes())?,
+            JsonNode::Bool(value) => value.get().write_json(writer)?,
+            JsonNode::String(value) => write!(writer, "\"{}\"", value.get())?,
+            JsonNode::Null => writer.write_all(b"null")?
+        }
+
+        Ok(())
+    }
+}
+
+/// An object or array the JsonWriter is inside of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WriterScope {
+    /// `awaiting_value` is set between a key and its value.
+    Object { has_members: bool, awaiting_value: bool },
+    Array { has_items: bool }
+}
+
+/// Writes a json document piece by piece, for output too large or too dynamic to build as a
+/// single value first, ex: streaming a level's entities as they're visited.
+///
+/// The writer inserts the commas and colons, and tracks which objects and arrays are open so
+/// anything that would make the document invalid is refused with InvalidStructure instead.
+///
+/// ```ignore
+/// let mut writer = JsonWriter::new(file);
+/// writer.begin_object()?;
+/// writer.key("scores")?;
+/// writer.begin_array()?;
+/// writer.value(&10)?;
+/// writer.end_array()?;
+/// writer.end_object()?;
+/// writer.finish()?;
+/// ```
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    scopes: Vec<WriterScope>,
+    /// Set once the root value is complete, a document only has one.
+    finished_root: bool,
+    /// Set when the underlying writer failed partway through a write, leaving the document incomplete.
+    poisoned: bool
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonWriter {
+            writer,
+            scopes: vec![],
+            finished_root: false,
+            poisoned: false
+        }
+    }
+
+    /// How many objects and arrays are open.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), SerializeError> {
+        let separator = self.value_separator()?;
+        self.write_raw(separator)?;
+        self.write_raw(b"{")?;
+        self.record_value();
+        self.scopes.push(WriterScope::Object { has_members: false, awaiting_value: false });
+
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> Result<(), SerializeError> {
+        self.check_poisoned()?;
+
+        match self.scopes.last() {
+            Some(WriterScope::Object { awaiting_value: false, .. }) => {},
+            Some(WriterScope::Object { awaiting_value: true, .. }) =>
+                return Err(SerializeError::InvalidStructure("object closed after a key without a value".to_string())),
+            _ => return Err(SerializeError::InvalidStructure("end_object without an open object".to_string()))
+        }
+
+        self.write_raw(b"}")?;
+        self.end_value();
+
+        Ok(())
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), SerializeError> {
+        let separator = self.value_separator()?;
+        self.write_raw(separator)?;
+        self.write_raw(b"[")?;
+        self.record_value();
+        self.scopes.push(WriterScope::Array { has_items: false });
+
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> Result<(), SerializeError> {
+        self.check_poisoned()?;
+
+        if !matches!(self.scopes.last(), Some(WriterScope::Array { .. })) {
+            return Err(SerializeError::InvalidStructure("end_array without an open array".to_string()));
+        }
+
+        self.write_raw(b"]")?;
+        self.end_value();
+
+        Ok(())
+    }
+
+    /// Writes the key of the next member of the open object.
+    pub fn key(&mut self, key: &str) -> Result<(), SerializeError> {
+        self.check_poisoned()?;
+
+        let has_members = match self.scopes.last() {
+            Some(WriterScope::Object { has_members, awaiting_value: false }) => *has_members,
+            Some(WriterScope::Object { .. }) =>
+                return Err(SerializeError::InvalidStructure(format!("key \"{}\" written where a value is expected", key))),
+            _ => return Err(SerializeError::InvalidStructure(format!("key \"{}\" written outside of an object", key)))
+        };
+
+        let mut bytes = Vec::<u8>::with_capacity(key.len() + 4);
+        if has_members {
+            bytes.push(b',');
+        }
+
+        write_json_string(key, &mut bytes)?;
+        bytes.push(b':');
+        self.write_raw(&bytes)?;
+
+        if let Some(WriterScope::Object { has_members, awaiting_value }) = self.scopes.last_mut() {
+            *has_members = true;
+            *awaiting_value = true;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a whole value as an array item, an object member's value after its key, or the root.
+    /// The value is serialized before anything is written, so a value which can't be written, ex: NaN,
+    /// leaves the writer as it was.
+    pub fn value<T: JsonSerialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let separator = self.value_separator()?;
+
+        let mut bytes = separator.to_vec();
+        value.write_json(&mut bytes)?;
+        self.write_raw(&bytes)?;
+        self.record_value();
+
+        if self.scopes.is_empty() {
+            self.finished_root = true;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer. Fails if the document is empty or has open objects or arrays.
+    pub fn finish(mut self) -> Result<W, SerializeError> {
+        self.check_poisoned()?;
+
+        if !self.scopes.is_empty() {
+            return Err(SerializeError::InvalidStructure(format!("{} objects or arrays left open", self.scopes.len())));
+        }
+
+        if !self.finished_root {
+            return Err(SerializeError::InvalidStructure("no value was written".to_string()));
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Fails once a write to the underlying writer has failed, since part of it may have been written.
+    fn check_poisoned(&self) -> Result<(), SerializeError> {
+        match self.poisoned {
+            true => Err(SerializeError::InvalidStructure("an earlier write failed, the document is incomplete".to_string())),
+            false => Ok(())
+        }
+    }
+
+    /// Writes to the underlying writer, poisoning the JsonWriter when it fails.
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<(), SerializeError> {
+        if let Err(err) = self.writer.write_all(bytes) {
+            self.poisoned = true;
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks a value can go where the writer is, returning the comma to write before array items.
+    /// Nothing changes until the value is written, see `record_value`.
+    fn value_separator(&self) -> Result<&'static [u8], SerializeError> {
+        self.check_poisoned()?;
+
+        match self.scopes.last() {
+            None if self.finished_root =>
+                Err(SerializeError::InvalidStructure("a document can only have one root value".to_string())),
+            None => Ok(b""),
+            Some(WriterScope::Object { awaiting_value: false, .. }) =>
+                Err(SerializeError::InvalidStructure("value written in an object without a key".to_string())),
+            Some(WriterScope::Object { .. }) => Ok(b""),
+            Some(WriterScope::Array { has_items }) => Ok(if *has_items { b"," } else { b"" })
+        }
+    }
+
+    /// Records that a value was written in the innermost scope.
+    fn record_value(&mut self) {
+        match self.scopes.last_mut() {
+            Some(WriterScope::Object { awaiting_value, .. }) => *awaiting_value = false,
+            Some(WriterScope::Array { has_items }) => *has_items = true,
+            None => {}
+        }
+    }
+
+    /// Closes the innermost scope, completing the document when it was the root.
+    fn end_value(&mut self) {
+        self.scopes.pop();
+
+        if self.scopes.is_empty() {
+            self.finished_root = true;
+        }
+    }
+}
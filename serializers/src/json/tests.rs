@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
 
-    use crate::json::{lexer::{*}, self, parser::{JsonNode, JsonValueOps, JsonArray, JsonValue, JsonObject, parse_json}};
+    use crate::json::{lexer::{*}, self, parser::{JsonNode, JsonValueOps, JsonArray, JsonValue, JsonObject, ParseError, parse_json, parse_into}, deserialize::JsonDeserialize, serialize::{JsonSerialize, JsonWriter, SerializeError, write_to_file}};
 
     const HAPPY_TEST: &str = r#"{
         "glossary": {
@@ -337,4 +337,453 @@ mod tests {
 
         assert_eq!(current_token, expected_token_types.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_deep_and_approx_eq() {
+        const FIRST: &str = r#"{ "name": "snake", "speed": 0.1, "tiles": [1, 2, 3], "nested": { "a": true, "b": null } }"#;
+        const REORDERED: &str = r#"{ "tiles": [1, 2, 3], "nested": { "b": null, "a": true }, "speed": 0.1, "name": "snake" }"#;
+        const CLOSE: &str = r#"{ "name": "snake", "speed": 0.1000001, "tiles": [1, 2, 3], "nested": { "a": true, "b": null } }"#;
+        const ARRAY_REORDERED: &str = r#"{ "name": "snake", "speed": 0.1, "tiles": [3, 2, 1], "nested": { "a": true, "b": null } }"#;
+
+        let parse = |json: &str| parse_json(&mut JsonLexer::from_raw_json(json).unwrap()).unwrap();
+
+        let first = parse(FIRST);
+        let reordered = parse(REORDERED);
+        let close = parse(CLOSE);
+        let array_reordered = parse(ARRAY_REORDERED);
+
+        // Objects are unordered.
+        assert!(first.deep_eq(&reordered));
+        assert!(first.approx_eq(&reordered, 0.0));
+
+        // Floats only match exactly unless given a tolerance.
+        assert!(!first.deep_eq(&close));
+        assert!(first.approx_eq(&close, 0.00001));
+        assert!(!first.approx_eq(&close, 0.00000001));
+
+        // Arrays stay ordered.
+        assert!(!first.deep_eq(&array_reordered));
+        assert!(!first.approx_eq(&array_reordered, 1.0));
+    }
+
+    #[test]
+    fn test_pointer_and_deserialize() {
+        let mut lexer = JsonLexer::from_raw_json(HAPPY_TEST).unwrap();
+        let json = parse_json(&mut lexer).unwrap();
+
+        assert_eq!(json.pointer("glossary/GlossDiv/title").and_then(String::from_json), Some("S".to_string()));
+        assert_eq!(json.pointer("/glossary/GlossDiv/count").and_then(f32::from_json), Some(5.123));
+        assert_eq!(json.pointer("glossary/GlossDiv/hours").and_then(i32::from_json), Some(-1));
+        assert_eq!(json.pointer("array/1").and_then(f64::from_json), Some(-12.1));
+        assert_eq!(json.pointer("").map(|node| node == &json), Some(true));
+
+        // Wrong types and missing keys don't deserialize.
+        assert_eq!(json.pointer("glossary/GlossDiv/hours").and_then(u32::from_json), None);
+        assert_eq!(json.pointer("glossary/GlossDiv/title").and_then(bool::from_json), None);
+        assert!(json.pointer("glossary/missing").is_none());
+        assert!(json.pointer("array/3").is_none());
+    }
+
+    #[test]
+    fn test_preserve_raw_numbers() {
+        let mut lexer = JsonLexer::from_raw_json("[1.50, -20, 3.0e+2]").unwrap();
+        lexer.preserve_raw_numbers(true);
+
+        let mut token = Token::default();
+        lexer.next_token(&mut token);
+        lexer.next_token(&mut token);
+        assert_eq!(token.get_type(), TokenType::RawNumber { text: "1.50".to_string() });
+
+        let node = parse_json(&mut lexer).unwrap();
+        assert_eq!(node.to_string(), "[1.50,-20,3.0e+2]");
+        assert_eq!(node.pointer("0").and_then(f32::from_json), Some(1.5));
+        assert_eq!(node.pointer("1").and_then(i32::from_json), Some(-20));
+
+        // Numbers are normalized by default.
+        let mut lexer = JsonLexer::from_raw_json("[1.50]").unwrap();
+        let node = parse_json(&mut lexer).unwrap();
+        assert_eq!(node.to_string(), "[1.5]");
+        assert!(node.deep_eq(&parse_json(&mut JsonLexer::from_raw_json("[1.5]").unwrap()).unwrap()));
+    }
+
+    #[test]
+    fn test_dump_tokens() {
+        let mut lexer = JsonLexer::from_raw_json(r#"{"a": [1, 2.5, true, null]}"#).unwrap();
+        let tokens = lexer.dump_tokens();
+
+        let token_types: Vec<TokenType> = tokens.iter().map(|token| token.get_type()).collect();
+        assert_eq!(token_types, vec![
+            TokenType::Reserve { reserve_id: ReserveCode::OpenBrace },
+            TokenType::String { value: "a".to_string() },
+            TokenType::Reserve { reserve_id: ReserveCode::Colon },
+            TokenType::Reserve { reserve_id: ReserveCode::OpenBracket },
+            TokenType::Number { value: 1 },
+            TokenType::Reserve { reserve_id: ReserveCode::Comma },
+            TokenType::Float { value: 2.5 },
+            TokenType::Reserve { reserve_id: ReserveCode::Comma },
+            TokenType::Boolean { value: true },
+            TokenType::Reserve { reserve_id: ReserveCode::Comma },
+            TokenType::Null,
+            TokenType::Reserve { reserve_id: ReserveCode::CloseBracket },
+            TokenType::Reserve { reserve_id: ReserveCode::CloseBrace }
+        ]);
+
+        assert_eq!(tokens[1].get_position(), 1);
+        assert_eq!(tokens[1].to_string(), r#"1: String { value: "a" }"#);
+
+        // The lexer is left ready to parse.
+        assert!(parse_json(&mut lexer).is_some());
+
+        // Lexing stops at the first bad token, which is kept to show where it failed.
+        let tokens = JsonLexer::from_raw_json("[1, @]").unwrap().dump_tokens();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[3].get_type(), TokenType::Undefined);
+        assert_eq!(tokens[3].get_position(), 4);
+    }
+
+    #[test]
+    fn test_collect_all_tokens() {
+        let mut lexer = JsonLexer::from_raw_json(r#"{"a": 1}"#).unwrap();
+
+        // Tokens already read don't change where collecting starts.
+        let mut token = Token::default();
+        lexer.next_token(&mut token);
+        lexer.next_token(&mut token);
+
+        let tokens = lexer.collect_all_tokens();
+        let token_types: Vec<TokenType> = tokens.iter().map(|token| token.get_type()).collect();
+        assert_eq!(token_types, vec![
+            TokenType::Reserve { reserve_id: ReserveCode::OpenBrace },
+            TokenType::String { value: "a".to_string() },
+            TokenType::Reserve { reserve_id: ReserveCode::Colon },
+            TokenType::Number { value: 1 },
+            TokenType::Reserve { reserve_id: ReserveCode::CloseBrace }
+        ]);
+        assert_eq!(tokens[3].get_position(), 6);
+
+        // The stream is drained, and collecting again starts over.
+        lexer.next_token(&mut token);
+        assert_eq!(token.get_type(), TokenType::Undefined);
+        assert_eq!(lexer.collect_all_tokens().len(), 5);
+
+        assert!(JsonLexer::from_raw_json("").unwrap().collect_all_tokens().is_empty());
+    }
+
+    #[test]
+    fn test_serialize_and_write_to_file() {
+        use std::collections::HashMap;
+
+        let mut save = HashMap::<String, Vec<f32>>::new();
+        save.insert("scores".to_string(), vec![1.5, -2.0, 3.25]);
+        save.insert("empty".to_string(), vec![]);
+
+        assert_eq!(save.to_json_string().unwrap(), r#"{"empty":[],"scores":[1.5,-2,3.25]}"#);
+        assert_eq!("say \"hi\"\n".to_json_string().unwrap(), r#""say \"hi\"\n""#);
+        assert_eq!(Some(3u8).to_json_string().unwrap(), "3");
+        assert_eq!(None::<bool>.to_json_string().unwrap(), "null");
+        assert!(matches!(f64::NAN.to_json_string(), Err(SerializeError::InvalidValue(_))));
+
+        // Parsed nodes survive a trip through a file.
+        let node = parse_json(&mut JsonLexer::from_raw_json(HAPPY_TEST).unwrap()).unwrap();
+        let path = std::env::temp_dir().join("serializers_write_to_file_test.json");
+        let path = path.to_str().unwrap();
+
+        write_to_file(&node, path).unwrap();
+        let written = parse_json(&mut JsonLexer::new(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(written.deep_eq(&node));
+
+        // Escapes in keys and values are kept as they were read, not escaped a second time.
+        let escaped = r#"{"say \"hi\"":"a \\ b"}"#;
+        let node = parse_json(&mut JsonLexer::from_raw_json(escaped).unwrap()).unwrap();
+        assert_eq!(node.to_json_string().unwrap(), escaped);
+
+        // Nodes made from text escape it, so they write valid json.
+        assert_eq!(JsonNode::from("say \"hi\"\\").to_json_string().unwrap(), r#""say \"hi\"\\""#);
+    }
+
+    #[test]
+    fn test_string_escapes_round_trip() {
+        use crate::json::deserialize::unescape_json_string;
+
+        let text = "say \"hi\"\n\\ tab\t bell\u{7} caf\u{e9} \u{1F40D}".to_string();
+        assert_eq!(parse_into::<String>(&text.to_json_string().unwrap()), Ok(text.clone()));
+        assert_eq!(String::from_json(&JsonNode::from("a\"b")), Some("a\"b".to_string()));
+
+        // Every escape json allows decodes, including surrogate pairs.
+        assert_eq!(parse_into::<String>(r#""\/\b\f\r\u00e9\ud83d\udc0d""#), Ok("/\u{8}\u{c}\r\u{e9}\u{1F40D}".to_string()));
+
+        // Unknown escapes and broken \u escapes don't.
+        assert_eq!(unescape_json_string(r"\x"), None);
+        assert_eq!(unescape_json_string(r"\u12"), None);
+        assert_eq!(unescape_json_string(r"\u+123"), None);
+        assert_eq!(unescape_json_string(r"\ud83d"), None);
+        assert_eq!(unescape_json_string(r"\ud83d\u0041"), None);
+        assert_eq!(unescape_json_string(r"\udc0d"), None);
+        assert_eq!(unescape_json_string("trailing \\"), None);
+    }
+
+    #[test]
+    fn test_parse_into() {
+        use std::collections::HashMap;
+
+        assert_eq!(parse_into::<Vec<i64>>("[1,2,3]"), Ok(vec![1, 2, 3]));
+
+        let weights = parse_into::<HashMap<String, f32>>(r#"{ "head": 1.5, "body": 2, "tail": -0.25 }"#).unwrap();
+        assert_eq!(weights.len(), 3);
+        assert_eq!(weights["head"], 1.5);
+        assert_eq!(weights["body"], 2.0);
+        assert_eq!(weights["tail"], -0.25);
+
+        // Keys with escaped characters come back as they were.
+        let tricky = HashMap::from([("say \"hi\"".to_string(), 1), ("back\\slash\n".to_string(), 2)]);
+        assert_eq!(parse_into::<HashMap<String, i64>>(&tricky.to_json_string().unwrap()), Ok(tricky));
+
+        assert_eq!(parse_into::<Vec<i64>>("[1, 2.5]"), Err(ParseError::TypeMismatch));
+        assert_eq!(parse_into::<Vec<i64>>("[1, 2"), Err(ParseError::InvalidJson));
+
+        // Only whitespace may follow the value.
+        assert_eq!(parse_into::<Vec<i64>>("[1, 2] \n"), Ok(vec![1, 2]));
+        assert_eq!(parse_into::<Vec<i64>>("[1, 2]]"), Err(ParseError::InvalidJson));
+        assert_eq!(parse_into::<i64>("1 2"), Err(ParseError::InvalidJson));
+        assert_eq!(parse_into::<bool>("true false"), Err(ParseError::InvalidJson));
+        assert_eq!(parse_into::<HashMap<String, f32>>(r#"{} "extra""#), Err(ParseError::InvalidJson));
+        assert_eq!(parse_into::<String>("\"a\" @"), Err(ParseError::InvalidJson));
+    }
+
+    #[test]
+    fn test_long_whitespace_runs() {
+        // Runs longer than a 16 byte block, ending at every offset within one, including the form feed
+        // is_ascii_whitespace accepts.
+        for run in 0..40 {
+            let padding: String = (0..run).map(|i| [' ', '\n', '\r', '\t', '\x0C'][i % 5]).collect();
+            let json = format!("[{}1,{}\"two\"{}]{}", padding, padding, padding, padding);
+
+            let mut lexer = JsonLexer::from_raw_json(&json).unwrap();
+            let tokens = lexer.dump_tokens();
+
+            assert_eq!(tokens.len(), 5, "run of {}", run);
+            assert_eq!(tokens[1].get_type(), TokenType::Number { value: 1 });
+            assert_eq!(tokens[1].get_position(), run + 1);
+            assert_eq!(tokens[3].get_type(), TokenType::String { value: "two".to_string() });
+            assert_eq!(tokens[4].get_position(), json.len() - run - 1);
+        }
+    }
+
+    #[test]
+    fn test_mixed_array() {
+        let node = parse_json(&mut JsonLexer::from_raw_json(r#"[1, "two", true, null, 3.5]"#).unwrap()).unwrap();
+
+        // Each element keeps its own type regardless of its neighbours.
+        let elements = node.as_array_of::<JsonNode>().unwrap();
+        assert_eq!(elements, vec![
+            JsonNode::Number(JsonValue::new(1)),
+            JsonNode::String(JsonValue::new("two".to_string())),
+            JsonNode::Bool(JsonValue::new(true)),
+            JsonNode::Null,
+            JsonNode::Float(JsonValue::new(3.5))
+        ]);
+
+        assert_eq!(node.as_array_of::<i64>(), Err(ParseError::TypeMismatch));
+        assert_eq!(elements[0].as_array_of::<i64>(), Err(ParseError::TypeMismatch));
+
+        // Whole numbers coerce to floats, but not the other way around.
+        let numbers = parse_json(&mut JsonLexer::from_raw_json("[1, 2.5, -3]").unwrap()).unwrap();
+        assert_eq!(numbers.as_array_of::<f64>(), Ok(vec![1.0, 2.5, -3.0]));
+        assert_eq!(numbers.as_array_of::<i64>(), Err(ParseError::TypeMismatch));
+    }
+
+    #[test]
+    fn test_null_coalescing() {
+        let node = parse_json(&mut JsonLexer::from_raw_json(r#"{"dev_mode": null, "seed": 7, "title": "Snake"}"#).unwrap()).unwrap();
+
+        assert!(node.pointer("dev_mode").unwrap().is_null());
+        assert!(!node.pointer("seed").unwrap().is_null());
+
+        // Null and missing keys both fall back to the default, anything else is kept.
+        assert_eq!(node.pointer_or("dev_mode", false.into()), JsonNode::from(false));
+        assert_eq!(node.pointer_or("missing", 1.5.into()), JsonNode::Float(JsonValue::new(1.5)));
+        assert_eq!(node.pointer_or("seed", 0.into()), JsonNode::Number(JsonValue::new(7)));
+        assert_eq!(node.pointer_or("title", "Untitled".into()), JsonNode::from("Snake"));
+
+        assert_eq!(JsonNode::Null.unwrap_or(true.into()), JsonNode::Bool(JsonValue::new(true)));
+        assert_eq!(JsonNode::from(3).unwrap_or(true.into()), JsonNode::Number(JsonValue::new(3)));
+        assert_eq!(JsonNode::Null.unwrap_or_else(|| "lazy".to_string().into()), JsonNode::from("lazy"));
+        assert_eq!(JsonNode::from(false).unwrap_or_else(|| panic!("only called for null")), JsonNode::from(false));
+    }
+
+    #[test]
+    fn test_numeric_coercion() {
+        let mut lexer = JsonLexer::from_raw_json(r#"{"whole": 1.0, "half": 1.5, "int": 3, "huge": 9007199254740993, "big_float": 1.0e300, "text": "1"}"#).unwrap();
+        let node = parse_json(&mut lexer).unwrap();
+        let get = |key: &str| node.pointer(key).unwrap();
+
+        assert_eq!(get("whole").coerce_to_i64(), Some(1));
+        assert_eq!(get("half").coerce_to_i64(), None);
+        assert_eq!(get("big_float").coerce_to_i64(), None);
+        assert_eq!(get("int").coerce_to_f64(), Some(3.0));
+        assert_eq!(get("huge").coerce_to_f64(), None, "2^53 + 1 isn't exact as a float");
+        assert_eq!(get("text").coerce_to_i64(), None);
+        assert_eq!(get("text").coerce_to_f64(), None);
+
+        // Deserializing goes through the same conversions.
+        assert_eq!(i32::from_json(get("whole")), Some(1));
+        assert_eq!(u8::from_json(get("half")), None);
+        assert_eq!(f64::from_json(get("int")), Some(3.0));
+
+        assert!(is_numeric_compatible(&TokenType::Float { value: 2.0 }, NumericKind::Integer));
+        assert!(!is_numeric_compatible(&TokenType::Float { value: 2.5 }, NumericKind::Integer));
+        assert!(is_numeric_compatible(&TokenType::Number { value: -4 }, NumericKind::Float));
+        assert!(!is_numeric_compatible(&TokenType::Number { value: i64::MAX }, NumericKind::Float));
+        assert!(is_numeric_compatible(&TokenType::RawNumber { text: "1e3".to_string() }, NumericKind::Integer));
+        assert!(!is_numeric_compatible(&TokenType::String { value: "1".to_string() }, NumericKind::Float));
+        assert!(!is_numeric_compatible(&TokenType::Float { value: f64::NAN }, NumericKind::Integer));
+
+        // Raw numbers convert from their text.
+        let raw = JsonNode::RawNumber(JsonValue::new("2.0".to_string()));
+        assert_eq!(raw.coerce_to_i64(), Some(2));
+        assert_eq!(u64::from_json(&JsonNode::RawNumber(JsonValue::new("18446744073709551615".to_string()))), Some(u64::MAX));
+    }
+
+    #[derive(Debug, Clone, PartialEq, JsonSerialize, JsonDeserialize)]
+    enum Difficulty {
+        Easy,
+        Hard,
+        Custom { speed: f32, walls: bool },
+        Seeded(u64),
+        Start(i32, i32),
+        Random(),
+        Endless {},
+        Level { name: String },
+        Logged { writer: String, object: u32 }
+    }
+
+    #[test]
+    fn test_enum_derives() {
+        let round_trip = |value: &Difficulty| {
+            let text = value.to_json_string().unwrap();
+            parse_into::<Difficulty>(&text).unwrap()
+        };
+
+        // Unit variants are plain strings, so a config can say "difficulty": "Hard".
+        assert_eq!(Difficulty::Hard.to_json_string().unwrap(), "\"Hard\"");
+        assert_eq!(round_trip(&Difficulty::Easy), Difficulty::Easy);
+
+        let custom = Difficulty::Custom { speed: 1.5, walls: true };
+        assert_eq!(custom.to_json_string().unwrap(), r#"{"type":"Custom","speed":1.5,"walls":true}"#);
+        assert_eq!(round_trip(&custom), custom);
+
+        assert_eq!(Difficulty::Seeded(42).to_json_string().unwrap(), r#"{"type":"Seeded","value":42}"#);
+        assert_eq!(round_trip(&Difficulty::Seeded(42)), Difficulty::Seeded(42));
+        assert_eq!(round_trip(&Difficulty::Start(-1, 3)), Difficulty::Start(-1, 3));
+
+        // Variants with empty fields are written like unit variants.
+        assert_eq!(Difficulty::Random().to_json_string().unwrap(), "\"Random\"");
+        assert_eq!(round_trip(&Difficulty::Random()), Difficulty::Random());
+        assert_eq!(round_trip(&Difficulty::Endless {}), Difficulty::Endless {});
+        assert_eq!(parse_into::<Difficulty>(r#"{"type":"Random"}"#).unwrap(), Difficulty::Random());
+
+        // String fields keep their escaped characters.
+        let level = Difficulty::Level { name: "the \"pit\"\\2".to_string() };
+        assert_eq!(round_trip(&level), level);
+
+        // Fields named like the generated code's locals still round trip.
+        let logged = Difficulty::Logged { writer: "stdout".to_string(), object: 3 };
+        assert_eq!(round_trip(&logged), logged);
+
+        let config = parse_json(&mut JsonLexer::from_raw_json(r#"{ "difficulty": "Hard", "tagged": { "type": "Easy" } }"#).unwrap()).unwrap();
+        assert_eq!(config.pointer("difficulty").and_then(Difficulty::from_json), Some(Difficulty::Hard));
+        assert_eq!(config.pointer("tagged").and_then(Difficulty::from_json), Some(Difficulty::Easy));
+
+        // Unknown variants, missing fields, and the wrong number of values don't deserialize.
+        assert!(parse_into::<Difficulty>("\"Impossible\"").is_err());
+        assert!(parse_into::<Difficulty>(r#"{"type":"Custom","speed":1.5}"#).is_err());
+        assert!(parse_into::<Difficulty>(r#"{"type":"Start","values":[1]}"#).is_err());
+    }
+
+    #[test]
+    fn test_json_writer() {
+        let mut writer = JsonWriter::new(Vec::<u8>::new());
+
+        writer.begin_object().unwrap();
+        writer.key("name").unwrap();
+        writer.value("snake \"classic\"").unwrap();
+        writer.key("scores").unwrap();
+        writer.begin_array().unwrap();
+        writer.value(&10).unwrap();
+        writer.value(&2.5f32).unwrap();
+        writer.begin_object().unwrap();
+        writer.end_object().unwrap();
+        writer.end_array().unwrap();
+        writer.key("options").unwrap();
+        writer.begin_object().unwrap();
+        writer.key("walls").unwrap();
+        writer.value(&true).unwrap();
+        writer.key("seed").unwrap();
+        writer.value(&None::<u64>).unwrap();
+        writer.end_object().unwrap();
+        assert_eq!(writer.depth(), 1);
+        writer.end_object().unwrap();
+
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(text, r#"{"name":"snake \"classic\"","scores":[10,2.5,{}],"options":{"walls":true,"seed":null}}"#);
+
+        let written = parse_json(&mut JsonLexer::from_raw_json(&text).unwrap()).unwrap();
+        let expected = parse_json(&mut JsonLexer::from_raw_json(
+            r#"{ "name": "snake \"classic\"", "scores": [10, 2.5, {}], "options": { "walls": true, "seed": null } }"#).unwrap()).unwrap();
+        assert!(written.deep_eq(&expected));
+
+        // Anything that would make the document invalid is refused.
+        fn invalid(write: impl Fn(&mut JsonWriter<Vec<u8>>) -> Result<(), SerializeError>) -> bool {
+            matches!(write(&mut JsonWriter::new(Vec::new())), Err(SerializeError::InvalidStructure(_)))
+        }
+
+        assert!(invalid(|writer| { writer.begin_object()?; writer.value(&1) }));
+        assert!(invalid(|writer| { writer.begin_object()?; writer.key("a")?; writer.key("b") }));
+        assert!(invalid(|writer| { writer.begin_object()?; writer.key("a")?; writer.end_object() }));
+        assert!(invalid(|writer| { writer.begin_array()?; writer.key("a") }));
+        assert!(invalid(|writer| { writer.begin_array()?; writer.end_object() }));
+        assert!(invalid(|writer| writer.end_array()));
+        assert!(invalid(|writer| { writer.value(&1)?; writer.value(&2) }));
+
+        let mut unfinished = JsonWriter::new(Vec::new());
+        unfinished.begin_array().unwrap();
+        assert!(matches!(unfinished.finish(), Err(SerializeError::InvalidStructure(_))));
+        assert!(matches!(JsonWriter::new(Vec::new()).finish(), Err(SerializeError::InvalidStructure(_))));
+
+        // A value which can't be written leaves nothing behind, so the document can carry on.
+        let mut skipped = JsonWriter::new(Vec::new());
+        skipped.begin_array().unwrap();
+        skipped.value(&1).unwrap();
+        assert!(matches!(skipped.value(&f32::NAN), Err(SerializeError::InvalidValue(_))));
+        skipped.value(&2).unwrap();
+        skipped.end_array().unwrap();
+        assert_eq!(skipped.finish().unwrap(), b"[1,2]");
+
+        // Once the underlying writer fails, part of the document may be missing, so later writes are refused.
+        struct FullWriter(usize);
+        impl std::io::Write for FullWriter {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                match self.0 {
+                    0 => Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "full")),
+                    space => {
+                        let written = bytes.len().min(space);
+                        self.0 -= written;
+                        Ok(written)
+                    }
+                }
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut full = JsonWriter::new(FullWriter(3));
+        full.begin_array().unwrap();
+        assert!(matches!(full.value("long"), Err(SerializeError::Io(_))));
+        assert!(matches!(full.end_array(), Err(SerializeError::InvalidStructure(_))));
+        assert!(matches!(full.finish(), Err(SerializeError::InvalidStructure(_))));
+    }
+}
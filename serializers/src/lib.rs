@@ -1 +1,4 @@
+// Lets the json derives refer to this crate as ::serializers from inside it too.
+extern crate self as serializers;
+
 pub mod json;
\ No newline at end of file
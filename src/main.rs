@@ -1,81 +1,282 @@
-use core_engine::{self, engine::GameManager, shader_program::{ShaderProgram, ShaderUniforms}, mesh::{Mesh2D, DrawableMesh}, texture::Texture, MouseKeyboardInputControl};
-use glmath::glmath::Vec2f;
+use core_engine::{self, engine::GameManager, shader_program::{ShaderProgram, UniformSet}, mesh::Mesh2D,
+    particles::{ParticleSystem, ParticleInstanceBuffers}, particle_emitter::{ParticleEmitter, EmitterParams, Keyframes}, render_state::RenderState, sprite_batch::SpriteBatch, atlas::TextureRegion, animation::AnimationPlayer, camera::Camera2D, accumulator::Accumulator, screenshot::PendingScreenshot, scheduler::TaskHandle, debug_render::DebugRenderMode, debug_draw::DebugDraw, color::Color, font::Font, ui::NineSlice, MouseKeyboardInputControl};
+use glmath::glmath::{Vec2f, Vec4f, Mat44f};
 use core_engine::render_pipeline::*;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use std::cell::Cell;
+use std::rc::Rc;
 use timer::Stopwatch;
 
+/// Seconds between moves at the start of the game.
+const START_MOVE_INTERVAL: f32 = 0.075;
+/// The snake speeds up until it moves this often.
+const MIN_MOVE_INTERVAL: f32 = 0.0375;
+/// Particles sprayed out when the snake eats food.
+const FOOD_BURST_COUNT: usize = 40;
+/// Pixel size the score is rasterized at.
+const SCORE_TEXT_SIZE: u32 = 32;
+/// World size of one pixel of score text.
+const SCORE_TEXT_SCALE: f32 = 0.002;
+/// Space between the score text and the edges of its panel.
+const SCORE_PADDING: f32 = 0.02;
+/// Seconds the game over label takes to fade in.
+const GAME_OVER_FADE_TIME: f32 = 0.5;
+/// Every other body segment is tinted a darker green.
+const BODY_STRIPE_TINT: Color = Color { r: 0.7, g: 0.85, b: 0.7, a: 1.0 };
+/// The direction the head faces in its texture, in radians: the tongue points down.
+const HEAD_TEXTURE_ANGLE: f32 = -std::f32::consts::FRAC_PI_2;
+/// How much one notch of the scroll wheel zooms the camera.
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 4.0;
+
+/// The food burst's particle emitter and what it's drawn with.
+struct FoodBurst {
+    emitter: ParticleEmitter,
+    shader: ShaderProgram,
+    mesh: Mesh2D,
+    buffers: ParticleInstanceBuffers
+}
+
+impl FoodBurst {
+    /// Returns None when the particle shader is missing, in which case the game runs without the burst.
+    fn new(game_manager: &GameManager, tile_size: f32, seed: Option<u64>) -> Option<FoodBurst> {
+        let shader = game_manager.resources.shader_resouces.get_registry("shader_particle")?.clone();
+
+        let mut mesh = Mesh2D::generate_quad();
+        let buffers = ParticleSystem::attach_sized_to_mesh(&mut mesh,
+            shader.query_attribute_location("particlePosition")?,
+            shader.query_attribute_location("particleColor")?,
+            shader.query_attribute_location("particleSize")?);
+
+        let params = EmitterParams {
+            lifetime: (0.35, 0.7),
+            speed: (0.3, 0.9),
+            acceleration: Vec2f::new(0.0, -1.5),
+            color: Keyframes::constant(Vec4f::new(1.0, 0.9, 0.3, 1.0))
+                .with_key(1.0, Vec4f::new(1.0, 0.3, 0.1, 0.0)),
+            size: Keyframes::constant(tile_size * 0.3).with_key(1.0, 0.0),
+            ..EmitterParams::default()
+        };
+
+        let emitter = match seed {
+            Some(seed) => ParticleEmitter::with_seed(params, FOOD_BURST_COUNT * 4, seed),
+            None => ParticleEmitter::new(params, FOOD_BURST_COUNT * 4)
+        };
+
+        Some(FoodBurst { emitter, shader, mesh, buffers })
+    }
+}
+
 struct SnakeRenderPipeline {
-    background_mesh: Mesh2D,
+    sprite_batch: SpriteBatch,
     gui_shader: ShaderProgram,
-    body_texture: Texture,
-    head_texture: Texture,
-    food_texture: Texture,
+    body_region: TextureRegion,
+    /// Wiggles the head while the snake is alive.
+    head_animation: AnimationPlayer,
+    /// Turns the head to face the way the snake last moved, in radians.
+    rotation_angle: f32,
+    food_region: TextureRegion,
     pos: Vec<Vec2f>,
     tile_size: f32,
     movement_direction: Vec2f,
     // The last movement direction is set once the movement direction changes from the x to y axis or vice versa. It is cleared once it's consumed.
     last_movement_direction: Vec2f,
-    location_pos: i32,
-    speed: i32,
-    update_count: i32,
+    view_projection: Mat44f,
+    /// The camera the snake was last rendered with, used to find the tile under the cursor.
+    camera: Camera2D,
+    /// Left clicking a tile moves the food there, in dev mode.
+    click_to_place_food: bool,
+    /// Fires each time the snake should move one tile.
+    move_accumulator: Accumulator,
+    /// Seconds between moves, shortened over time by the speed up task.
+    move_interval: Rc<Cell<f32>>,
+    speed_up: Option<TaskHandle>,
+    update_timer: Stopwatch,
     next_segment_pos: Option<Vec2f>,
     game_over: bool,
+    /// Picks where food spawns. Seeded from "game/rng_seed" when it's set, so runs can be replayed.
+    rng: SmallRng,
+    food_burst: Option<FoodBurst>,
+    /// Outlines the tiles and collision squares in dev mode.
+    debug_draw: DebugDraw,
+    /// The score and game over labels are drawn over a panel, when both loaded.
+    score_panel: Option<NineSlice>,
+    score_font: Option<Font>,
+    /// How far the game over label has faded in, from 0 to 1.
+    game_over_fade: f32,
 }
 
 impl SnakeRenderPipeline {
     pub fn new(game_manager: &GameManager) -> SnakeRenderPipeline {
-        // Create a mesh.
-        let vertices = vec![
-            -1.0, -1.0,
-            -1.0, 1.0,
-            1.0, 1.0,
-            1.0, 1.0,
-            1.0, -1.0,
-            -1.0, -1.0
-        ];
-
-        let mut mesh: Mesh2D = Mesh2D::new();
-        mesh.add_float_buffer(vertices, 2);
-
         let gui_shader = game_manager.resources.shader_resouces.get_registry("shader_game").unwrap().clone();
+        let sprite_batch = match game_manager.resources.mesh_resources.get_registry("mesh_quad") {
+            Some(quad) => SpriteBatch::with_quad(&gui_shader, quad.clone()),
+            None => SpriteBatch::new(&gui_shader)
+        };
+
+        // Every sprite comes from the same atlas, so the whole snake draws with a single texture bind.
+        let atlas = game_manager.resources.atlas_resources.get_registry("atlas_snake").unwrap();
+        let body_region = atlas.region("body").unwrap();
+        let head_animation = AnimationPlayer::new(atlas.animation("head_wiggle").unwrap().clone());
+        let food_region = atlas.region("food").unwrap();
+        let score_panel = atlas.nine_slice("score_panel").cloned();
+
+        let font_path = game_manager.config_get::<String>("snake/font").unwrap_or("./fonts/DejaVuSansMono.ttf".to_string());
+        let score_font = match Font::open(&game_manager.resource_path(&font_path)) {
+            Ok(font) => Some(font),
+            Err(err) => {
+                println!("Failed to load the score font {}: {}", font_path, err);
+                None
+            }
+        };
+
+        let seed = game_manager.config_get::<u64>("game/rng_seed");
+        let rng = match seed {
+            Some(seed) => SmallRng::seed_from_u64(seed),
+            None => SmallRng::from_entropy()
+        };
 
-        let body_texture = game_manager.resources.texture_resources.get_registry("tex_snake_body").unwrap().clone();
-        let head_texture = game_manager.resources.texture_resources.get_registry("tex_snake_head").unwrap().clone();
-        let food_texture = game_manager.resources.texture_resources.get_registry("tex_snake_food").unwrap().clone();
+        let tile_size = game_manager.config_get::<f32>("snake/tile_size").unwrap_or(0.08);
+        let click_to_place_food = game_manager.config_get::<bool>("game/dev_mode").unwrap_or(false);
 
         SnakeRenderPipeline { 
-            background_mesh: mesh,
+            sprite_batch,
             gui_shader,
-            body_texture,
-            head_texture,
-            food_texture,
-            tile_size: 0.08,
+            body_region,
+            head_animation,
+            rotation_angle: Vec2f::new(0.0, 1.0).angle() - HEAD_TEXTURE_ANGLE,
+            food_region,
+            tile_size,
             pos: vec![Vec2f::new(0.0, 0.0)],
             movement_direction: Vec2f::new(0.0, 1.0),
             last_movement_direction: Vec2f::new(0.0, 0.0),
-            location_pos: 0,
-            speed: 9,
-            update_count: 0,
+            view_projection: Mat44f::IDENTITY,
+            camera: *game_manager.get_camera(),
+            click_to_place_food,
+            move_accumulator: Accumulator::new(START_MOVE_INTERVAL),
+            move_interval: Rc::new(Cell::new(START_MOVE_INTERVAL)),
+            speed_up: None,
+            update_timer: Stopwatch::new(),
             next_segment_pos: None,
             game_over: false,
+            rng,
+            food_burst: FoodBurst::new(game_manager, tile_size, seed),
+            debug_draw: game_manager.debug_draw(),
+            score_panel,
+            score_font,
+            game_over_fade: 0.0,
         }
     }
 
     /// Spawns a new segment somewhere on the map.
     /// Can be anywhere except on one of the snake positions.
     fn spawn_segment(&mut self) {
-        let mut thread_rng = rand::thread_rng();
         let lower_range = (-1.0 / self.tile_size) as i32;
         let upper_range = -lower_range; 
 
-        let x = thread_rng.gen_range(lower_range..=upper_range);
-        let y = thread_rng.gen_range(lower_range..=upper_range);
+        let x = self.rng.gen_range(lower_range..=upper_range);
+        let y = self.rng.gen_range(lower_range..=upper_range);
 
         self.next_segment_pos = Some(Vec2f::new(x as f32 * self.tile_size, y as f32 * self.tile_size));
     }
 
+    /// Moves the food to the tile under the cursor, unless the snake is on it.
+    fn place_food_at_cursor(&mut self, input: &dyn MouseKeyboardInputControl) {
+        let world = self.camera.screen_to_world(input.mouse_position());
+        let tile = Vec2f::new((world.x / self.tile_size).round() * self.tile_size,
+            (world.y / self.tile_size).round() * self.tile_size);
+
+        if !self.pos.iter().any(|pos| self.check_collision(*pos, tile)) {
+            self.next_segment_pos = Some(tile);
+            self.queue_sprites();
+        }
+    }
+
+    /// Queues the food, snake, and labels into the sprite batch.
+    /// The food sits on the lowest layer so the snake covers it, and the head is drawn above the body.
+    /// The body is striped in two shades of green, and the game over label fades in over everything.
+    fn queue_sprites(&mut self) {
+        let scale = Vec2f::new(self.tile_size / 2.0, self.tile_size / 2.0);
+
+        self.sprite_batch.begin();
+
+        if let Some(segment_pos) = self.next_segment_pos {
+            self.sprite_batch.draw_region(&self.food_region, segment_pos, scale, 0);
+        }
+
+        for i in 1..self.pos.len() {
+            self.sprite_batch.set_tint(if i % 2 == 0 { BODY_STRIPE_TINT } else { Color::WHITE });
+            self.sprite_batch.draw_region(&self.body_region, self.pos[i], scale, 1);
+        }
+
+        self.sprite_batch.set_tint(Color::WHITE);
+        if let Some(head_region) = self.head_animation.current_region() {
+            self.sprite_batch.draw_region_rotated(head_region, self.pos[0], scale, self.rotation_angle, 2);
+        }
+
+        let score = format!("Score: {}", self.pos.len());
+        self.queue_label(&score, Vec2f::new(-1.0 + SCORE_PADDING, 1.0 - SCORE_PADDING), Vec2f::new(0.0, 1.0), 3);
+
+        if self.game_over {
+            self.sprite_batch.set_tint(Color::new(1.0, 1.0, 1.0, self.game_over_fade));
+            self.queue_label("Game over!", Vec2f::ZERO, Vec2f::new(0.5, 0.5), 5);
+        }
+
+        self.sprite_batch.end();
+    }
+
+    /// Queues the text over a panel sized to fit it, on the layer and the one above. The panel is placed
+    /// so the point at `pivot` across it, from 0 to 1 left to right and bottom to top, lands on the anchor.
+    fn queue_label(&mut self, text: &str, anchor: Vec2f, pivot: Vec2f, layer: i32) {
+        let (Some(panel), Some(font)) = (&self.score_panel, &mut self.score_font) else {
+            return;
+        };
+
+        let glyphs = font.layout_text(text, SCORE_TEXT_SIZE);
+        let metrics = font.line_metrics(SCORE_TEXT_SIZE);
+
+        let text_width = glyphs.iter().fold(0.0f32, |width, glyph| width.max(glyph.max.x)) * SCORE_TEXT_SCALE;
+        let text_height = (metrics.ascent - metrics.descent) * SCORE_TEXT_SCALE;
+
+        let size = Vec2f::new(text_width, text_height) + Vec2f::new(SCORE_PADDING, SCORE_PADDING) * 2.0;
+        let position = anchor - Vec2f::new(size.x * pivot.x, size.y * pivot.y);
+        self.sprite_batch.draw_nine_slice(panel, position, size, layer);
+
+        // Glyphs are laid out in pixels from the baseline, with their texture coordinates starting at the bottom.
+        let baseline = position + Vec2f::new(SCORE_PADDING, SCORE_PADDING - metrics.descent * SCORE_TEXT_SCALE);
+
+        for glyph in glyphs {
+            if let Some(texture) = font.page_texture(SCORE_TEXT_SIZE, glyph.page) {
+                let region = TextureRegion {
+                    texture: texture.clone(),
+                    uv_min: Vec2f::new(glyph.uv_min.x, glyph.uv_max.y),
+                    uv_max: Vec2f::new(glyph.uv_max.x, glyph.uv_min.y)
+                };
+                let center = baseline + (glyph.min + glyph.max) * (0.5 * SCORE_TEXT_SCALE);
+                let scale = (glyph.max - glyph.min) * (0.5 * SCORE_TEXT_SCALE);
+
+                self.sprite_batch.draw_region(&region, center, scale, layer + 1);
+            }
+        }
+    }
+
+    /// Outlines the tile grid, and the squares the head and food collide within.
+    fn draw_debug_shapes(&self) {
+        let half_tile = Vec2f::new(self.tile_size / 2.0, self.tile_size / 2.0);
+
+        // Segments sit on the tile centers, so the tile edges are half a tile off the origin.
+        self.debug_draw.grid_at(half_tile, self.tile_size, Color::new(1.0, 1.0, 1.0, 0.15));
+        self.debug_draw.rect(self.pos[0] - half_tile, self.pos[0] + half_tile, Color::rgb(0.2, 1.0, 0.2));
+
+        if let Some(segment_pos) = self.next_segment_pos {
+            self.debug_draw.rect(segment_pos - half_tile, segment_pos + half_tile, Color::rgb(1.0, 0.3, 0.2));
+        }
+    }
+
     /// Checks whether the position collides with the square.
-    fn check_collision(&mut self, pos: Vec2f, sq: Vec2f) -> bool {
+    fn check_collision(&self, pos: Vec2f, sq: Vec2f) -> bool {
         (pos.x >= sq.x - self.tile_size / 2.0) && (pos.x <= sq.x + self.tile_size / 2.0) && 
             (pos.y >= sq.y - self.tile_size / 2.0) && (pos.y <= sq.y + self.tile_size / 2.0)
     }
@@ -83,9 +284,8 @@ impl SnakeRenderPipeline {
     fn handle_movement(&mut self, direction: Vec2f) {
         let mut previous_head = self.pos[0];
 
-        self.update_count = 0;
-
         self.pos[0] += direction * self.tile_size;
+        self.rotation_angle = direction.angle() - HEAD_TEXTURE_ANGLE;
 
         let half_tile_size = self.tile_size / 2.0;
         if self.pos[0].x > 1.0 - half_tile_size {
@@ -122,6 +322,11 @@ impl SnakeRenderPipeline {
                 if self.check_collision(self.pos[0], segment_pos) {
                     self.pos.push(previous_head);
                     self.spawn_segment();
+
+                    if let Some(food_burst) = &mut self.food_burst {
+                        food_burst.emitter.set_position(segment_pos);
+                        food_burst.emitter.burst(FOOD_BURST_COUNT);
+                    }
                 }
             }
         }
@@ -132,63 +337,117 @@ impl SnakeRenderPipeline {
 impl RenderPipelineHandler for SnakeRenderPipeline {
     fn init(&mut self) {
         self.gui_shader.bind();
-
-        self.location_pos = self.gui_shader.get_uniform_location("pos");
-        let location_scale = self.gui_shader.get_uniform_location("scale");
-        let location_gui_texture = self.gui_shader.get_uniform_location("guiTexture");
-
-        self.gui_shader.load_vec2(location_scale, glmath::glmath::Vec2f::new(self.tile_size / 2.0, self.tile_size / 2.0));
-        self.gui_shader.load_int(location_gui_texture, 0);
+        if let Err(err) = UniformSet::new().set_int("guiTexture", 0).apply(&self.gui_shader) {
+            println!("Snake shader: {}", err);
+        }
+        self.queue_sprites();
     }
 
     fn prepare(&self) {
+        let mut uniforms = UniformSet::new();
+        uniforms.set_mat4("viewProjection", self.view_projection);
+
+        if let Some(food_burst) = &self.food_burst {
+            food_burst.shader.bind();
+            food_burst.shader.load_uniforms(&uniforms);
+        }
+
         self.gui_shader.bind();
+        self.gui_shader.load_uniforms(&uniforms);
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.view_projection = camera.view_projection();
+        self.camera = *camera;
     }
 
     fn execute(&self) {
-        // Render the snake head.
-        self.head_texture.bind(0);
-        self.gui_shader.load_vec2(self.location_pos, self.pos[0]);
-        self.background_mesh.render();
+        self.execute_pass(0);
+        self.execute_pass(1);
+    }
 
-        for i in 1..self.pos.len() {
-            self.body_texture.bind(0);
-            self.gui_shader.load_vec2(self.location_pos, self.pos[i]);
-            self.background_mesh.render();
+    /// The sprites are drawn first, then the particles with their own blending.
+    fn pass_count(&self) -> usize {
+        2
+    }
+
+    fn pass_render_state(&self, pass: usize) -> RenderState {
+        match (pass, &self.food_burst) {
+            (1, Some(food_burst)) => food_burst.emitter.render_state(),
+            _ => RenderState::default()
         }
+    }
 
-        // Render the target segment.
-        self.food_texture.bind(0);
-        match self.next_segment_pos {
-            Some(segment_pos) => {
-                self.gui_shader.load_vec2(self.location_pos, segment_pos);
-                self.background_mesh.render();
-            }
+    fn execute_pass(&self, pass: usize) {
+        match (pass, &self.food_burst) {
+            (0, _) => {
+                self.gui_shader.bind();
+                self.sprite_batch.render(&self.gui_shader);
+                self.draw_debug_shapes();
+            },
+            (_, Some(food_burst)) => {
+                food_burst.shader.bind();
+                food_burst.emitter.render(&food_burst.mesh, food_burst.buffers);
+            },
             _ => {}
         }
     }
 
     fn update(&mut self, input: &Box<dyn MouseKeyboardInputControl>) {
-        self.update_count += 1;
-        if self.update_count >= self.speed && !self.game_over {
+        let delta_time = self.update_timer.elapsed_seconds() as f32;
+        self.update_timer.start();
+        self.move_accumulator.set_interval(self.move_interval.get());
+        self.move_accumulator.add(delta_time);
+
+        while self.move_accumulator.should_tick() && !self.game_over {
             self.handle_movement(self.movement_direction);
             self.last_movement_direction = self.movement_direction;
+            self.queue_sprites();
+        }
+
+        // The head stops wiggling and the snake stops speeding up once the game is over.
+        if self.game_over && self.head_animation.is_playing() {
+            self.head_animation.stop();
+
+            if let Some(speed_up) = &self.speed_up {
+                speed_up.cancel();
+            }
+        }
+
+        if self.game_over && self.game_over_fade < 1.0 {
+            self.game_over_fade = (self.game_over_fade + delta_time / GAME_OVER_FADE_TIME).min(1.0);
+            self.queue_sprites();
+        }
+
+        if let Some(food_burst) = &mut self.food_burst {
+            food_burst.emitter.update(delta_time);
+        }
+
+        let head_frame = self.head_animation.current_frame();
+        self.head_animation.update(delta_time);
+
+        if self.head_animation.current_frame() != head_frame {
+            self.queue_sprites();
+        }
+
+        if self.click_to_place_food && !self.game_over && input.is_cursor_inside() && input.is_mouse_clicked(core_engine::MouseButtonLeft) {
+            self.place_food_at_cursor(input.as_ref());
         }
 
         // Update new input.
-        if (input.is_key_down(core_engine::Key::W) || input.is_key_clicked(core_engine::Key::W)) && (self.pos.len() == 1 || self.last_movement_direction.y != -1.0 ) {
+        if input.is_key_clicked(core_engine::Key::W) && (self.pos.len() == 1 || self.last_movement_direction.y != -1.0 ) {
             self.movement_direction = Vec2f::new(0.0, 1.0);
         }
 
-        if (input.is_key_down(core_engine::Key::S) || input.is_key_clicked(core_engine::Key::S)) && (self.pos.len() == 1 || self.last_movement_direction.y != 1.0) {
+        if input.is_key_clicked(core_engine::Key::S) && (self.pos.len() == 1 || self.last_movement_direction.y != 1.0) {
             self.movement_direction = Vec2f::new(0.0, -1.0);
         }
 
-        if (input.is_key_down(core_engine::Key::A) || input.is_key_clicked(core_engine::Key::A)) && (self.pos.len() == 1 || self.last_movement_direction.x != 1.0) {
+        if input.is_key_clicked(core_engine::Key::A) && (self.pos.len() == 1 || self.last_movement_direction.x != 1.0) {
             self.movement_direction = Vec2f::new(-1.0, 0.0);
         }
 
-        if (input.is_key_down(core_engine::Key::D) || input.is_key_clicked(core_engine::Key::D)) && (self.pos.len() == 1 || self.last_movement_direction.x != -1.0) {
+        if input.is_key_clicked(core_engine::Key::D) && (self.pos.len() == 1 || self.last_movement_direction.x != -1.0) {
             self.movement_direction = Vec2f::new(1.0, 0.0);
         }
     }
@@ -200,22 +459,79 @@ fn main() {
 
     match game_manager {
         Some(mut game_manager) => {
-            // Create a shader.
+            // Finish building the shaders before the first frame rather than during it.
+            if let Err(err) = game_manager.warmup_shaders(|finished, total| println!("Built shader {} of {}", finished, total)) {
+                println!("{}", err);
+            }
+
+            let mut pipeline = SnakeRenderPipeline::new(&game_manager);
+
+            // The snake moves 10% faster every 10 seconds.
+            let move_interval = pipeline.move_interval.clone();
+            pipeline.speed_up = Some(game_manager.get_scheduler_mut().every(10.0, move || {
+                move_interval.set((move_interval.get() * 0.9).max(MIN_MOVE_INTERVAL));
+            }));
 
-            let pipeline = SnakeRenderPipeline::new(&game_manager);
             game_manager.add_render_pipeline(Box::new(pipeline));
             game_manager.init();
 
-            let mut _frame_timer = Stopwatch::new();
-            let mut _fps = 0;
+            let mut post_process_key_down = false;
+            let mut screenshot_key_down = false;
+            let dev_mode = game_manager.config_get::<bool>("game/dev_mode").unwrap_or(false);
+            let mut debug_mode_keys_down = (false, false);
+            let mut screenshots = Vec::<PendingScreenshot>::new();
             while !game_manager.update() {
-                _fps += 1;
+                // P toggles the post processing effects.
+                let key_down = game_manager.get_input().is_key_down(core_engine::Key::P);
+                if key_down && !post_process_key_down {
+                    game_manager.get_post_process_mut().toggle();
+                }
+                post_process_key_down = key_down;
 
-                if _frame_timer.elapsed_seconds() >= 1.0 {
-                    println!("{}", _fps);
-                    _frame_timer.start();
-                    _fps = 0;
+                // The scroll wheel zooms the camera.
+                let scroll = game_manager.get_input().scroll_delta();
+                if scroll.y != 0.0 {
+                    let camera = game_manager.get_camera_mut();
+                    camera.zoom = (camera.zoom * ZOOM_STEP.powf(scroll.y)).clamp(MIN_ZOOM, MAX_ZOOM);
                 }
+
+                // F12 saves the next frame.
+                let key_down = game_manager.get_input().is_key_down(core_engine::Key::F12);
+                if key_down && !screenshot_key_down {
+                    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+                    screenshots.push(game_manager.capture_screenshot(&format!("screenshot_{}.png", time.as_millis())));
+                }
+                screenshot_key_down = key_down;
+
+                // In dev mode F1 toggles wireframes and F2 toggles overdraw. F3 toggles the debug overlay.
+                if dev_mode {
+                    let keys_down = (game_manager.get_input().is_key_down(core_engine::Key::F1),
+                        game_manager.get_input().is_key_down(core_engine::Key::F2));
+
+                    let toggled = match keys_down {
+                        (true, _) if !debug_mode_keys_down.0 => Some(DebugRenderMode::Wireframe),
+                        (_, true) if !debug_mode_keys_down.1 => Some(DebugRenderMode::Overdraw),
+                        _ => None
+                    };
+
+                    if let Some(mode) = toggled {
+                        let current = game_manager.get_debug_render_mode();
+                        game_manager.set_debug_render_mode(if current == mode { DebugRenderMode::Normal } else { mode });
+                    }
+
+                    debug_mode_keys_down = keys_down;
+                }
+
+                screenshots.retain(|screenshot| match screenshot.try_result() {
+                    Some(Ok(path)) => { println!("Saved screenshot {}", path.display()); false },
+                    Some(Err(err)) => { println!("Failed to save screenshot {}: {}", screenshot.path().display(), err); false },
+                    None => true
+                });
+            }
+
+            // Finish any saves still queued before the process exits.
+            if let Err(err) = game_manager.flush_saves() {
+                println!("Failed to save: {}", err);
             }
         },
         None => {